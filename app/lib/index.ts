@@ -1,4 +1,4 @@
-import { Connection, Keypair, PublicKey, SystemProgram, SYSVAR_RENT_PUBKEY, Transaction } from "@solana/web3.js";
+import { Connection, Keypair, PublicKey, SystemProgram, SYSVAR_RENT_PUBKEY, Transaction, TransactionInstruction } from "@solana/web3.js";
 import { Program, BN } from "@coral-xyz/anchor";
 import type { Anyswap } from "../../target/types/anyswap";
 import type { Idl } from "@coral-xyz/anchor";
@@ -43,6 +43,26 @@ export class Client {
         return poolMint;
     }
 
+    // 辅助函数：获取 token 在某一时刻的权重（权重调整期间按时间线性插值，与合约一致）
+    // tokenItem: pool 账户中的 token item，now: unix 时间戳（秒）
+    public getTokenWeight(tokenItem: any, now: number = Math.floor(Date.now() / 1000)): BN {
+        const startTime = tokenItem.weightStartTime.toNumber();
+        const endTime = tokenItem.weightEndTime.toNumber();
+        const start: BN = tokenItem.weight;
+        const target: BN = tokenItem.targetWeight;
+        if (now >= endTime || endTime <= startTime) {
+            return target;
+        }
+        if (now <= startTime) {
+            return start;
+        }
+        const elapsed = new BN(now - startTime);
+        const duration = new BN(endTime - startTime);
+        return target.gt(start)
+            ? start.add(target.sub(start).mul(elapsed).div(duration))
+            : start.sub(start.sub(target).mul(elapsed).div(duration));
+    }
+
     // 辅助函数：获取 Vault PDA
     public getVault(pool: PublicKey, mint: PublicKey): PublicKey {
         const [vault] = PublicKey.findProgramAddressSync(
@@ -52,11 +72,101 @@ export class Client {
         return vault;
     }
 
+    // 辅助函数：获取时间锁操作 PDA
+    public getQueuedAction(pool: PublicKey, actionId: BN): PublicKey {
+        const [queuedAction] = PublicKey.findProgramAddressSync(
+            [Buffer.from("timelock"), pool.toBuffer(), actionId.toArrayLike(Buffer, "le", 8)],
+            this.program.programId
+        );
+        return queuedAction;
+    }
+
+    // 辅助函数：获取多签 signer PDA（作为 pool 的 admin）
+    public getMultisigSigner(multisig: PublicKey): PublicKey {
+        const [multisigSigner] = PublicKey.findProgramAddressSync(
+            [Buffer.from("multisig_signer"), multisig.toBuffer()],
+            this.program.programId
+        );
+        return multisigSigner;
+    }
+
+    // 辅助函数：获取多签提案 PDA
+    public getMultisigProposal(multisig: PublicKey, proposalId: BN): PublicKey {
+        const [proposal] = PublicKey.findProgramAddressSync(
+            [Buffer.from("multisig_proposal"), multisig.toBuffer(), proposalId.toArrayLike(Buffer, "le", 8)],
+            this.program.programId
+        );
+        return proposal;
+    }
+
+    // 辅助函数：获取协议全局配置 PDA
+    public getFactoryConfig(): PublicKey {
+        const [factoryConfig] = PublicKey.findProgramAddressSync(
+            [Buffer.from("factory_config")],
+            this.program.programId
+        );
+        return factoryConfig;
+    }
+
+    // 初始化协议全局配置（只能由程序的升级权限调用一次）
+    // params: { protocolAuthority, treasury, minFee, maxFee, defaultProtocolFeeShare, creationFee, paused }
+    // minFee / maxFee 为 18 位定点数，例如 3e15 表示 0.3%
+    async initializeFactoryConfig(params: any): Promise<string> {
+        const [programData] = PublicKey.findProgramAddressSync(
+            [this.program.programId.toBuffer()],
+            new PublicKey("BPFLoaderUpgradeab1e11111111111111111111111")
+        );
+
+        return await this.program.methods
+            .initializeFactoryConfig(params)
+            .accountsPartial({
+                authority: this.provider.wallet.publicKey,
+                programData: programData,
+            })
+            .rpc();
+    }
+
+    // 修改协议全局配置（协议管理员）
+    async updateFactoryConfig(
+        params: any,
+        protocolAuthority?: PublicKey
+    ): Promise<string> {
+        const authorityPubkey = protocolAuthority || this.provider.wallet.publicKey;
+
+        return await this.program.methods
+            .updateFactoryConfig(params)
+            .accounts({
+                protocolAuthority: authorityPubkey,
+            })
+            .rpc();
+    }
+
+    // 领取创建 pool 时收取的创建费（协议管理员）
+    async collectCreationFees(
+        destination: PublicKey,
+        protocolAuthority?: PublicKey
+    ): Promise<string> {
+        const authorityPubkey = protocolAuthority || this.provider.wallet.publicKey;
+
+        return await this.program.methods
+            .collectCreationFees()
+            .accounts({
+                protocolAuthority: authorityPubkey,
+                destination: destination,
+            })
+            .rpc();
+    }
+
     // 创建 Pool
+    // 费率必须在全局配置允许的范围内，payer 需要支付全局配置中的创建费
+    // curveType: 定价曲线，{ constantSum: {} }、{ weighted: {} } 或 { stable: {} }
+    // amp: StableSwap 的放大系数，仅 stable 曲线使用
     async createPool(
         feeNumerator: BN,
         feeDenominator: BN,
         adminPubkey: PublicKey,
+        curveType: any = { constantSum: {} },
+        amp: BN = new BN(0),
     ): Promise<{
         pool: PublicKey;
         poolKeypair: Keypair;
@@ -69,7 +179,8 @@ export class Client {
         const [poolAuthority] = this.getPoolAuthority(pool);
         const poolMint = this.getPoolMint(pool);
 
-        const poolSpace = 8 + 2 + 6 + 32 + 8 + 8 + 8 + (72 * 1024); // 73792 bytes
+        // 只分配头部空间（352 bytes），添加 token 时账户会自动扩展
+        const poolSpace = 8 + 2 + 1 + 1 + 1 + 3 + 32 + 8 + 8 + 8 + 32 + 8 + 32 + 8 + 8 + 32 + 32 + 32 + 32 + 32 + 8 + 8 + 16;
         const lamports = await this.connection.getMinimumBalanceForRentExemption(poolSpace);
 
         const createAccountIx = SystemProgram.createAccount({
@@ -81,7 +192,7 @@ export class Client {
         });
 
         const createPoolIx = await this.program.methods
-            .createPool(feeNumerator, feeDenominator)
+            .createPool(feeNumerator, feeDenominator, curveType, amp)
             .accountsPartial({
                 poolCreator: adminPubkey,
                 pool: pool,
@@ -113,22 +224,99 @@ export class Client {
         };
     }
 
+    // 辅助函数：获取 PDA pool 的地址（由 poolCreator 和 poolId 确定）
+    public getPoolAddress(poolCreator: PublicKey, poolId: BN): PublicKey {
+        const [pool] = PublicKey.findProgramAddressSync(
+            [Buffer.from("pool"), poolCreator.toBuffer(), poolId.toArrayLike(Buffer, "le", 8)],
+            this.program.programId
+        );
+        return pool;
+    }
+
+    // 辅助函数：获取 pool 注册表 PDA
+    public getPoolRegistry(): PublicKey {
+        const [poolRegistry] = PublicKey.findProgramAddressSync(
+            [Buffer.from("pool_registry")],
+            this.program.programId
+        );
+        return poolRegistry;
+    }
+
+    // 读取 pool 注册表中的所有 pool（索引即 pool 的全局编号）
+    async getRegisteredPools(): Promise<PublicKey[]> {
+        const info = await this.connection.getAccountInfo(this.getPoolRegistry());
+        if (!info) {
+            return [];
+        }
+        // 账户数据：discriminator(8) + pool_count(8) + pool_count 个地址
+        const poolCount = new BN(info.data.subarray(8, 16), "le").toNumber();
+        const pools: PublicKey[] = [];
+        for (let i = 0; i < poolCount; i++) {
+            const offset = 16 + i * 32;
+            pools.push(new PublicKey(info.data.subarray(offset, offset + 32)));
+        }
+        return pools;
+    }
+
+    // 创建 PDA Pool（地址可以由 poolCreator 和 poolId 推导），并追加到 pool 注册表
+    async createPoolWithId(
+        poolId: BN,
+        feeNumerator: BN,
+        feeDenominator: BN,
+        adminPubkey: PublicKey,
+        curveType: any = { constantSum: {} },
+        amp: BN = new BN(0),
+    ): Promise<{
+        pool: PublicKey;
+        poolAuthority: PublicKey;
+        poolMint: PublicKey;
+        signature: string;
+    }> {
+        const pool = this.getPoolAddress(adminPubkey, poolId);
+        const [poolAuthority] = this.getPoolAuthority(pool);
+        const poolMint = this.getPoolMint(pool);
+
+        const signature = await this.program.methods
+            .createPoolWithId(poolId, feeNumerator, feeDenominator, curveType, amp)
+            .accountsPartial({
+                poolCreator: adminPubkey,
+                pool: pool,
+                poolAuthority: poolAuthority,
+                poolMint: poolMint,
+                admin: adminPubkey,
+                payer: this.provider.wallet.publicKey,
+            })
+            .rpc();
+
+        return {
+            pool,
+            poolAuthority,
+            poolMint,
+            signature,
+        };
+    }
+
     // 添加 Token 到 Pool
+    // seedAmount: Weighted 曲线的 pool 已有流动性时新 token 存入的数量（决定新 token 的初始价格），
+    // 其他情况由程序按定价曲线计算，传入 null
     async addTokenToPool(
         pool: PublicKey,
         mint: PublicKey,
         weight: BN,
         existingVaults: PublicKey[] = [],
-        admin?: PublicKey
+        admin?: PublicKey,
+        seedAmount: BN | null = null
     ): Promise<string> {
         const adminPubkey = admin || this.provider.wallet.publicKey;
         const [poolAuthority] = this.getPoolAuthority(pool);
         const vault = this.getVault(pool, mint);
+        // vault 和 admin 的 ATA 都由 mint 所属的 token 程序创建
+        const tokenProgram = await this.getTokenProgram(mint);
         const adminToken = await token.getAssociatedTokenAddress(
             mint,
             adminPubkey,
             false,
-            token.TOKEN_PROGRAM_ID,
+            tokenProgram,
             token.ASSOCIATED_TOKEN_PROGRAM_ID
         );
 
@@ -137,10 +325,11 @@ export class Client {
             poolAuthority: poolAuthority,
             mint: mint,
             vault: vault,
+            mintIndex: this.getMintIndex(mint),
             adminToken: adminToken,
             admin: adminPubkey,
             payer: this.provider.wallet.publicKey,
-            tokenProgram: token.TOKEN_PROGRAM_ID,
+            tokenProgram: tokenProgram,
             associatedTokenProgram: token.ASSOCIATED_TOKEN_PROGRAM_ID,
             systemProgram: SystemProgram.programId,
             rent: SYSVAR_RENT_PUBKEY,
@@ -151,19 +340,23 @@ export class Client {
         ]);
 
         return await this.program.methods
-            .addTokenToPool(weight)
+            .addTokenToPool(weight, seedAmount)
             .accounts(accounts)
             .remainingAccounts(remainingAccounts)
             .rpc();
     }
 
     // 添加流动性
+    // amounts: 每个 token 的最大添加数量
+    // minLpOut: 最少获得的 LP 数量；deadline: 可选的截止时间（unix 时间戳）
     async addLiquidity(
         pool: PublicKey,
         amounts: BN[],
         userTokenAccounts: PublicKey[],
         vaultAccounts: PublicKey[],
-        owner?: PublicKey
+        owner?: PublicKey,
+        minLpOut: BN = new BN(0),
+        deadline: BN | null = null
     ): Promise<string> {
         const ownerPubkey = owner || this.provider.wallet.publicKey;
         const [poolAuthority] = this.getPoolAuthority(pool);
@@ -183,29 +376,80 @@ export class Client {
             userPoolAta: userPoolAta,
             owner: ownerPubkey,
             tokenProgram: token.TOKEN_PROGRAM_ID,
+            token2022Program: token.TOKEN_2022_PROGRAM_ID,
         };
 
+        const mints = await this.getVaultMints(vaultAccounts);
         const remainingAccounts = userTokenAccounts
             .map((userAccount, index) => [
                 { pubkey: userAccount, isWritable: true, isSigner: false },
                 { pubkey: vaultAccounts[index], isWritable: true, isSigner: false },
+                { pubkey: mints[index], isWritable: false, isSigner: false },
             ])
             .flat();
 
         return await this.program.methods
-            .addLiquidity(amounts)
+            .addLiquidity(amounts, minLpOut, deadline)
             .accounts(accounts)
             .remainingAccounts(remainingAccounts)
             .rpc();
     }
 
+    // 单边添加流动性：只存入一个 token
+    // allVaults: 按 pool 中 token 的顺序传入所有 vault（用于计算不变量）
+    async addLiquiditySingle(
+        pool: PublicKey,
+        amountIn: BN,
+        minLpOut: BN,
+        vault: PublicKey,
+        userToken: PublicKey,
+        allVaults: PublicKey[],
+        owner?: PublicKey
+    ): Promise<string> {
+        const ownerPubkey = owner || this.provider.wallet.publicKey;
+        const [poolAuthority] = this.getPoolAuthority(pool);
+        const poolMint = this.getPoolMint(pool);
+        const userPoolAta = await token.getAssociatedTokenAddress(
+            poolMint,
+            ownerPubkey,
+            false,
+            token.TOKEN_PROGRAM_ID,
+            token.ASSOCIATED_TOKEN_PROGRAM_ID
+        );
+
+        const remainingAccounts = allVaults.map((v) => ({
+            pubkey: v, isWritable: false, isSigner: false,
+        }));
+
+        return await this.program.methods
+            .addLiquiditySingle(amountIn, minLpOut)
+            .accountsPartial({
+                pool: pool,
+                poolAuthority: poolAuthority,
+                poolMint: poolMint,
+                vault: vault,
+                userToken: userToken,
+                mint: (await this.getVaultMints([vault]))[0],
+                userPoolAta: userPoolAta,
+                owner: ownerPubkey,
+                tokenProgram: token.TOKEN_PROGRAM_ID,
+                token2022Program: token.TOKEN_2022_PROGRAM_ID,
+            })
+            .remainingAccounts(remainingAccounts)
+            .rpc();
+    }
+
     // 移除流动性
+    // minAmountsOut: 每个 token 最少取出的数量，默认不限制
+    // deadline: 可选的截止时间（unix 时间戳）
     async removeLiquidity(
         pool: PublicKey,
         burnAmount: BN,
         userTokenAccounts: PublicKey[],
         vaultAccounts: PublicKey[],
-        owner?: PublicKey
+        owner?: PublicKey,
+        minAmountsOut: BN[] = userTokenAccounts.map(() => new BN(0)),
+        deadline: BN | null = null
     ): Promise<string> {
         const ownerPubkey = owner || this.provider.wallet.publicKey;
         const [poolAuthority] = this.getPoolAuthority(pool);
@@ -225,23 +469,74 @@ export class Client {
             userPoolAta: userPoolAta,
             owner: ownerPubkey,
             tokenProgram: token.TOKEN_PROGRAM_ID,
+            token2022Program: token.TOKEN_2022_PROGRAM_ID,
         };
 
+        const mints = await this.getVaultMints(vaultAccounts);
         const remainingAccounts = userTokenAccounts
             .map((userAccount, index) => [
                 { pubkey: userAccount, isWritable: true, isSigner: false },
                 { pubkey: vaultAccounts[index], isWritable: true, isSigner: false },
+                { pubkey: mints[index], isWritable: false, isSigner: false },
             ])
             .flat();
 
         return await this.program.methods
-            .removeLiquidity(burnAmount)
+            .removeLiquidity(burnAmount, minAmountsOut, deadline)
             .accounts(accounts)
             .remainingAccounts(remainingAccounts)
             .rpc();
     }
 
+    // 单边移除流动性：销毁 LP，只取出一个 token
+    // allVaults: stable 曲线，或 ConstantSum 曲线记录的 Σ(vault * weight) 未同步时（迁移、移除 token、
+    // 修改权重之后），需要按 pool 中 token 的顺序传入所有 vault；weighted 曲线可以传入空数组
+    async removeLiquiditySingle(
+        pool: PublicKey,
+        lpIn: BN,
+        minAmountOut: BN,
+        vault: PublicKey,
+        userToken: PublicKey,
+        allVaults: PublicKey[],
+        owner?: PublicKey
+    ): Promise<string> {
+        const ownerPubkey = owner || this.provider.wallet.publicKey;
+        const [poolAuthority] = this.getPoolAuthority(pool);
+        const poolMint = this.getPoolMint(pool);
+        const userPoolAta = await token.getAssociatedTokenAddress(
+            poolMint,
+            ownerPubkey,
+            false,
+            token.TOKEN_PROGRAM_ID,
+            token.ASSOCIATED_TOKEN_PROGRAM_ID
+        );
+
+        const remainingAccounts = allVaults.map((v) => ({
+            pubkey: v, isWritable: false, isSigner: false,
+        }));
+
+        return await this.program.methods
+            .removeLiquiditySingle(lpIn, minAmountOut)
+            .accountsPartial({
+                pool: pool,
+                poolAuthority: poolAuthority,
+                poolMint: poolMint,
+                vault: vault,
+                userToken: userToken,
+                mint: (await this.getVaultMints([vault]))[0],
+                userPoolAta: userPoolAta,
+                owner: ownerPubkey,
+                tokenProgram: token.TOKEN_PROGRAM_ID,
+                token2022Program: token.TOKEN_2022_PROGRAM_ID,
+            })
+            .remainingAccounts(remainingAccounts)
+            .rpc();
+    }
+
     // 交换代币
+    // allVaults: stable 曲线或启用动态失衡手续费时需要按 pool 中 token 的顺序传入所有 vault
+    // deadline: 可选的截止时间（unix 时间戳）
+    // tokenIndexHints: 可选的 [tokenInIndex, tokenOutIndex]，程序只验证不查找（可以从 fetchPool 的 tokens 中得到）
     async swap(
         pool: PublicKey,
         amountIn: BN,
@@ -250,20 +545,173 @@ export class Client {
         vaultOut: PublicKey,
         userIn: PublicKey,
         userOut: PublicKey,
-        owner?: PublicKey
+        owner?: PublicKey,
+        allVaults: PublicKey[] = [],
+        deadline: BN | null = null,
+        tokenIndexHints: [number, number] | null = null
+    ): Promise<string> {
+        const ownerPubkey = owner || this.provider.wallet.publicKey;
+
+        const remainingAccounts = allVaults.map((vault) => ({
+            pubkey: vault, isWritable: false, isSigner: false,
+        }));
+        const [mintIn, mintOut] = await this.getVaultMints([vaultIn, vaultOut]);
+
+        return await this.program.methods
+            .swapAnyswap(amountIn, minAmountOut, deadline, tokenIndexHints?.[0] ?? null, tokenIndexHints?.[1] ?? null)
+            .accountsPartial({
+                pool: pool,
+                vaultIn: vaultIn,
+                vaultOut: vaultOut,
+                userIn: userIn,
+                userOut: userOut,
+                mintIn: mintIn,
+                mintOut: mintOut,
+                owner: ownerPubkey,
+                tokenProgram: token.TOKEN_PROGRAM_ID,
+                token2022Program: token.TOKEN_2022_PROGRAM_ID,
+            })
+            .remainingAccounts(remainingAccounts)
+            .rpc();
+    }
+
+    // 交换报价：返回 { amountIn, amountOut, feeAmount, feeNumerator, feeDenominator }
+    // 实际费率包含动态失衡手续费，不发送交易
+    async quoteSwap(
+        pool: PublicKey,
+        amountIn: BN,
+        vaultIn: PublicKey,
+        vaultOut: PublicKey,
+        allVaults: PublicKey[] = []
+    ): Promise<any> {
+        const remainingAccounts = allVaults.map((vault) => ({
+            pubkey: vault, isWritable: false, isSigner: false,
+        }));
+
+        return await this.program.methods
+            .quoteSwap(amountIn)
+            .accountsPartial({
+                pool: pool,
+                vaultIn: vaultIn,
+                vaultOut: vaultOut,
+            })
+            .remainingAccounts(remainingAccounts)
+            .view();
+    }
+
+    // 精确输出交换：获得 amountOut，最多支付 maxAmountIn
+    // allVaults: stable 曲线或启用动态失衡手续费时需要按 pool 中 token 的顺序传入所有 vault
+    // deadline: 可选的截止时间（unix 时间戳）
+    // tokenIndexHints: 可选的 [tokenInIndex, tokenOutIndex]，程序只验证不查找
+    async swapExactOut(
+        pool: PublicKey,
+        amountOut: BN,
+        maxAmountIn: BN,
+        vaultIn: PublicKey,
+        vaultOut: PublicKey,
+        userIn: PublicKey,
+        userOut: PublicKey,
+        owner?: PublicKey,
+        allVaults: PublicKey[] = [],
+        deadline: BN | null = null,
+        tokenIndexHints: [number, number] | null = null
     ): Promise<string> {
         const ownerPubkey = owner || this.provider.wallet.publicKey;
 
+        const remainingAccounts = allVaults.map((vault) => ({
+            pubkey: vault, isWritable: false, isSigner: false,
+        }));
+        const [mintIn, mintOut] = await this.getVaultMints([vaultIn, vaultOut]);
+
         return await this.program.methods
-            .swapAnyswap(amountIn, minAmountOut)
+            .swapExactOut(amountOut, maxAmountIn, deadline, tokenIndexHints?.[0] ?? null, tokenIndexHints?.[1] ?? null)
             .accountsPartial({
                 pool: pool,
                 vaultIn: vaultIn,
                 vaultOut: vaultOut,
                 userIn: userIn,
                 userOut: userOut,
+                mintIn: mintIn,
+                mintOut: mintOut,
+                owner: ownerPubkey,
+                tokenProgram: token.TOKEN_PROGRAM_ID,
+                token2022Program: token.TOKEN_2022_PROGRAM_ID,
+            })
+            .remainingAccounts(remainingAccounts)
+            .rpc();
+    }
+
+    // 批量交换：在同一个 pool 中按顺序执行多笔交换，只按净额转账
+    // userTokenAccounts / vaultAccounts: legs 中用到的 token，按 token 在 pool 中的索引从小到大排列
+    // allVaults: stable 曲线或启用动态失衡手续费时需要按 pool 中 token 的顺序传入所有 vault
+    // deadline: 可选的截止时间（unix 时间戳）
+    async batchSwap(
+        pool: PublicKey,
+        legs: { tokenInIndex: number; tokenOutIndex: number; amountIn: BN; minAmountOut: BN }[],
+        userTokenAccounts: PublicKey[],
+        vaultAccounts: PublicKey[],
+        owner?: PublicKey,
+        allVaults: PublicKey[] = [],
+        deadline: BN | null = null
+    ): Promise<string> {
+        const ownerPubkey = owner || this.provider.wallet.publicKey;
+        const [poolAuthority] = this.getPoolAuthority(pool);
+
+        const mints = await this.getVaultMints(vaultAccounts);
+        const remainingAccounts = userTokenAccounts
+            .map((userAccount, index) => [
+                { pubkey: userAccount, isWritable: true, isSigner: false },
+                { pubkey: vaultAccounts[index], isWritable: true, isSigner: false },
+                { pubkey: mints[index], isWritable: false, isSigner: false },
+            ])
+            .flat()
+            .concat(allVaults.map((vault) => ({
+                pubkey: vault, isWritable: false, isSigner: false,
+            })));
+
+        return await this.program.methods
+            .batchSwap(legs, deadline)
+            .accountsPartial({
+                pool: pool,
+                poolAuthority: poolAuthority,
                 owner: ownerPubkey,
                 tokenProgram: token.TOKEN_PROGRAM_ID,
+                token2022Program: token.TOKEN_2022_PROGRAM_ID,
+            })
+            .remainingAccounts(remainingAccounts)
+            .rpc();
+    }
+
+    // 调整 StableSwap 放大系数（targetAmp 为整数 A，需要管理员或权重管理员，启用时间锁后使用 queueAction）
+    async rampAmp(
+        pool: PublicKey,
+        targetAmp: BN,
+        endTime: BN,
+        admin?: PublicKey
+    ): Promise<string> {
+        const adminPubkey = admin || this.provider.wallet.publicKey;
+
+        return await this.program.methods
+            .rampAmp(targetAmp, endTime)
+            .accounts({
+                pool: pool,
+                admin: adminPubkey,
+            })
+            .rpc();
+    }
+
+    // 停止放大系数调整
+    async stopRampAmp(
+        pool: PublicKey,
+        admin?: PublicKey
+    ): Promise<string> {
+        const adminPubkey = admin || this.provider.wallet.publicKey;
+
+        return await this.program.methods
+            .stopRampAmp()
+            .accounts({
+                pool: pool,
+                admin: adminPubkey,
             })
             .rpc();
     }
@@ -286,17 +734,18 @@ export class Client {
             .rpc();
     }
 
-    // 修改 Token 权重
-    async modifyTokenWeight(
+    // 修改单个 Token 的费率（feeDenominator 为 0 表示恢复使用 pool 的费率）
+    async modifyTokenFee(
         pool: PublicKey,
         mint: PublicKey,
-        newWeight: BN,
+        feeNumerator: BN,
+        feeDenominator: BN,
         admin?: PublicKey
     ): Promise<string> {
         const adminPubkey = admin || this.provider.wallet.publicKey;
 
         return await this.program.methods
-            .modifyTokenWeight(newWeight)
+            .modifyTokenFee(feeNumerator, feeDenominator)
             .accounts({
                 pool: pool,
                 mint: mint,
@@ -305,34 +754,664 @@ export class Client {
             .rpc();
     }
 
-    // 从 Pool 移除 Token
-    async removeTokenFromPool(
+    // 修改手续费策略：{ tokenIn: {} } | { tokenOut: {} } | { max: {} }
+    async modifyFeePolicy(
         pool: PublicKey,
-        mint: PublicKey,
+        feePolicy: any,
         admin?: PublicKey
     ): Promise<string> {
         const adminPubkey = admin || this.provider.wallet.publicKey;
 
         return await this.program.methods
-            .removeTokenFromPool()
+            .modifyFeePolicy(feePolicy)
+            .accounts({
+                pool: pool,
+                admin: adminPubkey,
+            })
+            .rpc();
+    }
+
+    // 修改动态失衡手续费（maxFee 和 threshold 为 18 位定点数，maxFee 为 0 表示不启用）
+    async modifySurgeFee(
+        pool: PublicKey,
+        maxFee: BN,
+        threshold: BN,
+        admin?: PublicKey
+    ): Promise<string> {
+        const adminPubkey = admin || this.provider.wallet.publicKey;
+
+        return await this.program.methods
+            .modifySurgeFee(maxFee, threshold)
             .accounts({
                 pool: pool,
-                mint: mint,
                 admin: adminPubkey,
             })
             .rpc();
     }
 
+    // 修改协议手续费比例（占手续费的 protocolFeeShare / 10000）和协议金库，只有协议管理员可以调用
+    async modifyProtocolFee(
+        pool: PublicKey,
+        protocolFeeShare: BN,
+        treasury: PublicKey,
+        protocolAuthority?: PublicKey
+    ): Promise<string> {
+        const authorityPubkey = protocolAuthority || this.provider.wallet.publicKey;
+
+        return await this.program.methods
+            .modifyProtocolFee(protocolFeeShare, treasury)
+            .accounts({
+                pool: pool,
+                protocolAuthority: authorityPubkey,
+            })
+            .rpc();
+    }
+
+    // 领取协议手续费
+    // vaults 和 destinations 一一对应：把 vaults[i] 中累计的协议手续费转到 destinations[i]
+    async collectProtocolFees(
+        pool: PublicKey,
+        vaults: PublicKey[],
+        destinations: PublicKey[],
+        treasury?: PublicKey
+    ): Promise<string> {
+        const treasuryPubkey = treasury || this.provider.wallet.publicKey;
+        const [poolAuthority] = this.getPoolAuthority(pool);
+
+        const mints = await this.getVaultMints(vaults);
+        const remainingAccounts = vaults.flatMap((vault, i) => [
+            { pubkey: vault, isWritable: true, isSigner: false },
+            { pubkey: destinations[i], isWritable: true, isSigner: false },
+            { pubkey: mints[i], isWritable: false, isSigner: false },
+        ]);
+
+        return await this.program.methods
+            .collectProtocolFees()
+            .accountsPartial({
+                pool: pool,
+                poolAuthority: poolAuthority,
+                treasury: treasuryPubkey,
+                tokenProgram: token.TOKEN_PROGRAM_ID,
+                token2022Program: token.TOKEN_2022_PROGRAM_ID,
+            })
+            .remainingAccounts(remainingAccounts)
+            .rpc();
+    }
+
+    // 修改 Token 权重
+    async modifyTokenWeight(
+        pool: PublicKey,
+        mint: PublicKey,
+        newWeight: BN,
+        admin?: PublicKey
+    ): Promise<string> {
+        const adminPubkey = admin || this.provider.wallet.publicKey;
+
+        return await this.program.methods
+            .modifyTokenWeight(newWeight)
+            .accounts({
+                pool: pool,
+                mint: mint,
+                admin: adminPubkey,
+            })
+            .rpc();
+    }
+
+    // 按时间线性调整 Token 权重：从 startTime 的当前权重调整到 endTime 的 endWeight
+    async rampTokenWeight(
+        pool: PublicKey,
+        mint: PublicKey,
+        endWeight: BN,
+        startTime: BN,
+        endTime: BN,
+        admin?: PublicKey
+    ): Promise<string> {
+        const adminPubkey = admin || this.provider.wallet.publicKey;
+
+        return await this.program.methods
+            .rampTokenWeight(endWeight, startTime, endTime)
+            .accounts({
+                pool: pool,
+                mint: mint,
+                admin: adminPubkey,
+            })
+            .rpc();
+    }
+
+    // 暂停或恢复 Pool（守护者只能暂停，恢复需要管理员）
+    async setPoolPaused(
+        pool: PublicKey,
+        paused: boolean,
+        authority?: PublicKey
+    ): Promise<string> {
+        const authorityPubkey = authority || this.provider.wallet.publicKey;
+
+        return await this.program.methods
+            .setPoolPaused(paused)
+            .accounts({
+                pool: pool,
+                authority: authorityPubkey,
+            })
+            .rpc();
+    }
+
+    // 设置 Token 的禁用标志（守护者只能增加禁用，解除需要管理员）
+    async setTokenFlags(
+        pool: PublicKey,
+        mint: PublicKey,
+        swapInDisabled: boolean,
+        swapOutDisabled: boolean,
+        depositsDisabled: boolean,
+        authority?: PublicKey
+    ): Promise<string> {
+        const authorityPubkey = authority || this.provider.wallet.publicKey;
+
+        return await this.program.methods
+            .setTokenFlags(swapInDisabled, swapOutDisabled, depositsDisabled)
+            .accounts({
+                pool: pool,
+                mint: mint,
+                authority: authorityPubkey,
+            })
+            .rpc();
+    }
+
+    // 设置 Pool 的守护者
+    async setGuardian(
+        pool: PublicKey,
+        guardian: PublicKey,
+        admin?: PublicKey
+    ): Promise<string> {
+        const adminPubkey = admin || this.provider.wallet.publicKey;
+
+        return await this.program.methods
+            .setGuardian(guardian)
+            .accounts({
+                pool: pool,
+                authority: adminPubkey,
+            })
+            .rpc();
+    }
+
+    // 提议转移管理员（第一步）
+    async proposeAdmin(
+        pool: PublicKey,
+        newAdmin: PublicKey,
+        admin?: PublicKey
+    ): Promise<string> {
+        const adminPubkey = admin || this.provider.wallet.publicKey;
+
+        return await this.program.methods
+            .proposeAdmin(newAdmin)
+            .accounts({
+                pool: pool,
+                admin: adminPubkey,
+            })
+            .rpc();
+    }
+
+    // 取消转移管理员的提议
+    async cancelAdminProposal(
+        pool: PublicKey,
+        admin?: PublicKey
+    ): Promise<string> {
+        const adminPubkey = admin || this.provider.wallet.publicKey;
+
+        return await this.program.methods
+            .cancelAdminProposal()
+            .accounts({
+                pool: pool,
+                admin: adminPubkey,
+            })
+            .rpc();
+    }
+
+    // 接受管理员转移（第二步，由新管理员签名）
+    async acceptAdmin(
+        pool: PublicKey,
+        pendingAdmin?: PublicKey
+    ): Promise<string> {
+        const pendingAdminPubkey = pendingAdmin || this.provider.wallet.publicKey;
+
+        return await this.program.methods
+            .acceptAdmin()
+            .accounts({
+                pool: pool,
+                pendingAdmin: pendingAdminPubkey,
+            })
+            .rpc();
+    }
+
+    // 授予角色，role 如 { feeManager: {} }、{ weightManager: {} }、{ tokenCurator: {} }、{ pauseGuardian: {} }
+    async grantRole(
+        pool: PublicKey,
+        role: any,
+        holder: PublicKey,
+        admin?: PublicKey
+    ): Promise<string> {
+        const adminPubkey = admin || this.provider.wallet.publicKey;
+
+        return await this.program.methods
+            .grantRole(role, holder)
+            .accounts({
+                pool: pool,
+                admin: adminPubkey,
+            })
+            .rpc();
+    }
+
+    // 撤销角色
+    async revokeRole(
+        pool: PublicKey,
+        role: any,
+        admin?: PublicKey
+    ): Promise<string> {
+        const adminPubkey = admin || this.provider.wallet.publicKey;
+
+        return await this.program.methods
+            .revokeRole(role)
+            .accounts({
+                pool: pool,
+                admin: adminPubkey,
+            })
+            .rpc();
+    }
+
+    // 设置时间锁的最短延迟（秒），只能提高；降低延迟需要排队 { setTimelockDelay: { delay } }
+    async setTimelockDelay(
+        pool: PublicKey,
+        delay: BN,
+        admin?: PublicKey
+    ): Promise<string> {
+        const adminPubkey = admin || this.provider.wallet.publicKey;
+
+        return await this.program.methods
+            .setTimelockDelay(delay)
+            .accounts({
+                pool: pool,
+                admin: adminPubkey,
+            })
+            .rpc();
+    }
+
+    // 把操作加入时间锁队列
+    // action 如 { modifyFee: { feeNumerator, feeDenominator } }、{ modifyTokenFee: { mint, feeNumerator, feeDenominator } }、
+    // { modifyFeePolicy: { feePolicy } }、{ modifySurgeFee: { maxFee, threshold } }、{ modifyTokenWeight: { mint, newWeight } }、
+    // { rampTokenWeight: { mint, endWeight, startTime, endTime } }、{ rampAmp: { targetAmp, endTime } }、{ removeToken: { mint } }、
+    // { changeAdmin: { newAdmin } } 或 { setTimelockDelay: { delay } }
+    // 延迟大于 0 时，修改费率（包括 token 费率、手续费策略和动态失衡手续费）、权重、放大系数、移除 token 和转移管理员只能通过队列执行
+    async queueAction(
+        pool: PublicKey,
+        action: any,
+        authority?: PublicKey
+    ): Promise<{ queuedAction: PublicKey; actionId: BN; signature: string }> {
+        const authorityPubkey = authority || this.provider.wallet.publicKey;
+        const poolAccount = await this.program.account.anySwapPool.fetch(pool);
+        const actionId: BN = poolAccount.timelockNonce;
+        const queuedAction = this.getQueuedAction(pool, actionId);
+
+        const accounts: any = {
+            pool: pool,
+            queuedAction: queuedAction,
+            authority: authorityPubkey,
+            payer: this.provider.wallet.publicKey,
+            systemProgram: SystemProgram.programId,
+        };
+
+        const signature = await this.program.methods
+            .queueAction(actionId, action)
+            .accounts(accounts)
+            .rpc();
+
+        return { queuedAction, actionId, signature };
+    }
+
+    // 执行时间锁中到期的操作
+    async executeAction(
+        pool: PublicKey,
+        queuedAction: PublicKey,
+        authority?: PublicKey
+    ): Promise<string> {
+        const authorityPubkey = authority || this.provider.wallet.publicKey;
+        const action = await this.program.account.queuedAction.fetch(queuedAction);
+
+        const accounts: any = {
+            pool: pool,
+            queuedAction: queuedAction,
+            authority: authorityPubkey,
+            payer: action.payer,
+        };
+
+        // 移除 token 时需要同步 mint 索引
+        const removeToken = (action.action as any).removeToken;
+        const remainingAccounts = removeToken
+            ? [
+                { pubkey: this.getMintIndex(removeToken.mint), isWritable: true, isSigner: false },
+                ...(await this.getMovedMintIndexAccounts(pool, removeToken.mint)),
+            ]
+            : [];
+
+        return await this.program.methods
+            .executeAction()
+            .accounts(accounts)
+            .remainingAccounts(remainingAccounts)
+            .rpc();
+    }
+
+    // 取消时间锁中排队的操作
+    async cancelAction(
+        pool: PublicKey,
+        queuedAction: PublicKey,
+        authority?: PublicKey
+    ): Promise<string> {
+        const authorityPubkey = authority || this.provider.wallet.publicKey;
+        const action = await this.program.account.queuedAction.fetch(queuedAction);
+
+        const accounts: any = {
+            pool: pool,
+            queuedAction: queuedAction,
+            authority: authorityPubkey,
+            payer: action.payer,
+        };
+
+        return await this.program.methods
+            .cancelAction()
+            .accounts(accounts)
+            .rpc();
+    }
+
+    // 创建 M-of-N 多签，把返回的 multisigSigner 设为 pool 的 admin 后由多签管理 pool
+    async createMultisig(
+        members: PublicKey[],
+        threshold: number
+    ): Promise<{ multisig: PublicKey; multisigSigner: PublicKey; signature: string }> {
+        const multisigKeypair = Keypair.generate();
+        const multisig = multisigKeypair.publicKey;
+
+        const signature = await this.program.methods
+            .createMultisig(members, threshold)
+            .accounts({
+                multisig: multisig,
+                payer: this.provider.wallet.publicKey,
+            })
+            .signers([multisigKeypair])
+            .rpc();
+
+        return { multisig, multisigSigner: this.getMultisigSigner(multisig), signature };
+    }
+
+    // 成员同时签名，直接以多签身份执行管理员指令
+    // ix: 以 multisigSigner 作为 admin 构造的本程序指令，例如 program.methods.modifyFee(...).instruction()
+    // memberSigners: 签名的成员（达到阈值），需要作为交易的签名者
+    async executeMultisigInstruction(
+        multisig: PublicKey,
+        ix: TransactionInstruction,
+        memberSigners: Keypair[]
+    ): Promise<string> {
+        const multisigSigner = this.getMultisigSigner(multisig);
+        const remainingAccounts = [
+            ...memberSigners.map((member) => ({
+                pubkey: member.publicKey,
+                isWritable: false,
+                isSigner: true,
+            })),
+            // 多签 signer PDA 由程序签名，交易中不能标记为签名者
+            ...ix.keys.map((key) => ({
+                pubkey: key.pubkey,
+                isWritable: key.isWritable,
+                isSigner: key.isSigner && !key.pubkey.equals(multisigSigner),
+            })),
+        ];
+
+        return await this.program.methods
+            .executeMultisigInstruction(memberSigners.length, ix.data)
+            .accounts({
+                multisig: multisig,
+            })
+            .remainingAccounts(remainingAccounts)
+            .signers(memberSigners)
+            .rpc();
+    }
+
+    // 创建多签提案（成员在不同时间签名），提案人自动批准
+    async createMultisigProposal(
+        multisig: PublicKey,
+        ix: TransactionInstruction,
+        proposer?: PublicKey
+    ): Promise<{ proposal: PublicKey; signature: string }> {
+        const proposerPubkey = proposer || this.provider.wallet.publicKey;
+        const multisigAccount = await this.program.account.adminMultisig.fetch(multisig);
+        const proposalId: BN = multisigAccount.proposalNonce;
+        const proposal = this.getMultisigProposal(multisig, proposalId);
+        const accounts = ix.keys.map((key) => ({
+            pubkey: key.pubkey,
+            isSigner: key.isSigner,
+            isWritable: key.isWritable,
+        }));
+
+        const signature = await this.program.methods
+            .createMultisigProposal(proposalId, accounts, ix.data)
+            .accounts({
+                multisig: multisig,
+                proposer: proposerPubkey,
+                payer: this.provider.wallet.publicKey,
+            })
+            .rpc();
+
+        return { proposal, signature };
+    }
+
+    // 批准多签提案
+    async approveMultisigProposal(
+        multisig: PublicKey,
+        proposal: PublicKey,
+        member?: PublicKey
+    ): Promise<string> {
+        const memberPubkey = member || this.provider.wallet.publicKey;
+
+        return await this.program.methods
+            .approveMultisigProposal()
+            .accounts({
+                multisig: multisig,
+                proposal: proposal,
+                member: memberPubkey,
+            })
+            .rpc();
+    }
+
+    // 执行已达到阈值的多签提案
+    async executeMultisigProposal(
+        multisig: PublicKey,
+        proposal: PublicKey
+    ): Promise<string> {
+        const multisigSigner = this.getMultisigSigner(multisig);
+        const proposalAccount = await this.program.account.multisigProposal.fetch(proposal);
+        const remainingAccounts = proposalAccount.accounts.map((account: any) => ({
+            pubkey: account.pubkey,
+            isWritable: account.isWritable,
+            isSigner: account.isSigner && !account.pubkey.equals(multisigSigner),
+        }));
+
+        const accounts: any = {
+            multisig: multisig,
+            proposal: proposal,
+            multisigSigner: multisigSigner,
+            payer: proposalAccount.payer,
+        };
+
+        return await this.program.methods
+            .executeMultisigProposal()
+            .accounts(accounts)
+            .remainingAccounts(remainingAccounts)
+            .rpc();
+    }
+
+    // 从 Pool 移除 Token
+    async removeTokenFromPool(
+        pool: PublicKey,
+        mint: PublicKey,
+        admin?: PublicKey
+    ): Promise<string> {
+        const adminPubkey = admin || this.provider.wallet.publicKey;
+
+        return await this.program.methods
+            .removeTokenFromPool()
+            .accounts({
+                pool: pool,
+                mint: mint,
+                admin: adminPubkey,
+            })
+            .remainingAccounts(await this.getMovedMintIndexAccounts(pool, mint))
+            .rpc();
+    }
+
+    // 缩小 Pool 账户到刚好容纳现有的 token，多余的租金转给 recipient
+    // 用于在移除 token 后回收租金（旧的固定大小 pool 需要先调用 migratePool）
+    async compactPool(
+        pool: PublicKey,
+        recipient?: PublicKey,
+        admin?: PublicKey
+    ): Promise<string> {
+        const adminPubkey = admin || this.provider.wallet.publicKey;
+
+        return await this.program.methods
+            .compactPool()
+            .accounts({
+                pool: pool,
+                admin: adminPubkey,
+                recipient: recipient || adminPubkey,
+            })
+            .rpc();
+    }
+
+    // 把旧的固定大小 pool（64 字节头部 + 1024 个 72 字节的 item）改写为当前布局，只有管理员可以调用
+    // 旧布局不能用 fetchPool 解码，这里直接从账户数据中按顺序读取所有 token 的 mint
+    // 迁移时同时把 pool 追加到注册表，并记录到每个 token 的 mint 反向索引
+    async migratePool(
+        pool: PublicKey,
+        admin?: PublicKey
+    ): Promise<string> {
+        const adminPubkey = admin || this.provider.wallet.publicKey;
+        const data = (await this.connection.getAccountInfo(pool))!.data;
+        const tokenCount = data.readUInt16LE(8);
+        const mints = Array.from({ length: tokenCount }, (_, i) => {
+            const offset = 8 + 64 + i * 72 + 32;
+            return new PublicKey(data.subarray(offset, offset + 32));
+        });
+        const remainingAccounts = [];
+        for (const mint of mints) {
+            remainingAccounts.push(
+                { pubkey: mint, isWritable: false, isSigner: false },
+                { pubkey: this.getMintIndex(mint), isWritable: true, isSigner: false }
+            );
+        }
+
+        return await this.program.methods
+            .migratePool()
+            .accounts({
+                pool: pool,
+                admin: adminPubkey,
+                payer: this.provider.wallet.publicKey,
+            })
+            .remainingAccounts(remainingAccounts)
+            .rpc();
+    }
+
+    // 辅助函数：移除 token 时最后一个 token 会被移动到被移除的位置，返回需要同步更新的 mint 索引账户
+    private async getMovedMintIndexAccounts(pool: PublicKey, mint: PublicKey) {
+        const { tokens } = await this.fetchPool(pool);
+        const lastMint: PublicKey = tokens[tokens.length - 1].mintAccount;
+        if (lastMint.equals(mint)) {
+            return [];
+        }
+        return [{ pubkey: this.getMintIndex(lastMint), isWritable: true, isSigner: false }];
+    }
+
+    // 辅助函数：返回 mint 所属的 token 程序（Token 或 Token-2022）
+    public async getTokenProgram(mint: PublicKey): Promise<PublicKey> {
+        const info = await this.connection.getAccountInfo(mint);
+        if (!info) {
+            throw new Error(`Mint not found: ${mint.toBase58()}`);
+        }
+        return info.owner;
+    }
+
+    // 辅助函数：读取 token 账户的 mint（transfer_checked 需要传入 mint 账户）
+    private async getVaultMints(vaults: PublicKey[]): Promise<PublicKey[]> {
+        const infos = await this.connection.getMultipleAccountsInfo(vaults);
+        return infos.map((info, i) => {
+            if (!info) {
+                throw new Error(`Token account not found: ${vaults[i].toBase58()}`);
+            }
+            return new PublicKey(info.data.subarray(0, 32));
+        });
+    }
+
+    // 辅助函数：获取 mint 反向索引 PDA
+    public getMintIndex(mint: PublicKey): PublicKey {
+        const [mintIndex] = PublicKey.findProgramAddressSync(
+            [Buffer.from("mint_index"), mint.toBuffer()],
+            this.program.programId
+        );
+        return mintIndex;
+    }
+
+    // 查询包含某个 mint 的所有 pool 以及该 token 在 pool 中的索引（读取 mint 反向索引账户）
+    async getPoolsForMint(mint: PublicKey): Promise<{ pool: PublicKey; tokenIndex: number }[]> {
+        const info = await this.connection.getAccountInfo(this.getMintIndex(mint));
+        if (!info) {
+            return [];
+        }
+        // 账户数据：discriminator(8) + mint(32) + entry_count(8) + entry_count 个 (pool(32) + token_index(8))
+        const entryCount = new BN(info.data.subarray(40, 48), "le").toNumber();
+        const entries: { pool: PublicKey; tokenIndex: number }[] = [];
+        for (let i = 0; i < entryCount; i++) {
+            const offset = 48 + i * 40;
+            entries.push({
+                pool: new PublicKey(info.data.subarray(offset, offset + 32)),
+                tokenIndex: new BN(info.data.subarray(offset + 32, offset + 40), "le").toNumber(),
+            });
+        }
+        return entries;
+    }
+
+    // 读取 Pool 账户：头部由 IDL 解码，token 列表（头部之后的变长部分）按偏移读取前 tokenCount 个
+    async fetchPool(pool: PublicKey) {
+        const info = await this.connection.getAccountInfo(pool);
+        if (!info) {
+            throw new Error(`Pool account not found: ${pool.toString()}`);
+        }
+        const header = this.program.coder.accounts.decode("anySwapPool", info.data);
+        // discriminator(8) + 头部(344)，每个 token 128 bytes
+        const tokens = [];
+        for (let i = 0; i < header.tokenCount; i++) {
+            const item = info.data.subarray(352 + i * 128, 352 + (i + 1) * 128);
+            tokens.push({
+                vaultAccount: new PublicKey(item.subarray(0, 32)),
+                mintAccount: new PublicKey(item.subarray(32, 64)),
+                weight: new BN(item.subarray(64, 72), "le"),
+                targetWeight: new BN(item.subarray(72, 80), "le"),
+                weightStartTime: new BN(item.subarray(80, 88), "le"),
+                weightEndTime: new BN(item.subarray(88, 96), "le"),
+                protocolFees: new BN(item.subarray(96, 104), "le"),
+                feeNumerator: new BN(item.subarray(104, 112), "le"),
+                feeDenominator: new BN(item.subarray(112, 120), "le"),
+                decimals: item[120],
+                flags: item[121],
+            });
+        }
+        return { ...header, tokens };
+    }
+
     // 获取 Pool 账户信息（公开方法）
     async getPoolInfo(pool: PublicKey) {
-        const poolInfo = await this.program.account.anySwapPool.fetch(pool);
+        const poolInfo = await this.fetchPool(pool);
         const lpMint = this.getPoolMint(pool);
         const lpSupply = await token.getAssociatedTokenAddress(lpMint, this.provider.wallet.publicKey);
         return {
             lpMint: lpMint,
             lpSupply: lpSupply,
             ...poolInfo,
-            tokens: poolInfo.tokens.slice(0, poolInfo.tokenCount),
         };
     }
 }
\ No newline at end of file
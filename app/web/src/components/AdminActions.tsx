@@ -84,11 +84,24 @@ export default function AdminActions({
       const poolInfo = await client.getPoolInfo(pool);
       const existingVaults = poolInfo.tokens.map((t: any) => t.vaultAccount);
 
+      // 加权曲线的 pool 已有流动性时，新 token 的存入数量决定其初始价格，由管理员输入
+      let seedAmount = null;
+      if (poolInfo.curveType === 1 && !poolInfo.totalAmountMinted.isZero()) {
+        const seed = prompt('请输入新 Token 的初始存入数量（最小单位）:');
+        if (!seed) {
+          onStatusChange('请输入有效的初始存入数量');
+          return;
+        }
+        seedAmount = new BN(seed);
+      }
+
       const signature = await client.addTokenToPool(
         pool,
         mint,
         weightBN,
-        existingVaults
+        existingVaults,
+        undefined,
+        seedAmount
       );
 
       onStatusChange(`Token 添加成功！交易签名: ${signature}`);
@@ -129,8 +129,10 @@ export default function UserActions({
         }
       }
 
-      // 判断是否是首次添加流动性（所有 vault 余额为 0）
-      const isFirstLiquidity = vaultBalances.every(b => b === BigInt(0));
+      // 判断是否是首次添加流动性（pool 中还没有 LP）
+      const isFirstLiquidity = poolAccount.totalAmountMinted.isZero();
+      // 定价曲线（0: ConstantSum, 1: Weighted, 2: Stable），决定首次添加的比例要求
+      const isWeighted = poolAccount.curveType === 1;
 
       // 显示 token 列表，让用户选择基准币
       let tokenListStr = '请选择作为基准的 Token（输入序号）:\n\n';
@@ -231,14 +233,40 @@ export default function UserActions({
         if (i === baseTokenIndex) {
           // 基准币使用用户输入的数量
           amountBN = baseAmountBN;
+        } else if (isFirstLiquidity && isWeighted) {
+          // 首次添加（Weighted）：任意比例都是合法的初始价格，合约不检查比例
+          // 默认按权重比例存入（各 token 初始价格相同），由用户确认或修改
+          // 权重调整期间使用当前时刻插值后的权重
+          const weightBase = BigInt(client.getTokenWeight(baseTokenInfo).toString());
+          const weightI = BigInt(client.getTokenWeight(tokenInfo).toString());
+          const scaleI = BigInt(10) ** BigInt(mintDecimals[i]);
+          const scaleBase = BigInt(10) ** BigInt(baseDecimals);
+          const suggestedAmount = (baseAmountInSmallestUnit * weightI * scaleI) / (weightBase * scaleBase);
+          const suggestedReadable = (Number(suggestedAmount.toString()) / Math.pow(10, mintDecimals[i])).toFixed(mintDecimals[i]);
+          const tokenAmountInput = prompt(
+            `Token ${i + 1}（权重: ${tokenInfo.weight.toString()}）\n` +
+            `加权曲线的初始价格由存入比例决定，请输入要注入的数量:`,
+            suggestedReadable
+          );
+          if (tokenAmountInput === null) {
+            onStatusChange('已取消添加流动性');
+            onLoadingChange(false);
+            return;
+          }
+          const tokenAmountFloat = parseFloat(tokenAmountInput.trim());
+          if (isNaN(tokenAmountFloat) || tokenAmountFloat <= 0) {
+            onStatusChange('请输入有效的正数数量');
+            onLoadingChange(false);
+            return;
+          }
+          amountBN = new BN(Math.floor(tokenAmountFloat * Math.pow(10, mintDecimals[i])).toString());
         } else if (isFirstLiquidity) {
-          // 首次添加：根据权重比例计算
-          // amount_i = baseAmount * (weight_i / weight_base)
-          // 使用整数运算避免精度损失
-          const weightBase = BigInt(baseTokenInfo.weight.toString());
-          const weightI = BigInt(tokenInfo.weight.toString());
-          // 使用更大的倍数来保持精度，然后向下取整
-          const calculatedAmount = (baseAmountInSmallestUnit * weightI) / weightBase;
+          // 首次添加（ConstantSum / Stable）：与合约的 verify_initial_ratio 一致，
+          // 各 token 换算到统一精度后的数量相同（价值占比等于权重占比），与权重无关
+          // amount_i = baseAmount * 10^(decimals_i - decimals_base)，使用整数运算避免精度损失
+          const scaleI = BigInt(10) ** BigInt(mintDecimals[i]);
+          const scaleBase = BigInt(10) ** BigInt(baseDecimals);
+          const calculatedAmount = (baseAmountInSmallestUnit * scaleI) / scaleBase;
           amountBN = new BN(calculatedAmount.toString());
         } else {
           // 后续添加：根据当前池余额比例计算（与合约逻辑一致）
@@ -28,5 +28,63 @@ pub enum ErrorCode {
     SameTokenSwap,
     #[msg("无效的管理员")]
     InvalidAdmin,
+    #[msg("无效的定价曲线类型")]
+    InvalidCurveType,
+    #[msg("无效的放大系数")]
+    InvalidAmplification,
+    #[msg("无效的调整计划（放大系数或权重）")]
+    InvalidRampSchedule,
+    #[msg("StableSwap 不变量迭代未收敛")]
+    StableInvariantNotConverged,
+    #[msg("交易已超过截止时间")]
+    DeadlineExceeded,
+    #[msg("超出滑点限制")]
+    SlippageExceeded,
+    #[msg("流动性低于永久锁定的最小数量")]
+    MinimumLiquidityLocked,
+    #[msg("存入比例与 token 权重不匹配")]
+    InvalidDepositRatio,
+    #[msg("无效的协议手续费比例")]
+    InvalidProtocolFee,
+    #[msg("无效的协议金库")]
+    InvalidTreasury,
+    #[msg("无效的手续费策略")]
+    InvalidFeePolicy,
+    #[msg("Pool 已暂停")]
+    PoolPaused,
+    #[msg("该 token 已被禁用")]
+    TokenDisabled,
+    #[msg("不是待接受的管理员")]
+    InvalidPendingAdmin,
+    #[msg("无效的时间锁操作")]
+    InvalidTimelockAction,
+    #[msg("时间锁尚未到期")]
+    TimelockNotReady,
+    #[msg("无效的多签配置")]
+    InvalidMultisig,
+    #[msg("不是多签成员")]
+    NotMultisigMember,
+    #[msg("多签签名数量不足")]
+    NotEnoughSigners,
+    #[msg("无效的协议管理员")]
+    InvalidFactoryAuthority,
+    #[msg("协议已暂停")]
+    FactoryPaused,
+    #[msg("费率超出协议允许的范围")]
+    FeeOutOfBounds,
+    #[msg("Pool 账户空间未分配完成")]
+    PoolNotAllocated,
+    #[msg("无效的 mint 索引账户")]
+    InvalidMintIndex,
+    #[msg("mint 含有不支持的 Token-2022 扩展")]
+    UnsupportedMintExtension,
+    #[msg("已启用时间锁，必须通过时间锁队列执行")]
+    TimelockRequired,
+    #[msg("Pool 账户是旧的固定大小布局，需要先调用 migrate_pool")]
+    PoolNeedsMigration,
+    #[msg("Pool 账户不是旧的固定大小布局")]
+    PoolAlreadyMigrated,
+    #[msg("无效的新 token 初始数量")]
+    InvalidSeedAmount,
 }
 
@@ -0,0 +1,93 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{self, Allocate, Assign, CreateAccount, Transfer};
+
+/// 创建由本程序拥有的 PDA 账户
+/// 地址已有 lamports（例如被提前转账）时 create_account 会失败，此时补足租金后分配空间并转移所有权
+pub(crate) fn create_pda_account<'info>(
+    account: &AccountInfo<'info>,
+    space: usize,
+    signer_seeds: &[&[u8]],
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+) -> Result<()> {
+    let signer = &[signer_seeds];
+    let required_lamports = Rent::get()?.minimum_balance(space);
+    
+    if account.lamports() == 0 {
+        system_program::create_account(
+            CpiContext::new_with_signer(
+                system_program.to_account_info(),
+                CreateAccount {
+                    from: payer.to_account_info(),
+                    to: account.clone(),
+                },
+                signer,
+            ),
+            required_lamports,
+            space as u64,
+            &crate::ID,
+        )?;
+        return Ok(());
+    }
+    
+    let top_up = required_lamports.saturating_sub(account.lamports());
+    if top_up > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                system_program.to_account_info(),
+                Transfer {
+                    from: payer.to_account_info(),
+                    to: account.clone(),
+                },
+            ),
+            top_up,
+        )?;
+    }
+    system_program::allocate(
+        CpiContext::new_with_signer(
+            system_program.to_account_info(),
+            Allocate {
+                account_to_allocate: account.clone(),
+            },
+            signer,
+        ),
+        space as u64,
+    )?;
+    system_program::assign(
+        CpiContext::new_with_signer(
+            system_program.to_account_info(),
+            Assign {
+                account_to_assign: account.clone(),
+            },
+            signer,
+        ),
+        &crate::ID,
+    )?;
+    Ok(())
+}
+
+/// 扩展本程序拥有的账户到 new_len（每条指令最多扩展 10KB），并补足租金
+pub(crate) fn grow_account<'info>(
+    account: &AccountInfo<'info>,
+    new_len: usize,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+) -> Result<()> {
+    let top_up = Rent::get()?
+        .minimum_balance(new_len)
+        .saturating_sub(account.lamports());
+    if top_up > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                system_program.to_account_info(),
+                Transfer {
+                    from: payer.to_account_info(),
+                    to: account.clone(),
+                },
+            ),
+            top_up,
+        )?;
+    }
+    account.resize(new_len)?;
+    Ok(())
+}
@@ -1,7 +1,11 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Mint, MintTo, Token, TokenAccount, Transfer};
-use crate::state::AnySwapPool;
+use anchor_spl::token::{self, MintTo, Token};
+use anchor_spl::token_2022::Token2022;
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TransferChecked};
+use crate::state::{AnySwapPool, PoolLoader, MINIMUM_LIQUIDITY, TOKEN_DEPOSITS_DISABLED};
 use crate::error::ErrorCode;
+use super::swap::check_deadline;
+use super::token_utils::{amount_after_transfer_fee, amount_before_transfer_fee, token_program_for};
 
 /// 添加流动性操作
 /// 按照 Balancer 的方式：按当前池的比例添加所有 token
@@ -25,7 +29,7 @@ pub struct AddLiquidity<'info> {
         seeds = [b"pool_mint", pool.key().as_ref()],
         bump
     )]
-    pub pool_mint: Box<Account<'info, Mint>>,
+    pub pool_mint: Box<InterfaceAccount<'info, Mint>>,
 
     /// 用户的 LP token 账户（接收 LP token）
     #[account(
@@ -33,27 +37,42 @@ pub struct AddLiquidity<'info> {
         constraint = user_pool_ata.mint == pool_mint.key(),
         constraint = user_pool_ata.owner == owner.key()
     )]
-    pub user_pool_ata: Box<Account<'info, TokenAccount>>,
+    pub user_pool_ata: Box<InterfaceAccount<'info, TokenAccount>>,
 
     pub owner: Signer<'info>,
 
+    /// 普通 Token 程序（LP mint 和普通 Token 的转账）
     pub token_program: Program<'info, Token>,
+
+    /// Token-2022 程序（Token-2022 token 的转账）
+    pub token_2022_program: Program<'info, Token2022>,
 }
 
 /// 添加流动性（多 token 版本）
 /// 按照 Balancer 的方式：按当前池的比例添加所有 token
 /// 
 /// RemainingAccounts 结构：
-/// - 每两个账户为一对：(user_token_account, vault_account)
+/// - 每三个账户为一组：(user_token_account, vault_account, mint)
 /// - 必须按照 pool 中 token 的顺序传入
-/// - 例如：pool 有 [A, B, C]，则传入 [user_A, vault_A, user_B, vault_B, user_C, vault_C]
+/// - 例如：pool 有 [A, B, C]，则传入 [user_A, vault_A, mint_A, user_B, vault_B, mint_B, user_C, vault_C, mint_C]
+///
+/// 首次添加时存入数量（换算到统一精度后）必须与 token 权重成比例
+/// Token-2022 转账手续费：按 vault 实际收到的数量计算 LP，用户转出的数量（含手续费）不超过 amounts
 /// 
-/// amounts: 每个 token 的添加数量（按 pool 中 token 的顺序）
+/// amounts: 每个 token 的最大添加数量（用户转出的数量，按 pool 中 token 的顺序）
+/// min_lp_out: 最少获得的 LP token 数量（滑点保护）
+/// deadline: 可选的截止时间（unix 时间戳），超过后交易失败
 pub fn add_liquidity<'remaining: 'info, 'info>(
     ctx: Context<'_, '_, 'remaining, 'info, AddLiquidity<'info>>,
     amounts: Vec<u64>,
+    min_lp_out: u64,
+    deadline: Option<i64>,
 ) -> Result<()> {
-    let pool = ctx.accounts.pool.load()?;
+    let now = Clock::get()?.unix_timestamp;
+    check_deadline(deadline, now)?;
+
+    let pool = ctx.accounts.pool.load_pool()?;
+    pool.require_not_paused()?;
     let token_count = pool.get_token_count();
     
     require!(token_count > 0, ErrorCode::InvalidTokenCount);
@@ -62,10 +81,13 @@ pub fn add_liquidity<'remaining: 'info, 'info>(
         ErrorCode::InvalidTokenCount
     );
 
-    // 验证 RemainingAccounts 数量：每个 token 需要 2 个账户（user_token, vault）
+    // 没有 LP 时为初始添加（vault 中可能有别人直接转入的余额，归首个存入者所有）
+    let total_minted = pool.get_total_amount_minted();
+
+    // 验证 RemainingAccounts 数量：每个 token 需要 3 个账户（user_token, vault, mint）
     let remaining_accounts = ctx.remaining_accounts;
     require!(
-        remaining_accounts.len() == token_count * 2,
+        remaining_accounts.len() == token_count * 3,
         ErrorCode::InvalidTokenCount
     );
 
@@ -81,60 +103,71 @@ pub fn add_liquidity<'remaining: 'info, 'info>(
     ];
     let signer = &[&seeds[..]];
 
-    // 检查是否所有 vault 都为空（初始添加）
-    let mut all_vaults_empty = true;
     let mut vault_balances = Vec::new();
+    // 扣除转账手续费后 vault 实际能收到的最大数量
+    let mut received_amounts = Vec::new();
+    let mut decimals = Vec::new();
     
     // 先收集所有 vault 余额（避免生命周期问题）
     // 在循环中立即读取数据，不保留 Account 对象
     for i in 0..token_count {
-        let vault_info = &remaining_accounts[i * 2 + 1];
+        let vault_info = &remaining_accounts[i * 3 + 1];
+        let mint_info = &remaining_accounts[i * 3 + 2];
         
-        // 验证 vault mint 匹配 pool 中的 token
+        // 验证 vault 和 mint 匹配 pool 中的 token，且该 token 允许存入
         let token_item = pool.get_token(i).ok_or(ErrorCode::InvalidTokenIndex)?;
+        pool.require_token_enabled(i, TOKEN_DEPOSITS_DISABLED)?;
         require!(
             vault_info.key == token_item.vault_pubkey(),
             ErrorCode::InvalidTokenMint
         );
+        require!(
+            mint_info.key == token_item.mint_pubkey(),
+            ErrorCode::InvalidTokenMint
+        );
         
         // 读取 vault 账户并验证 owner 是 pool_authority
-        let vault_account = Account::<TokenAccount>::try_from_unchecked(vault_info)?;
+        let vault_account = InterfaceAccount::<TokenAccount>::try_from(vault_info)?;
         require!(
             vault_account.owner == pool_authority_key,
             ErrorCode::InvalidTokenMint
         );
         
-        // 读取 vault 余额
-        let balance = vault_account.amount;
-        vault_balances.push(balance);
-        if balance > 0 {
-            all_vaults_empty = false;
-        }
+        // 读取 vault 余额（不包括待领取的协议手续费）
+        vault_balances.push(pool.get_reserve(i, vault_account.amount));
+        received_amounts.push(amount_after_transfer_fee(mint_info, amounts[i])?);
+        decimals.push(token_item.get_decimals());
     }
 
     let mut deposits = Vec::new();
     let amount_to_mint;
+    // total_amount_minted 的增加量（初始添加时包含永久锁定的部分）
+    let minted_total_increase;
 
-    if all_vaults_empty {
-        // 初始添加：使用所有 token 的总和作为 LP token 数量
-        // 简化版本：使用所有 token 数量的平均值
-        let total_amount: u128 = amounts.iter().map(|&a| a as u128).sum();
-        amount_to_mint = (total_amount / token_count as u128) as u64;
-        deposits = amounts;
+    if total_minted == 0 {
+        // 初始添加：LP token 数量由 pool 的定价曲线决定
+        // ConstantSum 使用 Σ(amount * weight) / Σweight，Weighted 使用加权几何平均，Stable 使用不变量 D
+        // 计算前先把每个 token 的数量换算到统一精度（即 LP mint 的精度）
+        // 其中 MINIMUM_LIQUIDITY 永久锁定，不铸造给用户
+        let initial_lp = pool.calculate_initial_lp(&received_amounts, now)?;
+        require!(
+            initial_lp > MINIMUM_LIQUIDITY,
+            ErrorCode::MinimumLiquidityLocked
+        );
+        amount_to_mint = initial_lp - MINIMUM_LIQUIDITY;
+        minted_total_increase = initial_lp;
+        deposits = received_amounts;
     } else {
         // 后续添加：按当前池的比例
         // 计算每个 token 应该添加的数量（基于第一个非零的 token）
         // 找到第一个非零的 vault 作为基准
-        let mut base_index = 0;
-        for i in 0..token_count {
-            if vault_balances[i] > 0 {
-                base_index = i;
-                break;
-            }
-        }
+        let base_index = vault_balances
+            .iter()
+            .position(|&balance| balance > 0)
+            .unwrap_or(0);
 
         let base_vault_balance = vault_balances[base_index] as u128;
-        let base_amount = amounts[base_index] as u128;
+        let base_amount = received_amounts[base_index] as u128;
 
         // 计算每个 token 应该添加的数量
         for i in 0..token_count {
@@ -148,10 +181,10 @@ pub fn add_liquidity<'remaining: 'info, 'info>(
                 .checked_div(base_vault_balance)
                 .ok_or(ErrorCode::MathOverflow)?) as u64;
 
-            // 检查用户提供的数量是否足够
+            // 检查需要的数量是否超过用户给出的最大数量
             require!(
-                expected_deposit <= amounts[i],
-                ErrorCode::InsufficientTokenAmount
+                expected_deposit <= received_amounts[i],
+                ErrorCode::SlippageExceeded
             );
 
             deposits.push(expected_deposit);
@@ -159,27 +192,36 @@ pub fn add_liquidity<'remaining: 'info, 'info>(
 
         // 计算 LP token 数量：基于基准 token
         // amount_to_mint = deposit_base * total_minted / vault_balance_base
-        let total_minted = pool.get_total_amount_minted();
         amount_to_mint = (base_amount
             .checked_mul(total_minted as u128)
             .ok_or(ErrorCode::MathOverflow)?
             .checked_div(base_vault_balance)
             .ok_or(ErrorCode::MathOverflow)?) as u64;
+        minted_total_increase = amount_to_mint;
     }
 
     require!(amount_to_mint > 0, ErrorCode::InsufficientTokenAmount);
+    require!(amount_to_mint >= min_lp_out, ErrorCode::SlippageExceeded);
     
     // drop
     drop(pool);
 
     // 更新 total_amount_minted
-    let mut pool_mut = ctx.accounts.pool.load_mut()?;
+    let mut pool_mut = ctx.accounts.pool.load_pool_mut()?;
     let current_total = pool_mut.get_total_amount_minted();
     pool_mut.set_total_amount_minted(
         current_total
-            .checked_add(amount_to_mint)
+            .checked_add(minted_total_increase)
             .ok_or(ErrorCode::MathOverflow)?
     );
+    // 用存入后的储备量同步 ConstantSum 曲线记录的 Σ(vault * weight)
+    let reserves_after = vault_balances
+        .iter()
+        .zip(deposits.iter())
+        .map(|(&balance, &deposit)| balance.checked_add(deposit).ok_or(ErrorCode::MathOverflow.into()))
+        .collect::<Result<Vec<u64>>>()?;
+    pool_mut.sync_constant_sum_value(&reserves_after, now)?;
+    drop(pool_mut);
 
     // 铸造 LP token 给用户
     token::mint_to(
@@ -197,26 +239,37 @@ pub fn add_liquidity<'remaining: 'info, 'info>(
 
     // 转移所有 token 到对应的 vault
     for i in 0..token_count {
-        let user_token_info = &remaining_accounts[i * 2];
-        let vault_info = &remaining_accounts[i * 2 + 1];
+        let user_token_info = &remaining_accounts[i * 3];
+        let vault_info = &remaining_accounts[i * 3 + 1];
+        let mint_info = &remaining_accounts[i * 3 + 2];
         
         // 验证 user_token owner（从 TokenAccount 数据中读取）
-        let user_token_account = Account::<TokenAccount>::try_from_unchecked(user_token_info)?;
+        let user_token_account = InterfaceAccount::<TokenAccount>::try_from(user_token_info)?;
         require!(
             user_token_account.owner == owner_key,
             ErrorCode::InvalidTokenMint
         );
 
-        token::transfer(
+        // 加上转账手续费，保证 vault 实际收到 deposits[i]
+        let transfer_amount = amount_before_transfer_fee(mint_info, deposits[i])?;
+        require!(transfer_amount <= amounts[i], ErrorCode::SlippageExceeded);
+
+        token_interface::transfer_checked(
             CpiContext::new(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
+                token_program_for(
+                    mint_info,
+                    &ctx.accounts.token_program,
+                    &ctx.accounts.token_2022_program,
+                )?,
+                TransferChecked {
                     from: user_token_info.clone(),
+                    mint: mint_info.clone(),
                     to: vault_info.clone(),
                     authority: ctx.accounts.owner.to_account_info(),
                 },
             ),
-            deposits[i],
+            transfer_amount,
+            decimals[i],
         )?;
     }
 
@@ -0,0 +1,198 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, MintTo, Token};
+use anchor_spl::token_2022::Token2022;
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TransferChecked};
+use crate::state::{AnySwapPool, PoolLoader, TOKEN_DEPOSITS_DISABLED};
+use crate::error::ErrorCode;
+use super::swap::load_all_reserves;
+use super::token_utils::{amount_after_transfer_fee, token_program_for};
+
+/// 单边添加流动性操作
+/// 只存入一个 token，按 pool 的不变量铸造 LP token
+#[derive(Accounts)]
+pub struct AddLiquiditySingle<'info> {
+    #[account(mut)]
+    pub pool: AccountLoader<'info, AnySwapPool>,
+
+    /// Pool authority PDA
+    /// CHECK: PDA derived from pool key, used as token account owner
+    #[account(
+        seeds = [b"anyswap_authority", pool.key().as_ref()],
+        bump
+    )]
+    pub pool_authority: AccountInfo<'info>,
+
+    /// Pool mint - LP token
+    #[account(
+        mut,
+        seeds = [b"pool_mint", pool.key().as_ref()],
+        bump
+    )]
+    pub pool_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// 存入 token 的 vault 账户
+    #[account(
+        mut,
+        constraint = vault.owner == pool_authority.key(),
+        constraint = vault.mint == user_token.mint,
+    )]
+    pub vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// 用户的 token 账户（转出代币）
+    #[account(mut, has_one = owner)]
+    pub user_token: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// 存入 token 的 mint 账户（transfer_checked 需要）
+    #[account(constraint = mint.key() == vault.mint)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// 用户的 LP token 账户（接收 LP token）
+    #[account(
+        mut,
+        constraint = user_pool_ata.mint == pool_mint.key(),
+        constraint = user_pool_ata.owner == owner.key()
+    )]
+    pub user_pool_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub owner: Signer<'info>,
+
+    /// 普通 Token 程序（LP mint 和普通 Token 的转账）
+    pub token_program: Program<'info, Token>,
+
+    /// Token-2022 程序（Token-2022 token 的转账）
+    pub token_2022_program: Program<'info, Token2022>,
+}
+
+/// 单边添加流动性
+/// 存入 amount_in 个 token（由 vault 的 mint 决定），铸造的 LP 由 pool 的不变量决定
+///
+/// 等比例部分不收手续费；超出等比例的部分相当于先交换成其他 token 再等比例存入，
+/// 按该 token 的费率收取手续费，避免稀释等比例 LP
+///
+/// Token-2022 转账手续费：按 vault 实际收到的数量计算 LP
+///
+/// RemainingAccounts 结构：
+/// - 按照 pool 中 token 的顺序传入所有 vault 账户（用于计算不变量）
+///
+/// min_lp_out: 最少获得的 LP token 数量（滑点保护）
+pub fn add_liquidity_single<'remaining: 'info, 'info>(
+    ctx: Context<'_, '_, 'remaining, 'info, AddLiquiditySingle<'info>>,
+    amount_in: u64,
+    min_lp_out: u64,
+) -> Result<()> {
+    require!(amount_in > 0, ErrorCode::InsufficientTokenAmount);
+    require!(
+        ctx.accounts.user_token.amount >= amount_in,
+        ErrorCode::InsufficientTokenAmount
+    );
+
+    let pool = ctx.accounts.pool.load_pool()?;
+
+    // 查找 token 索引并验证 vault
+    let mint_key = ctx.accounts.vault.mint;
+    let token_index = pool.find_token_index(&mint_key)
+        .ok_or(ErrorCode::InvalidTokenMint)?;
+    let token_item = pool.get_token(token_index).ok_or(ErrorCode::InvalidTokenIndex)?;
+    require!(
+        *token_item.vault_pubkey() == ctx.accounts.vault.key(),
+        ErrorCode::InvalidTokenMint
+    );
+
+    // 检查 pool 没有暂停，且该 token 允许存入
+    pool.require_not_paused()?;
+    pool.require_token_enabled(token_index, TOKEN_DEPOSITS_DISABLED)?;
+
+    // 读取所有 vault 的余额
+    let reserves = load_all_reserves(
+        &pool,
+        ctx.remaining_accounts,
+        &ctx.accounts.pool_authority.key(),
+    )?;
+
+    // 输入 token 有转账手续费时，只按 vault 实际收到的数量计算
+    let amount_received = amount_after_transfer_fee(&ctx.accounts.mint.to_account_info(), amount_in)?;
+    let now = Clock::get()?.unix_timestamp;
+    let (amount_to_mint, fee_amount) = pool.calculate_single_deposit_lp(
+        token_index,
+        &reserves,
+        amount_received,
+        now,
+    )?;
+
+    require!(amount_to_mint > 0, ErrorCode::InsufficientTokenAmount);
+    require!(
+        amount_to_mint >= min_lp_out,
+        ErrorCode::SlippageExceeded
+    );
+
+    let protocol_fee = pool.calculate_protocol_fee(fee_amount)?;
+
+    drop(pool);
+
+    // 更新 total_amount_minted，累加协议手续费
+    let mut pool_mut = ctx.accounts.pool.load_pool_mut()?;
+    pool_mut.accrue_protocol_fee(token_index, protocol_fee)?;
+    let current_total = pool_mut.get_total_amount_minted();
+    pool_mut.set_total_amount_minted(
+        current_total
+            .checked_add(amount_to_mint)
+            .ok_or(ErrorCode::MathOverflow)?
+    );
+    // 用存入后的储备量同步 ConstantSum 曲线记录的 Σ(vault * weight)（协议手续费不计入储备量）
+    let mut reserves_after = reserves;
+    reserves_after[token_index] = reserves_after[token_index]
+        .checked_add(amount_received - protocol_fee)
+        .ok_or(ErrorCode::MathOverflow)?;
+    pool_mut.sync_constant_sum_value(&reserves_after, now)?;
+    drop(pool_mut);
+
+    // 准备 seeds 用于签名
+    let pool_key = ctx.accounts.pool.key();
+    let bump = ctx.bumps.pool_authority;
+    let seeds = &[
+        b"anyswap_authority",
+        pool_key.as_ref(),
+        &[bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    // 转移 token 到 vault（手续费留在 vault 中）
+    let mint = ctx.accounts.mint.to_account_info();
+    token_interface::transfer_checked(
+        CpiContext::new(
+            token_program_for(&mint, &ctx.accounts.token_program, &ctx.accounts.token_2022_program)?,
+            TransferChecked {
+                from: ctx.accounts.user_token.to_account_info(),
+                mint,
+                to: ctx.accounts.vault.to_account_info(),
+                authority: ctx.accounts.owner.to_account_info(),
+            },
+        ),
+        amount_in,
+        ctx.accounts.mint.decimals,
+    )?;
+
+    // 铸造 LP token 给用户
+    token::mint_to(
+        CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            MintTo {
+                to: ctx.accounts.user_pool_ata.to_account_info(),
+                mint: ctx.accounts.pool_mint.to_account_info(),
+                authority: ctx.accounts.pool_authority.to_account_info(),
+            },
+            signer,
+        ),
+        amount_to_mint,
+    )?;
+
+    msg!(
+        "Single-sided liquidity added: {} {} in, {} LP tokens minted, fee: {}",
+        amount_in,
+        mint_key,
+        amount_to_mint,
+        fee_amount
+    );
+
+    Ok(())
+}
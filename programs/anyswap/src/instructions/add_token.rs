@@ -1,10 +1,14 @@
 use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token::{self, Mint, Token, TokenAccount, Transfer},
+    token_interface::{self, Mint, TokenAccount, TokenInterface, TransferChecked},
 };
-use crate::state::AnySwapPool;
+use crate::state::{AnySwapPool, CurveType, PoolLoader, Role, NORMALIZED_DECIMALS};
 use crate::error::ErrorCode;
+use crate::math::fixed_point;
+use super::account_utils::grow_account;
+use super::mint_index::record_mint_index;
+use super::token_utils::{amount_before_transfer_fee, validate_mint_extensions};
 
 /// 添加 token 到 pool
 #[derive(Accounts)]
@@ -20,11 +24,12 @@ pub struct AddTokenToPool<'info> {
     )]
     pub pool_authority: AccountInfo<'info>,
 
-    /// Token 的 mint 账户
-    pub mint: Account<'info, Mint>,
+    /// Token 的 mint 账户（普通 Token 或 Token-2022）
+    #[account(mint::token_program = token_program)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
 
     /// Token 的 vault 账户（存储该 token 的账户）
-    /// 作为 PDA 由程序自动创建，owner 是 pool_authority
+    /// 作为 PDA 由 mint 所属的 token 程序自动创建，owner 是 pool_authority
     /// 地址：seeds = [b"vault", pool.key(), mint.key()]
     #[account(
         init,
@@ -33,8 +38,18 @@ pub struct AddTokenToPool<'info> {
         bump,
         token::mint = mint,
         token::authority = pool_authority,
+        token::token_program = token_program,
     )]
-    pub vault: Box<Account<'info, TokenAccount>>,
+    pub vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Mint 反向索引（记录包含该 mint 的所有 pool），不存在时自动创建
+    /// CHECK: PDA derived from mint key, created and validated in record_mint_index
+    #[account(
+        mut,
+        seeds = [b"mint_index", mint.key().as_ref()],
+        bump
+    )]
+    pub mint_index: AccountInfo<'info>,
 
     /// Admin 的 token 账户（提供流动性）
     /// 如果 pool 中已有流动性，必须提供新 token 的流动性
@@ -43,18 +58,20 @@ pub struct AddTokenToPool<'info> {
         mut,
         associated_token::mint = mint,
         associated_token::authority = admin,
+        associated_token::token_program = token_program,
     )]
-    pub admin_token: Box<Account<'info, TokenAccount>>,
+    pub admin_token: Box<InterfaceAccount<'info, TokenAccount>>,
 
-    /// Pool 管理员 - 必须签名所有操作
-    /// CHECK: 验证是否为 pool 的管理员
+    /// Pool 管理员或 Token 管理员 - 必须签名
+    /// CHECK: 验证是否为 pool 的管理员或 Token 管理员
     pub admin: Signer<'info>,
 
-    /// 支付创建 vault 账户的费用
+    /// 支付创建 vault 账户和扩展 pool 账户的费用
     #[account(mut)]
     pub payer: Signer<'info>,
 
-    pub token_program: Program<'info, Token>,
+    /// mint 所属的 token 程序（Token 或 Token-2022）
+    pub token_program: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
     pub rent: Sysvar<'info, Rent>,
@@ -62,34 +79,59 @@ pub struct AddTokenToPool<'info> {
 
 /// 添加 token 到 pool
 /// weight: 该 token 的权重，作为不变量保持不变
+/// seed_amount: Weighted 曲线的 pool 已有流动性时，新 token 存入的数量（决定新 token 的初始价格），
+///   其他情况必须为 None
 /// 
-/// 如果 pool 中已有流动性，必须同时提供新 token 的流动性以保持池子平衡
-/// 流动性数量根据恒定乘积和公式计算：vault_new * weight_new = vault_base * weight_base
+/// 如果 pool 中已有流动性，必须同时提供新 token 的流动性以保持池子平衡，数量按 pool 的定价曲线决定：
+/// - ConstantSum：vault_new * weight_new = Σ(vault_i * weight_i)
+/// - Stable：vault_new = D / n，即平衡状态下每个 token 的数量，加入后不改变现有 token 之间的价格
+/// - Weighted：由管理员通过 seed_amount 指定
+///
 /// 流动性会从 admin 的 ATA 账户自动扣除
+///
+/// mint 的 decimals 会记录到 item 中，之后所有计算都使用换算到统一精度的数量
+/// Token-2022 mint 只允许 pool 能安全支持的扩展；有转账手续费时，admin 需要多转入手续费，
+/// 保证 vault 实际收到所需的流动性
+/// pool 账户的容量不足时由 payer 支付租金扩展一个 token 的空间
 pub fn add_token_to_pool<'remaining: 'info, 'info>(
     ctx: Context<'_, '_, 'remaining, 'info, AddTokenToPool<'info>>,
     weight: u64,
+    seed_amount: Option<u64>,
 ) -> Result<()> {
+    // 验证 mint 没有不支持的 Token-2022 扩展
+    validate_mint_extensions(&ctx.accounts.mint.to_account_info())?;
+    
     // 先读取 pool 信息
     let token_count;
     let mint_key;
     {
-        let pool = ctx.accounts.pool.load()?;
+        let pool = ctx.accounts.pool.load_pool()?;
         token_count = pool.get_token_count();
         mint_key = ctx.accounts.mint.key();
         
         // 检查 token 是否已存在
-        if let Some(_) = pool.find_token_index(&mint_key) {
+        if pool.find_token_index(&mint_key).is_some() {
             return Err(ErrorCode::InvalidTokenMint.into());
         }
     }
     
-    let pool = &mut ctx.accounts.pool.load_mut()?;
+    // 容量不足时扩展 pool 账户，为新 token 留出空间
+    let pool_info = ctx.accounts.pool.to_account_info();
+    let required_len = AnySwapPool::space(token_count + 1);
+    if pool_info.data_len() < required_len {
+        grow_account(&pool_info, required_len, &ctx.accounts.payer, &ctx.accounts.system_program)?;
+    }
+    
+    let pool = &mut ctx.accounts.pool.load_pool_mut()?;
     
-    // 验证管理员权限
-    pool.verify_admin(&ctx.accounts.admin.key())?;
+    // 验证权限：管理员或 Token 管理员
+    pool.verify_role(Role::TokenCurator, &ctx.accounts.admin.key())?;
     
     require!(weight > 0, ErrorCode::InvalidTokenCount);
+    let now = Clock::get()?.unix_timestamp;
+    // 添加后所有 token 的储备量（用于同步记录的 Σ(vault * weight)，新 vault 刚创建，只有存入的流动性）
+    let mut reserves = Vec::with_capacity(token_count + 1);
+    let mut new_reserve = 0;
     
     // 如果 pool 中已有 token，必须同时提供新 token 的流动性以保持池子平衡
     if token_count > 0 {
@@ -100,14 +142,8 @@ pub fn add_token_to_pool<'remaining: 'info, 'info>(
             ErrorCode::InvalidTokenCount
         );
         
-        // 读取现有 vault 的余额，累加作为基准（即使余额为0也可以）
-        // base = sum(vault * weight)
-        // 恒定乘积和公式：vault_new * weight_new = base
-        let mut base: u128 = 0;
-        
-        for i in 0..token_count {
-            let vault_info = &remaining_accounts[i];
-            
+        // 读取现有 vault 的余额（即使余额为0也可以）
+        for (i, vault_info) in remaining_accounts.iter().enumerate() {
             // 验证 vault 地址匹配 pool 中的 token
             let token_item = pool.get_token(i).ok_or(ErrorCode::InvalidTokenIndex)?;
             require!(
@@ -116,62 +152,95 @@ pub fn add_token_to_pool<'remaining: 'info, 'info>(
             );
             
             // 读取 vault 余额
-            let vault_account = Account::<TokenAccount>::try_from_unchecked(vault_info)?;
+            let vault_account = InterfaceAccount::<TokenAccount>::try_from(vault_info)?;
             require!(
                 vault_account.owner == ctx.accounts.pool_authority.key(),
                 ErrorCode::InvalidTokenMint
             );
             
-            // 使用 u128 避免溢出
-            let product = (vault_account.amount as u128)
-                .checked_mul(token_item.get_weight() as u128)
-                .ok_or(ErrorCode::MathOverflow)?;
-            base = base
-                .checked_add(product)
-                .ok_or(ErrorCode::MathOverflow)?;
+            // 不包括待领取的协议手续费
+            reserves.push(pool.get_reserve(i, vault_account.amount));
         }
         
-        // 计算需要的流动性：vault_new * weight_new = base
-        // vault_new = base / weight_new
-        let required_liquidity = base
-            .checked_div(weight as u128)
-            .ok_or(ErrorCode::MathOverflow)? as u64;
+        // 计算需要的流动性（所有 vault 为空时不需要）
+        let has_liquidity = reserves.iter().any(|&reserve| reserve > 0);
+        let required_liquidity = if pool.get_curve_type()? == CurveType::Weighted && has_liquidity {
+            let seed_amount = seed_amount.ok_or(ErrorCode::InvalidSeedAmount)?;
+            require!(seed_amount > 0, ErrorCode::InvalidSeedAmount);
+            seed_amount
+        } else {
+            require!(seed_amount.is_none(), ErrorCode::InvalidSeedAmount);
+            // 按定价曲线计算统一精度的数量，再换算回新 token 的精度（向上取整）
+            let normalized_liquidity = pool.calculate_new_token_seed(&reserves, weight, now)?;
+            let required_liquidity = fixed_point::scale_decimals_up(
+                normalized_liquidity,
+                NORMALIZED_DECIMALS,
+                ctx.accounts.mint.decimals,
+            )?;
+            u64::try_from(required_liquidity).map_err(|_| ErrorCode::MathOverflow)?
+        };
         
         // 如果计算出的流动性大于0，检查 admin 的 token 账户是否有足够的余额
         if required_liquidity > 0 {
+            // 加上转账手续费，保证 vault 实际收到 required_liquidity
+            let transfer_amount = amount_before_transfer_fee(
+                &ctx.accounts.mint.to_account_info(),
+                required_liquidity,
+            )?;
             require!(
-                ctx.accounts.admin_token.amount >= required_liquidity,
+                ctx.accounts.admin_token.amount >= transfer_amount,
                 ErrorCode::InsufficientTokenAmount
             );
             
             // 转移 token 到 vault（使用计算出的数量）
-            token::transfer(
+            token_interface::transfer_checked(
                 CpiContext::new(
                     ctx.accounts.token_program.to_account_info(),
-                    Transfer {
+                    TransferChecked {
                         from: ctx.accounts.admin_token.to_account_info(),
+                        mint: ctx.accounts.mint.to_account_info(),
                         to: ctx.accounts.vault.to_account_info(),
                         authority: ctx.accounts.admin.to_account_info(),
                     },
                 ),
-                required_liquidity,
+                transfer_amount,
+                ctx.accounts.mint.decimals,
             )?;
             
+            new_reserve = required_liquidity;
             msg!("Liquidity provided for new token: {} tokens (required: {})", 
-                 required_liquidity, required_liquidity);
+                 transfer_amount, required_liquidity);
         } else {
             msg!("No liquidity required (all vaults are empty)");
         }
     } else {
         // 如果 pool 为空，不需要提供流动性
+        require!(seed_amount.is_none(), ErrorCode::InvalidSeedAmount);
         msg!("Pool is empty, no liquidity required");
     }
     
     // 添加 token（设置 weight）
-    let index = pool.add_token(&mint_key, &ctx.accounts.vault.key(), weight)?;
+    let index = pool.add_token(
+        &mint_key,
+        &ctx.accounts.vault.key(),
+        weight,
+        ctx.accounts.mint.decimals,
+    )?;
+    reserves.push(new_reserve);
+    pool.sync_constant_sum_value(&reserves, now)?;
+    
+    // 在 mint 反向索引中记录该 pool
+    record_mint_index(
+        &ctx.accounts.mint_index,
+        &mint_key,
+        &ctx.accounts.pool.key(),
+        index as u64,
+        &ctx.accounts.payer,
+        &ctx.accounts.system_program,
+    )?;
     
-    msg!("Token added to pool at index: {}, mint: {}, weight: {}, vault_balance: {}", 
-         index, mint_key, weight, ctx.accounts.vault.amount);
+    msg!("Token added to pool at index: {}, mint: {}, weight: {}, decimals: {}, vault_balance: {}", 
+         index, mint_key, weight, ctx.accounts.mint.decimals, ctx.accounts.vault.amount);
     Ok(())
 }
 
@@ -0,0 +1,109 @@
+use anchor_lang::prelude::*;
+use crate::state::{AnySwapPool, PoolLoader, Role};
+
+/// 提议或取消转移管理员
+#[derive(Accounts)]
+pub struct ProposeAdmin<'info> {
+    #[account(mut)]
+    pub pool: AccountLoader<'info, AnySwapPool>,
+
+    /// 当前 Pool 管理员 - 必须签名
+    /// CHECK: 验证是否为 pool 的管理员
+    pub admin: Signer<'info>,
+}
+
+/// 提议转移管理员（第一步）
+/// new_admin: 新管理员，需要由其调用 accept_admin 确认后才生效
+/// 注意：重复提议会覆盖之前的提议，在确认前当前管理员仍然有效
+pub fn propose_admin(ctx: Context<ProposeAdmin>, new_admin: Pubkey) -> Result<()> {
+    let pool = &mut ctx.accounts.pool.load_pool_mut()?;
+    
+    // 验证管理员权限
+    pool.verify_admin(&ctx.accounts.admin.key())?;
+    
+    // 启用时间锁后只能通过 queue_action 提议
+    pool.require_no_timelock()?;
+    
+    pool.pending_admin = new_admin;
+    
+    msg!("Admin transfer proposed: {} -> {}", pool.admin, new_admin);
+    Ok(())
+}
+
+/// 取消转移管理员的提议
+pub fn cancel_admin_proposal(ctx: Context<ProposeAdmin>) -> Result<()> {
+    let pool = &mut ctx.accounts.pool.load_pool_mut()?;
+    
+    // 验证管理员权限
+    pool.verify_admin(&ctx.accounts.admin.key())?;
+    
+    let pending_admin = pool.pending_admin;
+    pool.pending_admin = Pubkey::default();
+    
+    msg!("Admin transfer cancelled: {}", pending_admin);
+    Ok(())
+}
+
+/// 接受管理员转移
+#[derive(Accounts)]
+pub struct AcceptAdmin<'info> {
+    #[account(mut)]
+    pub pool: AccountLoader<'info, AnySwapPool>,
+
+    /// 待接受的新管理员 - 必须签名
+    pub pending_admin: Signer<'info>,
+}
+
+/// 接受管理员转移（第二步），由 propose_admin 指定的新管理员签名
+pub fn accept_admin(ctx: Context<AcceptAdmin>) -> Result<()> {
+    let pool = &mut ctx.accounts.pool.load_pool_mut()?;
+    
+    let new_admin = ctx.accounts.pending_admin.key();
+    pool.verify_pending_admin(&new_admin)?;
+    
+    let old_admin = pool.admin;
+    pool.admin = new_admin;
+    pool.pending_admin = Pubkey::default();
+    
+    msg!("Admin transferred: {} -> {}", old_admin, new_admin);
+    Ok(())
+}
+
+/// 授予或撤销角色
+#[derive(Accounts)]
+pub struct ManageRole<'info> {
+    #[account(mut)]
+    pub pool: AccountLoader<'info, AnySwapPool>,
+
+    /// Pool 管理员 - 必须签名
+    /// CHECK: 验证是否为 pool 的管理员
+    pub admin: Signer<'info>,
+}
+
+/// 授予角色（只有管理员可以调用）
+/// 每个角色只有一个持有者，重复授予会替换之前的持有者
+pub fn grant_role(ctx: Context<ManageRole>, role: Role, holder: Pubkey) -> Result<()> {
+    let pool = &mut ctx.accounts.pool.load_pool_mut()?;
+    
+    // 验证管理员权限
+    pool.verify_admin(&ctx.accounts.admin.key())?;
+    
+    pool.set_role(role, holder);
+    
+    msg!("Role granted: {:?} -> {}", role, holder);
+    Ok(())
+}
+
+/// 撤销角色（只有管理员可以调用），撤销后该角色的操作只能由管理员执行
+pub fn revoke_role(ctx: Context<ManageRole>, role: Role) -> Result<()> {
+    let pool = &mut ctx.accounts.pool.load_pool_mut()?;
+    
+    // 验证管理员权限
+    pool.verify_admin(&ctx.accounts.admin.key())?;
+    
+    let holder = pool.get_role(role);
+    pool.set_role(role, Pubkey::default());
+    
+    msg!("Role revoked: {:?} from {}", role, holder);
+    Ok(())
+}
@@ -0,0 +1,312 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::Token;
+use anchor_spl::token_2022::Token2022;
+use anchor_spl::token_interface::{self, TokenAccount, TransferChecked};
+use crate::state::{AnySwapPool, PoolLoader, CurveType};
+use crate::error::ErrorCode;
+use super::swap::{check_deadline, load_all_reserves, verify_constant_sum_swap};
+use super::token_utils::{amount_before_transfer_fee, token_program_for};
+
+/// 批量交换中的一笔交换
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub struct BatchSwapLeg {
+    /// 输入 token 在 pool 中的索引
+    pub token_in_index: u16,
+    /// 输出 token 在 pool 中的索引
+    pub token_out_index: u16,
+    /// 输入数量（含手续费）
+    pub amount_in: u64,
+    /// 该笔交换的最小输出（滑点保护）
+    pub min_amount_out: u64,
+}
+
+/// 批量交换账户结构
+/// 在同一个 pool 中按顺序执行多笔交换，最后只按净额转账
+#[derive(Accounts)]
+pub struct BatchSwap<'info> {
+    #[account(mut)]
+    pub pool: AccountLoader<'info, AnySwapPool>,
+
+    /// Pool authority PDA - 用于管理所有 vault
+    /// CHECK: PDA derived from pool key, used as token account owner
+    #[account(
+        seeds = [b"anyswap_authority", pool.key().as_ref()],
+        bump
+    )]
+    pub pool_authority: AccountInfo<'info>,
+
+    pub owner: Signer<'info>,
+
+    /// 普通 Token 程序
+    pub token_program: Program<'info, Token>,
+
+    /// Token-2022 程序（pool 中的 token 可以属于任意一个 token 程序）
+    pub token_2022_program: Program<'info, Token2022>,
+}
+
+/// 批量交换（多笔交换合并为一条指令）
+/// 例如：卖出 A 和 B，买入 C、D 和 E
+///
+/// 每笔交换按顺序执行，后面的交换使用前面交换后的储备量
+/// 中间结果不会实际转账：所有交换完成后，每个 token 只按用户的净变化量转账一次
+///
+/// legs 中的数量都是 vault 一侧的数量。Token-2022 转账手续费：用户净支付时额外支付手续费，
+/// 保证 vault 实际收到净额；用户净收到时由用户承担手续费
+///
+/// RemainingAccounts 结构：
+/// - 每三个账户为一组：(user_token_account, vault_account, mint)
+/// - 只需要传入 legs 中用到的 token，按 token 在 pool 中的索引从小到大排列
+/// - Stable 曲线或启用动态失衡手续费时：在上述账户之后，再按 pool 中 token 的顺序传入所有 vault 账户
+///
+/// legs: 交换列表，每笔交换都有自己的 min_amount_out
+/// deadline: 可选的截止时间（unix 时间戳），超过后交易失败
+pub fn batch_swap<'remaining: 'info, 'info>(
+    ctx: Context<'_, '_, 'remaining, 'info, BatchSwap<'info>>,
+    legs: Vec<BatchSwapLeg>,
+    deadline: Option<i64>,
+) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    check_deadline(deadline, now)?;
+    require!(!legs.is_empty(), ErrorCode::InvalidTokenCount);
+
+    let pool = ctx.accounts.pool.load_pool()?;
+    let token_count = pool.get_token_count();
+    let curve_type = pool.get_curve_type()?;
+
+    // 收集 legs 中用到的 token（去重并排序）
+    let mut involved = Vec::with_capacity(legs.len() * 2);
+    for leg in legs.iter() {
+        let token_in_index = leg.token_in_index as usize;
+        let token_out_index = leg.token_out_index as usize;
+        require!(
+            token_in_index < token_count && token_out_index < token_count,
+            ErrorCode::InvalidTokenIndex
+        );
+        require!(token_in_index != token_out_index, ErrorCode::SameTokenSwap);
+        pool.require_swap_enabled(token_in_index, token_out_index)?;
+        involved.push(token_in_index);
+        involved.push(token_out_index);
+    }
+    involved.sort_unstable();
+    involved.dedup();
+
+    // 验证 RemainingAccounts 数量
+    let remaining_accounts = ctx.remaining_accounts;
+    let pair_accounts_len = involved.len() * 3;
+    let requires_all_reserves = pool.requires_all_reserves()?;
+    let expected_len = if requires_all_reserves {
+        pair_accounts_len + token_count
+    } else {
+        pair_accounts_len
+    };
+    require!(
+        remaining_accounts.len() == expected_len,
+        ErrorCode::InvalidTokenCount
+    );
+
+    let pool_authority_key = ctx.accounts.pool_authority.key();
+    let owner_key = ctx.accounts.owner.key();
+
+    // 读取储备量（按 token 索引）
+    // Stable 曲线或启用动态失衡手续费时需要所有 vault 的余额，否则只需要用到的 token
+    let mut reserves = if requires_all_reserves {
+        load_all_reserves(
+            &pool,
+            &remaining_accounts[pair_accounts_len..],
+            &pool_authority_key,
+        )?
+    } else {
+        vec![0u64; token_count]
+    };
+    let mut user_balances = Vec::with_capacity(involved.len());
+    let mut decimals = Vec::with_capacity(involved.len());
+
+    // 验证每一组 (user_token_account, vault_account, mint)
+    for (position, &token_index) in involved.iter().enumerate() {
+        let user_token_info = &remaining_accounts[position * 3];
+        let vault_info = &remaining_accounts[position * 3 + 1];
+        let mint_info = &remaining_accounts[position * 3 + 2];
+        let token_item = pool.get_token(token_index).ok_or(ErrorCode::InvalidTokenIndex)?;
+
+        require!(
+            vault_info.key == token_item.vault_pubkey(),
+            ErrorCode::InvalidTokenMint
+        );
+        require!(
+            mint_info.key == token_item.mint_pubkey(),
+            ErrorCode::InvalidTokenMint
+        );
+        let vault_account = InterfaceAccount::<TokenAccount>::try_from(vault_info)?;
+        require!(
+            vault_account.owner == pool_authority_key,
+            ErrorCode::InvalidTokenMint
+        );
+
+        let user_token_account = InterfaceAccount::<TokenAccount>::try_from(user_token_info)?;
+        require!(
+            user_token_account.owner == owner_key,
+            ErrorCode::InvalidTokenMint
+        );
+        require!(
+            user_token_account.mint == *token_item.mint_pubkey(),
+            ErrorCode::InvalidTokenMint
+        );
+
+        reserves[token_index] = pool.get_reserve(token_index, vault_account.amount);
+        user_balances.push(user_token_account.amount);
+        decimals.push(token_item.get_decimals());
+    }
+
+    let reserves_before = reserves.clone();
+
+    // 按顺序执行每笔交换，只更新内存中的储备量和用户净变化量
+    // net_amounts[position] > 0 表示用户净收到，< 0 表示用户净支付
+    let mut net_amounts = vec![0i128; involved.len()];
+    let mut protocol_fees = vec![0u64; involved.len()];
+    for leg in legs.iter() {
+        let token_in_index = leg.token_in_index as usize;
+        let token_out_index = leg.token_out_index as usize;
+        let reserve_in = reserves[token_in_index];
+        let reserve_out = reserves[token_out_index];
+
+        require!(
+            reserve_in > 0 && reserve_out > 0,
+            ErrorCode::InsufficientLiquidity
+        );
+
+        let quote = pool.quote_swap_exact_in(
+            token_in_index,
+            token_out_index,
+            &reserves,
+            leg.amount_in,
+            now,
+        )?;
+        let amount_in_minus_fees = leg.amount_in - quote.fee_amount;
+        let amount_out = quote.amount_out;
+        let protocol_fee = pool.calculate_protocol_fee(quote.fee_amount)?;
+
+        require!(
+            amount_out >= leg.min_amount_out,
+            ErrorCode::SlippageExceeded
+        );
+        require!(
+            amount_out <= reserve_out,
+            ErrorCode::InsufficientLiquidity
+        );
+
+        if curve_type == CurveType::ConstantSum {
+            verify_constant_sum_swap(
+                &pool,
+                token_in_index,
+                amount_in_minus_fees,
+                token_out_index,
+                amount_out,
+                now,
+            )?;
+        }
+
+        // 协议手续费不计入储备量
+        reserves[token_in_index] = reserve_in
+            .checked_add(leg.amount_in - protocol_fee)
+            .ok_or(ErrorCode::MathOverflow)?;
+        reserves[token_out_index] = reserve_out - amount_out;
+
+        let in_position = involved.binary_search(&token_in_index)
+            .map_err(|_| ErrorCode::InvalidTokenIndex)?;
+        let out_position = involved.binary_search(&token_out_index)
+            .map_err(|_| ErrorCode::InvalidTokenIndex)?;
+        net_amounts[in_position] -= leg.amount_in as i128;
+        net_amounts[out_position] += amount_out as i128;
+        protocol_fees[in_position] = protocol_fees[in_position]
+            .checked_add(protocol_fee)
+            .ok_or(ErrorCode::MathOverflow)?;
+    }
+
+    drop(pool);
+
+    // 累加协议手续费，更新 ConstantSum 曲线记录的 Σ(vault * weight)
+    let mut pool_mut = ctx.accounts.pool.load_pool_mut()?;
+    for (position, &token_index) in involved.iter().enumerate() {
+        if protocol_fees[position] > 0 {
+            pool_mut.accrue_protocol_fee(token_index, protocol_fees[position])?;
+        }
+        pool_mut.update_constant_sum_value(
+            token_index,
+            reserves_before[token_index],
+            reserves[token_index],
+            now,
+        )?;
+    }
+    drop(pool_mut);
+
+    // 准备 seeds 用于签名
+    let pool_key = ctx.accounts.pool.key();
+    let bump = ctx.bumps.pool_authority;
+    let seeds = &[
+        b"anyswap_authority",
+        pool_key.as_ref(),
+        &[bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    // 按净额转账
+    for (position, &net_amount) in net_amounts.iter().enumerate() {
+        let user_token_info = &remaining_accounts[position * 3];
+        let vault_info = &remaining_accounts[position * 3 + 1];
+        let mint_info = &remaining_accounts[position * 3 + 2];
+
+        if net_amount > 0 {
+            // 用户净收到：vault -> user
+            token_interface::transfer_checked(
+                CpiContext::new_with_signer(
+                    token_program_for(
+                        mint_info,
+                        &ctx.accounts.token_program,
+                        &ctx.accounts.token_2022_program,
+                    )?,
+                    TransferChecked {
+                        from: vault_info.clone(),
+                        mint: mint_info.clone(),
+                        to: user_token_info.clone(),
+                        authority: ctx.accounts.pool_authority.to_account_info(),
+                    },
+                    signer,
+                ),
+                net_amount as u64,
+                decimals[position],
+            )?;
+        } else if net_amount < 0 {
+            // 用户净支付：user -> vault（加上转账手续费，保证 vault 实际收到净额）
+            let amount = amount_before_transfer_fee(mint_info, net_amount.unsigned_abs() as u64)?;
+            require!(
+                user_balances[position] >= amount,
+                ErrorCode::InsufficientTokenAmount
+            );
+            token_interface::transfer_checked(
+                CpiContext::new(
+                    token_program_for(
+                        mint_info,
+                        &ctx.accounts.token_program,
+                        &ctx.accounts.token_2022_program,
+                    )?,
+                    TransferChecked {
+                        from: user_token_info.clone(),
+                        mint: mint_info.clone(),
+                        to: vault_info.clone(),
+                        authority: ctx.accounts.owner.to_account_info(),
+                    },
+                ),
+                amount,
+                decimals[position],
+            )?;
+        }
+    }
+
+    msg!(
+        "AnySwap batch: {} legs executed across {} tokens",
+        legs.len(),
+        involved.len()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,135 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::Token;
+use anchor_spl::token_2022::Token2022;
+use anchor_spl::token_interface::{self, TokenAccount, TransferChecked};
+use crate::state::{AnySwapPool, PoolLoader};
+use crate::error::ErrorCode;
+use super::token_utils::token_program_for;
+
+/// 领取协议手续费
+#[derive(Accounts)]
+pub struct CollectProtocolFees<'info> {
+    #[account(mut)]
+    pub pool: AccountLoader<'info, AnySwapPool>,
+
+    /// Pool authority PDA - 用于从 vault 转出
+    /// CHECK: PDA derived from pool key, used as token account owner
+    #[account(
+        seeds = [b"anyswap_authority", pool.key().as_ref()],
+        bump
+    )]
+    pub pool_authority: AccountInfo<'info>,
+
+    /// 协议金库 - 必须签名领取操作
+    pub treasury: Signer<'info>,
+
+    /// 普通 Token 程序
+    pub token_program: Program<'info, Token>,
+
+    /// Token-2022 程序（pool 中的 token 可以属于任意一个 token 程序）
+    pub token_2022_program: Program<'info, Token2022>,
+}
+
+/// 领取协议手续费
+/// 把每个 vault 中累计的协议手续费转给协议金库指定的 token 账户，并清零累计值
+///
+/// RemainingAccounts 结构：
+/// - 每三个账户为一组：(vault_account, destination_token_account, mint)
+/// - 只需要传入要领取的 token，顺序任意
+/// - Token-2022 转账手续费由接收方承担
+pub fn collect_protocol_fees<'remaining: 'info, 'info>(
+    ctx: Context<'_, '_, 'remaining, 'info, CollectProtocolFees<'info>>,
+) -> Result<()> {
+    let remaining_accounts = ctx.remaining_accounts;
+    require!(
+        !remaining_accounts.is_empty() && remaining_accounts.len() % 3 == 0,
+        ErrorCode::InvalidTokenCount
+    );
+
+    // 准备 seeds 用于签名
+    let pool_key = ctx.accounts.pool.key();
+    let pool_authority_key = ctx.accounts.pool_authority.key();
+    let bump = ctx.bumps.pool_authority;
+    let seeds = &[
+        b"anyswap_authority",
+        pool_key.as_ref(),
+        &[bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    let mut total_collected: u64 = 0;
+    for group in remaining_accounts.chunks(3) {
+        let vault_info = &group[0];
+        let destination_info = &group[1];
+        let mint_info = &group[2];
+
+        let mut pool = ctx.accounts.pool.load_pool_mut()?;
+        pool.verify_treasury(&ctx.accounts.treasury.key())?;
+
+        // 验证 vault 属于 pool
+        let vault_account = InterfaceAccount::<TokenAccount>::try_from(vault_info)?;
+        require!(
+            vault_account.owner == pool_authority_key,
+            ErrorCode::InvalidTokenMint
+        );
+        let token_index = pool.find_token_index(&vault_account.mint)
+            .ok_or(ErrorCode::InvalidTokenMint)?;
+        let token_item = pool.get_token_mut(token_index).ok_or(ErrorCode::InvalidTokenIndex)?;
+        require!(
+            vault_info.key == token_item.vault_pubkey(),
+            ErrorCode::InvalidTokenMint
+        );
+        require!(
+            mint_info.key == token_item.mint_pubkey(),
+            ErrorCode::InvalidTokenMint
+        );
+        let decimals = token_item.get_decimals();
+
+        // 验证接收账户的 mint
+        let destination_account = InterfaceAccount::<TokenAccount>::try_from(destination_info)?;
+        require!(
+            destination_account.mint == vault_account.mint,
+            ErrorCode::InvalidTokenMint
+        );
+
+        let amount = token_item.get_protocol_fees();
+        token_item.set_protocol_fees(0);
+        drop(pool);
+
+        if amount == 0 {
+            continue;
+        }
+
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                token_program_for(
+                    mint_info,
+                    &ctx.accounts.token_program,
+                    &ctx.accounts.token_2022_program,
+                )?,
+                TransferChecked {
+                    from: vault_info.clone(),
+                    mint: mint_info.clone(),
+                    to: destination_info.clone(),
+                    authority: ctx.accounts.pool_authority.to_account_info(),
+                },
+                signer,
+            ),
+            amount,
+            decimals,
+        )?;
+
+        msg!("Protocol fees collected: {} {}", amount, vault_account.mint);
+        total_collected = total_collected
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+    }
+
+    msg!(
+        "Protocol fees collected from {} vaults, total: {}",
+        remaining_accounts.len() / 3,
+        total_collected
+    );
+
+    Ok(())
+}
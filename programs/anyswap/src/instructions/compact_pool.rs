@@ -0,0 +1,51 @@
+use anchor_lang::prelude::*;
+use crate::state::{AnySwapPool, PoolLoader, Role};
+
+/// 缩小 pool 账户
+#[derive(Accounts)]
+pub struct CompactPool<'info> {
+    #[account(mut)]
+    pub pool: AccountLoader<'info, AnySwapPool>,
+
+    /// Pool 管理员或 Token 管理员 - 必须签名
+    /// CHECK: 验证是否为 pool 的管理员或 Token 管理员
+    pub admin: Signer<'info>,
+
+    /// 接收释放的租金
+    /// CHECK: 只接收 lamports
+    #[account(mut)]
+    pub recipient: AccountInfo<'info>,
+}
+
+/// 把 pool 账户缩小到刚好容纳现有的 token，多余的租金转给 recipient
+/// 用于在移除 token 后回收租金（旧的固定大小 pool 需要先调用 migrate_pool）
+/// 之后添加 token 时会按需重新扩展账户
+pub fn compact_pool(ctx: Context<CompactPool>) -> Result<()> {
+    let token_count = {
+        let pool = ctx.accounts.pool.load_pool()?;
+        
+        // 验证权限：管理员或 Token 管理员
+        pool.verify_role(Role::TokenCurator, &ctx.accounts.admin.key())?;
+        
+        pool.get_token_count()
+    };
+    
+    let pool_info = ctx.accounts.pool.to_account_info();
+    let old_len = pool_info.data_len();
+    let new_len = AnySwapPool::space(token_count);
+    if old_len > new_len {
+        pool_info.resize(new_len)?;
+    }
+    
+    // 转出超过租金豁免所需的 lamports
+    let rent_exempt = Rent::get()?.minimum_balance(pool_info.data_len());
+    let amount = pool_info.lamports().saturating_sub(rent_exempt);
+    if amount > 0 {
+        **pool_info.try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.recipient.try_borrow_mut_lamports()? += amount;
+    }
+    
+    msg!("Pool compacted: pool: {}, size: {} -> {}, refunded: {} lamports",
+         pool_info.key(), old_len, pool_info.data_len(), amount);
+    Ok(())
+}
@@ -1,7 +1,10 @@
 use anchor_lang::prelude::*;
+use anchor_lang::system_program::{self, Transfer};
 use anchor_spl::token::{Mint, Token};
-use crate::state::AnySwapPool;
+use crate::state::{AnySwapPool, CurveType, FactoryConfig, FeePolicy, PoolRegistry, NORMALIZED_DECIMALS};
 use crate::error::ErrorCode;
+use crate::math::stable;
+use super::account_utils::grow_account;
 
 /// 创建 Pool（PDA）
 /// 每个 Pool 可以包含多个 token（最多 1024 个）
@@ -12,6 +15,7 @@ pub struct CreatePool<'info> {
     /// CHECK: 用于区分不同的 pool
     pub pool_creator: AccountInfo<'info>,
 
+    /// Pool 账户 - 由调用者预先创建，只需要分配头部空间 AnySwapPool::space(0)，添加 token 时自动扩展
     #[account(zero)]
     pub pool: AccountLoader<'info, AnySwapPool>,
 
@@ -23,13 +27,29 @@ pub struct CreatePool<'info> {
     )]
     pub pool_authority: AccountInfo<'info>,
 
+    /// 协议全局配置 - 检查费率范围和全局开关，并收取创建费
+    #[account(
+        mut,
+        seeds = [b"factory_config"],
+        bump = factory_config.bump
+    )]
+    pub factory_config: Account<'info, FactoryConfig>,
+
+    /// Pool 注册表 - 新 pool 追加到末尾
+    #[account(
+        mut,
+        seeds = [b"pool_registry"],
+        bump
+    )]
+    pub pool_registry: AccountLoader<'info, PoolRegistry>,
+
     /// Pool mint - LP token，用于跟踪流动性提供者的份额
     #[account(
         init,
         payer = payer,
         seeds = [b"pool_mint", pool.key().as_ref()],
         bump,
-        mint::decimals = 9,
+        mint::decimals = NORMALIZED_DECIMALS,
         mint::authority = pool_authority
     )]
     pub pool_mint: Box<Account<'info, Mint>>,
@@ -50,32 +70,141 @@ pub struct CreatePool<'info> {
 /// fee_numerator: 手续费分子
 /// fee_denominator: 手续费分母
 /// 例如：fee_numerator=3, fee_denominator=1000 表示 0.3% 手续费
+/// curve_type: 定价曲线（ConstantSum、Weighted 或 Stable）
+/// amp: StableSwap 的放大系数 A，仅 Stable 曲线使用，其他曲线忽略
 pub fn create_pool(
     ctx: Context<CreatePool>,
     fee_numerator: u64,
     fee_denominator: u64,
+    curve_type: CurveType,
+    amp: u64,
 ) -> Result<()> {
+    let amp = initialize_pool(
+        &ctx.accounts.pool,
+        &ctx.accounts.factory_config,
+        &ctx.accounts.payer,
+        &ctx.accounts.system_program,
+        &ctx.accounts.admin.key(),
+        fee_numerator,
+        fee_denominator,
+        curve_type,
+        amp,
+    )?;
+    let pool_index = register_pool(
+        &ctx.accounts.pool_registry,
+        &ctx.accounts.pool.key(),
+        &ctx.accounts.payer,
+        &ctx.accounts.system_program,
+    )?;
+    
+    msg!("AnySwap Pool created: pool_creator: {}, pool: {}, pool_mint: {}, admin: {}, fee: {}/{}, curve: {:?}, amp: {}, index: {}", 
+         ctx.accounts.pool_creator.key(),
+         ctx.accounts.pool.key(),
+         ctx.accounts.pool_mint.key(),
+         ctx.accounts.admin.key(),
+         fee_numerator,
+         fee_denominator,
+         curve_type,
+         amp,
+         pool_index);
+    Ok(())
+}
+
+/// 检查参数、收取创建费并初始化 pool 账户（两种创建方式共用），返回实际使用的放大系数
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn initialize_pool<'info>(
+    pool_loader: &AccountLoader<'info, AnySwapPool>,
+    factory_config: &Account<'info, FactoryConfig>,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    admin: &Pubkey,
+    fee_numerator: u64,
+    fee_denominator: u64,
+    curve_type: CurveType,
+    amp: u64,
+) -> Result<u64> {
     require!(fee_denominator > 0, ErrorCode::MathOverflow);
     require!(fee_numerator <= fee_denominator, ErrorCode::MathOverflow);
     
-    let pool = &mut ctx.accounts.pool.load_init()?;
+    // 检查全局开关和费率范围
+    factory_config.require_not_paused()?;
+    factory_config.verify_fee(fee_numerator, fee_denominator)?;
+    
+    // 收取创建费，存入配置账户
+    if factory_config.creation_fee > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                system_program.to_account_info(),
+                Transfer {
+                    from: payer.to_account_info(),
+                    to: factory_config.to_account_info(),
+                },
+            ),
+            factory_config.creation_fee,
+        )?;
+    }
+    
+    let amp = if curve_type == CurveType::Stable {
+        require!(
+            (stable::MIN_AMP..=stable::MAX_AMP).contains(&amp),
+            ErrorCode::InvalidAmplification
+        );
+        amp
+    } else {
+        0
+    };
+    
+    let pool = &mut pool_loader.load_init()?;
     pool.token_count = 0;
-    pool.padding = [0u8; 6];
-    pool.admin = ctx.accounts.admin.key();
+    pool.curve_type = curve_type.to_u8();
+    pool.fee_policy = FeePolicy::TokenIn.to_u8();
+    pool.paused = 0;
+    pool.padding = [0u8; 3];
+    pool.admin = *admin;
+    pool.pending_admin = Pubkey::default();
     pool.total_amount_minted = 0;
     pool.fee_numerator = fee_numerator;
     pool.fee_denominator = fee_denominator;
+    // 放大系数按 AMP_PRECISION 精度记录
+    pool.initial_amp = amp * stable::AMP_PRECISION as u64;
+    pool.target_amp = pool.initial_amp;
+    pool.amp_start_time = 0;
+    pool.amp_end_time = 0;
+    // 协议手续费比例和协议金库使用全局配置的默认值
+    pool.protocol_fee_share = factory_config.default_protocol_fee_share;
+    pool.treasury = factory_config.treasury;
+    // 默认守护者为 admin，其他角色未授予（只有 admin 可以操作）
+    pool.guardian = *admin;
+    pool.fee_manager = Pubkey::default();
+    pool.weight_manager = Pubkey::default();
+    pool.token_curator = Pubkey::default();
+    // 默认时间锁延迟为 0，由管理员通过 set_timelock_delay 设置
+    pool.timelock_delay = 0;
+    pool.timelock_nonce = 0;
     
     // 初始化所有 token items 为零值（zero_copy 会自动处理）
     // 不需要显式初始化，因为 zero_copy 会使用未初始化的内存
     
-    msg!("AnySwap Pool created: pool_creator: {}, pool: {}, pool_mint: {}, admin: {}, fee: {}/{}", 
-         ctx.accounts.pool_creator.key(),
-         ctx.accounts.pool.key(),
-         ctx.accounts.pool_mint.key(),
-         ctx.accounts.admin.key(),
-         fee_numerator,
-         fee_denominator);
-    Ok(())
+    Ok(amp)
 }
 
+/// 把 pool 追加到注册表末尾（扩展账户空间并补足租金），返回 pool 在注册表中的索引
+pub(crate) fn register_pool<'info>(
+    pool_registry: &AccountLoader<'info, PoolRegistry>,
+    pool: &Pubkey,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+) -> Result<u64> {
+    let pool_index = pool_registry.load()?.pool_count;
+    let registry_info = pool_registry.to_account_info();
+    let offset = PoolRegistry::space(pool_index as usize);
+    let new_len = PoolRegistry::space(pool_index as usize + 1);
+    
+    grow_account(&registry_info, new_len, payer, system_program)?;
+    registry_info.try_borrow_mut_data()?[offset..new_len].copy_from_slice(pool.as_ref());
+    
+    pool_registry.load_mut()?.pool_count = pool_index
+        .checked_add(1)
+        .ok_or(ErrorCode::MathOverflow)?;
+    Ok(pool_index)
+}
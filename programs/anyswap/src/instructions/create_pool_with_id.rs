@@ -0,0 +1,109 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Mint, Token};
+use crate::state::{AnySwapPool, CurveType, FactoryConfig, PoolRegistry, NORMALIZED_DECIMALS};
+use super::create_pool::{initialize_pool, register_pool};
+
+/// 创建 PDA pool
+#[derive(Accounts)]
+#[instruction(pool_id: u64)]
+pub struct CreatePoolWithId<'info> {
+    /// Pool creator - 必须签名
+    pub pool_creator: Signer<'info>,
+
+    /// Pool PDA（[b"pool", pool_creator, pool_id]），创建时只分配头部，添加 token 时再扩展
+    #[account(
+        init,
+        payer = payer,
+        space = AnySwapPool::space(0),
+        seeds = [b"pool", pool_creator.key().as_ref(), &pool_id.to_le_bytes()],
+        bump
+    )]
+    pub pool: AccountLoader<'info, AnySwapPool>,
+
+    /// Pool authority PDA - 用于管理该 pool 的所有 vault
+    /// CHECK: 用于管理该 pool 的所有 vault
+    #[account(
+        seeds = [b"anyswap_authority", pool.key().as_ref()],
+        bump
+    )]
+    pub pool_authority: AccountInfo<'info>,
+
+    /// 协议全局配置 - 检查费率范围和全局开关，并收取创建费
+    #[account(
+        mut,
+        seeds = [b"factory_config"],
+        bump = factory_config.bump
+    )]
+    pub factory_config: Account<'info, FactoryConfig>,
+
+    /// Pool 注册表 - 新 pool 追加到末尾
+    #[account(
+        mut,
+        seeds = [b"pool_registry"],
+        bump
+    )]
+    pub pool_registry: AccountLoader<'info, PoolRegistry>,
+
+    /// Pool mint - LP token，用于跟踪流动性提供者的份额
+    #[account(
+        init,
+        payer = payer,
+        seeds = [b"pool_mint", pool.key().as_ref()],
+        bump,
+        mint::decimals = NORMALIZED_DECIMALS,
+        mint::authority = pool_authority
+    )]
+    pub pool_mint: Box<Account<'info, Mint>>,
+
+    /// Pool 管理员 - 用于所有操作的权限控制
+    pub admin: Signer<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+/// 创建 PDA pool（地址由 pool_creator 和 pool_id 确定，可以直接推导）
+/// 参数与 create_pool 相同
+pub fn create_pool_with_id(
+    ctx: Context<CreatePoolWithId>,
+    pool_id: u64,
+    fee_numerator: u64,
+    fee_denominator: u64,
+    curve_type: CurveType,
+    amp: u64,
+) -> Result<()> {
+    let amp = initialize_pool(
+        &ctx.accounts.pool,
+        &ctx.accounts.factory_config,
+        &ctx.accounts.payer,
+        &ctx.accounts.system_program,
+        &ctx.accounts.admin.key(),
+        fee_numerator,
+        fee_denominator,
+        curve_type,
+        amp,
+    )?;
+    let pool_index = register_pool(
+        &ctx.accounts.pool_registry,
+        &ctx.accounts.pool.key(),
+        &ctx.accounts.payer,
+        &ctx.accounts.system_program,
+    )?;
+    
+    msg!("AnySwap Pool created: pool_creator: {}, pool_id: {}, pool: {}, pool_mint: {}, admin: {}, fee: {}/{}, curve: {:?}, amp: {}, index: {}", 
+         ctx.accounts.pool_creator.key(),
+         pool_id,
+         ctx.accounts.pool.key(),
+         ctx.accounts.pool_mint.key(),
+         ctx.accounts.admin.key(),
+         fee_numerator,
+         fee_denominator,
+         curve_type,
+         amp,
+         pool_index);
+    Ok(())
+}
@@ -0,0 +1,119 @@
+use anchor_lang::prelude::*;
+use crate::state::{FactoryConfig, FactoryConfigParams, PoolRegistry};
+use crate::error::ErrorCode;
+
+/// 初始化协议全局配置
+#[derive(Accounts)]
+pub struct InitializeFactoryConfig<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = FactoryConfig::space(),
+        seeds = [b"factory_config"],
+        bump
+    )]
+    pub factory_config: Account<'info, FactoryConfig>,
+
+    /// Pool 注册表 - 与全局配置一起创建，创建 pool 时追加
+    #[account(
+        init,
+        payer = authority,
+        space = PoolRegistry::space(0),
+        seeds = [b"pool_registry"],
+        bump
+    )]
+    pub pool_registry: AccountLoader<'info, PoolRegistry>,
+
+    /// 程序的升级权限 - 只有它可以初始化全局配置
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(constraint = anyswap_program.programdata_address()? == Some(program_data.key()))]
+    pub anyswap_program: Program<'info, crate::program::Anyswap>,
+
+    #[account(constraint = program_data.upgrade_authority_address == Some(authority.key()) @ ErrorCode::InvalidFactoryAuthority)]
+    pub program_data: Account<'info, ProgramData>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// 初始化协议全局配置和 pool 注册表（只能由程序的升级权限调用一次）
+pub fn initialize_factory_config(
+    ctx: Context<InitializeFactoryConfig>,
+    params: FactoryConfigParams,
+) -> Result<()> {
+    let factory_config = &mut ctx.accounts.factory_config;
+    factory_config.set_params(&params)?;
+    factory_config.bump = ctx.bumps.factory_config;
+    
+    let pool_registry = &mut ctx.accounts.pool_registry.load_init()?;
+    pool_registry.pool_count = 0;
+    
+    msg!("Factory config initialized: {:?}", params);
+    Ok(())
+}
+
+/// 修改协议全局配置
+#[derive(Accounts)]
+pub struct UpdateFactoryConfig<'info> {
+    #[account(
+        mut,
+        seeds = [b"factory_config"],
+        bump = factory_config.bump
+    )]
+    pub factory_config: Account<'info, FactoryConfig>,
+
+    /// 协议管理员 - 必须签名
+    pub protocol_authority: Signer<'info>,
+}
+
+/// 修改协议全局配置（包括转移协议管理员、全局开关）
+/// 注意：修改费率范围不影响已有 pool 的费率，只在之后修改费率时检查
+pub fn update_factory_config(
+    ctx: Context<UpdateFactoryConfig>,
+    params: FactoryConfigParams,
+) -> Result<()> {
+    let factory_config = &mut ctx.accounts.factory_config;
+    factory_config.verify_protocol_authority(&ctx.accounts.protocol_authority.key())?;
+    factory_config.set_params(&params)?;
+    
+    msg!("Factory config updated: {:?}", params);
+    Ok(())
+}
+
+/// 领取创建费
+#[derive(Accounts)]
+pub struct CollectCreationFees<'info> {
+    #[account(
+        mut,
+        seeds = [b"factory_config"],
+        bump = factory_config.bump
+    )]
+    pub factory_config: Account<'info, FactoryConfig>,
+
+    /// 协议管理员 - 必须签名
+    pub protocol_authority: Signer<'info>,
+
+    /// 接收创建费的账户
+    /// CHECK: 只接收 lamports
+    #[account(mut)]
+    pub destination: AccountInfo<'info>,
+}
+
+/// 领取配置账户中累计的创建费（保留租金豁免所需的 lamports）
+pub fn collect_creation_fees(ctx: Context<CollectCreationFees>) -> Result<()> {
+    let factory_config = &ctx.accounts.factory_config;
+    factory_config.verify_protocol_authority(&ctx.accounts.protocol_authority.key())?;
+    
+    let config_info = factory_config.to_account_info();
+    let rent_exempt = Rent::get()?.minimum_balance(config_info.data_len());
+    let amount = config_info.lamports().saturating_sub(rent_exempt);
+    
+    if amount > 0 {
+        **config_info.try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.destination.try_borrow_mut_lamports()? += amount;
+    }
+    
+    msg!("Creation fees collected: {} lamports", amount);
+    Ok(())
+}
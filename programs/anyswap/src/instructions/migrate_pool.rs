@@ -0,0 +1,158 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::Mint;
+use bytemuck::Zeroable;
+use crate::state::{
+    AnySwapPool, CurveType, FactoryConfig, FeePolicy, LegacyAnySwapPool, PoolLoader, PoolRegistry,
+    LEGACY_MAX_TOKENS,
+};
+use crate::error::ErrorCode;
+use super::account_utils::grow_account;
+use super::create_pool::register_pool;
+use super::mint_index::record_mint_index;
+
+/// 迁移旧的固定大小 pool
+#[derive(Accounts)]
+pub struct MigratePool<'info> {
+    /// 旧布局的 pool 账户（discriminator 与当前布局相同，在指令中按账户大小检查布局）
+    #[account(mut)]
+    pub pool: AccountLoader<'info, AnySwapPool>,
+
+    /// Pool 管理员（旧布局中记录的 admin）- 必须签名
+    /// CHECK: 验证是否为 pool 的管理员
+    pub admin: Signer<'info>,
+
+    /// 协议全局配置 - 迁移后的协议手续费比例和协议金库使用默认值
+    #[account(
+        seeds = [b"factory_config"],
+        bump = factory_config.bump
+    )]
+    pub factory_config: Account<'info, FactoryConfig>,
+
+    /// Pool 注册表 - 旧 pool 创建时没有注册，迁移时追加到末尾
+    #[account(
+        mut,
+        seeds = [b"pool_registry"],
+        bump
+    )]
+    pub pool_registry: AccountLoader<'info, PoolRegistry>,
+
+    /// 账户需要扩展时支付租金，缩小后接收多余的租金
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// 把旧的固定大小 pool（64 字节头部 + 1024 个 72 字节的 item）改写为当前布局
+/// （AnySwapPool 头部 + token_count 个 AnySwapItem），账户大小变为 AnySwapPool::space(token_count)
+/// 旧 pool 使用恒定乘积和曲线，迁移后保持 ConstantSum，并记录每个 token 的 decimals
+/// 新增的字段使用与 create_pool 相同的默认值，同时建立按 mint 排序的查找表
+/// 旧 pool 没有记录在注册表和 mint 反向索引中，迁移时补上
+///
+/// RemainingAccounts 结构：
+/// - 按照 pool 中 token 的顺序，每两个账户为一组：(mint, mint_index)
+/// - mint 用于读取 decimals，mint_index 为 [b"mint_index", mint] PDA（可写，不存在时自动创建）
+///
+/// 注意：每条指令最多扩展 10KB，token 数量超过 653 个的旧 pool 不能迁移
+pub fn migrate_pool<'remaining: 'info, 'info>(
+    ctx: Context<'_, '_, 'remaining, 'info, MigratePool<'info>>,
+) -> Result<()> {
+    let pool_info = ctx.accounts.pool.to_account_info();
+    let old_len = pool_info.data_len();
+    require!(
+        LegacyAnySwapPool::is_legacy_layout(old_len),
+        ErrorCode::PoolAlreadyMigrated
+    );
+
+    let legacy = LegacyAnySwapPool::read(&pool_info.try_borrow_data()?);
+
+    // 验证管理员权限
+    require_keys_eq!(ctx.accounts.admin.key(), legacy.admin, ErrorCode::InvalidAdmin);
+
+    let token_count = legacy.token_count as usize;
+    require!(token_count <= LEGACY_MAX_TOKENS, ErrorCode::InvalidTokenCount);
+    require!(
+        ctx.remaining_accounts.len() == token_count * 2,
+        ErrorCode::InvalidTokenCount
+    );
+
+    // 读取每个 token 的 decimals（mint 账户必须与旧 item 一致）
+    let mut decimals = Vec::with_capacity(token_count);
+    {
+        let data = pool_info.try_borrow_data()?;
+        for (i, accounts) in ctx.remaining_accounts.chunks(2).enumerate() {
+            let mint_info = &accounts[0];
+            let legacy_item = LegacyAnySwapPool::read_item(&data, i);
+            require_keys_eq!(*mint_info.key, legacy_item.mint_account, ErrorCode::InvalidTokenMint);
+            let mint = InterfaceAccount::<Mint>::try_from(mint_info)?;
+            decimals.push(mint.decimals);
+        }
+    }
+
+    // 新布局更大时先扩展账户，改写完成后再缩小
+    let new_len = AnySwapPool::space(token_count);
+    if new_len > old_len {
+        grow_account(&pool_info, new_len, &ctx.accounts.payer, &ctx.accounts.system_program)?;
+    }
+
+    {
+        let mut data = pool_info.try_borrow_mut_data()?;
+        LegacyAnySwapPool::migrate_items(&mut data, &decimals);
+
+        // 最后写入头部（覆盖的旧 item 都已经改写过）
+        let factory_config = &ctx.accounts.factory_config;
+        let mut pool = AnySwapPool::zeroed();
+        pool.token_count = legacy.token_count;
+        pool.curve_type = CurveType::ConstantSum.to_u8();
+        pool.fee_policy = FeePolicy::TokenIn.to_u8();
+        pool.admin = legacy.admin;
+        pool.total_amount_minted = legacy.total_amount_minted;
+        pool.fee_numerator = legacy.fee_numerator;
+        pool.fee_denominator = legacy.fee_denominator;
+        // 协议手续费比例和协议金库使用全局配置的默认值
+        pool.protocol_fee_share = factory_config.default_protocol_fee_share;
+        pool.treasury = factory_config.treasury;
+        // 默认守护者为 admin，其他角色未授予
+        pool.guardian = legacy.admin;
+        // 没有读取 vault，记录的 Σ(vault * weight) 在下一次读取所有 vault 的操作中同步
+        pool.set_constant_sum_value(None);
+        data[8..AnySwapPool::space(0)].copy_from_slice(bytemuck::bytes_of(&pool));
+    }
+
+    if new_len < old_len {
+        pool_info.resize(new_len)?;
+    }
+
+    // 建立按 mint 排序的查找表
+    ctx.accounts.pool.load_pool_mut()?.build_sorted_lookup();
+
+    // 追加到 pool 注册表，并在每个 token 的 mint 反向索引中记录该 pool
+    let pool_index = register_pool(
+        &ctx.accounts.pool_registry,
+        &pool_info.key(),
+        &ctx.accounts.payer,
+        &ctx.accounts.system_program,
+    )?;
+    for (i, accounts) in ctx.remaining_accounts.chunks(2).enumerate() {
+        record_mint_index(
+            &accounts[1],
+            accounts[0].key,
+            &pool_info.key(),
+            i as u64,
+            &ctx.accounts.payer,
+            &ctx.accounts.system_program,
+        )?;
+    }
+
+    // 转出超过租金豁免所需的 lamports
+    let rent_exempt = Rent::get()?.minimum_balance(new_len);
+    let refund = pool_info.lamports().saturating_sub(rent_exempt);
+    if refund > 0 {
+        **pool_info.try_borrow_mut_lamports()? -= refund;
+        **ctx.accounts.payer.to_account_info().try_borrow_mut_lamports()? += refund;
+    }
+
+    msg!("Pool migrated: pool: {}, tokens: {}, size: {} -> {}, refunded: {} lamports, index: {}",
+         pool_info.key(), token_count, old_len, new_len, refund, pool_index);
+    Ok(())
+}
@@ -0,0 +1,120 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+use anchor_lang::Discriminator;
+use crate::state::{MintIndex, PoolRefMut};
+use crate::error::ErrorCode;
+use super::account_utils::{create_pda_account, grow_account};
+
+/// 验证 mint 索引账户的地址，返回 PDA bump
+fn verify_mint_index_address(mint_index: &AccountInfo, mint: &Pubkey) -> Result<u8> {
+    let (expected, bump) = Pubkey::find_program_address(
+        &[b"mint_index", mint.as_ref()],
+        &crate::ID,
+    );
+    require_keys_eq!(*mint_index.key, expected, ErrorCode::InvalidMintIndex);
+    Ok(bump)
+}
+
+/// 检查 mint 索引账户是否已创建（没有创建时返回 false）
+fn is_mint_index_initialized(mint_index: &AccountInfo) -> Result<bool> {
+    if mint_index.owner == &system_program::ID {
+        return Ok(false);
+    }
+    require_keys_eq!(*mint_index.owner, crate::ID, ErrorCode::InvalidMintIndex);
+    require!(
+        mint_index.try_borrow_data()?.starts_with(MintIndex::DISCRIMINATOR),
+        ErrorCode::InvalidMintIndex
+    );
+    Ok(true)
+}
+
+/// 在 mint 索引中记录 pool 和 token 索引
+/// 账户不存在时创建，空间不足时扩展（由 payer 支付租金）
+pub(crate) fn record_mint_index<'info>(
+    mint_index: &AccountInfo<'info>,
+    mint: &Pubkey,
+    pool: &Pubkey,
+    token_index: u64,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+) -> Result<()> {
+    let bump = verify_mint_index_address(mint_index, mint)?;
+    
+    if !is_mint_index_initialized(mint_index)? {
+        let seeds: &[&[u8]] = &[b"mint_index", mint.as_ref(), &[bump]];
+        create_pda_account(mint_index, MintIndex::space(1), seeds, payer, system_program)?;
+        let mut data = mint_index.try_borrow_mut_data()?;
+        data[..8].copy_from_slice(MintIndex::DISCRIMINATOR);
+        let header = MintIndex::header_mut(&mut data)?;
+        header.mint = *mint;
+        header.entry_count = 0;
+    }
+    
+    // 已有该 pool 的条目时直接更新，否则确保空间足够再追加
+    if MintIndex::update_entry(&mut mint_index.try_borrow_mut_data()?, pool, token_index)? {
+        return Ok(());
+    }
+    let entry_count = MintIndex::header(&mint_index.try_borrow_data()?)?.entry_count as usize;
+    let required_len = MintIndex::space(entry_count + 1);
+    if mint_index.data_len() < required_len {
+        grow_account(mint_index, required_len, payer, system_program)?;
+    }
+    MintIndex::upsert_entry(&mut mint_index.try_borrow_mut_data()?, pool, token_index)
+}
+
+/// 更新 mint 索引中 pool 对应条目的 token 索引（token 在 pool 中被移动时调用）
+/// 索引账户不存在或没有该 pool 的条目时（例如在引入索引前添加的 token）不做任何修改
+pub(crate) fn update_mint_index(
+    mint_index: &AccountInfo,
+    mint: &Pubkey,
+    pool: &Pubkey,
+    token_index: u64,
+) -> Result<()> {
+    verify_mint_index_address(mint_index, mint)?;
+    if is_mint_index_initialized(mint_index)? {
+        MintIndex::update_entry(&mut mint_index.try_borrow_mut_data()?, pool, token_index)?;
+    }
+    Ok(())
+}
+
+/// 从 mint 索引中移除 pool 对应的条目
+/// 索引账户不存在或没有该 pool 的条目时不做任何修改
+pub(crate) fn remove_mint_index(
+    mint_index: &AccountInfo,
+    mint: &Pubkey,
+    pool: &Pubkey,
+) -> Result<()> {
+    verify_mint_index_address(mint_index, mint)?;
+    if is_mint_index_initialized(mint_index)? {
+        MintIndex::remove_entry(&mut mint_index.try_borrow_mut_data()?, pool)?;
+    }
+    Ok(())
+}
+
+/// 从 pool 中移除 token，并同步 mint 索引
+/// 被移除的 token 从其 mint 索引中删除；最后一个 token 会被移动到被移除的位置，同时更新它的索引
+/// moved_mint_index: 最后一个 token 的 mint 索引账户（被移除的就是最后一个 token 时不需要）
+pub(crate) fn remove_token_with_index(
+    pool: &mut PoolRefMut,
+    pool_key: &Pubkey,
+    token_index: usize,
+    removed_mint_index: &AccountInfo,
+    moved_mint_index: Option<&AccountInfo>,
+) -> Result<()> {
+    let removed_mint = pool.get_token(token_index)
+        .ok_or(ErrorCode::InvalidTokenIndex)?
+        .mint_account;
+    let last_index = pool.get_token_count() - 1;
+    let moved_mint = pool.get_token(last_index)
+        .ok_or(ErrorCode::InvalidTokenIndex)?
+        .mint_account;
+    
+    pool.remove_token(token_index)?;
+    
+    remove_mint_index(removed_mint_index, &removed_mint, pool_key)?;
+    if token_index != last_index {
+        let moved_mint_index = moved_mint_index.ok_or(ErrorCode::InvalidMintIndex)?;
+        update_mint_index(moved_mint_index, &moved_mint, pool_key, token_index as u64)?;
+    }
+    Ok(())
+}
@@ -1,17 +1,50 @@
+mod account_utils;
+mod mint_index;
+mod token_utils;
+pub mod factory;
 pub mod create_pool;
+pub mod create_pool_with_id;
 pub mod add_token;
 pub mod remove_token;
+pub mod compact_pool;
+pub mod migrate_pool;
 pub mod modify_weight;
 pub mod modify_fee;
+pub mod ramp_amp;
+pub mod pause;
+pub mod admin;
+pub mod timelock;
+pub mod multisig;
 pub mod swap;
+pub mod swap_exact_out;
+pub mod batch_swap;
+pub mod quote_swap;
 pub mod add_liquidity;
+pub mod add_liquidity_single;
 pub mod remove_liquidity;
+pub mod remove_liquidity_single;
+pub mod collect_protocol_fees;
 
+pub use factory::*;
 pub use create_pool::*;
+pub use create_pool_with_id::*;
 pub use add_token::*;
 pub use remove_token::*;
+pub use compact_pool::*;
+pub use migrate_pool::*;
 pub use modify_weight::*;
 pub use modify_fee::*;
+pub use ramp_amp::*;
+pub use pause::*;
+pub use admin::*;
+pub use timelock::*;
+pub use multisig::*;
 pub use swap::*;
+pub use swap_exact_out::*;
+pub use batch_swap::*;
+pub use quote_swap::*;
 pub use add_liquidity::*;
-pub use remove_liquidity::*;
\ No newline at end of file
+pub use add_liquidity_single::*;
+pub use remove_liquidity::*;
+pub use remove_liquidity_single::*;
+pub use collect_protocol_fees::*;
\ No newline at end of file
@@ -1,6 +1,8 @@
 use anchor_lang::prelude::*;
-use crate::state::AnySwapPool;
+use anchor_spl::token_interface::Mint;
+use crate::state::{AnySwapPool, PoolLoader, PoolRefMut, FactoryConfig, FeePolicy, Role, PROTOCOL_FEE_SHARE_DENOMINATOR};
 use crate::error::ErrorCode;
+use crate::math::fixed_point;
 
 /// 修改 pool 的费率
 #[derive(Accounts)]
@@ -8,9 +10,16 @@ pub struct ModifyFee<'info> {
     #[account(mut)]
     pub pool: AccountLoader<'info, AnySwapPool>,
 
-    /// Pool 管理员 - 必须签名费率修改操作
-    /// CHECK: 验证是否为 pool 的管理员
+    /// Pool 管理员或费率管理员 - 必须签名费率修改操作
+    /// CHECK: 验证是否为 pool 的管理员或费率管理员
     pub admin: Signer<'info>,
+
+    /// 协议全局配置 - 检查费率范围和全局开关
+    #[account(
+        seeds = [b"factory_config"],
+        bump = factory_config.bump
+    )]
+    pub factory_config: Account<'info, FactoryConfig>,
 }
 
 /// 修改 pool 的费率
@@ -25,20 +34,197 @@ pub fn modify_fee(
     require!(fee_denominator > 0, ErrorCode::MathOverflow);
     require!(fee_numerator <= fee_denominator, ErrorCode::MathOverflow);
     
-    let pool = &mut ctx.accounts.pool.load_mut()?;
+    let pool = &mut ctx.accounts.pool.load_pool_mut()?;
     
-    // 验证管理员权限
-    pool.verify_admin(&ctx.accounts.admin.key())?;
+    // 验证权限：管理员或费率管理员
+    pool.verify_role(Role::FeeManager, &ctx.accounts.admin.key())?;
     
-    // 检查费率是否合理
-    require!(fee_denominator > 0, ErrorCode::MathOverflow);
-    require!(fee_numerator > 0, ErrorCode::MathOverflow);
-    require!(fee_numerator <= fee_denominator, ErrorCode::MathOverflow);
-    // 修改费率
-    pool.fee_numerator = fee_numerator;
-    pool.fee_denominator = fee_denominator;
+    // 启用时间锁后只能通过 queue_action 修改
+    pool.require_no_timelock()?;
+    
+    // 检查全局开关和费率范围
+    let factory_config = &ctx.accounts.factory_config;
+    factory_config.require_not_paused()?;
+    factory_config.verify_fee(fee_numerator, fee_denominator)?;
+    
+    // 检查并修改费率
+    pool.set_fee(fee_numerator, fee_denominator)?;
     
     msg!("Pool fee updated to {}/{}", fee_numerator, fee_denominator);
     Ok(())
 }
 
+
+/// 修改单个 token 的费率
+#[derive(Accounts)]
+pub struct ModifyTokenFee<'info> {
+    #[account(mut)]
+    pub pool: AccountLoader<'info, AnySwapPool>,
+
+    /// 要修改的 token 的 mint 账户
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    /// Pool 管理员或费率管理员 - 必须签名费率修改操作
+    /// CHECK: 验证是否为 pool 的管理员或费率管理员
+    pub admin: Signer<'info>,
+
+    /// 协议全局配置 - 检查费率范围和全局开关
+    #[account(
+        seeds = [b"factory_config"],
+        bump = factory_config.bump
+    )]
+    pub factory_config: Account<'info, FactoryConfig>,
+}
+
+/// 修改单个 token 的费率（覆盖 pool 的费率）
+/// fee_numerator: 新的手续费分子
+/// fee_denominator: 新的手续费分母，为 0 表示取消单独设置，恢复使用 pool 的费率
+/// 注意：交换时使用哪个 token 的费率由 pool 的手续费策略决定
+pub fn modify_token_fee(
+    ctx: Context<ModifyTokenFee>,
+    fee_numerator: u64,
+    fee_denominator: u64,
+) -> Result<()> {
+    let pool = &mut ctx.accounts.pool.load_pool_mut()?;
+    
+    // 验证权限：管理员或费率管理员
+    pool.verify_role(Role::FeeManager, &ctx.accounts.admin.key())?;
+    
+    // 启用时间锁后只能通过 queue_action 修改
+    pool.require_no_timelock()?;
+    
+    let mint_key = ctx.accounts.mint.key();
+    set_token_fee(pool, &ctx.accounts.factory_config, &mint_key, fee_numerator, fee_denominator)?;
+    
+    msg!("Token fee updated: mint: {}, fee: {}/{}", mint_key, fee_numerator, fee_denominator);
+    Ok(())
+}
+
+/// 检查并设置 token 单独的费率（直接修改和时间锁执行共用）
+/// fee_denominator 为 0 表示取消单独设置
+pub(crate) fn set_token_fee(
+    pool: &mut PoolRefMut,
+    factory_config: &FactoryConfig,
+    mint: &Pubkey,
+    fee_numerator: u64,
+    fee_denominator: u64,
+) -> Result<()> {
+    let fee = if fee_denominator == 0 {
+        None
+    } else {
+        // 费率必须小于 100%，否则无法反推精确输出交换的输入
+        require!(fee_numerator < fee_denominator, ErrorCode::MathOverflow);
+        // 检查全局开关和费率范围
+        factory_config.require_not_paused()?;
+        factory_config.verify_fee(fee_numerator, fee_denominator)?;
+        Some((fee_numerator, fee_denominator))
+    };
+    
+    let token_index = pool.find_token_index(mint)
+        .ok_or(ErrorCode::InvalidTokenMint)?;
+    let token = pool.get_token_mut(token_index)
+        .ok_or(ErrorCode::InvalidTokenIndex)?;
+    token.set_fee(fee);
+    Ok(())
+}
+
+/// 修改 pool 的手续费策略（交换时使用输入 token、输出 token 还是两者中较高的费率）
+pub fn modify_fee_policy(
+    ctx: Context<ModifyFee>,
+    fee_policy: FeePolicy,
+) -> Result<()> {
+    let pool = &mut ctx.accounts.pool.load_pool_mut()?;
+    
+    // 验证权限：管理员或费率管理员
+    pool.verify_role(Role::FeeManager, &ctx.accounts.admin.key())?;
+    
+    // 启用时间锁后只能通过 queue_action 修改
+    pool.require_no_timelock()?;
+    
+    pool.fee_policy = fee_policy.to_u8();
+    
+    msg!("Pool fee policy updated to {:?}", fee_policy);
+    Ok(())
+}
+
+/// 修改动态失衡手续费
+/// max_fee: 最高费率（18 位定点数，例如 1e16 表示 1%），为 0 表示不启用
+/// threshold: 失衡阈值（18 位定点数），交换后失衡程度超过阈值且比交换前更失衡时费率开始上升
+/// 注意：基础费率为 pool（或 token 单独设置）的费率，启用后交换需要传入所有 vault
+/// Weighted 曲线没有失衡程度，不能启用
+/// 启用时 max_fee 必须在全局配置的费率范围内
+pub fn modify_surge_fee(
+    ctx: Context<ModifyFee>,
+    max_fee: u64,
+    threshold: u64,
+) -> Result<()> {
+    let pool = &mut ctx.accounts.pool.load_pool_mut()?;
+    
+    // 验证权限：管理员或费率管理员
+    pool.verify_role(Role::FeeManager, &ctx.accounts.admin.key())?;
+    
+    // 启用时间锁后只能通过 queue_action 修改
+    pool.require_no_timelock()?;
+    
+    set_surge_fee(pool, &ctx.accounts.factory_config, max_fee, threshold)?;
+    
+    msg!("Pool surge fee updated: max_fee: {}, threshold: {}", max_fee, threshold);
+    Ok(())
+}
+
+/// 检查并设置动态失衡手续费（直接修改和时间锁执行共用）
+pub(crate) fn set_surge_fee(
+    pool: &mut PoolRefMut,
+    factory_config: &FactoryConfig,
+    max_fee: u64,
+    threshold: u64,
+) -> Result<()> {
+    // 启用时检查全局开关和费率范围（关闭不受限制）
+    if max_fee > 0 {
+        factory_config.require_not_paused()?;
+        factory_config.verify_fee(max_fee, fixed_point::ONE as u64)?;
+    }
+    pool.set_surge_fee(max_fee, threshold)
+}
+
+/// 修改 pool 的协议手续费
+#[derive(Accounts)]
+pub struct ModifyProtocolFee<'info> {
+    #[account(mut)]
+    pub pool: AccountLoader<'info, AnySwapPool>,
+
+    /// 协议管理员 - 必须签名，pool 管理员不能修改协议的抽成和金库
+    pub protocol_authority: Signer<'info>,
+
+    /// 协议全局配置 - 验证协议管理员
+    #[account(
+        seeds = [b"factory_config"],
+        bump = factory_config.bump
+    )]
+    pub factory_config: Account<'info, FactoryConfig>,
+}
+
+/// 修改协议手续费（只有协议管理员可以调用）
+/// protocol_fee_share: 协议从手续费中抽取的比例（分母为 PROTOCOL_FEE_SHARE_DENOMINATOR）
+/// treasury: 有权领取协议手续费的协议金库
+/// 注意：只影响后续交易，已累计的协议手续费由新的协议金库领取
+pub fn modify_protocol_fee(
+    ctx: Context<ModifyProtocolFee>,
+    protocol_fee_share: u64,
+    treasury: Pubkey,
+) -> Result<()> {
+    // 验证协议管理员权限
+    ctx.accounts.factory_config.verify_protocol_authority(&ctx.accounts.protocol_authority.key())?;
+    
+    let pool = &mut ctx.accounts.pool.load_pool_mut()?;
+    
+    pool.set_protocol_fee(protocol_fee_share, &treasury)?;
+    
+    msg!(
+        "Pool protocol fee updated to {}/{}, treasury: {}",
+        protocol_fee_share,
+        PROTOCOL_FEE_SHARE_DENOMINATOR,
+        treasury
+    );
+    Ok(())
+}
@@ -1,6 +1,6 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::Mint;
-use crate::state::AnySwapPool;
+use anchor_spl::token_interface::Mint;
+use crate::state::{AnySwapPool, PoolLoader, Role};
 use crate::error::ErrorCode;
 
 /// 修改 token 的 weight
@@ -10,39 +10,87 @@ pub struct ModifyTokenWeight<'info> {
     pub pool: AccountLoader<'info, AnySwapPool>,
 
     /// 要修改的 token 的 mint 账户
-    pub mint: Account<'info, Mint>,
+    pub mint: InterfaceAccount<'info, Mint>,
 
-    /// Pool 管理员 - 必须签名所有操作
-    /// CHECK: 验证是否为 pool 的管理员
+    /// Pool 管理员或权重管理员 - 必须签名
+    /// CHECK: 验证是否为 pool 的管理员或权重管理员
     pub admin: Signer<'info>,
 }
 
-/// 修改 token 的 weight
+/// 修改 token 的 weight（立即生效，并取消该 token 的权重调整计划）
 /// new_weight: 新的权重值
-/// 注意：修改 weight 会影响池的恒定乘积和，需要谨慎操作
+/// 注意：修改 weight 会立即改变价格，需要谨慎操作；平滑调整使用 ramp_token_weight
 pub fn modify_token_weight(
     ctx: Context<ModifyTokenWeight>,
     new_weight: u64,
 ) -> Result<()> {
     require!(new_weight > 0, ErrorCode::InvalidTokenCount);
     
-    let pool = &mut ctx.accounts.pool.load_mut()?;
+    let pool = &mut ctx.accounts.pool.load_pool_mut()?;
+    
+    // 验证权限：管理员或权重管理员
+    pool.verify_role(Role::WeightManager, &ctx.accounts.admin.key())?;
     
-    // 验证管理员权限
-    pool.verify_admin(&ctx.accounts.admin.key())?;
+    // 启用时间锁后只能通过 queue_action 修改
+    pool.require_no_timelock()?;
     
     let mint_key = ctx.accounts.mint.key();
     let token_index = pool.find_token_index(&mint_key)
         .ok_or(ErrorCode::InvalidTokenMint)?;
     
+    let old_weight = pool.get_token_weight(token_index, Clock::get()?.unix_timestamp);
     let token = pool.get_token_mut(token_index)
         .ok_or(ErrorCode::InvalidTokenIndex)?;
-    
-    let old_weight = token.get_weight();
     token.set_weight(new_weight);
+    // 权重变化后记录的 Σ(vault * weight) 需要重新同步
+    pool.set_constant_sum_value(None);
     
     msg!("Token weight modified: mint: {}, old_weight: {}, new_weight: {}", 
          mint_key, old_weight, new_weight);
     Ok(())
 }
 
+
+/// 按时间线性调整 token 的 weight（例如 LBP 发行或指数再平衡）
+/// end_weight: 调整结束时的权重
+/// start_time: 开始调整的时间（unix timestamp），不早于当前时间
+/// end_time: 调整完成的时间（unix timestamp），必须晚于 start_time
+/// 开始权重为当前时刻的权重（正在进行的调整会停在当前值），start_time 之前保持不变
+/// 交换和流动性操作都使用按 Clock 插值得到的权重
+pub fn ramp_token_weight(
+    ctx: Context<ModifyTokenWeight>,
+    end_weight: u64,
+    start_time: i64,
+    end_time: i64,
+) -> Result<()> {
+    require!(end_weight > 0, ErrorCode::InvalidTokenCount);
+    
+    let pool = &mut ctx.accounts.pool.load_pool_mut()?;
+    
+    // 验证权限：管理员或权重管理员
+    pool.verify_role(Role::WeightManager, &ctx.accounts.admin.key())?;
+    
+    // 启用时间锁后只能通过 queue_action 修改
+    pool.require_no_timelock()?;
+    
+    let now = Clock::get()?.unix_timestamp;
+    require!(
+        start_time >= now && end_time > start_time,
+        ErrorCode::InvalidRampSchedule
+    );
+    
+    let mint_key = ctx.accounts.mint.key();
+    let token_index = pool.find_token_index(&mint_key)
+        .ok_or(ErrorCode::InvalidTokenMint)?;
+    
+    let start_weight = pool.get_token_weight(token_index, now);
+    let token = pool.get_token_mut(token_index)
+        .ok_or(ErrorCode::InvalidTokenIndex)?;
+    token.set_weight_ramp(start_weight, end_weight, start_time, end_time);
+    // 权重变化后记录的 Σ(vault * weight) 需要重新同步
+    pool.set_constant_sum_value(None);
+    
+    msg!("Token weight ramp scheduled: mint: {}, weight: {} -> {}, time: {} -> {}", 
+         mint_key, start_weight, end_weight, start_time, end_time);
+    Ok(())
+}
@@ -0,0 +1,338 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program::invoke_signed;
+use crate::state::{AdminMultisig, MultisigProposal, ProposalAccount};
+use crate::error::ErrorCode;
+
+/// 创建多签
+#[derive(Accounts)]
+pub struct CreateMultisig<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = AdminMultisig::space()
+    )]
+    pub multisig: Account<'info, AdminMultisig>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// 创建 M-of-N 多签
+/// members: 成员列表（最多 MAX_MULTISIG_MEMBERS 个，不能重复）
+/// threshold: 执行所需的最少成员签名数
+/// 注意：把多签 signer PDA（[b"multisig_signer", multisig]）设为 pool 的 admin 后，pool 的管理员指令就由多签控制
+pub fn create_multisig(
+    ctx: Context<CreateMultisig>,
+    members: Vec<Pubkey>,
+    threshold: u8,
+) -> Result<()> {
+    AdminMultisig::verify_members(&members, threshold)?;
+    
+    let multisig_key = ctx.accounts.multisig.key();
+    let (multisig_signer, signer_bump) = Pubkey::find_program_address(
+        &[b"multisig_signer", multisig_key.as_ref()],
+        ctx.program_id,
+    );
+    
+    let multisig = &mut ctx.accounts.multisig;
+    multisig.members = members;
+    multisig.threshold = threshold;
+    multisig.signer_bump = signer_bump;
+    multisig.members_seqno = 0;
+    multisig.proposal_nonce = 0;
+    
+    msg!("Multisig created: multisig: {}, signer: {}, threshold: {}/{}", 
+         multisig_key, multisig_signer, threshold, multisig.members.len());
+    Ok(())
+}
+
+/// 修改多签成员
+#[derive(Accounts)]
+pub struct SetMultisigMembers<'info> {
+    #[account(mut)]
+    pub multisig: Account<'info, AdminMultisig>,
+
+    /// 多签 signer PDA - 只能通过多签执行本指令
+    #[account(
+        seeds = [b"multisig_signer", multisig.key().as_ref()],
+        bump = multisig.signer_bump
+    )]
+    pub multisig_signer: Signer<'info>,
+}
+
+/// 修改多签成员和阈值（需要通过多签执行）
+/// 修改后成员集合版本号递增，之前创建的提案全部失效
+pub fn set_multisig_members(
+    ctx: Context<SetMultisigMembers>,
+    members: Vec<Pubkey>,
+    threshold: u8,
+) -> Result<()> {
+    AdminMultisig::verify_members(&members, threshold)?;
+    
+    let multisig = &mut ctx.accounts.multisig;
+    multisig.members = members;
+    multisig.threshold = threshold;
+    multisig.members_seqno = multisig.members_seqno
+        .checked_add(1)
+        .ok_or(ErrorCode::MathOverflow)?;
+    
+    msg!("Multisig members updated: threshold: {}/{}", threshold, multisig.members.len());
+    Ok(())
+}
+
+/// 以多签 signer PDA 的身份调用本程序的指令
+fn invoke_as_multisig<'info>(
+    multisig_key: &Pubkey,
+    signer_bump: u8,
+    accounts: Vec<AccountMeta>,
+    data: Vec<u8>,
+    account_infos: &[AccountInfo<'info>],
+) -> Result<()> {
+    let instruction = Instruction {
+        program_id: crate::ID,
+        accounts,
+        data,
+    };
+    let seeds = &[
+        b"multisig_signer",
+        multisig_key.as_ref(),
+        &[signer_bump],
+    ];
+    invoke_signed(&instruction, account_infos, &[&seeds[..]])?;
+    Ok(())
+}
+
+/// 成员同时签名，直接执行
+#[derive(Accounts)]
+pub struct ExecuteMultisigInstruction<'info> {
+    pub multisig: Account<'info, AdminMultisig>,
+
+    /// 多签 signer PDA - 被调用指令中的 admin
+    /// CHECK: PDA derived from multisig key, signs via invoke_signed
+    #[account(
+        seeds = [b"multisig_signer", multisig.key().as_ref()],
+        bump = multisig.signer_bump
+    )]
+    pub multisig_signer: AccountInfo<'info>,
+
+    pub anyswap_program: Program<'info, crate::program::Anyswap>,
+}
+
+/// 达到阈值的成员同时签名时，直接以多签 signer PDA 的身份执行本程序的指令
+/// signer_count: 成员签名账户的数量
+/// data: 被调用指令的数据（包含 discriminator）
+///
+/// RemainingAccounts 结构：
+/// - 前 signer_count 个：签名的成员
+/// - 之后：被调用指令的账户（按指令顺序，多签 signer PDA 会自动作为签名者）
+pub fn execute_multisig_instruction<'remaining: 'info, 'info>(
+    ctx: Context<'_, '_, 'remaining, 'info, ExecuteMultisigInstruction<'info>>,
+    signer_count: u8,
+    data: Vec<u8>,
+) -> Result<()> {
+    let multisig = &ctx.accounts.multisig;
+    let remaining_accounts = ctx.remaining_accounts;
+    let signer_count = signer_count as usize;
+    require!(signer_count <= remaining_accounts.len(), ErrorCode::NotEnoughSigners);
+    
+    // 验证成员签名数量
+    let (member_accounts, instruction_accounts) = remaining_accounts.split_at(signer_count);
+    require!(
+        multisig.count_member_signers(member_accounts)? >= multisig.threshold as usize,
+        ErrorCode::NotEnoughSigners
+    );
+    
+    let multisig_signer = ctx.accounts.multisig_signer.key();
+    let accounts = instruction_accounts
+        .iter()
+        .map(|account| AccountMeta {
+            pubkey: *account.key,
+            is_signer: account.is_signer || *account.key == multisig_signer,
+            is_writable: account.is_writable,
+        })
+        .collect();
+    
+    let mut account_infos = instruction_accounts.to_vec();
+    account_infos.push(ctx.accounts.anyswap_program.to_account_info());
+    invoke_as_multisig(
+        &multisig.key(),
+        multisig.signer_bump,
+        accounts,
+        data,
+        &account_infos,
+    )?;
+    
+    msg!("Multisig instruction executed with {} signers", signer_count);
+    Ok(())
+}
+
+/// 创建多签提案
+#[derive(Accounts)]
+#[instruction(proposal_id: u64, accounts: Vec<ProposalAccount>, data: Vec<u8>)]
+pub struct CreateMultisigProposal<'info> {
+    #[account(mut)]
+    pub multisig: Account<'info, AdminMultisig>,
+
+    /// 提案（PDA），proposal_id 必须等于多签当前的 proposal_nonce
+    #[account(
+        init,
+        payer = payer,
+        space = MultisigProposal::space(accounts.len(), data.len()),
+        seeds = [b"multisig_proposal", multisig.key().as_ref(), &proposal_id.to_le_bytes()],
+        bump
+    )]
+    pub proposal: Account<'info, MultisigProposal>,
+
+    /// 提案人 - 必须是多签成员，创建时自动批准
+    pub proposer: Signer<'info>,
+
+    /// 支付提案账户的租金，执行后退还
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// 创建多签提案（成员在不同时间签名的场景）
+/// accounts: 被调用指令的账户（多签 signer PDA 标记为签名者）
+/// data: 被调用指令的数据（包含 discriminator）
+pub fn create_multisig_proposal(
+    ctx: Context<CreateMultisigProposal>,
+    proposal_id: u64,
+    accounts: Vec<ProposalAccount>,
+    data: Vec<u8>,
+) -> Result<()> {
+    let multisig = &mut ctx.accounts.multisig;
+    require!(proposal_id == multisig.proposal_nonce, ErrorCode::InvalidMultisig);
+    let proposer_index = multisig.find_member_index(&ctx.accounts.proposer.key())
+        .ok_or(ErrorCode::NotMultisigMember)?;
+    
+    multisig.proposal_nonce = multisig.proposal_nonce
+        .checked_add(1)
+        .ok_or(ErrorCode::MathOverflow)?;
+    
+    let mut approvals = vec![false; multisig.members.len()];
+    approvals[proposer_index] = true;
+    
+    let proposal = &mut ctx.accounts.proposal;
+    proposal.multisig = multisig.key();
+    proposal.proposal_id = proposal_id;
+    proposal.members_seqno = multisig.members_seqno;
+    proposal.accounts = accounts;
+    proposal.data = data;
+    proposal.approvals = approvals;
+    proposal.payer = ctx.accounts.payer.key();
+    
+    msg!("Multisig proposal created: id: {}, proposer: {}", proposal_id, ctx.accounts.proposer.key());
+    Ok(())
+}
+
+/// 批准多签提案
+#[derive(Accounts)]
+pub struct ApproveMultisigProposal<'info> {
+    pub multisig: Account<'info, AdminMultisig>,
+
+    #[account(mut, has_one = multisig)]
+    pub proposal: Account<'info, MultisigProposal>,
+
+    /// 批准的成员 - 必须签名
+    pub member: Signer<'info>,
+}
+
+/// 批准多签提案
+pub fn approve_multisig_proposal(ctx: Context<ApproveMultisigProposal>) -> Result<()> {
+    let multisig = &ctx.accounts.multisig;
+    let proposal = &mut ctx.accounts.proposal;
+    
+    // 成员修改后旧提案失效
+    require!(proposal.members_seqno == multisig.members_seqno, ErrorCode::InvalidMultisig);
+    let member_index = multisig.find_member_index(&ctx.accounts.member.key())
+        .ok_or(ErrorCode::NotMultisigMember)?;
+    proposal.approvals[member_index] = true;
+    
+    msg!("Multisig proposal approved: id: {}, member: {}, approvals: {}/{}", 
+         proposal.proposal_id, ctx.accounts.member.key(), proposal.approval_count(), multisig.threshold);
+    Ok(())
+}
+
+/// 执行多签提案
+#[derive(Accounts)]
+pub struct ExecuteMultisigProposal<'info> {
+    pub multisig: Account<'info, AdminMultisig>,
+
+    /// 提案，执行后关闭并把租金退还给 payer
+    #[account(
+        mut,
+        has_one = multisig,
+        has_one = payer,
+        close = payer
+    )]
+    pub proposal: Account<'info, MultisigProposal>,
+
+    /// 多签 signer PDA - 被调用指令中的 admin
+    /// CHECK: PDA derived from multisig key, signs via invoke_signed
+    #[account(
+        seeds = [b"multisig_signer", multisig.key().as_ref()],
+        bump = multisig.signer_bump
+    )]
+    pub multisig_signer: AccountInfo<'info>,
+
+    /// 创建提案时支付租金的账户
+    /// CHECK: 通过 has_one 验证
+    #[account(mut)]
+    pub payer: AccountInfo<'info>,
+
+    pub anyswap_program: Program<'info, crate::program::Anyswap>,
+}
+
+/// 执行已达到阈值的多签提案（任何人都可以调用），执行后关闭提案账户
+///
+/// RemainingAccounts 结构：
+/// - 被调用指令的账户，必须与提案中的账户顺序一致
+pub fn execute_multisig_proposal<'remaining: 'info, 'info>(
+    ctx: Context<'_, '_, 'remaining, 'info, ExecuteMultisigProposal<'info>>,
+) -> Result<()> {
+    let multisig = &ctx.accounts.multisig;
+    let proposal = &ctx.accounts.proposal;
+    let remaining_accounts = ctx.remaining_accounts;
+    
+    require!(proposal.members_seqno == multisig.members_seqno, ErrorCode::InvalidMultisig);
+    require!(
+        proposal.approval_count() >= multisig.threshold as usize,
+        ErrorCode::NotEnoughSigners
+    );
+    
+    // 验证账户与提案一致
+    require!(
+        remaining_accounts.len() == proposal.accounts.len(),
+        ErrorCode::InvalidMultisig
+    );
+    for (account, expected) in remaining_accounts.iter().zip(proposal.accounts.iter()) {
+        require!(account.key == &expected.pubkey, ErrorCode::InvalidMultisig);
+    }
+    
+    let accounts = proposal.accounts
+        .iter()
+        .map(|account| AccountMeta {
+            pubkey: account.pubkey,
+            is_signer: account.is_signer,
+            is_writable: account.is_writable,
+        })
+        .collect();
+    
+    let mut account_infos = remaining_accounts.to_vec();
+    account_infos.push(ctx.accounts.anyswap_program.to_account_info());
+    invoke_as_multisig(
+        &multisig.key(),
+        multisig.signer_bump,
+        accounts,
+        proposal.data.clone(),
+        &account_infos,
+    )?;
+    
+    msg!("Multisig proposal executed: id: {}", proposal.proposal_id);
+    Ok(())
+}
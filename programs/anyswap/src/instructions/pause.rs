@@ -0,0 +1,102 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::Mint;
+use crate::state::{
+    AnySwapPool, PoolLoader, TOKEN_DEPOSITS_DISABLED, TOKEN_SWAP_IN_DISABLED, TOKEN_SWAP_OUT_DISABLED,
+};
+use crate::error::ErrorCode;
+
+/// 暂停或恢复 pool
+#[derive(Accounts)]
+pub struct SetPoolPaused<'info> {
+    #[account(mut)]
+    pub pool: AccountLoader<'info, AnySwapPool>,
+
+    /// Pool 管理员或守护者
+    /// CHECK: 验证是否为 pool 的管理员或守护者
+    pub authority: Signer<'info>,
+}
+
+/// 暂停或恢复 pool
+/// 暂停后交换、添加流动性和单边取出都会失败（PoolPaused），等比例移除流动性不受影响
+/// 守护者只能暂停，恢复需要管理员
+pub fn set_pool_paused(ctx: Context<SetPoolPaused>, paused: bool) -> Result<()> {
+    let pool = &mut ctx.accounts.pool.load_pool_mut()?;
+    
+    // 验证权限：恢复只能由管理员操作
+    pool.verify_pause_authority(&ctx.accounts.authority.key(), paused)?;
+    
+    pool.paused = paused as u8;
+    
+    msg!("Pool paused: {}", paused);
+    Ok(())
+}
+
+/// 设置 token 的禁用标志
+#[derive(Accounts)]
+pub struct SetTokenFlags<'info> {
+    #[account(mut)]
+    pub pool: AccountLoader<'info, AnySwapPool>,
+
+    /// 要修改的 token 的 mint 账户
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    /// Pool 管理员或守护者
+    /// CHECK: 验证是否为 pool 的管理员或守护者
+    pub authority: Signer<'info>,
+}
+
+/// 设置 token 的禁用标志（TokenDisabled）
+/// swap_in_disabled: 禁止以该 token 作为输入交换
+/// swap_out_disabled: 禁止以该 token 作为输出交换（包括单边取出）
+/// deposits_disabled: 禁止存入该 token（包括多 token 添加流动性和单边存入）
+/// 守护者只能增加禁用标志，解除禁用需要管理员
+pub fn set_token_flags(
+    ctx: Context<SetTokenFlags>,
+    swap_in_disabled: bool,
+    swap_out_disabled: bool,
+    deposits_disabled: bool,
+) -> Result<()> {
+    let pool = &mut ctx.accounts.pool.load_pool_mut()?;
+    
+    let mint_key = ctx.accounts.mint.key();
+    let token_index = pool.find_token_index(&mint_key)
+        .ok_or(ErrorCode::InvalidTokenMint)?;
+    
+    let mut flags = 0u8;
+    if swap_in_disabled {
+        flags |= TOKEN_SWAP_IN_DISABLED;
+    }
+    if swap_out_disabled {
+        flags |= TOKEN_SWAP_OUT_DISABLED;
+    }
+    if deposits_disabled {
+        flags |= TOKEN_DEPOSITS_DISABLED;
+    }
+    
+    // 验证权限：只增加限制（新标志包含所有旧标志）时守护者也可以操作
+    let old_flags = pool.get_token(token_index)
+        .ok_or(ErrorCode::InvalidTokenIndex)?
+        .get_flags();
+    pool.verify_pause_authority(&ctx.accounts.authority.key(), flags & old_flags == old_flags)?;
+    
+    let token = pool.get_token_mut(token_index)
+        .ok_or(ErrorCode::InvalidTokenIndex)?;
+    token.set_flags(flags);
+    
+    msg!("Token flags updated: mint: {}, swap_in_disabled: {}, swap_out_disabled: {}, deposits_disabled: {}", 
+         mint_key, swap_in_disabled, swap_out_disabled, deposits_disabled);
+    Ok(())
+}
+
+/// 设置 pool 的守护者（只有管理员可以调用），等同于授予 PauseGuardian 角色
+pub fn set_guardian(ctx: Context<SetPoolPaused>, guardian: Pubkey) -> Result<()> {
+    let pool = &mut ctx.accounts.pool.load_pool_mut()?;
+    
+    // 验证管理员权限
+    pool.verify_admin(&ctx.accounts.authority.key())?;
+    
+    pool.guardian = guardian;
+    
+    msg!("Pool guardian updated: {}", guardian);
+    Ok(())
+}
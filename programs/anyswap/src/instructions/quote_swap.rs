@@ -0,0 +1,66 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::TokenAccount;
+use crate::state::{AnySwapPool, PoolLoader, SwapQuote};
+use super::swap::{load_swap_reserves, resolve_swap_tokens};
+
+/// 交换报价账户结构（只读）
+#[derive(Accounts)]
+pub struct QuoteSwap<'info> {
+    pub pool: AccountLoader<'info, AnySwapPool>,
+
+    /// Pool authority PDA
+    /// CHECK: PDA derived from pool key, used as token account owner
+    #[account(
+        seeds = [b"anyswap_authority", pool.key().as_ref()],
+        bump
+    )]
+    pub pool_authority: AccountInfo<'info>,
+
+    /// 输入 token 的 vault 账户
+    #[account(constraint = vault_in.owner == pool_authority.key())]
+    pub vault_in: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// 输出 token 的 vault 账户
+    #[account(constraint = vault_out.owner == pool_authority.key())]
+    pub vault_out: Box<InterfaceAccount<'info, TokenAccount>>,
+}
+
+/// 交换报价：按当前储备量计算 swap_anyswap 的输出和实际费率（含动态失衡手续费）
+/// 不修改任何状态，客户端可以通过模拟交易读取返回值
+/// amount_in 和返回的输出都是 vault 一侧的数量，不包括 Token-2022 转账手续费
+///
+/// RemainingAccounts（仅 Stable 曲线或启用动态失衡手续费时）：
+/// - 按照 pool 中 token 的顺序传入所有 vault 账户
+pub fn quote_swap<'remaining: 'info, 'info>(
+    ctx: Context<'_, '_, 'remaining, 'info, QuoteSwap<'info>>,
+    amount_in: u64,
+) -> Result<SwapQuote> {
+    let pool = ctx.accounts.pool.load_pool()?;
+
+    let (token_in_index, token_out_index) = resolve_swap_tokens(
+        &pool,
+        &ctx.accounts.vault_in,
+        &ctx.accounts.vault_out,
+        None,
+        None,
+    )?;
+    pool.require_swap_enabled(token_in_index, token_out_index)?;
+
+    let reserves = load_swap_reserves(
+        &pool,
+        ctx.remaining_accounts,
+        &ctx.accounts.pool_authority.key(),
+        token_in_index,
+        token_out_index,
+        pool.get_reserve(token_in_index, ctx.accounts.vault_in.amount),
+        pool.get_reserve(token_out_index, ctx.accounts.vault_out.amount),
+    )?;
+
+    pool.quote_swap_exact_in(
+        token_in_index,
+        token_out_index,
+        &reserves,
+        amount_in,
+        Clock::get()?.unix_timestamp,
+    )
+}
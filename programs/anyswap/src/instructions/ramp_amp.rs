@@ -0,0 +1,99 @@
+use anchor_lang::prelude::*;
+use crate::state::{AnySwapPool, CurveType, PoolLoader, PoolRefMut, Role};
+use crate::error::ErrorCode;
+use crate::math::stable;
+
+/// 调整 StableSwap pool 的放大系数
+#[derive(Accounts)]
+pub struct ModifyAmp<'info> {
+    #[account(mut)]
+    pub pool: AccountLoader<'info, AnySwapPool>,
+
+    /// Pool 管理员或权重管理员 - 必须签名放大系数调整操作
+    /// CHECK: 验证是否为 pool 的管理员或权重管理员
+    pub admin: Signer<'info>,
+}
+
+/// 调整放大系数
+/// target_amp: 目标放大系数 A
+/// end_time: 调整完成的时间（unix timestamp），至少在 MIN_RAMP_DURATION 之后
+/// 注意：A 从当前值线性过渡到 target_amp，单次最多变化 MAX_AMP_CHANGE 倍，避免瞬间改变价格
+pub fn ramp_amp(
+    ctx: Context<ModifyAmp>,
+    target_amp: u64,
+    end_time: i64,
+) -> Result<()> {
+    let pool = &mut ctx.accounts.pool.load_pool_mut()?;
+
+    // 验证权限：管理员或权重管理员
+    pool.verify_role(Role::WeightManager, &ctx.accounts.admin.key())?;
+
+    // 启用时间锁后只能通过 queue_action 调整
+    pool.require_no_timelock()?;
+
+    let now = Clock::get()?.unix_timestamp;
+    let current_amp = start_amp_ramp(pool, target_amp, end_time, now)?;
+
+    msg!("Amp ramp started: {} -> {}, end_time: {} (precision: {})",
+         current_amp, target_amp as u128 * stable::AMP_PRECISION, end_time, stable::AMP_PRECISION);
+    Ok(())
+}
+
+/// 检查并开始放大系数调整（ramp_amp 和时间锁执行共用），返回调整开始时的 A（已乘以 AMP_PRECISION）
+pub(crate) fn start_amp_ramp(
+    pool: &mut PoolRefMut,
+    target_amp: u64,
+    end_time: i64,
+    now: i64,
+) -> Result<u128> {
+    require!(
+        (stable::MIN_AMP..=stable::MAX_AMP).contains(&target_amp),
+        ErrorCode::InvalidAmplification
+    );
+    require!(
+        pool.get_curve_type()? == CurveType::Stable,
+        ErrorCode::InvalidCurveType
+    );
+    require!(
+        end_time >= now.checked_add(stable::MIN_RAMP_DURATION).ok_or(ErrorCode::MathOverflow)?,
+        ErrorCode::InvalidRampSchedule
+    );
+
+    // 检查变化幅度（按 AMP_PRECISION 精度比较）
+    let current_amp = pool.get_current_amp(now)?;
+    let target = target_amp as u128 * stable::AMP_PRECISION;
+    let max_change = stable::MAX_AMP_CHANGE as u128;
+    let within_limit = if target >= current_amp {
+        target <= current_amp.saturating_mul(max_change)
+    } else {
+        target.saturating_mul(max_change) >= current_amp
+    };
+    require!(within_limit, ErrorCode::InvalidRampSchedule);
+
+    pool.set_amp_ramp(target as u64, now, end_time)?;
+    Ok(current_amp)
+}
+
+/// 停止放大系数调整，固定为当前时刻的值
+/// 只冻结正在进行的调整，不会把 A 调整到新的值，启用时间锁后也可以直接调用
+pub fn stop_ramp_amp(ctx: Context<ModifyAmp>) -> Result<()> {
+    let pool = &mut ctx.accounts.pool.load_pool_mut()?;
+
+    // 验证权限：管理员或权重管理员
+    pool.verify_role(Role::WeightManager, &ctx.accounts.admin.key())?;
+    require!(
+        pool.get_curve_type()? == CurveType::Stable,
+        ErrorCode::InvalidCurveType
+    );
+
+    let now = Clock::get()?.unix_timestamp;
+    let current_amp = pool.get_current_amp(now)?;
+    pool.set_amp_ramp(
+        u64::try_from(current_amp).map_err(|_| ErrorCode::MathOverflow)?,
+        now,
+        now,
+    )?;
+
+    msg!("Amp ramp stopped at {} (precision: {})", current_amp, stable::AMP_PRECISION);
+    Ok(())
+}
@@ -1,7 +1,11 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Burn, Mint, Token, TokenAccount, Transfer};
-use crate::state::AnySwapPool;
+use anchor_spl::token::{self, Burn, Token};
+use anchor_spl::token_2022::Token2022;
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TransferChecked};
+use crate::state::{AnySwapPool, PoolLoader, MINIMUM_LIQUIDITY};
 use crate::error::ErrorCode;
+use super::swap::check_deadline;
+use super::token_utils::{amount_after_transfer_fee, token_program_for};
 
 /// 移除流动性操作
 /// 按照 Balancer 的方式：按 LP token 比例移除所有 token
@@ -25,7 +29,7 @@ pub struct RemoveLiquidity<'info> {
         seeds = [b"pool_mint", pool.key().as_ref()],
         bump
     )]
-    pub pool_mint: Box<Account<'info, Mint>>,
+    pub pool_mint: Box<InterfaceAccount<'info, Mint>>,
 
     /// 用户的 LP token 账户（销毁 LP token）
     #[account(
@@ -33,47 +37,69 @@ pub struct RemoveLiquidity<'info> {
         constraint = user_pool_ata.mint == pool_mint.key(),
         constraint = user_pool_ata.owner == owner.key()
     )]
-    pub user_pool_ata: Box<Account<'info, TokenAccount>>,
+    pub user_pool_ata: Box<InterfaceAccount<'info, TokenAccount>>,
 
     pub owner: Signer<'info>,
 
+    /// 普通 Token 程序（LP mint 和普通 Token 的转账）
     pub token_program: Program<'info, Token>,
+
+    /// Token-2022 程序（Token-2022 token 的转账）
+    pub token_2022_program: Program<'info, Token2022>,
 }
 
 /// 移除流动性（多 token 版本）
 /// 按照 Balancer 的方式：按 LP token 比例移除所有 token
+/// 等比例取出不改变价格，pool 暂停或 token 被禁用时仍然可以取出，保证 LP 随时可以退出
 /// 
 /// RemainingAccounts 结构：
-/// - 每个账户是一个 user_token_account（接收返回的 token）
+/// - 每三个账户为一组：(user_token_account, vault_account, mint)，user_token_account 接收返回的 token
 /// - 必须按照 pool 中 token 的顺序传入
-/// - 例如：pool 有 [A, B, C]，则传入 [user_A, user_B, user_C]
+/// - 例如：pool 有 [A, B, C]，则传入 [user_A, vault_A, mint_A, user_B, vault_B, mint_B, user_C, vault_C, mint_C]
 /// 
 /// burn_amount: 要销毁的 LP token 数量
+/// min_amounts_out: 每个 token 最少取出的数量（用户实际到账的数量，扣除 Token-2022 转账手续费，
+///   按 pool 中 token 的顺序，滑点保护）
+/// deadline: 可选的截止时间（unix 时间戳），超过后交易失败
 pub fn remove_liquidity<'remaining: 'info, 'info>(
     ctx: Context<'_, '_, 'remaining, 'info, RemoveLiquidity<'info>>,
     burn_amount: u64,
+    min_amounts_out: Vec<u64>,
+    deadline: Option<i64>,
 ) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    check_deadline(deadline, now)?;
+
     // 检查用户 LP token 余额
     require!(
         ctx.accounts.user_pool_ata.amount >= burn_amount,
         ErrorCode::InsufficientTokenAmount
     );
 
-    let pool = ctx.accounts.pool.load()?;
+    let pool = ctx.accounts.pool.load_pool()?;
     let token_count = pool.get_token_count();
     
     require!(token_count > 0, ErrorCode::InvalidTokenCount);
+    require!(
+        min_amounts_out.len() == token_count,
+        ErrorCode::InvalidTokenCount
+    );
     
     let total_minted = pool.get_total_amount_minted();
     require!(
         total_minted >= burn_amount,
         ErrorCode::MathOverflow
     );
+    // 永久锁定的 LP 不能被销毁，保证 vault 中有余额时 total_amount_minted 不会归零
+    require!(
+        total_minted - burn_amount >= MINIMUM_LIQUIDITY,
+        ErrorCode::MinimumLiquidityLocked
+    );
 
-    // 验证 RemainingAccounts 数量：每个 token 需要 2 个账户（user_token, vault）
+    // 验证 RemainingAccounts 数量：每个 token 需要 3 个账户（user_token, vault, mint）
     let remaining_accounts = ctx.remaining_accounts;
     require!(
-        remaining_accounts.len() == token_count * 2,
+        remaining_accounts.len() == token_count * 3,
         ErrorCode::InvalidTokenCount
     );
 
@@ -91,72 +117,97 @@ pub fn remove_liquidity<'remaining: 'info, 'info>(
 
     let burn_amount_u128 = burn_amount as u128;
     let mut amounts = Vec::new();
+    let mut decimals = Vec::new();
+    // 取出后的储备量（用于同步记录的 Σ(vault * weight)）
+    let mut reserves_after = Vec::new();
 
     // 计算每个 token 要返回的数量
     // 在循环中立即读取数据，不保留 Account 对象
     for i in 0..token_count {
-        let vault_info = &remaining_accounts[i * 2 + 1];
+        let vault_info = &remaining_accounts[i * 3 + 1];
+        let mint_info = &remaining_accounts[i * 3 + 2];
         
-        // 验证 vault
+        // 验证 vault 和 mint
         let token_item = pool.get_token(i).ok_or(ErrorCode::InvalidTokenIndex)?;
         require!(
             vault_info.key.to_bytes() == token_item.vault_pubkey().to_bytes(),
             ErrorCode::InvalidTokenMint
         );
+        require!(
+            mint_info.key == token_item.mint_pubkey(),
+            ErrorCode::InvalidTokenMint
+        );
 
         // 读取 vault 账户并验证 owner 是 pool_authority
         let vault_balance = {
-            let vault_account = Account::<TokenAccount>::try_from_unchecked(vault_info)?;
+            let vault_account = InterfaceAccount::<TokenAccount>::try_from(vault_info)?;
             require!(
                 vault_account.owner == pool_authority_key,
                 ErrorCode::InvalidTokenMint
             );
-            vault_account.amount as u128
+            // 不包括待领取的协议手续费
+            pool.get_reserve(i, vault_account.amount)
         };
         
         // amount = burn_amount * vault_balance / total_minted
         let amount = (burn_amount_u128
-            .checked_mul(vault_balance)
+            .checked_mul(vault_balance as u128)
             .ok_or(ErrorCode::MathOverflow)?
             .checked_div(total_minted as u128)
             .ok_or(ErrorCode::MathOverflow)?) as u64;
+        require!(
+            amount_after_transfer_fee(mint_info, amount)? >= min_amounts_out[i],
+            ErrorCode::SlippageExceeded
+        );
         
         amounts.push(amount);
+        decimals.push(token_item.get_decimals());
+        reserves_after.push(vault_balance - amount);
     }
 
     drop(pool);
     // 更新 total_amount_minted
-    let mut pool_mut = ctx.accounts.pool.load_mut()?;
+    let mut pool_mut = ctx.accounts.pool.load_pool_mut()?;
     let current_total = pool_mut.get_total_amount_minted();
     pool_mut.set_total_amount_minted(
         current_total
             .checked_sub(burn_amount)
             .ok_or(ErrorCode::MathOverflow)?
     );
+    // 用取出后的储备量同步 ConstantSum 曲线记录的 Σ(vault * weight)
+    pool_mut.sync_constant_sum_value(&reserves_after, now)?;
+    drop(pool_mut);
 
     // 从 vault 转移所有 token 给用户
     for i in 0..token_count {
-        let user_token_info = &remaining_accounts[i * 2];
-        let vault_info = &remaining_accounts[i * 2 + 1];
+        let user_token_info = &remaining_accounts[i * 3];
+        let vault_info = &remaining_accounts[i * 3 + 1];
+        let mint_info = &remaining_accounts[i * 3 + 2];
         
         // 验证 user_token owner（从 TokenAccount 数据中读取）
-        let user_token_account = Account::<TokenAccount>::try_from_unchecked(user_token_info)?;
+        let user_token_account = InterfaceAccount::<TokenAccount>::try_from(user_token_info)?;
         require!(
             user_token_account.owner == owner_key,
             ErrorCode::InvalidTokenMint
         );
 
-        token::transfer(
+        token_interface::transfer_checked(
             CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
+                token_program_for(
+                    mint_info,
+                    &ctx.accounts.token_program,
+                    &ctx.accounts.token_2022_program,
+                )?,
+                TransferChecked {
                     from: vault_info.clone(),
+                    mint: mint_info.clone(),
                     to: user_token_info.clone(),
                     authority: ctx.accounts.pool_authority.to_account_info(),
                 },
                 signer,
             ),
             amounts[i],
+            decimals[i],
         )?;
     }
 
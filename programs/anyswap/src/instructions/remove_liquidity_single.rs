@@ -0,0 +1,218 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Burn, Token};
+use anchor_spl::token_2022::Token2022;
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TransferChecked};
+use crate::state::{AnySwapPool, PoolLoader, TOKEN_SWAP_OUT_DISABLED};
+use crate::error::ErrorCode;
+use super::swap::load_all_reserves;
+use super::token_utils::{amount_after_transfer_fee, token_program_for};
+
+/// 单边移除流动性操作
+/// 销毁 LP token，只取出一个 token
+#[derive(Accounts)]
+pub struct RemoveLiquiditySingle<'info> {
+    #[account(mut)]
+    pub pool: AccountLoader<'info, AnySwapPool>,
+
+    /// Pool authority PDA
+    /// CHECK: PDA derived from pool key, used as token account owner
+    #[account(
+        seeds = [b"anyswap_authority", pool.key().as_ref()],
+        bump
+    )]
+    pub pool_authority: AccountInfo<'info>,
+
+    /// Pool mint - LP token
+    #[account(
+        mut,
+        seeds = [b"pool_mint", pool.key().as_ref()],
+        bump
+    )]
+    pub pool_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// 取出 token 的 vault 账户
+    #[account(
+        mut,
+        constraint = vault.owner == pool_authority.key(),
+        constraint = vault.mint == user_token.mint,
+    )]
+    pub vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// 用户的 token 账户（接收取出的代币）
+    #[account(mut, has_one = owner)]
+    pub user_token: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// 取出 token 的 mint 账户（transfer_checked 需要）
+    #[account(constraint = mint.key() == vault.mint)]
+    pub mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// 用户的 LP token 账户（销毁 LP token）
+    #[account(
+        mut,
+        constraint = user_pool_ata.mint == pool_mint.key(),
+        constraint = user_pool_ata.owner == owner.key()
+    )]
+    pub user_pool_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub owner: Signer<'info>,
+
+    /// 普通 Token 程序（LP mint 和普通 Token 的转账）
+    pub token_program: Program<'info, Token>,
+
+    /// Token-2022 程序（Token-2022 token 的转账）
+    pub token_2022_program: Program<'info, Token2022>,
+}
+
+/// 单边移除流动性
+/// 销毁 lp_in 个 LP token，只取出一个 token（由 vault 的 mint 决定），数量由 pool 的不变量决定
+///
+/// 等比例部分不收手续费；超出等比例的部分相当于先等比例取出再把其他 token 交换成该 token，
+/// 按该 token 的费率收取手续费，手续费（扣除协议手续费后）留在 vault 中归剩余 LP 所有
+///
+/// 取出数量只取决于取出 token 的 vault 时不需要其他 vault：
+/// - Weighted：加权几何平均按 LP 比例下降，只使用该 token 的余额和权重
+/// - ConstantSum：使用 pool 记录的 Σ(vault * weight)
+///
+/// 例外：Stable 曲线需要所有储备量计算不变量 D；ConstantSum 记录的 Σ(vault * weight) 未同步时
+/// （迁移、移除 token 或修改权重之后，直到下一次读取所有 vault 的操作）也需要所有 vault，并在取出后同步
+///
+/// RemainingAccounts 结构（仅 Stable 曲线或 ConstantSum 未同步时）：
+/// - 按照 pool 中 token 的顺序传入所有 vault 账户（只读，用于计算不变量）
+///
+/// min_amount_out: 最少取出的 token 数量（用户实际到账的数量，扣除 Token-2022 转账手续费，滑点保护）
+pub fn remove_liquidity_single<'remaining: 'info, 'info>(
+    ctx: Context<'_, '_, 'remaining, 'info, RemoveLiquiditySingle<'info>>,
+    lp_in: u64,
+    min_amount_out: u64,
+) -> Result<()> {
+    require!(lp_in > 0, ErrorCode::InsufficientTokenAmount);
+    // 检查用户 LP token 余额
+    require!(
+        ctx.accounts.user_pool_ata.amount >= lp_in,
+        ErrorCode::InsufficientTokenAmount
+    );
+
+    let pool = ctx.accounts.pool.load_pool()?;
+
+    // 查找 token 索引并验证 vault
+    let mint_key = ctx.accounts.vault.mint;
+    let token_index = pool.find_token_index(&mint_key)
+        .ok_or(ErrorCode::InvalidTokenMint)?;
+    let token_item = pool.get_token(token_index).ok_or(ErrorCode::InvalidTokenIndex)?;
+    require!(
+        *token_item.vault_pubkey() == ctx.accounts.vault.key(),
+        ErrorCode::InvalidTokenMint
+    );
+
+    // 检查 pool 没有暂停，且该 token 允许取出
+    pool.require_not_paused()?;
+    pool.require_token_enabled(token_index, TOKEN_SWAP_OUT_DISABLED)?;
+
+    // 读取储备量：需要时读取所有 vault，否则只填入取出 token 的储备量
+    let requires_all_reserves = pool.single_withdraw_requires_all_reserves()?;
+    let mut reserves = if requires_all_reserves {
+        load_all_reserves(
+            &pool,
+            ctx.remaining_accounts,
+            &ctx.accounts.pool_authority.key(),
+        )?
+    } else {
+        vec![0u64; pool.get_token_count()]
+    };
+    let reserve = pool.get_reserve(token_index, ctx.accounts.vault.amount);
+    reserves[token_index] = reserve;
+
+    let now = Clock::get()?.unix_timestamp;
+    let (amount_out, fee_amount) = pool.calculate_single_withdraw_amount(
+        token_index,
+        &reserves,
+        lp_in,
+        now,
+    )?;
+
+    require!(amount_out > 0, ErrorCode::InsufficientOutputAmount);
+    require!(
+        amount_after_transfer_fee(&ctx.accounts.mint.to_account_info(), amount_out)? >= min_amount_out,
+        ErrorCode::SlippageExceeded
+    );
+    require!(
+        amount_out < reserve,
+        ErrorCode::InsufficientLiquidity
+    );
+
+    let protocol_fee = pool.calculate_protocol_fee(fee_amount)?;
+
+    drop(pool);
+
+    // 更新 total_amount_minted，累加协议手续费
+    let mut pool_mut = ctx.accounts.pool.load_pool_mut()?;
+    pool_mut.accrue_protocol_fee(token_index, protocol_fee)?;
+    let current_total = pool_mut.get_total_amount_minted();
+    pool_mut.set_total_amount_minted(
+        current_total
+            .checked_sub(lp_in)
+            .ok_or(ErrorCode::MathOverflow)?
+    );
+    // 更新 ConstantSum 曲线记录的 Σ(vault * weight)（协议手续费不计入储备量），
+    // 读取了所有 vault 时直接同步
+    let reserve_after = reserve
+        .checked_sub(amount_out + protocol_fee)
+        .ok_or(ErrorCode::MathOverflow)?;
+    if requires_all_reserves {
+        reserves[token_index] = reserve_after;
+        pool_mut.sync_constant_sum_value(&reserves, now)?;
+    } else {
+        pool_mut.update_constant_sum_value(token_index, reserve, reserve_after, now)?;
+    }
+    drop(pool_mut);
+
+    // 准备 seeds 用于签名
+    let pool_key = ctx.accounts.pool.key();
+    let bump = ctx.bumps.pool_authority;
+    let seeds = &[
+        b"anyswap_authority",
+        pool_key.as_ref(),
+        &[bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    // 从 vault 转移 token 给用户
+    let mint = ctx.accounts.mint.to_account_info();
+    token_interface::transfer_checked(
+        CpiContext::new_with_signer(
+            token_program_for(&mint, &ctx.accounts.token_program, &ctx.accounts.token_2022_program)?,
+            TransferChecked {
+                from: ctx.accounts.vault.to_account_info(),
+                mint,
+                to: ctx.accounts.user_token.to_account_info(),
+                authority: ctx.accounts.pool_authority.to_account_info(),
+            },
+            signer,
+        ),
+        amount_out,
+        ctx.accounts.mint.decimals,
+    )?;
+
+    // 销毁用户的 LP token（用户自己签名销毁）
+    token::burn(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Burn {
+                mint: ctx.accounts.pool_mint.to_account_info(),
+                from: ctx.accounts.user_pool_ata.to_account_info(),
+                authority: ctx.accounts.owner.to_account_info(),
+            },
+        ),
+        lp_in,
+    )?;
+
+    msg!(
+        "Single-sided liquidity removed: {} LP tokens burned, {} {} out, fee: {}",
+        lp_in,
+        amount_out,
+        mint_key,
+        fee_amount
+    );
+
+    Ok(())
+}
@@ -1,7 +1,8 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::Mint;
-use crate::state::AnySwapPool;
+use anchor_spl::token_interface::Mint;
+use crate::state::{AnySwapPool, PoolLoader, Role};
 use crate::error::ErrorCode;
+use super::mint_index::remove_token_with_index;
 
 /// 从 pool 中移除 token
 #[derive(Accounts)]
@@ -10,45 +11,50 @@ pub struct RemoveTokenFromPool<'info> {
     pub pool: AccountLoader<'info, AnySwapPool>,
 
     /// 要移除的 token 的 mint 账户
-    pub mint: Account<'info, Mint>,
+    pub mint: InterfaceAccount<'info, Mint>,
 
-    /// Pool 管理员 - 必须签名所有操作
-    /// CHECK: 验证是否为 pool 的管理员
+    /// 被移除 token 的 mint 反向索引
+    /// CHECK: PDA derived from mint key, validated in remove_token_with_index
+    #[account(
+        mut,
+        seeds = [b"mint_index", mint.key().as_ref()],
+        bump
+    )]
+    pub mint_index: AccountInfo<'info>,
+
+    /// Pool 管理员或 Token 管理员 - 必须签名
+    /// CHECK: 验证是否为 pool 的管理员或 Token 管理员
     pub admin: Signer<'info>,
 }
 
 /// 从 pool 中移除 token
 /// 注意：移除 token 前需要确保 vault 中没有余额，或者由调用者处理余额
+/// 移除后账户大小不变，可以调用 compact_pool 回收多余的租金
+///
+/// RemainingAccounts 结构：
+/// - 如果被移除的不是最后一个 token，需要传入最后一个 token 的 mint 索引账户
+///   （最后一个 token 会被移动到被移除的位置，需要更新它在索引中的位置）
 pub fn remove_token_from_pool(ctx: Context<RemoveTokenFromPool>) -> Result<()> {
-    let pool = &mut ctx.accounts.pool.load_mut()?;
+    let pool = &mut ctx.accounts.pool.load_pool_mut()?;
+    
+    // 验证权限：管理员或 Token 管理员
+    pool.verify_role(Role::TokenCurator, &ctx.accounts.admin.key())?;
     
-    // 验证管理员权限
-    pool.verify_admin(&ctx.accounts.admin.key())?;
+    // 启用时间锁后只能通过 queue_action 移除
+    pool.require_no_timelock()?;
     
     let mint_key = ctx.accounts.mint.key();
     let token_index = pool.find_token_index(&mint_key)
         .ok_or(ErrorCode::InvalidTokenMint)?;
     
-    // 检查是否是最后一个 token
-    let token_count = pool.get_token_count();
-    require!(token_count > 0, ErrorCode::InvalidTokenCount);
-    
-    // 如果是最后一个 token，直接减少计数
-    if token_index == token_count - 1 {
-        pool.token_count -= 1;
-    } else {
-        // 如果不是最后一个，将最后一个 token 移动到当前位置
-        let last_index = token_count - 1;
-        
-        // 先获取最后一个 token 的数据（通过索引访问，避免借用冲突）
-        let last_token_data = pool.tokens[last_index];
-        
-        // 复制最后一个 token 到当前位置
-        pool.tokens[token_index] = last_token_data;
-        
-        // 减少计数
-        pool.token_count -= 1;
-    }
+    // 将最后一个 token 移动到当前位置并减少计数，同时更新 mint 索引
+    remove_token_with_index(
+        pool,
+        &ctx.accounts.pool.key(),
+        token_index,
+        &ctx.accounts.mint_index,
+        ctx.remaining_accounts.first(),
+    )?;
     
     msg!("Token removed from pool: mint: {}", mint_key);
     Ok(())
@@ -1,7 +1,10 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Token, TokenAccount, Transfer};
-use crate::state::AnySwapPool;
+use anchor_spl::token::Token;
+use anchor_spl::token_2022::Token2022;
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TransferChecked};
+use crate::state::{AnySwapPool, CurveType, PoolLoader, PoolRef};
 use crate::error::ErrorCode;
+use super::token_utils::{amount_after_transfer_fee, token_program_for};
 
 /// AnySwap 交换账户结构
 #[derive(Accounts)]
@@ -23,36 +26,67 @@ pub struct Swap<'info> {
         constraint = vault_in.owner == pool_authority.key(),
         constraint = vault_in.mint == user_in.mint,
     )]
-    pub vault_in: Box<Account<'info, TokenAccount>>,
+    pub vault_in: Box<InterfaceAccount<'info, TokenAccount>>,
     
     /// 输出 token 的 vault 账户
     #[account(
         mut,
         constraint = vault_out.owner == pool_authority.key(),
     )]
-    pub vault_out: Box<Account<'info, TokenAccount>>,
+    pub vault_out: Box<InterfaceAccount<'info, TokenAccount>>,
     
     /// 用户的输入代币账户（转出代币）
     #[account(mut, has_one = owner)]
-    pub user_in: Box<Account<'info, TokenAccount>>,
+    pub user_in: Box<InterfaceAccount<'info, TokenAccount>>,
     
     /// 用户的输出代币账户（接收代币）
     #[account(mut, has_one = owner)]
-    pub user_out: Box<Account<'info, TokenAccount>>,
+    pub user_out: Box<InterfaceAccount<'info, TokenAccount>>,
+    
+    /// 输入 token 的 mint 账户（transfer_checked 需要）
+    #[account(constraint = mint_in.key() == vault_in.mint)]
+    pub mint_in: Box<InterfaceAccount<'info, Mint>>,
+    
+    /// 输出 token 的 mint 账户（transfer_checked 需要）
+    #[account(constraint = mint_out.key() == vault_out.mint)]
+    pub mint_out: Box<InterfaceAccount<'info, Mint>>,
     
     pub owner: Signer<'info>,
     
+    /// 普通 Token 程序
     pub token_program: Program<'info, Token>,
+    
+    /// Token-2022 程序（pool 中的 token 可以属于任意一个 token 程序）
+    pub token_2022_program: Program<'info, Token2022>,
 }
 
 /// AnySwap 交换代币
-/// 使用恒定乘积和公式：Σ(vault * weight) = constant
-/// 公式：amount_in * weight_in = amount_out * weight_out
-pub fn swap_anyswap(
-    ctx: Context<Swap>,
+/// 按 pool 的定价曲线计算输出：
+/// - ConstantSum：Σ(vault * weight) = constant，amount_in * weight_in = amount_out * weight_out
+/// - Weighted：Π(vault ^ weight) = constant
+/// - Stable：StableSwap 不变量 D，需要所有 token 的储备量
+///
+/// 启用动态失衡手续费时，交换使 pool 更失衡会收取更高的手续费
+///
+/// Token-2022 转账手续费：pool 按 vault 实际收到的输入计算输出，
+/// min_amount_out 与用户实际到账的输出（扣除输出 token 的转账手续费）比较
+///
+/// RemainingAccounts（仅 Stable 曲线或启用动态失衡手续费时）：
+/// - 按照 pool 中 token 的顺序传入所有 vault 账户
+///
+/// deadline: 可选的截止时间（unix 时间戳），超过后交易失败
+/// token_in_index / token_out_index: 可选的 token 索引提示，提供时只验证不查找
+pub fn swap_anyswap<'remaining: 'info, 'info>(
+    ctx: Context<'_, '_, 'remaining, 'info, Swap<'info>>,
     amount_in: u64,
     min_amount_out: u64,
+    deadline: Option<i64>,
+    token_in_index: Option<u16>,
+    token_out_index: Option<u16>,
 ) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    check_deadline(deadline, now)?;
+
     // 检查用户余额
     require!(
         ctx.accounts.user_in.amount >= amount_in,
@@ -60,59 +94,56 @@ pub fn swap_anyswap(
     );
     
     // 加载 pool
-    let pool = ctx.accounts.pool.load()?;
+    let pool = ctx.accounts.pool.load_pool()?;
     
     // 从 vault 账户获取 mint 地址
     let mint_in_key = ctx.accounts.vault_in.mint;
     let mint_out_key = ctx.accounts.vault_out.mint;
     
-    // 查找 token 索引
-    let token_in_index = pool.find_token_index(&mint_in_key)
-        .ok_or(ErrorCode::InvalidTokenMint)?;
-    let token_out_index = pool.find_token_index(&mint_out_key)
-        .ok_or(ErrorCode::InvalidTokenMint)?;
-    
-    require!(token_in_index != token_out_index, ErrorCode::SameTokenSwap);
-    
-    // 验证 vault 账户
-    let token_in = pool.get_token(token_in_index)
-        .ok_or(ErrorCode::InvalidTokenIndex)?;
-    let token_out = pool.get_token(token_out_index)
-        .ok_or(ErrorCode::InvalidTokenIndex)?;
+    // 查找 token 索引并验证 vault 账户
+    let (token_in_index, token_out_index) = resolve_swap_tokens(
+        &pool,
+        &ctx.accounts.vault_in,
+        &ctx.accounts.vault_out,
+        token_in_index,
+        token_out_index,
+    )?;
     
-    require!(
-        token_in.vault_pubkey().to_bytes() == ctx.accounts.vault_in.key().to_bytes(),
-        ErrorCode::InvalidTokenMint
-    );
-    require!(
-        token_out.vault_pubkey().to_bytes() == ctx.accounts.vault_out.key().to_bytes(),
-        ErrorCode::InvalidTokenMint
-    );
+    // 检查 pool 没有暂停，且两个 token 都允许交换
+    pool.require_swap_enabled(token_in_index, token_out_index)?;
     
-    // 获取当前储备量
-    let reserve_in = ctx.accounts.vault_in.amount;
-    let reserve_out = ctx.accounts.vault_out.amount;
+    // 获取当前储备量（不包括待领取的协议手续费）
+    let reserve_in = pool.get_reserve(token_in_index, ctx.accounts.vault_in.amount);
+    let reserve_out = pool.get_reserve(token_out_index, ctx.accounts.vault_out.amount);
     
     require!(
         reserve_in > 0 && reserve_out > 0,
         ErrorCode::InsufficientLiquidity
     );
     
-    // 使用 pool 计算手续费
-    let (_fee_amount, amount_in_minus_fees) = pool.calculate_fee(amount_in)?;
-    
-    // 使用 pool 计算交换输出（基于扣除手续费后的输入）
-    // amount_out = (amount_in_minus_fees * weight_in) / weight_out
-    let amount_out = pool.calculate_swap_output(
+    // 使用 pool 计算手续费（含动态失衡手续费）和交换输出（基于扣除手续费后的输入）
+    let reserves = load_swap_reserves(
+        &pool,
+        ctx.remaining_accounts,
+        &ctx.accounts.pool_authority.key(),
         token_in_index,
         token_out_index,
-        amount_in_minus_fees,
+        reserve_in,
+        reserve_out,
     )?;
+    // 输入 token 有转账手续费时，只按 vault 实际收到的数量计算
+    let amount_received = amount_after_transfer_fee(&ctx.accounts.mint_in.to_account_info(), amount_in)?;
+    let quote = pool.quote_swap_exact_in(token_in_index, token_out_index, &reserves, amount_received, now)?;
+    let fee_amount = quote.fee_amount;
+    let amount_in_minus_fees = amount_received - fee_amount;
+    let amount_out = quote.amount_out;
+    let curve_type = pool.get_curve_type()?;
     
-    // 检查输出数量是否足够
+    // 检查用户实际到账的输出数量是否足够
+    let amount_out_received = amount_after_transfer_fee(&ctx.accounts.mint_out.to_account_info(), amount_out)?;
     require!(
-        amount_out >= min_amount_out,
-        ErrorCode::InsufficientOutputAmount
+        amount_out_received >= min_amount_out,
+        ErrorCode::SlippageExceeded
     );
     
     // 检查 vault 是否有足够的储备
@@ -121,75 +152,222 @@ pub fn swap_anyswap(
         ErrorCode::InsufficientLiquidity
     );
     
-    // 验证恒定乘积和公式
-    // 由于 weight 是不变量，我们只需要验证 amount_in_minus_fees * weight_in >= amount_out * weight_out
-    // 注意：由于整数除法向下取整，delta_out 可能略小于 delta_in，这是允许的
-    let weight_in = token_in.get_weight();
-    let weight_out = token_out.get_weight();
-    
-    // 验证：amount_in_minus_fees * weight_in >= amount_out * weight_out
-    // 由于 amount_out = (amount_in_minus_fees * weight_in) / weight_out（整数除法向下取整）
-    // 所以 delta_out = amount_out * weight_out <= amount_in_minus_fees * weight_in = delta_in
-    let delta_in = (amount_in_minus_fees as u128)
-        .checked_mul(weight_in as u128)
+    let weight_in = pool.get_token_weight(token_in_index, now);
+    let weight_out = pool.get_token_weight(token_out_index, now);
+    
+    // 恒定乘积和曲线：验证 amount_in_minus_fees * weight_in >= amount_out * weight_out
+    // 加权曲线和 StableSwap 的取整方向已在各自的数学模块中保证有利于 pool
+    if curve_type == CurveType::ConstantSum {
+        verify_constant_sum_swap(
+            &pool,
+            token_in_index,
+            amount_in_minus_fees,
+            token_out_index,
+            amount_out,
+            now,
+        )?;
+    }
+    
+    let protocol_fee = pool.calculate_protocol_fee(fee_amount)?;
+    
+    drop(pool);
+    
+    // 累加协议手续费，更新 ConstantSum 曲线记录的 Σ(vault * weight)（协议手续费不计入储备量）
+    {
+        let mut pool_mut = ctx.accounts.pool.load_pool_mut()?;
+        pool_mut.accrue_protocol_fee(token_in_index, protocol_fee)?;
+        let reserve_in_after = reserve_in
+            .checked_add(amount_received - protocol_fee)
+            .ok_or(ErrorCode::MathOverflow)?;
+        pool_mut.update_constant_sum_value(token_in_index, reserve_in, reserve_in_after, now)?;
+        pool_mut.update_constant_sum_value(token_out_index, reserve_out, reserve_out - amount_out, now)?;
+    }
+    
+    // 转出输出代币给用户，并接收用户的输入代币
+    transfer_swap(ctx.accounts, ctx.bumps.pool_authority, amount_in, amount_out)?;
+    
+    msg!(
+        "AnySwap: {} tokens swapped, {} in -> {} out (weight_in: {}, weight_out: {})",
+        amount_in,
+        mint_in_key,
+        mint_out_key,
+        weight_in,
+        weight_out
+    );
+    
+    Ok(())
+}
+
+/// 检查交易是否已超过截止时间（deadline 为 None 时不检查）
+pub(crate) fn check_deadline(deadline: Option<i64>, now: i64) -> Result<()> {
+    if let Some(deadline) = deadline {
+        require!(now <= deadline, ErrorCode::DeadlineExceeded);
+    }
+    Ok(())
+}
+
+/// 根据 vault 的 mint 查找交换的两个 token，返回 (token_in_index, token_out_index)
+/// 调用者提供索引提示时只验证提示对应的 mint，不再查找
+/// 同时验证传入的 vault 与 pool 中记录的 vault 地址一致
+pub(crate) fn resolve_swap_tokens(
+    pool: &PoolRef,
+    vault_in: &InterfaceAccount<TokenAccount>,
+    vault_out: &InterfaceAccount<TokenAccount>,
+    token_in_hint: Option<u16>,
+    token_out_hint: Option<u16>,
+) -> Result<(usize, usize)> {
+    let token_in_index = pool.resolve_token_index(&vault_in.mint, token_in_hint)?;
+    let token_out_index = pool.resolve_token_index(&vault_out.mint, token_out_hint)?;
+    
+    require!(token_in_index != token_out_index, ErrorCode::SameTokenSwap);
+    
+    let token_in = pool.get_token(token_in_index)
+        .ok_or(ErrorCode::InvalidTokenIndex)?;
+    let token_out = pool.get_token(token_out_index)
+        .ok_or(ErrorCode::InvalidTokenIndex)?;
+    
+    require!(
+        token_in.vault_pubkey().to_bytes() == vault_in.key().to_bytes(),
+        ErrorCode::InvalidTokenMint
+    );
+    require!(
+        token_out.vault_pubkey().to_bytes() == vault_out.key().to_bytes(),
+        ErrorCode::InvalidTokenMint
+    );
+    
+    Ok((token_in_index, token_out_index))
+}
+
+/// 读取 pool 中所有 vault 的储备量（按 pool 中 token 的顺序，不包括待领取的协议手续费）
+/// remaining_accounts 必须按 token 顺序传入所有 vault，且 owner 为 pool_authority
+pub(crate) fn load_all_reserves<'info>(
+    pool: &PoolRef,
+    remaining_accounts: &'info [AccountInfo<'info>],
+    pool_authority: &Pubkey,
+) -> Result<Vec<u64>> {
+    require!(
+        remaining_accounts.len() == pool.get_token_count(),
+        ErrorCode::InvalidTokenCount
+    );
+    
+    let mut reserves = Vec::with_capacity(remaining_accounts.len());
+    for (i, vault_info) in remaining_accounts.iter().enumerate() {
+        let token_item = pool.get_token(i).ok_or(ErrorCode::InvalidTokenIndex)?;
+        require!(
+            vault_info.key == token_item.vault_pubkey(),
+            ErrorCode::InvalidTokenMint
+        );
+        
+        let vault_account = InterfaceAccount::<TokenAccount>::try_from(vault_info)?;
+        require!(
+            vault_account.owner == *pool_authority,
+            ErrorCode::InvalidTokenMint
+        );
+        reserves.push(pool.get_reserve(i, vault_account.amount));
+    }
+    
+    Ok(reserves)
+}
+
+/// 读取交换需要的储备量（按 token 索引）
+/// Stable 曲线或启用动态失衡手续费时从 remaining_accounts 读取所有 vault，
+/// 否则只填入输入和输出 token 的储备量
+pub(crate) fn load_swap_reserves<'info>(
+    pool: &PoolRef,
+    remaining_accounts: &'info [AccountInfo<'info>],
+    pool_authority: &Pubkey,
+    token_in_index: usize,
+    token_out_index: usize,
+    reserve_in: u64,
+    reserve_out: u64,
+) -> Result<Vec<u64>> {
+    if pool.requires_all_reserves()? {
+        return load_all_reserves(pool, remaining_accounts, pool_authority);
+    }
+    
+    let mut reserves = vec![0u64; pool.get_token_count()];
+    reserves[token_in_index] = reserve_in;
+    reserves[token_out_index] = reserve_out;
+    Ok(reserves)
+}
+
+/// 验证恒定乘积和公式：amount_in * weight_in >= amount_out * weight_out
+/// 由于 weight 在同一时刻不变，只需要比较两边的变化量（换算到统一精度后比较）
+/// 注意：由于整数除法的取整，delta_out 可能略小于 delta_in，这是允许的
+pub(crate) fn verify_constant_sum_swap(
+    pool: &PoolRef,
+    token_in_index: usize,
+    amount_in: u64,
+    token_out_index: usize,
+    amount_out: u64,
+    now: i64,
+) -> Result<()> {
+    let delta_in = pool
+        .normalize_amount(token_in_index, amount_in)?
+        .checked_mul(pool.get_token_weight(token_in_index, now) as u128)
         .ok_or(ErrorCode::MathOverflow)?;
-    let delta_out = (amount_out as u128)
-        .checked_mul(weight_out as u128)
+    let delta_out = pool
+        .normalize_amount(token_out_index, amount_out)?
+        .checked_mul(pool.get_token_weight(token_out_index, now) as u128)
         .ok_or(ErrorCode::MathOverflow)?;
     
-    // 验证：delta_out <= delta_in（允许整数除法的舍入误差）
     // 如果 delta_out > delta_in，说明计算有误
     require!(
         delta_out <= delta_in,
         ErrorCode::MathOverflow
     );
-    
+    Ok(())
+}
+
+/// 执行交换的两笔转账：vault_out -> user_out（pool authority 签名），user_in -> vault_in
+/// 两笔转账都使用 transfer_checked，由 mint 所属的 token 程序执行
+pub(crate) fn transfer_swap(
+    accounts: &Swap,
+    pool_authority_bump: u8,
+    amount_in: u64,
+    amount_out: u64,
+) -> Result<()> {
     // 准备 seeds 用于签名
-    let pool_key = ctx.accounts.pool.key();
-    let bump = ctx.bumps.pool_authority;
+    let pool_key = accounts.pool.key();
     let seeds = &[
         b"anyswap_authority",
         pool_key.as_ref(),
-        &[bump],
+        &[pool_authority_bump],
     ];
     let signer = &[&seeds[..]];
     
     // 转出输出代币给用户
-    token::transfer(
+    let mint_out = accounts.mint_out.to_account_info();
+    token_interface::transfer_checked(
         CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            Transfer {
-                from: ctx.accounts.vault_out.to_account_info(),
-                to: ctx.accounts.user_out.to_account_info(),
-                authority: ctx.accounts.pool_authority.to_account_info(),
+            token_program_for(&mint_out, &accounts.token_program, &accounts.token_2022_program)?,
+            TransferChecked {
+                from: accounts.vault_out.to_account_info(),
+                mint: mint_out,
+                to: accounts.user_out.to_account_info(),
+                authority: accounts.pool_authority.to_account_info(),
             },
             signer,
         ),
         amount_out,
+        accounts.mint_out.decimals,
     )?;
     
     // 接收用户的输入代币
-    token::transfer(
+    let mint_in = accounts.mint_in.to_account_info();
+    token_interface::transfer_checked(
         CpiContext::new(
-            ctx.accounts.token_program.to_account_info(),
-            Transfer {
-                from: ctx.accounts.user_in.to_account_info(),
-                to: ctx.accounts.vault_in.to_account_info(),
-                authority: ctx.accounts.owner.to_account_info(),
+            token_program_for(&mint_in, &accounts.token_program, &accounts.token_2022_program)?,
+            TransferChecked {
+                from: accounts.user_in.to_account_info(),
+                mint: mint_in,
+                to: accounts.vault_in.to_account_info(),
+                authority: accounts.owner.to_account_info(),
             },
         ),
         amount_in,
+        accounts.mint_in.decimals,
     )?;
     
-    msg!(
-        "AnySwap: {} tokens swapped, {} in -> {} out (weight_in: {}, weight_out: {})",
-        amount_in,
-        mint_in_key,
-        mint_out_key,
-        weight_in,
-        weight_out
-    );
-    
     Ok(())
 }
-
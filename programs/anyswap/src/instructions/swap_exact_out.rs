@@ -0,0 +1,164 @@
+use anchor_lang::prelude::*;
+use crate::state::{CurveType, PoolLoader};
+use crate::error::ErrorCode;
+use super::swap::{
+    check_deadline, load_swap_reserves, resolve_swap_tokens, transfer_swap,
+    verify_constant_sum_swap, Swap,
+};
+use super::token_utils::amount_before_transfer_fee;
+
+/// AnySwap 精确输出交换
+/// 用户指定要获得的 amount_out，以及愿意支付的最大输入 max_amount_in
+/// 账户结构与 swap_anyswap 相同
+///
+/// 计算顺序与 swap_anyswap 相反：
+/// 1. 按 pool 的定价曲线反推扣除手续费后所需的输入
+/// 2. 反推含手续费的输入金额（费率可能包含动态失衡手续费）
+///
+/// 两步都向上取整，保证有利于 pool
+///
+/// Token-2022 转账手续费：amount_out 是用户实际到账的数量，vault 转出时加上输出 token 的转账手续费；
+/// 用户转入时加上输入 token 的转账手续费，保证 vault 实际收到所需的输入，max_amount_in 与用户转出的数量比较
+///
+/// RemainingAccounts（仅 Stable 曲线或启用动态失衡手续费时）：
+/// - 按照 pool 中 token 的顺序传入所有 vault 账户
+///
+/// deadline: 可选的截止时间（unix 时间戳），超过后交易失败
+/// token_in_index / token_out_index: 可选的 token 索引提示，提供时只验证不查找
+pub fn swap_exact_out<'remaining: 'info, 'info>(
+    ctx: Context<'_, '_, 'remaining, 'info, Swap<'info>>,
+    amount_out: u64,
+    max_amount_in: u64,
+    deadline: Option<i64>,
+    token_in_index: Option<u16>,
+    token_out_index: Option<u16>,
+) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    check_deadline(deadline, now)?;
+    require!(amount_out > 0, ErrorCode::InsufficientOutputAmount);
+    
+    // 加载 pool
+    let pool = ctx.accounts.pool.load_pool()?;
+    
+    // 从 vault 账户获取 mint 地址
+    let mint_in_key = ctx.accounts.vault_in.mint;
+    let mint_out_key = ctx.accounts.vault_out.mint;
+    
+    // 查找 token 索引并验证 vault 账户
+    let (token_in_index, token_out_index) = resolve_swap_tokens(
+        &pool,
+        &ctx.accounts.vault_in,
+        &ctx.accounts.vault_out,
+        token_in_index,
+        token_out_index,
+    )?;
+    
+    // 检查 pool 没有暂停，且两个 token 都允许交换
+    pool.require_swap_enabled(token_in_index, token_out_index)?;
+    
+    // 获取当前储备量（不包括待领取的协议手续费）
+    let reserve_in = pool.get_reserve(token_in_index, ctx.accounts.vault_in.amount);
+    let reserve_out = pool.get_reserve(token_out_index, ctx.accounts.vault_out.amount);
+    
+    require!(
+        reserve_in > 0 && reserve_out > 0,
+        ErrorCode::InsufficientLiquidity
+    );
+    
+    // vault 需要转出的数量（用户实际到账 amount_out）
+    let vault_amount_out = amount_before_transfer_fee(
+        &ctx.accounts.mint_out.to_account_info(),
+        amount_out,
+    )?;
+    
+    // 输出不能耗尽 vault
+    require!(
+        vault_amount_out < reserve_out,
+        ErrorCode::InsufficientLiquidity
+    );
+    
+    // 使用 pool 反推所需输入和手续费（含动态失衡手续费）
+    let reserves = load_swap_reserves(
+        &pool,
+        ctx.remaining_accounts,
+        &ctx.accounts.pool_authority.key(),
+        token_in_index,
+        token_out_index,
+        reserve_in,
+        reserve_out,
+    )?;
+    let quote = pool.quote_swap_exact_out(
+        token_in_index,
+        token_out_index,
+        &reserves,
+        vault_amount_out,
+        now,
+    )?;
+    let fee_amount = quote.fee_amount;
+    let vault_amount_in = quote.amount_in;
+    let amount_in_minus_fees = vault_amount_in - fee_amount;
+    let curve_type = pool.get_curve_type()?;
+    
+    // 用户需要转出的数量（vault 实际收到 vault_amount_in）
+    let amount_in = amount_before_transfer_fee(
+        &ctx.accounts.mint_in.to_account_info(),
+        vault_amount_in,
+    )?;
+    
+    // 检查输入数量是否超过用户上限
+    require!(
+        amount_in <= max_amount_in,
+        ErrorCode::SlippageExceeded
+    );
+    
+    // 检查用户余额
+    require!(
+        ctx.accounts.user_in.amount >= amount_in,
+        ErrorCode::InsufficientTokenAmount
+    );
+    
+    let weight_in = pool.get_token_weight(token_in_index, now);
+    let weight_out = pool.get_token_weight(token_out_index, now);
+    
+    // 恒定乘积和曲线：验证 amount_in_minus_fees * weight_in >= amount_out * weight_out
+    if curve_type == CurveType::ConstantSum {
+        verify_constant_sum_swap(
+            &pool,
+            token_in_index,
+            amount_in_minus_fees,
+            token_out_index,
+            vault_amount_out,
+            now,
+        )?;
+    }
+    
+    let protocol_fee = pool.calculate_protocol_fee(fee_amount)?;
+    
+    drop(pool);
+    
+    // 累加协议手续费，更新 ConstantSum 曲线记录的 Σ(vault * weight)（协议手续费不计入储备量）
+    {
+        let mut pool_mut = ctx.accounts.pool.load_pool_mut()?;
+        pool_mut.accrue_protocol_fee(token_in_index, protocol_fee)?;
+        let reserve_in_after = reserve_in
+            .checked_add(vault_amount_in - protocol_fee)
+            .ok_or(ErrorCode::MathOverflow)?;
+        pool_mut.update_constant_sum_value(token_in_index, reserve_in, reserve_in_after, now)?;
+        pool_mut.update_constant_sum_value(token_out_index, reserve_out, reserve_out - vault_amount_out, now)?;
+    }
+    
+    // 转出输出代币给用户，并接收用户的输入代币
+    transfer_swap(ctx.accounts, ctx.bumps.pool_authority, amount_in, vault_amount_out)?;
+    
+    msg!(
+        "AnySwap exact out: {} {} in -> {} {} out (weight_in: {}, weight_out: {})",
+        amount_in,
+        mint_in_key,
+        amount_out,
+        mint_out_key,
+        weight_in,
+        weight_out
+    );
+    
+    Ok(())
+}
@@ -0,0 +1,283 @@
+use anchor_lang::prelude::*;
+use crate::state::{AnySwapPool, CurveType, PoolLoader, FactoryConfig, QueuedAction, TimelockAction};
+use crate::math::{fixed_point, stable};
+use crate::error::ErrorCode;
+use super::mint_index::remove_token_with_index;
+use super::modify_fee::{set_surge_fee, set_token_fee};
+use super::ramp_amp::start_amp_ramp;
+
+/// 设置时间锁延迟
+#[derive(Accounts)]
+pub struct SetTimelockDelay<'info> {
+    #[account(mut)]
+    pub pool: AccountLoader<'info, AnySwapPool>,
+
+    /// Pool 管理员 - 必须签名
+    /// CHECK: 验证是否为 pool 的管理员
+    pub admin: Signer<'info>,
+}
+
+/// 设置时间锁的最短延迟（只有管理员可以调用）
+/// 只影响之后排队的操作，已排队操作的 eta 不变
+/// 只能提高延迟；降低延迟必须通过 queue_action 排队 SetTimelockDelay，否则时间锁可以被立即绕过
+pub fn set_timelock_delay(ctx: Context<SetTimelockDelay>, delay: i64) -> Result<()> {
+    require!(delay >= 0, ErrorCode::InvalidTimelockAction);
+    
+    let pool = &mut ctx.accounts.pool.load_pool_mut()?;
+    
+    // 验证管理员权限
+    pool.verify_admin(&ctx.accounts.admin.key())?;
+    
+    require!(delay >= pool.timelock_delay, ErrorCode::TimelockRequired);
+    
+    pool.timelock_delay = delay;
+    
+    msg!("Timelock delay updated: {}", delay);
+    Ok(())
+}
+
+/// 把操作加入时间锁队列
+#[derive(Accounts)]
+#[instruction(action_id: u64)]
+pub struct QueueAction<'info> {
+    #[account(mut)]
+    pub pool: AccountLoader<'info, AnySwapPool>,
+
+    /// 排队的操作（PDA），action_id 必须等于 pool 当前的 timelock_nonce
+    #[account(
+        init,
+        payer = payer,
+        space = QueuedAction::space(),
+        seeds = [b"timelock", pool.key().as_ref(), &action_id.to_le_bytes()],
+        bump
+    )]
+    pub queued_action: Account<'info, QueuedAction>,
+
+    /// Pool 管理员或该操作对应角色的持有者
+    /// CHECK: 验证是否为 pool 的管理员或对应角色
+    pub authority: Signer<'info>,
+
+    /// 支付操作账户的租金，执行或取消后退还
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// 执行或取消排队的操作
+#[derive(Accounts)]
+pub struct ResolveAction<'info> {
+    #[account(mut)]
+    pub pool: AccountLoader<'info, AnySwapPool>,
+
+    /// 排队的操作，完成后关闭并把租金退还给 payer
+    #[account(
+        mut,
+        has_one = pool,
+        has_one = payer,
+        close = payer
+    )]
+    pub queued_action: Account<'info, QueuedAction>,
+
+    /// Pool 管理员或该操作对应角色的持有者
+    /// CHECK: 验证是否为 pool 的管理员或对应角色
+    pub authority: Signer<'info>,
+
+    /// 排队时支付租金的账户
+    /// CHECK: 通过 has_one 验证
+    #[account(mut)]
+    pub payer: AccountInfo<'info>,
+
+    /// 协议全局配置 - 执行修改费率时检查费率范围和全局开关
+    #[account(
+        seeds = [b"factory_config"],
+        bump = factory_config.bump
+    )]
+    pub factory_config: Account<'info, FactoryConfig>,
+}
+
+/// 验证 authority 有权排队、执行或取消该操作
+fn verify_action_authority(pool: &AnySwapPool, action: &TimelockAction, authority: &Pubkey) -> Result<()> {
+    match action.role() {
+        Some(role) => pool.verify_role(role, authority),
+        None => pool.verify_admin(authority),
+    }
+}
+
+/// 把操作加入时间锁队列（在 timelock_delay 秒之后才能执行）
+/// action_id: 必须等于 pool 当前的 timelock_nonce，用于派生操作账户地址
+/// 注意：参数在排队时检查一次，执行时会按当时的 pool 状态再次检查
+pub fn queue_action(
+    ctx: Context<QueueAction>,
+    action_id: u64,
+    action: TimelockAction,
+) -> Result<()> {
+    let pool = &mut ctx.accounts.pool.load_pool_mut()?;
+    
+    // 验证权限：管理员或对应角色
+    verify_action_authority(pool, &action, &ctx.accounts.authority.key())?;
+    require!(action_id == pool.timelock_nonce, ErrorCode::InvalidTimelockAction);
+    
+    // 检查参数
+    match action {
+        TimelockAction::ModifyFee { fee_numerator, fee_denominator } => {
+            require!(fee_denominator > 0, ErrorCode::MathOverflow);
+            require!(fee_numerator > 0, ErrorCode::MathOverflow);
+            require!(fee_numerator <= fee_denominator, ErrorCode::MathOverflow);
+        }
+        TimelockAction::ModifyTokenFee { mint, fee_numerator, fee_denominator } => {
+            require!(
+                fee_denominator == 0 || fee_numerator < fee_denominator,
+                ErrorCode::MathOverflow
+            );
+            pool.find_token_index(&mint).ok_or(ErrorCode::InvalidTokenMint)?;
+        }
+        TimelockAction::ModifyFeePolicy { .. } => {}
+        TimelockAction::ModifySurgeFee { max_fee, threshold } => {
+            require!(
+                max_fee == 0 || pool.get_curve_type()? != CurveType::Weighted,
+                ErrorCode::InvalidCurveType
+            );
+            require!((max_fee as u128) < fixed_point::ONE, ErrorCode::MathOverflow);
+            require!((threshold as u128) < fixed_point::ONE, ErrorCode::MathOverflow);
+        }
+        TimelockAction::ModifyTokenWeight { mint, new_weight } => {
+            require!(new_weight > 0, ErrorCode::InvalidTokenCount);
+            pool.find_token_index(&mint).ok_or(ErrorCode::InvalidTokenMint)?;
+        }
+        TimelockAction::RampTokenWeight { mint, end_weight, start_time, end_time } => {
+            require!(end_weight > 0, ErrorCode::InvalidTokenCount);
+            require!(end_time > start_time, ErrorCode::InvalidRampSchedule);
+            pool.find_token_index(&mint).ok_or(ErrorCode::InvalidTokenMint)?;
+        }
+        TimelockAction::RampAmp { target_amp, .. } => {
+            require!(
+                (stable::MIN_AMP..=stable::MAX_AMP).contains(&target_amp),
+                ErrorCode::InvalidAmplification
+            );
+            require!(
+                pool.get_curve_type()? == CurveType::Stable,
+                ErrorCode::InvalidCurveType
+            );
+        }
+        TimelockAction::RemoveToken { mint } => {
+            pool.find_token_index(&mint).ok_or(ErrorCode::InvalidTokenMint)?;
+        }
+        TimelockAction::ChangeAdmin { .. } => {}
+        TimelockAction::SetTimelockDelay { delay } => {
+            require!(delay >= 0, ErrorCode::InvalidTimelockAction);
+        }
+    }
+    
+    let eta = Clock::get()?.unix_timestamp
+        .checked_add(pool.timelock_delay)
+        .ok_or(ErrorCode::MathOverflow)?;
+    pool.timelock_nonce = pool.timelock_nonce
+        .checked_add(1)
+        .ok_or(ErrorCode::MathOverflow)?;
+    
+    let queued_action = &mut ctx.accounts.queued_action;
+    queued_action.pool = ctx.accounts.pool.key();
+    queued_action.action_id = action_id;
+    queued_action.action = action;
+    queued_action.eta = eta;
+    queued_action.payer = ctx.accounts.payer.key();
+    
+    msg!("Timelock action queued: id: {}, action: {:?}, eta: {}", action_id, action, eta);
+    Ok(())
+}
+
+/// 执行到期的操作（eta 之后），执行后关闭操作账户
+///
+/// RemainingAccounts 结构（仅移除 token 时需要）：
+/// - 被移除 token 的 mint 索引账户
+/// - 如果被移除的不是最后一个 token，最后一个 token 的 mint 索引账户
+pub fn execute_action(ctx: Context<ResolveAction>) -> Result<()> {
+    let pool = &mut ctx.accounts.pool.load_pool_mut()?;
+    let queued_action = &ctx.accounts.queued_action;
+    let action = queued_action.action;
+    
+    // 验证权限：管理员或对应角色（排队后角色可能已被撤销）
+    verify_action_authority(pool, &action, &ctx.accounts.authority.key())?;
+    let now = Clock::get()?.unix_timestamp;
+    require!(now >= queued_action.eta, ErrorCode::TimelockNotReady);
+    
+    match action {
+        TimelockAction::ModifyFee { fee_numerator, fee_denominator } => {
+            let factory_config = &ctx.accounts.factory_config;
+            factory_config.require_not_paused()?;
+            factory_config.verify_fee(fee_numerator, fee_denominator)?;
+            pool.set_fee(fee_numerator, fee_denominator)?;
+        }
+        TimelockAction::ModifyTokenFee { mint, fee_numerator, fee_denominator } => {
+            set_token_fee(pool, &ctx.accounts.factory_config, &mint, fee_numerator, fee_denominator)?;
+        }
+        TimelockAction::ModifyFeePolicy { fee_policy } => {
+            pool.fee_policy = fee_policy.to_u8();
+        }
+        TimelockAction::ModifySurgeFee { max_fee, threshold } => {
+            set_surge_fee(pool, &ctx.accounts.factory_config, max_fee, threshold)?;
+        }
+        TimelockAction::ModifyTokenWeight { mint, new_weight } => {
+            require!(new_weight > 0, ErrorCode::InvalidTokenCount);
+            let token_index = pool.find_token_index(&mint)
+                .ok_or(ErrorCode::InvalidTokenMint)?;
+            let token = pool.get_token_mut(token_index)
+                .ok_or(ErrorCode::InvalidTokenIndex)?;
+            token.set_weight(new_weight);
+            // 权重变化后记录的 Σ(vault * weight) 需要重新同步
+            pool.set_constant_sum_value(None);
+        }
+        TimelockAction::RampTokenWeight { mint, end_weight, start_time, end_time } => {
+            require!(
+                start_time >= now && end_time > start_time,
+                ErrorCode::InvalidRampSchedule
+            );
+            let token_index = pool.find_token_index(&mint)
+                .ok_or(ErrorCode::InvalidTokenMint)?;
+            let start_weight = pool.get_token_weight(token_index, now);
+            let token = pool.get_token_mut(token_index)
+                .ok_or(ErrorCode::InvalidTokenIndex)?;
+            token.set_weight_ramp(start_weight, end_weight, start_time, end_time);
+            // 权重变化后记录的 Σ(vault * weight) 需要重新同步
+            pool.set_constant_sum_value(None);
+        }
+        TimelockAction::RampAmp { target_amp, end_time } => {
+            start_amp_ramp(pool, target_amp, end_time, now)?;
+        }
+        TimelockAction::RemoveToken { mint } => {
+            let token_index = pool.find_token_index(&mint)
+                .ok_or(ErrorCode::InvalidTokenMint)?;
+            let removed_mint_index = ctx.remaining_accounts.first()
+                .ok_or(ErrorCode::InvalidMintIndex)?;
+            remove_token_with_index(
+                pool,
+                &ctx.accounts.pool.key(),
+                token_index,
+                removed_mint_index,
+                ctx.remaining_accounts.get(1),
+            )?;
+        }
+        TimelockAction::ChangeAdmin { new_admin } => {
+            pool.pending_admin = new_admin;
+        }
+        TimelockAction::SetTimelockDelay { delay } => {
+            pool.timelock_delay = delay;
+        }
+    }
+    
+    msg!("Timelock action executed: id: {}, action: {:?}", queued_action.action_id, action);
+    Ok(())
+}
+
+/// 取消排队的操作，取消后关闭操作账户
+pub fn cancel_action(ctx: Context<ResolveAction>) -> Result<()> {
+    let pool = ctx.accounts.pool.load_pool()?;
+    let queued_action = &ctx.accounts.queued_action;
+    
+    // 验证权限：管理员或对应角色
+    verify_action_authority(&pool, &queued_action.action, &ctx.accounts.authority.key())?;
+    
+    msg!("Timelock action cancelled: id: {}, action: {:?}", queued_action.action_id, queued_action.action);
+    Ok(())
+}
@@ -0,0 +1,94 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::Token;
+use anchor_spl::token_2022::spl_token_2022::{
+    extension::{
+        transfer_fee::TransferFeeConfig, BaseStateWithExtensions, ExtensionType,
+        StateWithExtensions,
+    },
+    state::Mint as MintState,
+};
+use anchor_spl::token_2022::Token2022;
+use crate::error::ErrorCode;
+
+/// pool 可以安全支持的 Token-2022 mint 扩展
+/// 转账手续费按实际到账数量记账；其余扩展只影响元数据或显示，不影响转账数量
+/// 转账钩子、永久代理、不可转账、机密转账、默认冻结和可暂停等扩展会导致 vault 余额
+/// 被第三方控制或无法转出，上架时拒绝
+const SUPPORTED_MINT_EXTENSIONS: [ExtensionType; 10] = [
+    ExtensionType::TransferFeeConfig,
+    ExtensionType::MintCloseAuthority,
+    ExtensionType::InterestBearingConfig,
+    ExtensionType::ScaledUiAmount,
+    ExtensionType::MetadataPointer,
+    ExtensionType::TokenMetadata,
+    ExtensionType::GroupPointer,
+    ExtensionType::TokenGroup,
+    ExtensionType::GroupMemberPointer,
+    ExtensionType::TokenGroupMember,
+];
+
+/// 返回 mint 所属的 token 程序（Token 或 Token-2022），用于转账 CPI
+pub(crate) fn token_program_for<'info>(
+    mint: &AccountInfo<'info>,
+    token_program: &Program<'info, Token>,
+    token_2022_program: &Program<'info, Token2022>,
+) -> Result<AccountInfo<'info>> {
+    if mint.owner == token_program.key {
+        Ok(token_program.to_account_info())
+    } else if mint.owner == token_2022_program.key {
+        Ok(token_2022_program.to_account_info())
+    } else {
+        Err(ErrorCode::InvalidTokenMint.into())
+    }
+}
+
+/// 验证 mint 没有 pool 无法安全支持的扩展（普通 Token mint 直接通过）
+pub(crate) fn validate_mint_extensions(mint: &AccountInfo) -> Result<()> {
+    if *mint.owner != Token2022::id() {
+        return Ok(());
+    }
+    let data = mint.try_borrow_data()?;
+    let state = StateWithExtensions::<MintState>::unpack(&data)?;
+    for extension in state.get_extension_types()? {
+        require!(
+            SUPPORTED_MINT_EXTENSIONS.contains(&extension),
+            ErrorCode::UnsupportedMintExtension
+        );
+    }
+    Ok(())
+}
+
+/// 读取 mint 在当前 epoch 的转账手续费配置，没有转账手续费时返回 None
+fn with_transfer_fee<R>(
+    mint: &AccountInfo,
+    f: impl FnOnce(&TransferFeeConfig, u64) -> Option<R>,
+) -> Result<Option<R>> {
+    if *mint.owner != Token2022::id() {
+        return Ok(None);
+    }
+    let data = mint.try_borrow_data()?;
+    let state = StateWithExtensions::<MintState>::unpack(&data)?;
+    match state.get_extension::<TransferFeeConfig>() {
+        Ok(config) => {
+            let epoch = Clock::get()?.epoch;
+            Ok(Some(f(config, epoch).ok_or(ErrorCode::MathOverflow)?))
+        }
+        Err(_) => Ok(None),
+    }
+}
+
+/// 转出 amount 后接收方实际到账的数量（扣除 Token-2022 转账手续费）
+pub(crate) fn amount_after_transfer_fee(mint: &AccountInfo, amount: u64) -> Result<u64> {
+    let received = with_transfer_fee(mint, |config, epoch| {
+        amount.checked_sub(config.calculate_epoch_fee(epoch, amount)?)
+    })?;
+    Ok(received.unwrap_or(amount))
+}
+
+/// 让接收方实际到账 received 需要转出的数量（加上 Token-2022 转账手续费）
+pub(crate) fn amount_before_transfer_fee(mint: &AccountInfo, received: u64) -> Result<u64> {
+    let amount = with_transfer_fee(mint, |config, epoch| {
+        config.get_epoch_fee(epoch).calculate_pre_fee_amount(received)
+    })?;
+    Ok(amount.unwrap_or(received))
+}
@@ -3,40 +3,96 @@ use anchor_lang::prelude::*;
 pub mod instructions;
 pub mod state;
 pub mod error;
+pub mod math;
 
 use instructions::*;
+use state::{CurveType, FactoryConfigParams, FeePolicy, ProposalAccount, Role, SwapQuote, TimelockAction};
 declare_id!("3GBxn5VSThpKNyUgaQ96xjSXD2zJ1164LzK28MXv4MDC");
 
 #[program]
 pub mod anyswap {
     use super::*;
 
-    /// 创建 Pool（PDA）
+    /// 初始化协议全局配置（只能由程序的升级权限调用一次）
+    pub fn initialize_factory_config(
+        ctx: Context<InitializeFactoryConfig>,
+        params: FactoryConfigParams,
+    ) -> Result<()> {
+        instructions::initialize_factory_config(ctx, params)
+    }
+
+    /// 修改协议全局配置（协议管理员）
+    pub fn update_factory_config(
+        ctx: Context<UpdateFactoryConfig>,
+        params: FactoryConfigParams,
+    ) -> Result<()> {
+        instructions::update_factory_config(ctx, params)
+    }
+
+    /// 领取创建 pool 时收取的创建费（协议管理员）
+    pub fn collect_creation_fees(ctx: Context<CollectCreationFees>) -> Result<()> {
+        instructions::collect_creation_fees(ctx)
+    }
+
+    /// 创建 Pool（预先分配的账户），并追加到 pool 注册表
+    /// 费率必须在全局配置允许的范围内，并支付全局配置中的创建费
+    /// curve_type: pool 使用的定价曲线，创建后不可修改
+    /// amp: StableSwap 的放大系数，仅 Stable 曲线使用
     pub fn create_pool(
         ctx: Context<CreatePool>,
         fee_numerator: u64,
         fee_denominator: u64,
+        curve_type: CurveType,
+        amp: u64,
     ) -> Result<()> {
-        instructions::create_pool(ctx, fee_numerator, fee_denominator)
+        instructions::create_pool(ctx, fee_numerator, fee_denominator, curve_type, amp)
     }
 
-    /// 添加 token 到 AnySwap Pool
+    /// 创建 PDA pool（地址为 [b"pool", pool_creator, pool_id]），并追加到 pool 注册表
+    pub fn create_pool_with_id(
+        ctx: Context<CreatePoolWithId>,
+        pool_id: u64,
+        fee_numerator: u64,
+        fee_denominator: u64,
+        curve_type: CurveType,
+        amp: u64,
+    ) -> Result<()> {
+        instructions::create_pool_with_id(ctx, pool_id, fee_numerator, fee_denominator, curve_type, amp)
+    }
+
+    /// 添加 token 到 AnySwap Pool（管理员或 Token 管理员），支持普通 Token 和 Token-2022 mint
     /// RemainingAccounts: 如果 pool 中已有 token，需要传入现有 vault 的账户信息
+    /// seed_amount: Weighted 曲线的 pool 已有流动性时新 token 存入的数量，其他曲线按不变量计算，传入 None
     pub fn add_token_to_pool<'remaining: 'info, 'info>(
         ctx: Context<'_, '_, 'remaining, 'info, AddTokenToPool<'info>>,
         weight: u64,
+        seed_amount: Option<u64>,
     ) -> Result<()> {
-        instructions::add_token_to_pool(ctx, weight)
+        instructions::add_token_to_pool(ctx, weight, seed_amount)
     }
 
-    /// 从 AnySwap Pool 移除 token
+    /// 从 AnySwap Pool 移除 token（管理员或 Token 管理员）
     pub fn remove_token_from_pool(
         ctx: Context<RemoveTokenFromPool>,
     ) -> Result<()> {
         instructions::remove_token_from_pool(ctx)
     }
 
-    /// 修改 token 的 weight
+    /// 缩小 pool 账户到刚好容纳现有的 token，回收多余的租金（管理员或 Token 管理员）
+    pub fn compact_pool(ctx: Context<CompactPool>) -> Result<()> {
+        instructions::compact_pool(ctx)
+    }
+
+    /// 把旧的固定大小 pool 改写为当前布局（只有管理员可以调用）
+    /// 旧 pool 在迁移前不能使用其他指令；迁移时把 pool 追加到注册表，并记录到每个 token 的 mint 反向索引
+    /// remaining accounts 按 token 顺序传入 (mint, mint_index)
+    pub fn migrate_pool<'remaining: 'info, 'info>(
+        ctx: Context<'_, '_, 'remaining, 'info, MigratePool<'info>>,
+    ) -> Result<()> {
+        instructions::migrate_pool(ctx)
+    }
+
+    /// 修改 token 的 weight（管理员或权重管理员）
     pub fn modify_token_weight(
         ctx: Context<ModifyTokenWeight>,
         new_weight: u64,
@@ -44,7 +100,17 @@ pub mod anyswap {
         instructions::modify_token_weight(ctx, new_weight)
     }
 
-    /// 修改 pool 的费率
+    /// 按时间线性调整 token 的 weight（从 start_time 的当前权重到 end_time 的 end_weight）
+    pub fn ramp_token_weight(
+        ctx: Context<ModifyTokenWeight>,
+        end_weight: u64,
+        start_time: i64,
+        end_time: i64,
+    ) -> Result<()> {
+        instructions::ramp_token_weight(ctx, end_weight, start_time, end_time)
+    }
+
+    /// 修改 pool 的费率（管理员或费率管理员）
     pub fn modify_fee(
         ctx: Context<ModifyFee>,
         fee_numerator: u64,
@@ -53,32 +119,302 @@ pub mod anyswap {
         instructions::modify_fee(ctx, fee_numerator, fee_denominator)
     }
 
+    /// 修改单个 token 的费率（fee_denominator 为 0 表示恢复使用 pool 的费率）
+    pub fn modify_token_fee(
+        ctx: Context<ModifyTokenFee>,
+        fee_numerator: u64,
+        fee_denominator: u64,
+    ) -> Result<()> {
+        instructions::modify_token_fee(ctx, fee_numerator, fee_denominator)
+    }
+
+    /// 修改 pool 的手续费策略（交换时按输入 token、输出 token 或较高者的费率收取）
+    pub fn modify_fee_policy(
+        ctx: Context<ModifyFee>,
+        fee_policy: FeePolicy,
+    ) -> Result<()> {
+        instructions::modify_fee_policy(ctx, fee_policy)
+    }
+
+    /// 修改动态失衡手续费（max_fee 和 threshold 为 18 位定点数，max_fee 为 0 表示不启用）
+    /// Weighted 曲线的 pool 不能启用，max_fee 需要在全局配置的费率范围内
+    pub fn modify_surge_fee(
+        ctx: Context<ModifyFee>,
+        max_fee: u64,
+        threshold: u64,
+    ) -> Result<()> {
+        instructions::modify_surge_fee(ctx, max_fee, threshold)
+    }
+
+    /// 修改协议手续费比例（占手续费的 protocol_fee_share / 10000）和协议金库，只有协议管理员可以调用
+    pub fn modify_protocol_fee(
+        ctx: Context<ModifyProtocolFee>,
+        protocol_fee_share: u64,
+        treasury: Pubkey,
+    ) -> Result<()> {
+        instructions::modify_protocol_fee(ctx, protocol_fee_share, treasury)
+    }
+
+    /// 调整 StableSwap pool 的放大系数（在 end_time 前线性过渡到 target_amp，需要管理员或权重管理员，启用时间锁后通过 queue_action）
+    pub fn ramp_amp(
+        ctx: Context<ModifyAmp>,
+        target_amp: u64,
+        end_time: i64,
+    ) -> Result<()> {
+        instructions::ramp_amp(ctx, target_amp, end_time)
+    }
+
+    /// 停止放大系数调整，固定为当前值
+    pub fn stop_ramp_amp(
+        ctx: Context<ModifyAmp>,
+    ) -> Result<()> {
+        instructions::stop_ramp_amp(ctx)
+    }
+
+    /// 暂停或恢复 pool（守护者只能暂停，恢复需要管理员）
+    pub fn set_pool_paused(
+        ctx: Context<SetPoolPaused>,
+        paused: bool,
+    ) -> Result<()> {
+        instructions::set_pool_paused(ctx, paused)
+    }
+
+    /// 设置 token 的禁用标志（守护者只能增加禁用，解除需要管理员）
+    pub fn set_token_flags(
+        ctx: Context<SetTokenFlags>,
+        swap_in_disabled: bool,
+        swap_out_disabled: bool,
+        deposits_disabled: bool,
+    ) -> Result<()> {
+        instructions::set_token_flags(ctx, swap_in_disabled, swap_out_disabled, deposits_disabled)
+    }
+
+    /// 设置 pool 的守护者（只有管理员可以调用），等同于授予 PauseGuardian 角色
+    pub fn set_guardian(
+        ctx: Context<SetPoolPaused>,
+        guardian: Pubkey,
+    ) -> Result<()> {
+        instructions::set_guardian(ctx, guardian)
+    }
+
+    /// 提议转移管理员（需要新管理员调用 accept_admin 确认）
+    pub fn propose_admin(
+        ctx: Context<ProposeAdmin>,
+        new_admin: Pubkey,
+    ) -> Result<()> {
+        instructions::propose_admin(ctx, new_admin)
+    }
+
+    /// 取消转移管理员的提议
+    pub fn cancel_admin_proposal(ctx: Context<ProposeAdmin>) -> Result<()> {
+        instructions::cancel_admin_proposal(ctx)
+    }
+
+    /// 接受管理员转移（由待接受的新管理员签名）
+    pub fn accept_admin(ctx: Context<AcceptAdmin>) -> Result<()> {
+        instructions::accept_admin(ctx)
+    }
+
+    /// 授予角色（费率管理员、权重管理员、Token 管理员、紧急守护者），只有管理员可以调用
+    pub fn grant_role(
+        ctx: Context<ManageRole>,
+        role: Role,
+        holder: Pubkey,
+    ) -> Result<()> {
+        instructions::grant_role(ctx, role, holder)
+    }
+
+    /// 撤销角色，只有管理员可以调用
+    pub fn revoke_role(ctx: Context<ManageRole>, role: Role) -> Result<()> {
+        instructions::revoke_role(ctx, role)
+    }
+
+    /// 设置时间锁的最短延迟（秒），只有管理员可以调用
+    /// 只能提高延迟，降低延迟需要通过 queue_action 排队
+    pub fn set_timelock_delay(ctx: Context<SetTimelockDelay>, delay: i64) -> Result<()> {
+        instructions::set_timelock_delay(ctx, delay)
+    }
+
+    /// 把修改费率（包括 token 费率、手续费策略和动态失衡手续费）、修改或调整权重、调整放大系数、移除 token、
+    /// 转移管理员或降低延迟的操作加入时间锁队列
+    /// 延迟大于 0 时，对应的直接指令会被拒绝，只能通过队列执行
+    pub fn queue_action(
+        ctx: Context<QueueAction>,
+        action_id: u64,
+        action: TimelockAction,
+    ) -> Result<()> {
+        instructions::queue_action(ctx, action_id, action)
+    }
+
+    /// 执行时间锁中到期的操作
+    pub fn execute_action(ctx: Context<ResolveAction>) -> Result<()> {
+        instructions::execute_action(ctx)
+    }
+
+    /// 取消时间锁中排队的操作
+    pub fn cancel_action(ctx: Context<ResolveAction>) -> Result<()> {
+        instructions::cancel_action(ctx)
+    }
+
+    /// 创建 M-of-N 多签，多签 signer PDA 可以作为 pool 的 admin
+    pub fn create_multisig(
+        ctx: Context<CreateMultisig>,
+        members: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<()> {
+        instructions::create_multisig(ctx, members, threshold)
+    }
+
+    /// 修改多签成员和阈值（需要通过多签执行）
+    pub fn set_multisig_members(
+        ctx: Context<SetMultisigMembers>,
+        members: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<()> {
+        instructions::set_multisig_members(ctx, members, threshold)
+    }
+
+    /// 达到阈值的成员同时签名，直接以多签身份执行本程序的指令
+    pub fn execute_multisig_instruction<'remaining: 'info, 'info>(
+        ctx: Context<'_, '_, 'remaining, 'info, ExecuteMultisigInstruction<'info>>,
+        signer_count: u8,
+        data: Vec<u8>,
+    ) -> Result<()> {
+        instructions::execute_multisig_instruction(ctx, signer_count, data)
+    }
+
+    /// 创建多签提案（提案人自动批准）
+    pub fn create_multisig_proposal(
+        ctx: Context<CreateMultisigProposal>,
+        proposal_id: u64,
+        accounts: Vec<ProposalAccount>,
+        data: Vec<u8>,
+    ) -> Result<()> {
+        instructions::create_multisig_proposal(ctx, proposal_id, accounts, data)
+    }
+
+    /// 批准多签提案
+    pub fn approve_multisig_proposal(ctx: Context<ApproveMultisigProposal>) -> Result<()> {
+        instructions::approve_multisig_proposal(ctx)
+    }
+
+    /// 执行已达到阈值的多签提案
+    pub fn execute_multisig_proposal<'remaining: 'info, 'info>(
+        ctx: Context<'_, '_, 'remaining, 'info, ExecuteMultisigProposal<'info>>,
+    ) -> Result<()> {
+        instructions::execute_multisig_proposal(ctx)
+    }
+
     /// AnySwap 交换代币
-    pub fn swap_anyswap(
-        ctx: Context<Swap>,
+    /// deadline: 可选的截止时间（unix 时间戳）
+    /// token_in_index / token_out_index: 可选的 token 索引提示（只验证不查找）
+    /// RemainingAccounts: Stable 曲线或启用动态失衡手续费时需要按 pool 中 token 的顺序传入所有 vault 账户
+    pub fn swap_anyswap<'remaining: 'info, 'info>(
+        ctx: Context<'_, '_, 'remaining, 'info, Swap<'info>>,
         amount_in: u64,
         min_amount_out: u64,
+        deadline: Option<i64>,
+        token_in_index: Option<u16>,
+        token_out_index: Option<u16>,
     ) -> Result<()> {
-        instructions::swap_anyswap(ctx, amount_in, min_amount_out)
+        instructions::swap_anyswap(ctx, amount_in, min_amount_out, deadline, token_in_index, token_out_index)
+    }
+
+    /// AnySwap 精确输出交换：获得 amount_out，最多支付 max_amount_in
+    /// deadline: 可选的截止时间（unix 时间戳）
+    /// token_in_index / token_out_index: 可选的 token 索引提示（只验证不查找）
+    /// RemainingAccounts: Stable 曲线或启用动态失衡手续费时需要按 pool 中 token 的顺序传入所有 vault 账户
+    pub fn swap_exact_out<'remaining: 'info, 'info>(
+        ctx: Context<'_, '_, 'remaining, 'info, Swap<'info>>,
+        amount_out: u64,
+        max_amount_in: u64,
+        deadline: Option<i64>,
+        token_in_index: Option<u16>,
+        token_out_index: Option<u16>,
+    ) -> Result<()> {
+        instructions::swap_exact_out(ctx, amount_out, max_amount_in, deadline, token_in_index, token_out_index)
+    }
+
+    /// 批量交换：在同一个 pool 中按顺序执行多笔交换，只按净额转账
+    /// deadline: 可选的截止时间（unix 时间戳）
+    /// RemainingAccounts: 用到的 token 的 (user_token_account, vault_account, mint) 组，按 token 索引排序
+    /// Stable 曲线或启用动态失衡手续费时还需要在之后按 pool 中 token 的顺序传入所有 vault 账户
+    pub fn batch_swap<'remaining: 'info, 'info>(
+        ctx: Context<'_, '_, 'remaining, 'info, BatchSwap<'info>>,
+        legs: Vec<BatchSwapLeg>,
+        deadline: Option<i64>,
+    ) -> Result<()> {
+        instructions::batch_swap(ctx, legs, deadline)
+    }
+
+    /// 交换报价：返回 swap_anyswap 的输出和实际费率（含动态失衡手续费），不修改状态
+    /// RemainingAccounts: Stable 曲线或启用动态失衡手续费时，按 pool 中 token 的顺序传入所有 vault 账户
+    pub fn quote_swap<'remaining: 'info, 'info>(
+        ctx: Context<'_, '_, 'remaining, 'info, QuoteSwap<'info>>,
+        amount_in: u64,
+    ) -> Result<SwapQuote> {
+        instructions::quote_swap(ctx, amount_in)
     }
 
     /// 添加流动性（多 token 版本，按 Balancer 方式）
-    /// amounts: 每个 token 的添加数量（按 pool 中 token 的顺序）
-    /// RemainingAccounts: 每两个账户为一对 (user_token_account, vault_account)
+    /// amounts: 每个 token 的最大添加数量（按 pool 中 token 的顺序）
+    /// min_lp_out: 最少获得的 LP token 数量
+    /// deadline: 可选的截止时间（unix 时间戳）
+    /// RemainingAccounts: 每三个账户为一组 (user_token_account, vault_account, mint)
     pub fn add_liquidity<'remaining: 'info, 'info>(
         ctx: Context<'_, '_, 'remaining, 'info, AddLiquidity<'info>>,
         amounts: Vec<u64>,
+        min_lp_out: u64,
+        deadline: Option<i64>,
     ) -> Result<()> {
-        instructions::add_liquidity(ctx, amounts)
+        instructions::add_liquidity(ctx, amounts, min_lp_out, deadline)
+    }
+
+    /// 单边添加流动性：只存入一个 token（由 vault 决定），按不变量铸造 LP
+    /// amount_in: 存入的 token 数量
+    /// min_lp_out: 最少获得的 LP token 数量
+    /// RemainingAccounts: 按 pool 中 token 的顺序传入所有 vault 账户
+    pub fn add_liquidity_single<'remaining: 'info, 'info>(
+        ctx: Context<'_, '_, 'remaining, 'info, AddLiquiditySingle<'info>>,
+        amount_in: u64,
+        min_lp_out: u64,
+    ) -> Result<()> {
+        instructions::add_liquidity_single(ctx, amount_in, min_lp_out)
     }
 
     /// 移除流动性（多 token 版本，按 Balancer 方式）
     /// burn_amount: 要销毁的 LP token 数量
-    /// RemainingAccounts: 每两个账户为一对 (user_token_account, vault_account)
+    /// min_amounts_out: 每个 token 最少取出的数量（按 pool 中 token 的顺序）
+    /// deadline: 可选的截止时间（unix 时间戳）
+    /// RemainingAccounts: 每三个账户为一组 (user_token_account, vault_account, mint)
     pub fn remove_liquidity<'remaining: 'info, 'info>(
         ctx: Context<'_, '_, 'remaining, 'info, RemoveLiquidity<'info>>,
         burn_amount: u64,
+        min_amounts_out: Vec<u64>,
+        deadline: Option<i64>,
+    ) -> Result<()> {
+        instructions::remove_liquidity(ctx, burn_amount, min_amounts_out, deadline)
+    }
+
+    /// 单边移除流动性：销毁 LP，只取出一个 token（由 vault 决定）
+    /// lp_in: 要销毁的 LP token 数量
+    /// min_amount_out: 最少取出的 token 数量
+    /// Weighted 和 ConstantSum 曲线只需要取出 token 的 vault（ConstantSum 使用记录的 Σ(vault * weight)）
+    /// RemainingAccounts: Stable 曲线，或 ConstantSum 记录的 Σ(vault * weight) 未同步时（迁移、移除 token、
+    /// 修改权重之后），按 pool 中 token 的顺序传入所有 vault 账户
+    pub fn remove_liquidity_single<'remaining: 'info, 'info>(
+        ctx: Context<'_, '_, 'remaining, 'info, RemoveLiquiditySingle<'info>>,
+        lp_in: u64,
+        min_amount_out: u64,
+    ) -> Result<()> {
+        instructions::remove_liquidity_single(ctx, lp_in, min_amount_out)
+    }
+
+    /// 领取协议手续费（只有协议金库可以调用）
+    /// RemainingAccounts: 每三个账户为一组 (vault_account, destination_token_account, mint)
+    pub fn collect_protocol_fees<'remaining: 'info, 'info>(
+        ctx: Context<'_, '_, 'remaining, 'info, CollectProtocolFees<'info>>,
     ) -> Result<()> {
-        instructions::remove_liquidity(ctx, burn_amount)
+        instructions::collect_protocol_fees(ctx)
     }
 }
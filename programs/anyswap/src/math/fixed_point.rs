@@ -0,0 +1,313 @@
+use anchor_lang::prelude::*;
+use crate::error::ErrorCode;
+
+/// 定点数精度：1e18（与 Balancer 的 FixedPoint 一致）
+pub const ONE: u128 = 1_000_000_000_000_000_000;
+
+/// ln(2)，18 位精度
+const LN2: i128 = 693_147_180_559_945_309;
+
+/// exp 允许的最大指数（e^46 * 1e18 仍在 u128 范围内）
+const MAX_NATURAL_EXPONENT: i128 = 46 * ONE as i128;
+
+/// exp 的最小指数，更小的结果在 18 位精度下为 0
+const MIN_NATURAL_EXPONENT: i128 = -42 * ONE as i128;
+
+/// pow 的最大相对误差（1e-14），用于向上/向下取整
+pub const MAX_POW_RELATIVE_ERROR: u128 = 10_000;
+
+/// 把 amount 从 decimals 位精度换算到 target_decimals 位精度（向下取整）
+/// 使用 u128：精度较低的 token 换算到统一精度后可能超出 u64 范围
+pub fn scale_decimals(amount: u128, decimals: u8, target_decimals: u8) -> Result<u128> {
+    scale_decimals_with_rounding(amount, decimals, target_decimals, false)
+}
+
+/// 把 amount 从 decimals 位精度换算到 target_decimals 位精度（向上取整）
+pub fn scale_decimals_up(amount: u128, decimals: u8, target_decimals: u8) -> Result<u128> {
+    scale_decimals_with_rounding(amount, decimals, target_decimals, true)
+}
+
+fn scale_decimals_with_rounding(
+    amount: u128,
+    decimals: u8,
+    target_decimals: u8,
+    round_up: bool,
+) -> Result<u128> {
+    if decimals <= target_decimals {
+        let factor = 10u128
+            .checked_pow((target_decimals - decimals) as u32)
+            .ok_or(ErrorCode::MathOverflow)?;
+        amount.checked_mul(factor).ok_or(ErrorCode::MathOverflow.into())
+    } else {
+        let factor = 10u128
+            .checked_pow((decimals - target_decimals) as u32)
+            .ok_or(ErrorCode::MathOverflow)?;
+        if round_up {
+            Ok(amount.div_ceil(factor))
+        } else {
+            Ok(amount / factor)
+        }
+    }
+}
+
+/// a * b / ONE，向下取整
+pub fn mul_down(a: u128, b: u128) -> Result<u128> {
+    mul_div_down(a, b, ONE)
+}
+
+/// a * b / ONE，向上取整
+pub fn mul_up(a: u128, b: u128) -> Result<u128> {
+    mul_div_up(a, b, ONE)
+}
+
+/// a * ONE / b，向下取整
+pub fn div_down(a: u128, b: u128) -> Result<u128> {
+    mul_div_down(a, ONE, b)
+}
+
+/// a * ONE / b，向上取整
+pub fn div_up(a: u128, b: u128) -> Result<u128> {
+    mul_div_up(a, ONE, b)
+}
+
+/// 1 - x（x > 1 时返回 0）
+pub fn complement(x: u128) -> u128 {
+    ONE.saturating_sub(x)
+}
+
+/// 自然对数 ln(x)，x 为 18 位定点数且必须大于 0
+///
+/// 先把 x 归一化到 [1, 2)：x = m * 2^k，ln(x) = k * ln2 + ln(m)
+/// 再用 ln(m) = 2 * atanh((m - 1) / (m + 1)) 的级数展开计算
+pub fn ln(x: u128) -> Result<i128> {
+    require!(x > 0, ErrorCode::MathOverflow);
+
+    let mut k: i128 = 0;
+    let mut m = x;
+    while m >= 2 * ONE {
+        m >>= 1;
+        k += 1;
+    }
+    while m < ONE {
+        m <<= 1;
+        k -= 1;
+    }
+
+    // z ∈ [0, 1/3)，级数每项至少缩小 9 倍
+    let z = ((m - ONE) * ONE / (m + ONE)) as i128;
+    let z_squared = z * z / ONE as i128;
+
+    let mut term = z;
+    let mut sum: i128 = 0;
+    let mut n: i128 = 1;
+    while term != 0 {
+        sum += term / n;
+        term = term * z_squared / ONE as i128;
+        n += 2;
+    }
+
+    Ok(k * LN2 + 2 * sum)
+}
+
+/// 自然指数 e^x，x 为 18 位定点数
+///
+/// 先把 x 分解为 k * ln2 + r（|r| <= ln2 / 2），e^x = 2^k * e^r
+/// 再用泰勒级数计算 e^r
+pub fn exp(x: i128) -> Result<u128> {
+    require!(x <= MAX_NATURAL_EXPONENT, ErrorCode::MathOverflow);
+    if x < MIN_NATURAL_EXPONENT {
+        return Ok(0);
+    }
+
+    let k = if x >= 0 {
+        (x + LN2 / 2) / LN2
+    } else {
+        (x - LN2 / 2) / LN2
+    };
+    let r = x - k * LN2;
+
+    let mut term = ONE as i128;
+    let mut sum = ONE as i128;
+    let mut n: i128 = 1;
+    loop {
+        term = term * r / (n * ONE as i128);
+        if term == 0 {
+            break;
+        }
+        sum += term;
+        n += 1;
+    }
+
+    let sum = sum as u128;
+    if k >= 0 {
+        sum.checked_shl(k as u32)
+            .filter(|v| v >> k == sum)
+            .ok_or(ErrorCode::MathOverflow.into())
+    } else {
+        Ok(sum >> (-k) as u32)
+    }
+}
+
+/// x^y，x 和 y 都是 18 位定点数
+pub fn pow(x: u128, y: u128) -> Result<u128> {
+    if y == 0 {
+        return Ok(ONE);
+    }
+    if x == 0 {
+        return Ok(0);
+    }
+    if x == ONE {
+        return Ok(ONE);
+    }
+
+    let ln_x = ln(x)?;
+    let y = i128::try_from(y).map_err(|_| ErrorCode::MathOverflow)?;
+    let exponent = ln_x.checked_mul(y).ok_or(ErrorCode::MathOverflow)? / ONE as i128;
+    exp(exponent)
+}
+
+/// x^y，结果向上取整（保证不小于真实值）
+pub fn pow_up(x: u128, y: u128) -> Result<u128> {
+    let raw = pow(x, y)?;
+    let max_error = mul_up(raw, MAX_POW_RELATIVE_ERROR)?
+        .checked_add(1)
+        .ok_or(ErrorCode::MathOverflow)?;
+    raw.checked_add(max_error).ok_or(ErrorCode::MathOverflow.into())
+}
+
+/// a * b / c，向下取整
+/// 中间乘积使用 256 位，只要结果能放进 u128 就不会溢出
+pub fn mul_div_down(a: u128, b: u128, c: u128) -> Result<u128> {
+    Ok(mul_div(a, b, c)?.0)
+}
+
+/// a * b / c，向上取整
+pub fn mul_div_up(a: u128, b: u128, c: u128) -> Result<u128> {
+    let (quotient, remainder) = mul_div(a, b, c)?;
+    if remainder > 0 {
+        quotient.checked_add(1).ok_or(ErrorCode::MathOverflow.into())
+    } else {
+        Ok(quotient)
+    }
+}
+
+/// a * b / c，返回 (商, 余数)
+pub fn mul_div(a: u128, b: u128, c: u128) -> Result<(u128, u128)> {
+    require!(c > 0, ErrorCode::MathOverflow);
+    if let Some(product) = a.checked_mul(b) {
+        return Ok((product / c, product % c));
+    }
+
+    let (hi, lo) = full_mul(a, b);
+    // 商必须小于 2^128
+    require!(hi < c, ErrorCode::MathOverflow);
+
+    // 逐位长除法，余数始终小于 c
+    let mut remainder = hi;
+    let mut quotient: u128 = 0;
+    for bit in (0..128).rev() {
+        let carry = remainder >> 127;
+        remainder = (remainder << 1) | ((lo >> bit) & 1);
+        quotient <<= 1;
+        if carry == 1 || remainder >= c {
+            remainder = remainder.wrapping_sub(c);
+            quotient |= 1;
+        }
+    }
+    Ok((quotient, remainder))
+}
+
+/// 128 x 128 -> 256 位乘法，返回 (高 128 位, 低 128 位)
+fn full_mul(a: u128, b: u128) -> (u128, u128) {
+    let mask = u64::MAX as u128;
+    let (a0, a1) = (a & mask, a >> 64);
+    let (b0, b1) = (b & mask, b >> 64);
+
+    let p00 = a0 * b0;
+    let p01 = a0 * b1;
+    let p10 = a1 * b0;
+    let p11 = a1 * b1;
+
+    let mid = (p00 >> 64) + (p01 & mask) + (p10 & mask);
+    let lo = (p00 & mask) | (mid << 64);
+    let hi = p11 + (p01 >> 64) + (p10 >> 64) + (mid >> 64);
+    (hi, lo)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 相对误差不超过 1e-14
+    fn assert_close(actual: u128, expected: u128) {
+        let tolerance = expected / 100_000_000_000_000 + 1;
+        assert!(
+            actual.abs_diff(expected) <= tolerance,
+            "actual {actual}, expected {expected}"
+        );
+    }
+
+    #[test]
+    fn ln_known_values() {
+        assert_eq!(ln(ONE).unwrap(), 0);
+        assert_eq!(ln(2 * ONE).unwrap(), LN2);
+        // ln(10) = 2.302585092994045684
+        assert_close(ln(10 * ONE).unwrap() as u128, 2_302_585_092_994_045_684);
+        // ln(0.5) = -ln(2)
+        assert_eq!(ln(ONE / 2).unwrap(), -LN2);
+        assert!(ln(0).is_err());
+    }
+
+    #[test]
+    fn exp_known_values() {
+        assert_eq!(exp(0).unwrap(), ONE);
+        // e = 2.718281828459045235
+        assert_close(exp(ONE as i128).unwrap(), 2_718_281_828_459_045_235);
+        // e^-1 = 0.367879441171442321
+        assert_close(exp(-(ONE as i128)).unwrap(), 367_879_441_171_442_321);
+        assert_close(exp(LN2).unwrap(), 2 * ONE);
+        assert_eq!(exp(MIN_NATURAL_EXPONENT - 1).unwrap(), 0);
+        assert!(exp(MAX_NATURAL_EXPONENT + 1).is_err());
+    }
+
+    #[test]
+    fn pow_known_values() {
+        assert_eq!(pow(5 * ONE, 0).unwrap(), ONE);
+        assert_eq!(pow(0, ONE).unwrap(), 0);
+        assert_close(pow(2 * ONE, 3 * ONE).unwrap(), 8 * ONE);
+        // 4^0.5 = 2
+        assert_close(pow(4 * ONE, ONE / 2).unwrap(), 2 * ONE);
+        // 0.5^2 = 0.25
+        assert_close(pow(ONE / 2, 2 * ONE).unwrap(), ONE / 4);
+    }
+
+    #[test]
+    fn pow_up_is_not_below_pow() {
+        let cases = [(ONE / 3, 7 * ONE / 3), (3 * ONE, ONE / 7), (999 * ONE / 1000, 4 * ONE)];
+        for (x, y) in cases {
+            assert!(pow_up(x, y).unwrap() > pow(x, y).unwrap());
+        }
+    }
+
+    #[test]
+    fn mul_div_wide_product() {
+        // 乘积超过 u128，商仍在范围内
+        let (q, r) = mul_div(u128::MAX, u128::MAX, u128::MAX).unwrap();
+        assert_eq!((q, r), (u128::MAX, 0));
+        let a = u128::MAX / 2;
+        assert_eq!(mul_div_up(a, 3, 2).unwrap(), mul_div_down(a, 3, 2).unwrap() + 1);
+        assert!(mul_div(u128::MAX, u128::MAX, 1).is_err());
+    }
+
+    #[test]
+    fn scale_decimals_beyond_u64() {
+        // 0 位小数的 token 换算到 9 位精度
+        let amount = u64::MAX as u128;
+        assert_eq!(scale_decimals(amount, 0, 9).unwrap(), amount * 1_000_000_000);
+        assert_eq!(scale_decimals(amount * 1_000_000_000, 9, 0).unwrap(), amount);
+        assert_eq!(scale_decimals(1_999, 9, 6).unwrap(), 1);
+        assert_eq!(scale_decimals_up(1_999, 9, 6).unwrap(), 2);
+        // 大数相乘时中间乘积超出 u128
+        assert_eq!(mul_down(amount * 1_000_000_000, ONE / 2).unwrap(), amount * 500_000_000);
+    }
+}
@@ -0,0 +1,3 @@
+pub mod fixed_point;
+pub mod stable;
+pub mod weighted;
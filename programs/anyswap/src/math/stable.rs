@@ -0,0 +1,336 @@
+use anchor_lang::prelude::*;
+use crate::error::ErrorCode;
+use super::fixed_point::{mul_div, mul_div_down, mul_div_up};
+
+/// 放大系数 A 的精度（与 Curve 一致，A 在计算中以 A * AMP_PRECISION 表示）
+pub const AMP_PRECISION: u128 = 100;
+
+/// 允许的最小放大系数
+pub const MIN_AMP: u64 = 1;
+
+/// 允许的最大放大系数
+pub const MAX_AMP: u64 = 1_000_000;
+
+/// 放大系数调整的最短时间（1 天）
+pub const MIN_RAMP_DURATION: i64 = 86_400;
+
+/// 单次调整中放大系数最多变化的倍数
+pub const MAX_AMP_CHANGE: u64 = 10;
+
+/// Newton 迭代的最大次数
+const MAX_ITERATIONS: usize = 255;
+
+/// StableSwap 曲线（Curve 方式，N 个 token）
+/// 不变量: A * n^n * Σx + D = A * D * n^n + D^(n+1) / (n^n * Πx)
+///
+/// amp: 放大系数，已乘以 AMP_PRECISION
+/// 使用 Newton 迭代求解 D，所有 balance 必须大于 0
+pub fn calc_invariant(balances: &[u128], amp: u128) -> Result<u128> {
+    require!(!balances.is_empty(), ErrorCode::InvalidTokenCount);
+    require!(amp > 0, ErrorCode::InvalidAmplification);
+
+    let n = balances.len() as u128;
+    let sum = balances
+        .iter()
+        .try_fold(0u128, |sum, &b| sum.checked_add(b))
+        .ok_or(ErrorCode::MathOverflow)?;
+    if sum == 0 {
+        return Ok(0);
+    }
+    require!(
+        balances.iter().all(|&b| b > 0),
+        ErrorCode::InsufficientLiquidity
+    );
+
+    let ann = amp.checked_mul(n).ok_or(ErrorCode::MathOverflow)?;
+    let mut sorted = balances.to_vec();
+    sorted.sort_unstable();
+    let mut d = sum;
+    let mut d_prev_prev = 0;
+    for _ in 0..MAX_ITERATIONS {
+        let d_p = calc_d_p(&sorted, d, n)?;
+
+        let d_prev = d;
+        // D = (Ann * S / A_PREC + D_P * n) * D / ((Ann - A_PREC) * D / A_PREC + (n + 1) * D_P)
+        let numerator = mul_div_down(ann, sum, AMP_PRECISION)?
+            .checked_add(d_p.checked_mul(n).ok_or(ErrorCode::MathOverflow)?)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let denominator = mul_div_down(ann - AMP_PRECISION, d, AMP_PRECISION)?
+            .checked_add(d_p.checked_mul(n + 1).ok_or(ErrorCode::MathOverflow)?)
+            .ok_or(ErrorCode::MathOverflow)?;
+        d = mul_div_down(numerator, d, denominator)?;
+
+        if d.abs_diff(d_prev) <= 1 {
+            return Ok(d);
+        }
+        // 取整误差可能导致在两个值之间来回振荡，此时取较大值（有利于 pool）
+        if d == d_prev_prev {
+            return Ok(d.max(d_prev));
+        }
+        d_prev_prev = d_prev;
+    }
+
+    Err(ErrorCode::StableInvariantNotConverged.into())
+}
+
+/// d_p = D^(n+1) / (n^n * Πx)，sorted 为升序排列的 balance
+///
+/// 先除以较小的 balance，中间值保持较大，截断误差最小；余额极不平衡时中间值可能溢出，
+/// 此时改为先除以较大的 balance（中间值不超过最终结果）
+fn calc_d_p(sorted: &[u128], d: u128, n: u128) -> Result<u128> {
+    let fold = |d_p: Result<u128>, &balance: &u128| {
+        mul_div_down(d_p?, d, balance.checked_mul(n).ok_or(ErrorCode::MathOverflow)?)
+    };
+    sorted
+        .iter()
+        .fold(Ok(d), fold)
+        .or_else(|_| sorted.iter().rev().fold(Ok(d), fold))
+}
+
+/// 给定 token i 的新余额 x，计算保持不变量 D 时 token j 的余额 y
+/// 使用 Newton 迭代求解 y^2 + (b - D) * y = c
+/// c 和每一步迭代都向上取整，保证 y 不小于真实值（输出不会多付）
+pub fn calc_y(
+    balances: &[u128],
+    amp: u128,
+    token_in_index: usize,
+    token_out_index: usize,
+    new_balance_in: u128,
+    d: u128,
+) -> Result<u128> {
+    require!(
+        token_in_index < balances.len() && token_out_index < balances.len(),
+        ErrorCode::InvalidTokenIndex
+    );
+    require!(token_in_index != token_out_index, ErrorCode::SameTokenSwap);
+
+    let n = balances.len() as u128;
+    let ann = amp.checked_mul(n).ok_or(ErrorCode::MathOverflow)?;
+
+    // c = D^(n+1) / (n^n * Π_{k!=j} x_k * Ann)
+    // b = Σ_{k!=j} x_k + D / Ann
+    let mut c = d;
+    let mut sum: u128 = 0;
+    for (k, &balance) in balances.iter().enumerate() {
+        if k == token_out_index {
+            continue;
+        }
+        let x = if k == token_in_index {
+            new_balance_in
+        } else {
+            balance
+        };
+        require!(x > 0, ErrorCode::InsufficientLiquidity);
+        sum = sum.checked_add(x).ok_or(ErrorCode::MathOverflow)?;
+        c = mul_div_up(c, d, x.checked_mul(n).ok_or(ErrorCode::MathOverflow)?)?;
+    }
+    c = mul_div_up(
+        c,
+        d.checked_mul(AMP_PRECISION).ok_or(ErrorCode::MathOverflow)?,
+        ann.checked_mul(n).ok_or(ErrorCode::MathOverflow)?,
+    )?;
+    let b = sum
+        .checked_add(mul_div_down(d, AMP_PRECISION, ann)?)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    let mut y = d;
+    let mut y_prev_prev = 0;
+    for _ in 0..MAX_ITERATIONS {
+        let y_prev = y;
+        // y = (y^2 + c) / (2y + b - D)
+        let denominator = y
+            .checked_mul(2)
+            .and_then(|v| v.checked_add(b))
+            .and_then(|v| v.checked_sub(d))
+            .ok_or(ErrorCode::MathOverflow)?;
+        let (quotient, remainder) = mul_div(y, y, denominator)?;
+        let remainder = remainder + c % denominator;
+        y = quotient
+            .checked_add(c / denominator + remainder / denominator)
+            .ok_or(ErrorCode::MathOverflow)?;
+        if remainder % denominator > 0 {
+            y += 1;
+        }
+
+        if y.abs_diff(y_prev) <= 1 {
+            return Ok(y);
+        }
+        // 振荡时取较大的 y（输出更少，有利于 pool）
+        if y == y_prev_prev {
+            return Ok(y.max(y_prev));
+        }
+        y_prev_prev = y_prev;
+    }
+
+    Err(ErrorCode::StableInvariantNotConverged.into())
+}
+
+/// 给定输入计算输出
+/// amount_out = x_j - y - 1（减 1 使取整有利于 pool）
+pub fn calc_out_given_in(
+    balances: &[u128],
+    amp: u128,
+    token_in_index: usize,
+    token_out_index: usize,
+    amount_in: u128,
+) -> Result<u128> {
+    require!(
+        token_in_index < balances.len() && token_out_index < balances.len(),
+        ErrorCode::InvalidTokenIndex
+    );
+    // 余额极不平衡时 D 的截断误差会放大到 y 上，零输入必须直接返回零输出
+    if amount_in == 0 {
+        return Ok(0);
+    }
+
+    let d = calc_invariant(balances, amp)?;
+    let new_balance_in = balances[token_in_index]
+        .checked_add(amount_in)
+        .ok_or(ErrorCode::MathOverflow)?;
+    let y = calc_y(balances, amp, token_in_index, token_out_index, new_balance_in, d)?;
+
+    Ok(balances[token_out_index].saturating_sub(y).saturating_sub(1))
+}
+
+/// 给定输出计算所需输入（不含手续费）
+/// amount_in = y - x_i + 1（加 1 使取整有利于 pool）
+pub fn calc_in_given_out(
+    balances: &[u128],
+    amp: u128,
+    token_in_index: usize,
+    token_out_index: usize,
+    amount_out: u128,
+) -> Result<u128> {
+    require!(
+        token_in_index < balances.len() && token_out_index < balances.len(),
+        ErrorCode::InvalidTokenIndex
+    );
+    require!(
+        amount_out < balances[token_out_index],
+        ErrorCode::InsufficientLiquidity
+    );
+
+    let d = calc_invariant(balances, amp)?;
+    let new_balance_out = balances[token_out_index] - amount_out;
+    // 反向求解：已知输出 token 的新余额，求输入 token 的余额
+    let y = calc_y(balances, amp, token_out_index, token_in_index, new_balance_out, d)?;
+
+    y.saturating_sub(balances[token_in_index])
+        .checked_add(1)
+        .ok_or(ErrorCode::MathOverflow.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AMPS: [u64; 4] = [MIN_AMP, 10, 2_000, MAX_AMP];
+
+    #[test]
+    fn invariant_of_balanced_pool_is_the_sum() {
+        for amp in AMPS {
+            let amp = amp as u128 * AMP_PRECISION;
+            let d = calc_invariant(&[1_000_000_000; 3], amp).unwrap();
+            assert!(d.abs_diff(3_000_000_000) <= 1);
+        }
+    }
+
+    #[test]
+    fn invariant_converges_at_extreme_amp_and_ratios() {
+        let cases: [&[u128]; 6] = [
+            &[1, u64::MAX as u128 / 4],
+            &[1_000, 1_000_000_000_000_000],
+            &[u64::MAX as u128 / 8; 3],
+            // 0 位小数的 token 换算到 9 位精度后超出 u64
+            &[u64::MAX as u128 * 1_000_000_000; 2],
+            &[1_000, 1_000_000, 1_000_000_000, 1_000_000_000_000],
+            &[7, 7, 7, 7, 7, 7, 7, 7],
+        ];
+        for balances in cases {
+            let sum = balances.iter().sum::<u128>();
+            for amp in AMPS {
+                let d = calc_invariant(balances, amp as u128 * AMP_PRECISION).unwrap();
+                // D 不超过 Σx，且随 A 增大趋近 Σx
+                assert!(d > 0 && d <= sum + 1, "balances {balances:?}, amp {amp}, d {d}");
+            }
+        }
+    }
+
+    #[test]
+    fn invariant_does_not_depend_on_token_order() {
+        for amp in AMPS {
+            let amp = amp as u128 * AMP_PRECISION;
+            let d = calc_invariant(&[1_000, 1_000_000_000_000], amp).unwrap();
+            assert_eq!(calc_invariant(&[1_000_000_000_000, 1_000], amp).unwrap(), d);
+        }
+    }
+
+    #[test]
+    fn invariant_overflow_is_an_error() {
+        let balances = [1, 1_000_000, 1_000_000_000_000, 1_000_000_000_000_000_000];
+        assert!(calc_invariant(&balances, MIN_AMP as u128 * AMP_PRECISION).is_err());
+    }
+
+    #[test]
+    fn zero_input_gives_zero_output_at_extreme_ratios() {
+        let balances = [1_000u128, 1_000_000_000_000];
+        for amp in AMPS {
+            let amp = amp as u128 * AMP_PRECISION;
+            assert_eq!(calc_out_given_in(&balances, amp, 0, 1, 0).unwrap(), 0);
+            assert!(calc_out_given_in(&balances, amp, 0, 1, 1).unwrap() > 0);
+        }
+    }
+
+    #[test]
+    fn y_converges_at_extreme_amp_and_ratios() {
+        for amp in AMPS {
+            let amp = amp as u128 * AMP_PRECISION;
+            for balances in [[1_000u128, 1_000_000_000_000], [1_000_000_000_000, 1_000]] {
+                let d = calc_invariant(&balances, amp).unwrap();
+                // 输入越多 y 越小
+                let mut previous = calc_y(&balances, amp, 0, 1, balances[0], d).unwrap();
+                for multiplier in [2, 10, 1_000] {
+                    let new_balance_in = balances[0] * multiplier;
+                    let y = calc_y(&balances, amp, 0, 1, new_balance_in, d).unwrap();
+                    assert!(y < previous && y > 0);
+                    previous = y;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn y_returns_balance_when_input_unchanged() {
+        let balances = [3_000_000_000u128, 1_000_000_000, 2_000_000_000];
+        for amp in AMPS {
+            let amp = amp as u128 * AMP_PRECISION;
+            let d = calc_invariant(&balances, amp).unwrap();
+            for (i, j) in [(0, 1), (1, 2), (2, 0)] {
+                // D 向下取整带来的误差最多几个最小单位
+                let y = calc_y(&balances, amp, i, j, balances[i], d).unwrap();
+                assert!(y.abs_diff(balances[j]) <= 3, "amp {amp}, y {y}");
+            }
+        }
+    }
+
+    #[test]
+    fn swap_does_not_decrease_invariant() {
+        let balances = [2_000_000_000u128, 1_000_000_000, 500_000_000];
+        for amp in AMPS {
+            let amp = amp as u128 * AMP_PRECISION;
+            let before = calc_invariant(&balances, amp).unwrap();
+            for amount_in in [1, 1_000_000, 900_000_000] {
+                let out = calc_out_given_in(&balances, amp, 0, 2, amount_in).unwrap();
+                let after = calc_invariant(
+                    &[balances[0] + amount_in, balances[1], balances[2] - out],
+                    amp,
+                )
+                .unwrap();
+                assert!(after >= before);
+
+                let needed = calc_in_given_out(&balances, amp, 0, 2, out).unwrap();
+                assert!(needed <= amount_in + 2);
+            }
+        }
+    }
+}
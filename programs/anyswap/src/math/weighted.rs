@@ -0,0 +1,184 @@
+use anchor_lang::prelude::*;
+use crate::error::ErrorCode;
+use super::fixed_point::{self, ONE};
+
+/// 计算不变量时把余额视为 9 位小数的定点数（与统一精度一致），
+/// 使 ln 的输入和 exp 的结果在统一精度下的余额超出 u64 时仍在 u128 范围内
+const BALANCE_SCALE: u128 = 1_000_000_000;
+
+/// 加权几何平均曲线（Balancer 方式）
+/// 不变量: Π(balance_i ^ (weight_i / Σweight)) = constant
+///
+/// 给定输入计算输出：
+/// amount_out = balance_out * (1 - (balance_in / (balance_in + amount_in)) ^ (weight_in / weight_out))
+///
+/// 所有取整都向有利于 pool 的方向：底数和幂向上取整，输出向下取整
+pub fn calc_out_given_in(
+    balance_in: u128,
+    weight_in: u64,
+    balance_out: u128,
+    weight_out: u64,
+    amount_in: u128,
+) -> Result<u128> {
+    require!(weight_in > 0 && weight_out > 0, ErrorCode::InvalidTokenCount);
+    require!(
+        balance_in > 0 && balance_out > 0,
+        ErrorCode::InsufficientLiquidity
+    );
+
+    let denominator = balance_in
+        .checked_add(amount_in)
+        .ok_or(ErrorCode::MathOverflow)?;
+    let base = fixed_point::div_up(balance_in, denominator)?;
+    let exponent = fixed_point::div_down(weight_in as u128, weight_out as u128)?;
+    let power = fixed_point::pow_up(base, exponent)?;
+
+    fixed_point::mul_down(balance_out, fixed_point::complement(power))
+}
+
+/// 给定输出计算所需输入（不含手续费）：
+/// amount_in = balance_in * ((balance_out / (balance_out - amount_out)) ^ (weight_out / weight_in) - 1)
+///
+/// 所有取整都向有利于 pool 的方向：底数、指数、幂和输入都向上取整
+pub fn calc_in_given_out(
+    balance_in: u128,
+    weight_in: u64,
+    balance_out: u128,
+    weight_out: u64,
+    amount_out: u128,
+) -> Result<u128> {
+    require!(weight_in > 0 && weight_out > 0, ErrorCode::InvalidTokenCount);
+    require!(
+        balance_in > 0 && amount_out < balance_out,
+        ErrorCode::InsufficientLiquidity
+    );
+
+    let base = fixed_point::div_up(balance_out, balance_out - amount_out)?;
+    let exponent = fixed_point::div_up(weight_out as u128, weight_in as u128)?;
+    let power = fixed_point::pow_up(base, exponent)?;
+
+    let ratio = power.saturating_sub(ONE);
+    fixed_point::mul_up(balance_in, ratio)
+}
+
+/// 计算加权几何平均不变量
+/// 返回 Π(balance_i ^ (weight_i / Σweight))，单位与 balance 相同
+/// 任意 balance 为 0 时不变量为 0
+pub fn calc_invariant(balances: &[u128], weights: &[u64]) -> Result<u128> {
+    require!(
+        !balances.is_empty() && balances.len() == weights.len(),
+        ErrorCode::InvalidTokenCount
+    );
+
+    let total_weight = weights.iter().try_fold(0u128, |acc, &w| {
+        acc.checked_add(w as u128).ok_or(ErrorCode::MathOverflow)
+    })?;
+    require!(total_weight > 0, ErrorCode::InvalidTokenCount);
+
+    // ln(invariant / s) = Σ (weight_i / Σweight) * ln(balance_i / s)，s = BALANCE_SCALE
+    let mut ln_invariant: i128 = 0;
+    for (&balance, &weight) in balances.iter().zip(weights.iter()) {
+        if balance == 0 {
+            return Ok(0);
+        }
+        let normalized_weight = fixed_point::div_down(weight as u128, total_weight)? as i128;
+        let scaled_balance = balance
+            .checked_mul(ONE / BALANCE_SCALE)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let ln_balance = fixed_point::ln(scaled_balance)?;
+        let term = ln_balance
+            .checked_mul(normalized_weight)
+            .ok_or(ErrorCode::MathOverflow)?
+            / ONE as i128;
+        ln_invariant = ln_invariant
+            .checked_add(term)
+            .ok_or(ErrorCode::MathOverflow)?;
+    }
+
+    Ok(fixed_point::exp(ln_invariant)? / (ONE / BALANCE_SCALE))
+}
+
+/// 给定不变量的变化比例，计算只改变 token i 时它的新余额
+/// new_balance = balance * invariant_ratio ^ (Σweight / weight)
+///
+/// invariant_ratio 为 18 位定点数；指数、幂和新余额都向上取整（单边取出时少付）
+pub fn calc_balance_given_invariant(
+    balance: u128,
+    weight: u64,
+    total_weight: u128,
+    invariant_ratio: u128,
+) -> Result<u128> {
+    require!(weight > 0, ErrorCode::InvalidTokenCount);
+
+    let exponent = fixed_point::div_up(total_weight, weight as u128)?;
+    let power = fixed_point::pow_up(invariant_ratio, exponent)?;
+    fixed_point::mul_up(balance, power)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invariant_of_equal_balances_is_the_balance() {
+        let invariant = calc_invariant(&[1_000_000, 1_000_000, 1_000_000], &[1, 2, 3]).unwrap();
+        assert!(invariant.abs_diff(1_000_000) <= 1);
+        assert_eq!(calc_invariant(&[1_000_000, 0], &[1, 1]).unwrap(), 0);
+    }
+
+    #[test]
+    fn invariant_of_balances_beyond_u64() {
+        // 0 位小数的 token 换算到 9 位精度后超出 u64
+        let balance = u64::MAX as u128 * 1_000_000_000;
+        let invariant = calc_invariant(&[balance, balance], &[1, 1]).unwrap();
+        let max_error = fixed_point::mul_up(balance, fixed_point::MAX_POW_RELATIVE_ERROR).unwrap();
+        assert!(invariant.abs_diff(balance) <= max_error);
+
+        let out = calc_out_given_in(balance, 1, balance, 1, balance).unwrap();
+        assert!(out < balance / 2 && out > balance / 2 - balance / 1_000_000);
+    }
+
+    #[test]
+    fn invariant_is_monotonic_in_each_balance() {
+        let weights = [20, 30, 50];
+        let mut balances = [1_000_000_000u128, 2_000_000_000, 3_000_000_000];
+        for i in 0..balances.len() {
+            let mut previous = calc_invariant(&balances, &weights).unwrap();
+            for _ in 0..5 {
+                balances[i] += balances[i] / 10;
+                let current = calc_invariant(&balances, &weights).unwrap();
+                assert!(current > previous);
+                previous = current;
+            }
+        }
+    }
+
+    #[test]
+    fn swap_does_not_decrease_invariant() {
+        let weights = [80, 20];
+        let balances = [5_000_000_000u128, 1_000_000_000];
+        let before = calc_invariant(&balances, &weights).unwrap();
+        for amount_in in [1, 1_000, 1_000_000, 1_000_000_000] {
+            let out =
+                calc_out_given_in(balances[0], weights[0], balances[1], weights[1], amount_in)
+                    .unwrap();
+            let after =
+                calc_invariant(&[balances[0] + amount_in, balances[1] - out], &weights).unwrap();
+            assert!(after >= before);
+        }
+    }
+
+    #[test]
+    fn in_given_out_covers_out_given_in() {
+        let (balance_in, weight_in) = (3_000_000_000u128, 30);
+        let (balance_out, weight_out) = (7_000_000_000u128, 70);
+        for amount_out in [1_000, 1_000_000, 1_000_000_000] {
+            let amount_in =
+                calc_in_given_out(balance_in, weight_in, balance_out, weight_out, amount_out)
+                    .unwrap();
+            let out = calc_out_given_in(balance_in, weight_in, balance_out, weight_out, amount_in)
+                .unwrap();
+            assert!(out <= amount_out && amount_out - out <= amount_out / 1_000_000 + 1);
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use anchor_lang::prelude::*;
+use crate::error::ErrorCode;
+
+/// Pool 使用的定价曲线
+/// 在 create_pool 时选择，之后不可修改
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CurveType {
+    /// 恒定乘积和：Σ(vault_i * weight_i) = constant，无价格冲击
+    ConstantSum,
+    /// 加权几何平均（Balancer 方式）：Π(vault_i ^ weight_i) = constant
+    Weighted,
+    /// StableSwap（Curve 方式）：适用于锚定资产，需要放大系数 A
+    Stable,
+}
+
+impl CurveType {
+    /// 存储在 pool 中的编码
+    pub fn to_u8(self) -> u8 {
+        match self {
+            CurveType::ConstantSum => 0,
+            CurveType::Weighted => 1,
+            CurveType::Stable => 2,
+        }
+    }
+
+    /// 从 pool 中存储的编码解析
+    pub fn from_u8(value: u8) -> Result<Self> {
+        match value {
+            0 => Ok(CurveType::ConstantSum),
+            1 => Ok(CurveType::Weighted),
+            2 => Ok(CurveType::Stable),
+            _ => Err(ErrorCode::InvalidCurveType.into()),
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use anchor_lang::prelude::*;
+use crate::error::ErrorCode;
+use crate::math::fixed_point::ONE;
+use super::pool::PROTOCOL_FEE_SHARE_DENOMINATOR;
+
+/// 协议全局配置的参数
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct FactoryConfigParams {
+    /// 协议管理员 - 可以修改全局配置、领取创建费
+    pub protocol_authority: Pubkey,
+    /// 协议金库 - 新建 pool 的默认协议金库
+    pub treasury: Pubkey,
+    /// 允许的最低费率（18 位定点数）
+    pub min_fee: u64,
+    /// 允许的最高费率（18 位定点数）
+    pub max_fee: u64,
+    /// 新建 pool 的默认协议手续费比例（占手续费的 protocol_fee_share / 10000）
+    pub default_protocol_fee_share: u64,
+    /// 创建 pool 需要支付的费用（lamports），存入配置账户
+    pub creation_fee: u64,
+    /// 全局开关：暂停后不能创建 pool，也不能修改费率
+    pub paused: bool,
+}
+
+/// 协议全局配置（PDA: [b"factory_config"]）
+#[account]
+pub struct FactoryConfig {
+    /// 协议管理员
+    pub protocol_authority: Pubkey,
+    /// 协议金库
+    pub treasury: Pubkey,
+    /// 允许的最低费率（18 位定点数）
+    pub min_fee: u64,
+    /// 允许的最高费率（18 位定点数）
+    pub max_fee: u64,
+    /// 新建 pool 的默认协议手续费比例
+    pub default_protocol_fee_share: u64,
+    /// 创建 pool 需要支付的费用（lamports）
+    pub creation_fee: u64,
+    /// 全局开关
+    pub paused: bool,
+    /// PDA bump
+    pub bump: u8,
+}
+
+impl FactoryConfig {
+    /// 计算账户所需的空间大小
+    pub fn space() -> usize {
+        8 + // discriminator
+        32 + // protocol_authority
+        32 + // treasury
+        8 + // min_fee
+        8 + // max_fee
+        8 + // default_protocol_fee_share
+        8 + // creation_fee
+        1 + // paused
+        1 // bump
+    }
+
+    /// 检查并写入配置参数
+    pub fn set_params(&mut self, params: &FactoryConfigParams) -> Result<()> {
+        require!(
+            params.min_fee <= params.max_fee && params.max_fee as u128 <= ONE,
+            ErrorCode::FeeOutOfBounds
+        );
+        require!(
+            params.default_protocol_fee_share <= PROTOCOL_FEE_SHARE_DENOMINATOR,
+            ErrorCode::InvalidProtocolFee
+        );
+        self.protocol_authority = params.protocol_authority;
+        self.treasury = params.treasury;
+        self.min_fee = params.min_fee;
+        self.max_fee = params.max_fee;
+        self.default_protocol_fee_share = params.default_protocol_fee_share;
+        self.creation_fee = params.creation_fee;
+        self.paused = params.paused;
+        Ok(())
+    }
+
+    /// 验证协议管理员权限
+    pub fn verify_protocol_authority(&self, authority: &Pubkey) -> Result<()> {
+        require!(
+            *authority == self.protocol_authority,
+            ErrorCode::InvalidFactoryAuthority
+        );
+        Ok(())
+    }
+
+    /// 检查全局开关没有暂停
+    pub fn require_not_paused(&self) -> Result<()> {
+        require!(!self.paused, ErrorCode::FactoryPaused);
+        Ok(())
+    }
+
+    /// 检查费率在允许的范围内：min_fee <= fee_numerator / fee_denominator <= max_fee
+    pub fn verify_fee(&self, fee_numerator: u64, fee_denominator: u64) -> Result<()> {
+        require!(fee_denominator > 0, ErrorCode::MathOverflow);
+        let scaled_fee = fee_numerator as u128 * ONE;
+        require!(
+            scaled_fee >= self.min_fee as u128 * fee_denominator as u128
+                && scaled_fee <= self.max_fee as u128 * fee_denominator as u128,
+            ErrorCode::FeeOutOfBounds
+        );
+        Ok(())
+    }
+}
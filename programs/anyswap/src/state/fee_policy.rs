@@ -0,0 +1,35 @@
+use anchor_lang::prelude::*;
+use crate::error::ErrorCode;
+
+/// 交换时使用哪个 token 的费率
+/// 没有单独设置费率的 token 使用 pool 的费率
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FeePolicy {
+    /// 使用输入 token 的费率
+    TokenIn,
+    /// 使用输出 token 的费率
+    TokenOut,
+    /// 使用输入和输出 token 中较高的费率
+    Max,
+}
+
+impl FeePolicy {
+    /// 存储在 pool 中的编码
+    pub fn to_u8(self) -> u8 {
+        match self {
+            FeePolicy::TokenIn => 0,
+            FeePolicy::TokenOut => 1,
+            FeePolicy::Max => 2,
+        }
+    }
+
+    /// 从 pool 中存储的编码解析
+    pub fn from_u8(value: u8) -> Result<Self> {
+        match value {
+            0 => Ok(FeePolicy::TokenIn),
+            1 => Ok(FeePolicy::TokenOut),
+            2 => Ok(FeePolicy::Max),
+            _ => Err(ErrorCode::InvalidFeePolicy.into()),
+        }
+    }
+}
@@ -2,6 +2,13 @@ use anchor_lang::prelude::*;
 use static_assertions::const_assert_eq;
 use std::mem::size_of;
 
+/// token 标志位：禁止以该 token 作为输入交换
+pub const TOKEN_SWAP_IN_DISABLED: u8 = 1 << 0;
+/// token 标志位：禁止以该 token 作为输出交换（包括单边取出）
+pub const TOKEN_SWAP_OUT_DISABLED: u8 = 1 << 1;
+/// token 标志位：禁止存入该 token（包括多 token 添加流动性和单边存入）
+pub const TOKEN_DEPOSITS_DISABLED: u8 = 1 << 2;
+
 /// Token 配置项
 /// 每个 item 记录一个 token 的 vault、mint 和 weight，用于多 token 互相转换
 /// 遵循恒定乘积和公式：Σ(vault * weight) = constant
@@ -14,13 +21,37 @@ pub struct AnySwapItem {
     /// Mint account 地址 - 该 token 的 mint 地址 (32 bytes)
     pub mint_account: Pubkey,
     /// 权重 (weight) - 不变量，用于恒定乘积和公式计算 (8 bytes)
-    /// weight 在添加 token 时设置，之后保持不变
+    /// weight 在添加 token 时设置；权重调整期间为调整开始时的权重
+    /// 作用于换算到统一精度后的数量，与 mint 的 decimals 无关
     pub weight: u64,
+    /// 权重调整后的目标权重 (8 bytes)，没有调整计划时等于 weight
+    pub target_weight: u64,
+    /// 权重开始调整的时间（unix timestamp） (8 bytes)
+    pub weight_start_time: i64,
+    /// 权重调整结束的时间（unix timestamp） (8 bytes)
+    pub weight_end_time: i64,
+    /// 待领取的协议手续费 (8 bytes)
+    /// 留在 vault 中但不属于 LP，不计入定价使用的储备量
+    pub protocol_fees: u64,
+    /// 该 token 单独设置的手续费分子 (8 bytes)
+    pub fee_numerator: u64,
+    /// 该 token 单独设置的手续费分母 (8 bytes)，为 0 表示使用 pool 的费率
+    pub fee_denominator: u64,
+    /// mint 的 decimals (1 byte)，在添加 token 时记录
+    /// 计算前所有数量都按 decimals 换算到统一精度（NORMALIZED_DECIMALS）
+    pub decimals: u8,
+    /// 禁用标志位 (1 byte)，TOKEN_SWAP_IN_DISABLED / TOKEN_SWAP_OUT_DISABLED / TOKEN_DEPOSITS_DISABLED 的组合
+    pub flags: u8,
+    /// 按 mint 排序的查找表的第 k 项 (2 bytes)，k 为本 item 在 tokens 中的位置
+    /// 即 mint 第 k 小的 token 的索引，与本 item 自身的 token 无关，由 pool 维护
+    pub sorted_token: u16,
+    /// 填充字节（确保 8 字节对齐）
+    pub padding: [u8; 4],
 }
 
 // 验证结构体大小和对齐（Solana 要求 8 字节对齐）
-const_assert_eq!(size_of::<AnySwapItem>(), 32 + 32 + 8); // 72 bytes
-const_assert_eq!(size_of::<AnySwapItem>(), 72);
+const_assert_eq!(size_of::<AnySwapItem>(), 32 + 32 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 1 + 1 + 2 + 4); // 128 bytes
+const_assert_eq!(size_of::<AnySwapItem>(), 128);
 const_assert_eq!(size_of::<AnySwapItem>() % 8, 0); // 必须是 8 的倍数
 
 impl AnySwapItem {
@@ -39,14 +70,119 @@ impl AnySwapItem {
         &self.mint_account
     }
 
-    /// 获取 weight 值
+    /// 获取存储的 weight 值（权重调整期间为调整开始时的权重）
+    /// 定价时使用 get_weight_at 获取当前时刻的权重
     pub fn get_weight(&self) -> u64 {
         self.weight
     }
 
-    /// 设置 weight 值（仅在添加 token 时调用）
+    /// 获取权重调整后的目标权重
+    pub fn get_target_weight(&self) -> u64 {
+        self.target_weight
+    }
+
+    /// 获取某一时刻的权重
+    /// 调整期间在 weight 和 target_weight 之间按时间线性插值
+    pub fn get_weight_at(&self, now: i64) -> u64 {
+        if now >= self.weight_end_time || self.weight_end_time <= self.weight_start_time {
+            return self.target_weight;
+        }
+        if now <= self.weight_start_time {
+            return self.weight;
+        }
+
+        let start = self.weight as u128;
+        let target = self.target_weight as u128;
+        let elapsed = (now - self.weight_start_time) as u128;
+        let duration = (self.weight_end_time - self.weight_start_time) as u128;
+        let weight = if target > start {
+            start + (target - start) * elapsed / duration
+        } else {
+            start - (start - target) * elapsed / duration
+        };
+        weight as u64
+    }
+
+    /// now 之后权重是否还会变化（有尚未开始或正在进行的调整）
+    pub fn is_weight_changing(&self, now: i64) -> bool {
+        now < self.weight_end_time && self.weight != self.target_weight
+    }
+
+    /// 立即设置 weight 值，并取消权重调整计划
     pub fn set_weight(&mut self, weight: u64) {
         self.weight = weight;
+        self.target_weight = weight;
+        self.weight_start_time = 0;
+        self.weight_end_time = 0;
+    }
+
+    /// 设置权重调整计划：从 start_time 的 start_weight 线性调整到 end_time 的 end_weight
+    pub fn set_weight_ramp(&mut self, start_weight: u64, end_weight: u64, start_time: i64, end_time: i64) {
+        self.weight = start_weight;
+        self.target_weight = end_weight;
+        self.weight_start_time = start_time;
+        self.weight_end_time = end_time;
+    }
+
+    /// 获取待领取的协议手续费
+    pub fn get_protocol_fees(&self) -> u64 {
+        self.protocol_fees
+    }
+
+    /// 设置待领取的协议手续费
+    pub fn set_protocol_fees(&mut self, protocol_fees: u64) {
+        self.protocol_fees = protocol_fees;
+    }
+
+    /// 获取单独设置的费率，没有设置时返回 None
+    pub fn get_fee(&self) -> Option<(u64, u64)> {
+        if self.fee_denominator == 0 {
+            None
+        } else {
+            Some((self.fee_numerator, self.fee_denominator))
+        }
+    }
+
+    /// 设置单独的费率，None 表示使用 pool 的费率
+    pub fn set_fee(&mut self, fee: Option<(u64, u64)>) {
+        let (fee_numerator, fee_denominator) = fee.unwrap_or((0, 0));
+        self.fee_numerator = fee_numerator;
+        self.fee_denominator = fee_denominator;
+    }
+
+    /// 获取 mint 的 decimals
+    pub fn get_decimals(&self) -> u8 {
+        self.decimals
+    }
+
+    /// 设置 mint 的 decimals（仅在添加 token 时调用）
+    pub fn set_decimals(&mut self, decimals: u8) {
+        self.decimals = decimals;
+    }
+
+    /// 获取禁用标志位
+    pub fn get_flags(&self) -> u8 {
+        self.flags
+    }
+
+    /// 设置禁用标志位
+    pub fn set_flags(&mut self, flags: u8) {
+        self.flags = flags;
+    }
+
+    /// 检查某个标志位是否被设置
+    pub fn is_disabled(&self, flag: u8) -> bool {
+        self.flags & flag != 0
+    }
+
+    /// 获取查找表的第 k 项（k 为本 item 的位置）
+    pub fn get_sorted_token(&self) -> usize {
+        self.sorted_token as usize
+    }
+
+    /// 设置查找表的第 k 项（k 为本 item 的位置）
+    pub fn set_sorted_token(&mut self, token_index: usize) {
+        self.sorted_token = token_index as u16;
     }
 
     /// 设置 vault account
@@ -63,7 +199,17 @@ impl AnySwapItem {
     pub fn space() -> usize {
         32 + // vault_account (Pubkey)
         32 + // mint_account (Pubkey)
-        8 // weight
+        8 + // weight
+        8 + // target_weight
+        8 + // weight_start_time
+        8 + // weight_end_time
+        8 + // protocol_fees
+        8 + // fee_numerator
+        8 + // fee_denominator
+        1 + // decimals
+        1 + // flags
+        2 + // sorted_token
+        4 // padding
     }
 }
 
@@ -0,0 +1,140 @@
+use anchor_lang::prelude::*;
+use bytemuck::Zeroable;
+use static_assertions::const_assert_eq;
+use std::mem::size_of;
+use super::item::AnySwapItem;
+use super::pool::AnySwapPool;
+
+/// 旧的固定大小 pool 的 token 数量上限
+pub const LEGACY_MAX_TOKENS: usize = 1024;
+
+/// 旧的固定大小 pool 的头部（迁移前的布局，只用于 migrate_pool 读取）
+///
+/// 账户数据：discriminator + LegacyAnySwapPool + [LegacyAnySwapItem; 1024]
+/// discriminator 与 AnySwapPool 相同，只能通过账户大小区分，不能直接按 AnySwapPool 加载
+#[zero_copy]
+#[repr(C)]
+#[derive(Debug)]
+pub struct LegacyAnySwapPool {
+    /// 实际使用的 token 数量
+    pub token_count: u16,
+    /// 填充字节
+    pub padding: [u8; 6],
+    /// Pool 管理员
+    pub admin: Pubkey,
+    /// LP token 总发行量
+    pub total_amount_minted: u64,
+    /// 手续费分子
+    pub fee_numerator: u64,
+    /// 手续费分母
+    pub fee_denominator: u64,
+}
+
+/// 旧的固定大小 pool 的 token 配置项
+#[zero_copy]
+#[repr(C)]
+#[derive(Debug)]
+pub struct LegacyAnySwapItem {
+    /// Vault account 地址
+    pub vault_account: Pubkey,
+    /// Mint account 地址
+    pub mint_account: Pubkey,
+    /// 权重
+    pub weight: u64,
+}
+
+// 旧布局：64 字节头部 + 72 字节的 item * 1024 = 73792 bytes
+const_assert_eq!(size_of::<LegacyAnySwapPool>(), 64);
+const_assert_eq!(size_of::<LegacyAnySwapItem>(), 72);
+
+impl LegacyAnySwapPool {
+    /// 旧 pool 账户的大小（包括 discriminator）
+    /// 当前布局的账户大小为 AnySwapPool::space(n)，不会等于这个值
+    pub fn space() -> usize {
+        8 + size_of::<LegacyAnySwapPool>() + LEGACY_MAX_TOKENS * size_of::<LegacyAnySwapItem>()
+    }
+
+    /// 账户是否为旧布局
+    pub fn is_legacy_layout(data_len: usize) -> bool {
+        data_len == Self::space()
+    }
+
+    /// 第 index 个 item 在账户数据中的偏移（包括 discriminator）
+    pub fn item_offset(index: usize) -> usize {
+        8 + size_of::<LegacyAnySwapPool>() + index * size_of::<LegacyAnySwapItem>()
+    }
+
+    /// 读取旧布局的头部（data 为包括 discriminator 的账户数据）
+    pub fn read(data: &[u8]) -> LegacyAnySwapPool {
+        bytemuck::pod_read_unaligned(&data[8..8 + size_of::<LegacyAnySwapPool>()])
+    }
+
+    /// 读取旧布局中第 index 个 item
+    pub fn read_item(data: &[u8], index: usize) -> LegacyAnySwapItem {
+        let offset = Self::item_offset(index);
+        bytemuck::pod_read_unaligned(&data[offset..offset + size_of::<LegacyAnySwapItem>()])
+    }
+
+    /// 把前 decimals.len() 个旧 item 原地改写为当前布局的 AnySwapItem（第 i 个位于 AnySwapPool::space(i)）
+    /// data 至少要有 AnySwapPool::space(decimals.len()) 字节；头部由调用者写入
+    /// 从后往前改写：新 item i 的位置在旧 item i 之后，只会覆盖已经改写过的旧 item
+    pub fn migrate_items(data: &mut [u8], decimals: &[u8]) {
+        for i in (0..decimals.len()).rev() {
+            let legacy_item = Self::read_item(data, i);
+            let mut item = AnySwapItem::zeroed();
+            item.set_vault_account(&legacy_item.vault_account);
+            item.set_mint_account(&legacy_item.mint_account);
+            item.set_weight(legacy_item.weight);
+            item.set_decimals(decimals[i]);
+            let offset = AnySwapPool::space(i);
+            data[offset..offset + size_of::<AnySwapItem>()].copy_from_slice(bytemuck::bytes_of(&item));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migrate_items_rewrites_in_place() {
+        let token_count = 600;
+        let mut data = vec![0u8; LegacyAnySwapPool::space()];
+        let items: Vec<LegacyAnySwapItem> = (0..token_count)
+            .map(|i| LegacyAnySwapItem {
+                vault_account: Pubkey::new_unique(),
+                mint_account: Pubkey::new_unique(),
+                weight: i as u64 + 1,
+            })
+            .collect();
+        for (i, item) in items.iter().enumerate() {
+            let offset = LegacyAnySwapPool::item_offset(i);
+            data[offset..offset + size_of::<LegacyAnySwapItem>()].copy_from_slice(bytemuck::bytes_of(item));
+        }
+        let decimals: Vec<u8> = (0..token_count).map(|i| (i % 10) as u8).collect();
+
+        data.resize(AnySwapPool::space(token_count), 0);
+        LegacyAnySwapPool::migrate_items(&mut data, &decimals);
+
+        for (i, legacy_item) in items.iter().enumerate() {
+            let offset = AnySwapPool::space(i);
+            let item: AnySwapItem =
+                bytemuck::pod_read_unaligned(&data[offset..offset + size_of::<AnySwapItem>()]);
+            assert_eq!(item.vault_account, legacy_item.vault_account);
+            assert_eq!(item.mint_account, legacy_item.mint_account);
+            assert_eq!(item.get_weight(), legacy_item.weight);
+            assert_eq!(item.get_target_weight(), legacy_item.weight);
+            assert_eq!(item.get_decimals(), decimals[i]);
+            assert_eq!(item.get_protocol_fees(), 0);
+            assert_eq!(item.get_fee(), None);
+        }
+    }
+
+    #[test]
+    fn legacy_layout_is_distinguishable_by_size() {
+        assert_eq!(LegacyAnySwapPool::space(), 8 + 73792);
+        for token_count in 0..=LEGACY_MAX_TOKENS {
+            assert!(!LegacyAnySwapPool::is_legacy_layout(AnySwapPool::space(token_count)));
+        }
+    }
+}
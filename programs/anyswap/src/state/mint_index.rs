@@ -0,0 +1,106 @@
+use anchor_lang::prelude::*;
+use crate::error::ErrorCode;
+use static_assertions::const_assert_eq;
+use std::mem::size_of;
+
+/// Mint 反向索引（PDA: [b"mint_index", mint]）
+///
+/// 账户数据：discriminator + 头部 + entry_count 个 MintIndexEntry
+/// 记录包含该 mint 的所有 pool 以及该 token 在 pool 中的索引，
+/// 由 add_token_to_pool / remove_token_from_pool 维护，路由只需要读取一个账户即可找到所有 pool
+/// 条目列表按偏移直接读写，避免反序列化整个列表
+#[account(zero_copy)]
+#[repr(C)]
+#[derive(Debug)]
+pub struct MintIndex {
+    /// 对应的 mint
+    pub mint: Pubkey,
+    /// 有效条目数量（账户空间可能大于所需，移除条目时不缩小）
+    pub entry_count: u64,
+}
+
+/// Mint 反向索引的一个条目
+#[zero_copy]
+#[repr(C)]
+#[derive(Debug)]
+pub struct MintIndexEntry {
+    /// 包含该 mint 的 pool
+    pub pool: Pubkey,
+    /// 该 token 在 pool 中的索引
+    pub token_index: u64,
+}
+
+const_assert_eq!(size_of::<MintIndexEntry>(), 40);
+
+impl MintIndex {
+    /// 头部空间（discriminator + mint + entry_count）
+    pub const HEADER_SPACE: usize = 8 + 32 + 8;
+
+    /// 计算容纳 entry_count 个条目所需的空间大小
+    pub fn space(entry_count: usize) -> usize {
+        Self::HEADER_SPACE + size_of::<MintIndexEntry>() * entry_count
+    }
+
+    /// 从账户数据中读取头部
+    pub fn header(data: &[u8]) -> Result<&MintIndex> {
+        bytemuck::try_from_bytes(&data[8..Self::HEADER_SPACE])
+            .map_err(|_| ErrorCode::InvalidMintIndex.into())
+    }
+
+    /// 从账户数据中读取头部（可变）
+    pub fn header_mut(data: &mut [u8]) -> Result<&mut MintIndex> {
+        bytemuck::try_from_bytes_mut(&mut data[8..Self::HEADER_SPACE])
+            .map_err(|_| ErrorCode::InvalidMintIndex.into())
+    }
+
+    /// 读取前 count 个条目
+    pub fn entries(data: &[u8], count: usize) -> Result<&[MintIndexEntry]> {
+        let end = Self::space(count);
+        require!(data.len() >= end, ErrorCode::InvalidMintIndex);
+        bytemuck::try_cast_slice(&data[Self::HEADER_SPACE..end])
+            .map_err(|_| ErrorCode::InvalidMintIndex.into())
+    }
+
+    /// 读取前 count 个条目（可变）
+    pub fn entries_mut(data: &mut [u8], count: usize) -> Result<&mut [MintIndexEntry]> {
+        let end = Self::space(count);
+        require!(data.len() >= end, ErrorCode::InvalidMintIndex);
+        bytemuck::try_cast_slice_mut(&mut data[Self::HEADER_SPACE..end])
+            .map_err(|_| ErrorCode::InvalidMintIndex.into())
+    }
+
+    /// 设置 pool 对应条目的 token 索引，返回是否找到该 pool
+    pub fn update_entry(data: &mut [u8], pool: &Pubkey, token_index: u64) -> Result<bool> {
+        let count = Self::header(data)?.entry_count as usize;
+        match Self::entries_mut(data, count)?.iter_mut().find(|e| e.pool == *pool) {
+            Some(entry) => {
+                entry.token_index = token_index;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// 设置 pool 对应条目的 token 索引，没有该 pool 时追加（调用前需要保证空间足够）
+    pub fn upsert_entry(data: &mut [u8], pool: &Pubkey, token_index: u64) -> Result<()> {
+        if Self::update_entry(data, pool, token_index)? {
+            return Ok(());
+        }
+        let count = Self::header(data)?.entry_count as usize;
+        let entries = Self::entries_mut(data, count + 1)?;
+        entries[count] = MintIndexEntry { pool: *pool, token_index };
+        Self::header_mut(data)?.entry_count = count as u64 + 1;
+        Ok(())
+    }
+
+    /// 移除 pool 对应的条目（把最后一个条目移动到该位置），没有该 pool 时不做任何修改
+    pub fn remove_entry(data: &mut [u8], pool: &Pubkey) -> Result<()> {
+        let count = Self::header(data)?.entry_count as usize;
+        let entries = Self::entries_mut(data, count)?;
+        if let Some(position) = entries.iter().position(|e| e.pool == *pool) {
+            entries[position] = entries[count - 1];
+            Self::header_mut(data)?.entry_count = count as u64 - 1;
+        }
+        Ok(())
+    }
+}
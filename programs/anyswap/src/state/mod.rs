@@ -1,6 +1,29 @@
+pub mod curve;
+pub mod factory;
+pub mod fee_policy;
 pub mod item;
+pub mod legacy;
+pub mod mint_index;
+pub mod multisig;
 pub mod pool;
+pub mod quote;
+pub mod registry;
+pub mod role;
+pub mod timelock;
 
-pub use item::AnySwapItem;
+pub use curve::CurveType;
+pub use factory::{FactoryConfig, FactoryConfigParams};
+pub use fee_policy::FeePolicy;
+pub use item::{AnySwapItem, TOKEN_DEPOSITS_DISABLED, TOKEN_SWAP_IN_DISABLED, TOKEN_SWAP_OUT_DISABLED};
+pub use legacy::{LegacyAnySwapItem, LegacyAnySwapPool, LEGACY_MAX_TOKENS};
+pub use mint_index::{MintIndex, MintIndexEntry};
+pub use multisig::{AdminMultisig, MultisigProposal, ProposalAccount, MAX_MULTISIG_MEMBERS};
 pub use pool::MAX_TOKENS;
-pub use pool::AnySwapPool;
\ No newline at end of file
+pub use pool::MINIMUM_LIQUIDITY;
+pub use pool::NORMALIZED_DECIMALS;
+pub use pool::PROTOCOL_FEE_SHARE_DENOMINATOR;
+pub use pool::{AnySwapPool, PoolLoader, PoolRef, PoolRefMut};
+pub use quote::SwapQuote;
+pub use registry::PoolRegistry;
+pub use role::Role;
+pub use timelock::{QueuedAction, TimelockAction};
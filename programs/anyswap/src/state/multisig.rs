@@ -0,0 +1,114 @@
+use anchor_lang::prelude::*;
+use crate::error::ErrorCode;
+
+/// 多签最多支持的成员数量
+pub const MAX_MULTISIG_MEMBERS: usize = 10;
+
+/// M-of-N 多签管理员配置
+/// 多签的 signer PDA（[b"multisig_signer", multisig]）作为 pool 的 admin，
+/// 达到阈值的成员签名后，程序以该 PDA 签名调用本程序的管理员指令，verify_admin 无需区分普通账户和多签
+#[account]
+pub struct AdminMultisig {
+    /// 成员列表
+    pub members: Vec<Pubkey>,
+    /// 执行所需的最少成员签名数（M）
+    pub threshold: u8,
+    /// signer PDA 的 bump
+    pub signer_bump: u8,
+    /// 成员集合的版本号，修改成员后递增，使旧提案失效
+    pub members_seqno: u32,
+    /// 下一个提案的编号
+    pub proposal_nonce: u64,
+}
+
+impl AdminMultisig {
+    /// 计算账户所需的空间大小（按最大成员数量分配）
+    pub fn space() -> usize {
+        8 + // discriminator
+        4 + (32 * MAX_MULTISIG_MEMBERS) + // members
+        1 + // threshold
+        1 + // signer_bump
+        4 + // members_seqno
+        8 // proposal_nonce
+    }
+
+    /// 检查成员列表和阈值：成员不能为空或重复，1 <= threshold <= 成员数量
+    pub fn verify_members(members: &[Pubkey], threshold: u8) -> Result<()> {
+        require!(
+            !members.is_empty() && members.len() <= MAX_MULTISIG_MEMBERS,
+            ErrorCode::InvalidMultisig
+        );
+        require!(
+            threshold > 0 && threshold as usize <= members.len(),
+            ErrorCode::InvalidMultisig
+        );
+        for (i, member) in members.iter().enumerate() {
+            require!(!members[..i].contains(member), ErrorCode::InvalidMultisig);
+        }
+        Ok(())
+    }
+
+    /// 查找成员的索引
+    pub fn find_member_index(&self, member: &Pubkey) -> Option<usize> {
+        self.members.iter().position(|m| m == member)
+    }
+
+    /// 统计账户中已签名的不同成员数量
+    pub fn count_member_signers(&self, accounts: &[AccountInfo]) -> Result<usize> {
+        let mut signed = vec![false; self.members.len()];
+        for account in accounts {
+            let index = self.find_member_index(account.key)
+                .ok_or(ErrorCode::NotMultisigMember)?;
+            require!(account.is_signer, ErrorCode::NotMultisigMember);
+            signed[index] = true;
+        }
+        Ok(signed.iter().filter(|&&s| s).count())
+    }
+}
+
+/// 提案中被调用指令的一个账户
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ProposalAccount {
+    pub pubkey: Pubkey,
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
+
+/// 多签提案（PDA: [b"multisig_proposal", multisig, proposal_id]）
+/// 记录要以多签 signer PDA 身份调用的本程序指令，成员分别批准，达到阈值后执行
+#[account]
+pub struct MultisigProposal {
+    /// 所属的多签
+    pub multisig: Pubkey,
+    /// 提案编号（来自多签的 proposal_nonce）
+    pub proposal_id: u64,
+    /// 创建提案时的成员集合版本号
+    pub members_seqno: u32,
+    /// 被调用指令的账户
+    pub accounts: Vec<ProposalAccount>,
+    /// 被调用指令的数据（包含 discriminator）
+    pub data: Vec<u8>,
+    /// 每个成员是否已批准（与 members 一一对应）
+    pub approvals: Vec<bool>,
+    /// 支付账户租金的账户，执行后退还
+    pub payer: Pubkey,
+}
+
+impl MultisigProposal {
+    /// 计算账户所需的空间大小
+    pub fn space(account_count: usize, data_len: usize) -> usize {
+        8 + // discriminator
+        32 + // multisig
+        8 + // proposal_id
+        4 + // members_seqno
+        4 + (account_count * (32 + 1 + 1)) + // accounts
+        4 + data_len + // data
+        4 + MAX_MULTISIG_MEMBERS + // approvals
+        32 // payer
+    }
+
+    /// 已批准的成员数量
+    pub fn approval_count(&self) -> usize {
+        self.approvals.iter().filter(|&&a| a).count()
+    }
+}
@@ -1,24 +1,56 @@
 use anchor_lang::prelude::*;
 use crate::error::ErrorCode;
-use super::item::AnySwapItem;
+use crate::math::{fixed_point, stable, weighted};
+use super::item::{AnySwapItem, TOKEN_SWAP_IN_DISABLED, TOKEN_SWAP_OUT_DISABLED};
+use super::legacy::LegacyAnySwapPool;
+use super::curve::CurveType;
+use super::fee_policy::FeePolicy;
+use super::quote::SwapQuote;
+use super::role::Role;
 use static_assertions::const_assert_eq;
+use std::cell::{Ref, RefMut};
+use std::cmp::Ordering;
 use std::mem::size_of;
+use std::ops::{Deref, DerefMut};
 
 /// 池中最多支持的 token 数量（用于多 token 互相转换）
 pub const MAX_TOKENS: usize = 1024;
 
-/// AnySwap 池结构
-/// 
-/// 用于存储 token 列表（items 是内部数据，不是程序地址）
-/// 使用 zero_copy 以避免栈溢出（大数组需要）
+/// 计算时统一使用的精度（与 LP mint 的 decimals 一致）
+/// 所有 token 的数量在进入定价曲线前都换算到这个精度
+pub const NORMALIZED_DECIMALS: u8 = 9;
+
+/// 协议手续费比例的分母（协议从手续费中抽取 protocol_fee_share / 10000）
+pub const PROTOCOL_FEE_SHARE_DENOMINATOR: u64 = 10_000;
+
+/// 首次添加流动性时永久锁定的 LP 数量
+/// 计入 total_amount_minted 但不铸造给任何人，防止首个存入者通过捐赠抬高 LP 价格
+pub const MINIMUM_LIQUIDITY: u64 = 1_000;
+
+/// 首次添加流动性时，存入比例与权重比例允许的相对误差（1%，18 位定点数）
+pub const INITIAL_RATIO_TOLERANCE: u128 = fixed_point::ONE / 100;
+
+/// AnySwap 池结构（账户头部）
+///
+/// 账户数据：discriminator + AnySwapPool + 若干个 AnySwapItem（token 列表，items 是内部数据，不是程序地址）
+/// token 列表的容量由账户大小决定，添加 token 时按需扩展账户，compact_pool 可以缩小到 token_count
+/// 旧的固定大小 pool（64 字节头部 + 1024 个 72 字节的 item，见 LegacyAnySwapPool）与这个布局不兼容，
+/// 加载时拒绝，必须先调用 migrate_pool 改写为当前布局
+/// 通过 AccountLoader::load_pool / load_pool_mut 加载头部和 token 列表（PoolView）
 #[account(zero_copy)]
 #[repr(C)]
 #[derive(Debug)]
 pub struct AnySwapPool {
     /// 实际使用的 token 数量（账户长度）
     pub token_count: u16,
+    /// 定价曲线类型（CurveType 的编码），创建 pool 时设置
+    pub curve_type: u8,
+    /// 交换手续费策略（FeePolicy 的编码）
+    pub fee_policy: u8,
+    /// 是否暂停（非 0 表示暂停交换和添加流动性）
+    pub paused: u8,
     /// 填充字节（确保 admin 8 字节对齐）
-    pub padding: [u8; 6],
+    pub padding: [u8; 3],
     /// Pool 管理员 - 用于所有操作的权限控制
     pub admin: Pubkey,
     /// LP token 总发行量（用于跟踪流动性提供者的份额）
@@ -27,22 +59,53 @@ pub struct AnySwapPool {
     pub fee_numerator: u64,
     /// 手续费分母
     pub fee_denominator: u64,
-    /// Token 配置数组，最多支持 1024 个 token（固定大小）
-    /// 每个 item 是内部数据，不是程序地址
-    pub tokens: [AnySwapItem; MAX_TOKENS],
+    /// StableSwap 放大系数调整前的 A（已乘以 AMP_PRECISION，仅 Stable 曲线使用）
+    pub initial_amp: u64,
+    /// StableSwap 放大系数调整后的 A（已乘以 AMP_PRECISION）
+    pub target_amp: u64,
+    /// 放大系数开始调整的时间（unix timestamp）
+    pub amp_start_time: i64,
+    /// 放大系数调整结束的时间（unix timestamp）
+    pub amp_end_time: i64,
+    /// 协议从手续费中抽取的比例（分母为 PROTOCOL_FEE_SHARE_DENOMINATOR）
+    pub protocol_fee_share: u64,
+    /// 协议金库 - 有权领取协议手续费
+    pub treasury: Pubkey,
+    /// 动态失衡手续费的最高费率（18 位定点数），为 0 表示不启用
+    pub surge_max_fee: u64,
+    /// 动态失衡手续费的失衡阈值（18 位定点数），失衡程度超过阈值后费率开始上升
+    pub surge_threshold: u64,
+    /// 紧急守护者（PauseGuardian 角色）- 可以暂停 pool 或禁用 token，但不能解除
+    pub guardian: Pubkey,
+    /// 待接受的新管理员（转移管理员的第二步由其签名确认），Pubkey::default() 表示没有
+    pub pending_admin: Pubkey,
+    /// 费率管理员，Pubkey::default() 表示未授予（只有 admin 可以操作）
+    pub fee_manager: Pubkey,
+    /// 权重管理员，Pubkey::default() 表示未授予
+    pub weight_manager: Pubkey,
+    /// Token 管理员，Pubkey::default() 表示未授予
+    pub token_curator: Pubkey,
+    /// 时间锁的最短延迟（秒），排队的操作在 delay 之后才能执行
+    pub timelock_delay: i64,
+    /// 下一个排队操作的编号
+    pub timelock_nonce: u64,
+    /// ConstantSum 曲线记录的 Σ(vault * weight)（换算到统一精度，u128 的低 64 位和高 64 位）
+    /// 单边取出时代替读取所有 vault；全为 u64::MAX 表示未同步，需要用所有 vault 重新计算
+    pub constant_sum_value: [u64; 2],
 }
 
 // 验证结构体大小和对齐（Solana 要求 8 字节对齐）
-// 计算：2 + 6 + 32 + 8 + 8 + 8 + (72 * 1024) = 73792 bytes
+// 计算：2 + 1 + 1 + 1 + 1 + 2 + 32 + 8 + 8 + 8 + 32 + 8 + 32 + 8 + 8 + 32 + 32 + 32 + 32 + 32 + 8 + 8 + 16 = 344 bytes
 const_assert_eq!(
     size_of::<AnySwapPool>(),
-    2 + 6 + 32 + 8 + 8 + 8 + (size_of::<AnySwapItem>() * MAX_TOKENS)
+    2 + 1 + 1 + 1 + 1 + 2 + 32 + 8 + 8 + 8 + 32 + 8 + 32 + 8 + 8 + 32 + 32 + 32 + 32 + 32 + 8 + 8 + 16
 );
-const_assert_eq!(size_of::<AnySwapPool>(), 73792);
+const_assert_eq!(size_of::<AnySwapPool>(), 344);
 const_assert_eq!(size_of::<AnySwapPool>() % 8, 0); // 必须是 8 的倍数
 
 impl AnySwapPool {
     /// 验证管理员权限
+    /// admin 也可以是多签的 signer PDA，由多签指令以 PDA 签名调用，这里不需要区分
     pub fn verify_admin(&self, admin: &Pubkey) -> Result<()> {
         require!(
             *admin == self.admin,
@@ -51,56 +114,81 @@ impl AnySwapPool {
         Ok(())
     }
 
-    /// 获取实际使用的 token 数量
-    pub fn get_token_count(&self) -> usize {
-        self.token_count as usize
+    /// 检查没有启用时间锁
+    /// timelock_delay > 0 时，修改费率、权重、移除 token 和转移管理员只能通过时间锁队列执行
+    pub fn require_no_timelock(&self) -> Result<()> {
+        require!(self.timelock_delay == 0, ErrorCode::TimelockRequired);
+        Ok(())
     }
 
-    /// 根据 mint 地址查找 token 索引
-    pub fn find_token_index(&self, mint: &Pubkey) -> Option<usize> {
-        for i in 0..self.get_token_count() {
-            if self.tokens[i].mint_account == *mint {
-                return Some(i);
-            }
+    /// 获取角色的持有者，Pubkey::default() 表示未授予
+    pub fn get_role(&self, role: Role) -> Pubkey {
+        match role {
+            Role::FeeManager => self.fee_manager,
+            Role::WeightManager => self.weight_manager,
+            Role::TokenCurator => self.token_curator,
+            Role::PauseGuardian => self.guardian,
         }
-        None
     }
 
-    /// 根据索引获取 token item（可变引用）
-    pub fn get_token_mut(&mut self, index: usize) -> Option<&mut AnySwapItem> {
-        if index < self.get_token_count() {
-            Some(&mut self.tokens[index])
-        } else {
-            None
+    /// 设置角色的持有者，Pubkey::default() 表示撤销
+    pub fn set_role(&mut self, role: Role, holder: Pubkey) {
+        match role {
+            Role::FeeManager => self.fee_manager = holder,
+            Role::WeightManager => self.weight_manager = holder,
+            Role::TokenCurator => self.token_curator = holder,
+            Role::PauseGuardian => self.guardian = holder,
         }
     }
 
-    /// 根据索引获取 token item（不可变引用）
-    pub fn get_token(&self, index: usize) -> Option<&AnySwapItem> {
-        if index < self.get_token_count() {
-            Some(&self.tokens[index])
-        } else {
-            None
-        }
+    /// 验证角色权限：admin 或该角色的持有者
+    pub fn verify_role(&self, role: Role, authority: &Pubkey) -> Result<()> {
+        let holder = self.get_role(role);
+        require!(
+            *authority == self.admin || (holder != Pubkey::default() && *authority == holder),
+            ErrorCode::InvalidAdmin
+        );
+        Ok(())
     }
 
-    /// 添加新的 token（返回索引）
-    /// weight: 该 token 的权重，作为不变量保持不变
-    pub fn add_token(&mut self, mint: &Pubkey, vault: &Pubkey, weight: u64) -> Result<usize> {
+    /// 验证待接受的新管理员
+    pub fn verify_pending_admin(&self, pending_admin: &Pubkey) -> Result<()> {
         require!(
-            self.get_token_count() < MAX_TOKENS,
-            ErrorCode::MaxTokensReached
+            self.pending_admin != Pubkey::default() && *pending_admin == self.pending_admin,
+            ErrorCode::InvalidPendingAdmin
         );
-        require!(weight > 0, ErrorCode::InvalidTokenCount);
+        Ok(())
+    }
 
-        let index = self.get_token_count();
-        let token = &mut self.tokens[index];
-        token.set_mint_account(mint);
-        token.set_vault_account(vault);
-        token.set_weight(weight);
+    /// 验证暂停权限：admin 可以任意修改；guardian 只能暂停或禁用，不能解除
+    /// restrictive: 本次修改是否只增加限制
+    pub fn verify_pause_authority(&self, authority: &Pubkey, restrictive: bool) -> Result<()> {
+        require!(
+            *authority == self.admin || (restrictive && *authority == self.guardian),
+            ErrorCode::InvalidAdmin
+        );
+        Ok(())
+    }
 
-        self.token_count += 1;
-        Ok(index)
+    /// 是否暂停
+    pub fn is_paused(&self) -> bool {
+        self.paused != 0
+    }
+
+    /// 检查 pool 没有暂停
+    pub fn require_not_paused(&self) -> Result<()> {
+        require!(!self.is_paused(), ErrorCode::PoolPaused);
+        Ok(())
+    }
+
+    /// 获取定价曲线类型
+    pub fn get_curve_type(&self) -> Result<CurveType> {
+        CurveType::from_u8(self.curve_type)
+    }
+
+    /// 获取实际使用的 token 数量
+    pub fn get_token_count(&self) -> usize {
+        self.token_count as usize
     }
 
     /// 获取 LP token 总发行量
@@ -113,16 +201,40 @@ impl AnySwapPool {
         self.total_amount_minted = amount;
     }
 
-    /// 计算账户所需的空间大小
-    pub fn space() -> usize {
+    /// 计算容纳 token_count 个 token 所需的账户空间大小
+    pub fn space(token_count: usize) -> usize {
         8 + // discriminator
         2 + // token_count
-        6 + // padding
+        1 + // curve_type
+        1 + // fee_policy
+        1 + // paused
+        3 + // padding
         32 + // admin (Pubkey)
         8 + // total_amount_minted
         8 + // fee_numerator
         8 + // fee_denominator
-        (MAX_TOKENS * AnySwapItem::space()) // 固定大小数组
+        8 + // initial_amp
+        8 + // target_amp
+        8 + // amp_start_time
+        8 + // amp_end_time
+        8 + // protocol_fee_share
+        32 + // treasury (Pubkey)
+        8 + // surge_max_fee
+        8 + // surge_threshold
+        32 + // guardian (Pubkey)
+        32 + // pending_admin (Pubkey)
+        32 + // fee_manager (Pubkey)
+        32 + // weight_manager (Pubkey)
+        32 + // token_curator (Pubkey)
+        8 + // timelock_delay
+        8 + // timelock_nonce
+        16 + // constant_sum_value
+        (token_count * AnySwapItem::space()) // 变长 token 列表
+    }
+
+    /// 根据账户大小计算 token 列表的容量
+    pub fn token_capacity(data_len: usize) -> usize {
+        data_len.saturating_sub(Self::space(0)) / AnySwapItem::space()
     }
 
     /// 获取手续费分子
@@ -135,21 +247,93 @@ impl AnySwapPool {
         self.fee_denominator
     }
 
-    /// 设置费率
-    pub fn set_fee(&mut self, fee_numerator: u64, fee_denominator: u64) {
+    /// 设置费率（检查费率是否合理）
+    pub fn set_fee(&mut self, fee_numerator: u64, fee_denominator: u64) -> Result<()> {
+        require!(fee_denominator > 0, ErrorCode::MathOverflow);
+        require!(fee_numerator > 0, ErrorCode::MathOverflow);
+        require!(fee_numerator <= fee_denominator, ErrorCode::MathOverflow);
         self.fee_numerator = fee_numerator;
         self.fee_denominator = fee_denominator;
+        Ok(())
+    }
+
+    /// 获取交换手续费策略
+    pub fn get_fee_policy(&self) -> Result<FeePolicy> {
+        FeePolicy::from_u8(self.fee_policy)
+    }
+
+    /// 验证协议金库权限
+    pub fn verify_treasury(&self, treasury: &Pubkey) -> Result<()> {
+        require!(*treasury == self.treasury, ErrorCode::InvalidTreasury);
+        Ok(())
+    }
+
+    /// 设置协议手续费比例和协议金库
+    pub fn set_protocol_fee(&mut self, protocol_fee_share: u64, treasury: &Pubkey) -> Result<()> {
+        require!(
+            protocol_fee_share <= PROTOCOL_FEE_SHARE_DENOMINATOR,
+            ErrorCode::InvalidProtocolFee
+        );
+        self.protocol_fee_share = protocol_fee_share;
+        self.treasury = *treasury;
+        Ok(())
+    }
+
+    /// 计算手续费中归协议的部分（向下取整）
+    pub fn calculate_protocol_fee(&self, fee_amount: u64) -> Result<u64> {
+        let protocol_fee = (fee_amount as u128)
+            .checked_mul(self.protocol_fee_share as u128)
+            .ok_or(ErrorCode::MathOverflow)?
+            / PROTOCOL_FEE_SHARE_DENOMINATOR as u128;
+        Ok(protocol_fee as u64)
+    }
+
+    /// 获取当前时刻的放大系数（已乘以 AMP_PRECISION）
+    /// 在 amp_start_time 到 amp_end_time 之间从 initial_amp 线性过渡到 target_amp
+    pub fn get_current_amp(&self, now: i64) -> Result<u128> {
+        let initial = self.initial_amp as u128;
+        let target = self.target_amp as u128;
+
+        if now >= self.amp_end_time || self.amp_end_time <= self.amp_start_time {
+            return Ok(target);
+        }
+        if now <= self.amp_start_time {
+            return Ok(initial);
+        }
+
+        let elapsed = (now - self.amp_start_time) as u128;
+        let duration = (self.amp_end_time - self.amp_start_time) as u128;
+        let amp = if target > initial {
+            initial + (target - initial) * elapsed / duration
+        } else {
+            initial - (initial - target) * elapsed / duration
+        };
+        Ok(amp)
     }
 
-    /// 计算手续费
+    /// 设置放大系数调整计划
+    /// 从当前时刻的 A 线性调整到 target_amp（已乘以 AMP_PRECISION），在 end_time 完成
+    /// 开始值按 AMP_PRECISION 精度记录，正在进行的调整不会被截断到整数 A
+    pub fn set_amp_ramp(&mut self, target_amp: u64, now: i64, end_time: i64) -> Result<()> {
+        let current_amp = self.get_current_amp(now)?;
+        self.initial_amp = u64::try_from(current_amp).map_err(|_| ErrorCode::MathOverflow)?;
+        self.target_amp = target_amp;
+        self.amp_start_time = now;
+        self.amp_end_time = end_time;
+        Ok(())
+    }
+
+    /// 按费率计算手续费
+    /// fee: (手续费分子, 手续费分母)，由 get_swap_fee / get_token_fee / calculate_surge_fee 决定
     /// amount: 输入金额
     /// 返回: (手续费金额, 扣除手续费后的金额)
-    pub fn calculate_fee(&self, amount: u64) -> Result<(u64, u64)> {
+    pub fn calculate_fee(&self, fee: (u64, u64), amount: u64) -> Result<(u64, u64)> {
+        let (fee_numerator, fee_denominator) = fee;
         let amount_u128 = amount as u128;
         let fee_amount = amount_u128
-            .checked_mul(self.fee_numerator as u128)
+            .checked_mul(fee_numerator as u128)
             .ok_or(ErrorCode::MathOverflow)?
-            .checked_div(self.fee_denominator as u128)
+            .checked_div(fee_denominator as u128)
             .ok_or(ErrorCode::MathOverflow)?;
         let amount_after_fee = amount_u128
             .checked_sub(fee_amount)
@@ -158,21 +342,559 @@ impl AnySwapPool {
         Ok((fee_amount as u64, amount_after_fee as u64))
     }
 
-    /// 计算两个 token 之间的交换输出（使用恒定乘积和公式）
-    /// 公式: Σ(vault_i * weight_i) = constant
-    /// 在交换时，保持这个和不变
-    /// 
-    /// 对于两个 token 的交换：
-    /// (vault_in + amount_in) * weight_in + (vault_out - amount_out) * weight_out = 
+    /// 根据扣除手续费后的金额反推含手续费的输入金额（calculate_fee 的逆运算）
+    /// amount_after_fee: 扣除手续费后需要的金额
+    /// 返回: (手续费金额, 含手续费的输入金额)
+    /// 向上取整，保证 calculate_fee(输入金额) 扣除手续费后不少于 amount_after_fee
+    pub fn calculate_amount_with_fee(&self, fee: (u64, u64), amount_after_fee: u64) -> Result<(u64, u64)> {
+        let (fee_numerator, fee_denominator) = fee;
+        require!(
+            fee_numerator < fee_denominator,
+            ErrorCode::MathOverflow
+        );
+
+        let denominator = (fee_denominator - fee_numerator) as u128;
+        let amount = (amount_after_fee as u128)
+            .checked_mul(fee_denominator as u128)
+            .ok_or(ErrorCode::MathOverflow)?
+            .div_ceil(denominator);
+        let amount = u64::try_from(amount).map_err(|_| ErrorCode::MathOverflow)?;
+
+        Ok((amount - amount_after_fee, amount))
+    }
+
+    /// 是否启用动态失衡手续费
+    pub fn is_surge_fee_enabled(&self) -> bool {
+        self.surge_max_fee > 0
+    }
+
+    /// 设置动态失衡手续费（max_fee 为 0 表示不启用）
+    /// max_fee / threshold: 18 位定点数，都必须小于 1
+    /// Weighted 曲线的价值占比恒等于权重占比，没有失衡程度，不能启用
+    pub fn set_surge_fee(&mut self, max_fee: u64, threshold: u64) -> Result<()> {
+        require!(
+            max_fee == 0 || self.get_curve_type()? != CurveType::Weighted,
+            ErrorCode::InvalidCurveType
+        );
+        require!((max_fee as u128) < fixed_point::ONE, ErrorCode::MathOverflow);
+        require!((threshold as u128) < fixed_point::ONE, ErrorCode::MathOverflow);
+        self.surge_max_fee = max_fee;
+        self.surge_threshold = threshold;
+        Ok(())
+    }
+
+    /// 获取 ConstantSum 曲线记录的 Σ(vault * weight)（换算到统一精度），未同步时返回 None
+    pub fn get_constant_sum_value(&self) -> Option<u128> {
+        let value = self.constant_sum_value[0] as u128 | (self.constant_sum_value[1] as u128) << 64;
+        if value == u128::MAX {
+            None
+        } else {
+            Some(value)
+        }
+    }
+
+    /// 设置记录的 Σ(vault * weight)，None 表示未同步
+    /// 迁移、移除 token 和修改权重后记录值不再准确，设置为 None，直到用所有 vault 重新同步
+    pub fn set_constant_sum_value(&mut self, value: Option<u128>) {
+        let value = value.unwrap_or(u128::MAX);
+        self.constant_sum_value = [value as u64, (value >> 64) as u64];
+    }
+
+    /// 单边取出时是否需要所有 vault 的储备量
+    /// Stable 曲线需要计算不变量 D；ConstantSum 曲线只在记录的 Σ(vault * weight) 未同步时需要；
+    /// Weighted 曲线只需要取出 token 的储备量
+    pub fn single_withdraw_requires_all_reserves(&self) -> Result<bool> {
+        Ok(match self.get_curve_type()? {
+            CurveType::Stable => true,
+            CurveType::ConstantSum => self.get_constant_sum_value().is_none(),
+            CurveType::Weighted => false,
+        })
+    }
+
+    /// 交换时是否需要所有 vault 的储备量
+    /// Stable 曲线需要计算不变量 D，动态失衡手续费需要计算整个 pool 的失衡程度
+    pub fn requires_all_reserves(&self) -> Result<bool> {
+        Ok(self.get_curve_type()? == CurveType::Stable || self.is_surge_fee_enabled())
+    }
+}
+
+/// Pool 账户的完整视图：头部 + token 列表
+/// tokens 覆盖账户中的全部容量，其中前 token_count 个是有效的 token
+/// 通过 Deref 直接访问头部的字段和方法
+pub struct PoolView<H, T> {
+    header: H,
+    tokens: T,
+}
+
+/// 只读的 pool 视图
+pub type PoolRef<'a> = PoolView<Ref<'a, AnySwapPool>, Ref<'a, [AnySwapItem]>>;
+/// 可写的 pool 视图
+pub type PoolRefMut<'a> = PoolView<RefMut<'a, AnySwapPool>, RefMut<'a, [AnySwapItem]>>;
+
+impl<H: Deref<Target = AnySwapPool>, T> Deref for PoolView<H, T> {
+    type Target = AnySwapPool;
+
+    fn deref(&self) -> &AnySwapPool {
+        &self.header
+    }
+}
+
+impl<H: DerefMut<Target = AnySwapPool>, T> DerefMut for PoolView<H, T> {
+    fn deref_mut(&mut self) -> &mut AnySwapPool {
+        &mut self.header
+    }
+}
+
+/// 加载 pool 账户的头部和 token 列表
+pub trait PoolLoader {
+    /// 加载只读视图
+    fn load_pool(&self) -> Result<PoolRef<'_>>;
+    /// 加载可写视图（账户必须可写）
+    fn load_pool_mut(&self) -> Result<PoolRefMut<'_>>;
+}
+
+impl<'info> PoolLoader for AccountLoader<'info, AnySwapPool> {
+    fn load_pool(&self) -> Result<PoolRef<'_>> {
+        // 由 AccountLoader 检查 discriminator
+        drop(self.load()?);
+        let info: &AccountInfo<'info> = self.as_ref();
+        require!(!LegacyAnySwapPool::is_legacy_layout(info.data_len()), ErrorCode::PoolNeedsMigration);
+        let capacity = AnySwapPool::token_capacity(info.data_len());
+        let (header, tokens) = Ref::map_split(info.try_borrow_data()?, |data| {
+            let (header, tokens) = data[8..].split_at(size_of::<AnySwapPool>());
+            (
+                bytemuck::from_bytes::<AnySwapPool>(header),
+                bytemuck::cast_slice(&tokens[..capacity * size_of::<AnySwapItem>()]),
+            )
+        });
+        require!(header.get_token_count() <= capacity, ErrorCode::PoolNotAllocated);
+        Ok(PoolView { header, tokens })
+    }
+
+    fn load_pool_mut(&self) -> Result<PoolRefMut<'_>> {
+        // 由 AccountLoader 检查 discriminator 和账户是否可写
+        drop(self.load_mut()?);
+        let info: &AccountInfo<'info> = self.as_ref();
+        require!(!LegacyAnySwapPool::is_legacy_layout(info.data_len()), ErrorCode::PoolNeedsMigration);
+        let capacity = AnySwapPool::token_capacity(info.data_len());
+        let (header, tokens) = RefMut::map_split(info.try_borrow_mut_data()?, |data| {
+            let (header, tokens) = data[8..].split_at_mut(size_of::<AnySwapPool>());
+            (
+                bytemuck::from_bytes_mut::<AnySwapPool>(header),
+                bytemuck::cast_slice_mut(&mut tokens[..capacity * size_of::<AnySwapItem>()]),
+            )
+        });
+        require!(header.get_token_count() <= capacity, ErrorCode::PoolNotAllocated);
+        Ok(PoolView { header, tokens })
+    }
+}
+
+impl<H, T> PoolView<H, T>
+where
+    H: Deref<Target = AnySwapPool>,
+    T: Deref<Target = [AnySwapItem]>,
+{
+    /// 检查 token 没有被禁用（flag 为 TOKEN_SWAP_IN_DISABLED 等标志位）
+    pub fn require_token_enabled(&self, token_index: usize, flag: u8) -> Result<()> {
+        require!(
+            !self.tokens[token_index].is_disabled(flag),
+            ErrorCode::TokenDisabled
+        );
+        Ok(())
+    }
+
+    /// 检查交换可以进行：pool 没有暂停，输入 token 允许交换输入，输出 token 允许交换输出
+    pub fn require_swap_enabled(&self, token_in_index: usize, token_out_index: usize) -> Result<()> {
+        self.require_not_paused()?;
+        self.require_token_enabled(token_in_index, TOKEN_SWAP_IN_DISABLED)?;
+        self.require_token_enabled(token_out_index, TOKEN_SWAP_OUT_DISABLED)
+    }
+
+    /// 根据 mint 地址查找 token 索引（在按 mint 排序的查找表中二分查找）
+    pub fn find_token_index(&self, mint: &Pubkey) -> Option<usize> {
+        self.search_sorted_lookup(mint, self.get_token_count())
+            .ok()
+            .map(|position| self.tokens[position].get_sorted_token())
+    }
+
+    /// 根据 mint 地址获取 token 索引
+    /// hint: 调用者提供的 token 索引，提供时只验证该索引对应的 mint，不再查找
+    pub fn resolve_token_index(&self, mint: &Pubkey, hint: Option<u16>) -> Result<usize> {
+        match hint {
+            Some(index) => {
+                let token = self.get_token(index as usize).ok_or(ErrorCode::InvalidTokenIndex)?;
+                require!(token.mint_account == *mint, ErrorCode::InvalidTokenIndex);
+                Ok(index as usize)
+            }
+            None => Ok(self.find_token_index(mint).ok_or(ErrorCode::InvalidTokenMint)?),
+        }
+    }
+
+    /// 在查找表的前 len 项中二分查找 mint
+    /// 找到时返回 Ok(在查找表中的位置)，否则返回 Err(应插入的位置)
+    fn search_sorted_lookup(&self, mint: &Pubkey, len: usize) -> core::result::Result<usize, usize> {
+        let (mut low, mut high) = (0, len);
+        while low < high {
+            let mid = (low + high) / 2;
+            let token_index = self.tokens[mid].get_sorted_token();
+            match self.tokens[token_index].mint_account.cmp(mint) {
+                Ordering::Less => low = mid + 1,
+                Ordering::Greater => high = mid,
+                Ordering::Equal => return Ok(mid),
+            }
+        }
+        Err(low)
+    }
+
+    /// 根据索引获取 token item（不可变引用）
+    pub fn get_token(&self, index: usize) -> Option<&AnySwapItem> {
+        if index < self.get_token_count() {
+            Some(&self.tokens[index])
+        } else {
+            None
+        }
+    }
+
+    /// 获取 token 在某一时刻的权重（权重调整期间按时间线性插值）
+    pub fn get_token_weight(&self, index: usize, now: i64) -> u64 {
+        self.tokens[index].get_weight_at(now)
+    }
+
+    /// 获取所有 token 在某一时刻的权重（按 pool 中 token 的顺序）
+    pub fn get_weights(&self, now: i64) -> Vec<u64> {
+        self.tokens[..self.get_token_count()]
+            .iter()
+            .map(|token| token.get_weight_at(now))
+            .collect()
+    }
+
+    /// 把 token 的原始数量换算到统一精度（向下取整）
+    /// 返回 u128：精度低于 NORMALIZED_DECIMALS 的 token 换算后可能超出 u64
+    pub fn normalize_amount(&self, token_index: usize, amount: u64) -> Result<u128> {
+        let token = self.get_token(token_index).ok_or(ErrorCode::InvalidTokenIndex)?;
+        fixed_point::scale_decimals(amount as u128, token.get_decimals(), NORMALIZED_DECIMALS)
+    }
+
+    /// 把 token 的原始数量换算到统一精度（向上取整）
+    pub fn normalize_amount_up(&self, token_index: usize, amount: u64) -> Result<u128> {
+        let token = self.get_token(token_index).ok_or(ErrorCode::InvalidTokenIndex)?;
+        fixed_point::scale_decimals_up(amount as u128, token.get_decimals(), NORMALIZED_DECIMALS)
+    }
+
+    /// 把统一精度的数量换算回 token 的原始精度（向下取整）
+    pub fn denormalize_amount(&self, token_index: usize, amount: u128) -> Result<u64> {
+        let token = self.get_token(token_index).ok_or(ErrorCode::InvalidTokenIndex)?;
+        let amount = fixed_point::scale_decimals(amount, NORMALIZED_DECIMALS, token.get_decimals())?;
+        u64::try_from(amount).map_err(|_| ErrorCode::MathOverflow.into())
+    }
+
+    /// 把统一精度的数量换算回 token 的原始精度（向上取整）
+    pub fn denormalize_amount_up(&self, token_index: usize, amount: u128) -> Result<u64> {
+        let token = self.get_token(token_index).ok_or(ErrorCode::InvalidTokenIndex)?;
+        let amount = fixed_point::scale_decimals_up(amount, NORMALIZED_DECIMALS, token.get_decimals())?;
+        u64::try_from(amount).map_err(|_| ErrorCode::MathOverflow.into())
+    }
+
+    /// 把所有 token 的原始数量（按 pool 中 token 的顺序）换算到统一精度（向下取整）
+    pub fn normalize_amounts(&self, amounts: &[u64]) -> Result<Vec<u128>> {
+        require!(
+            amounts.len() == self.get_token_count(),
+            ErrorCode::InvalidTokenCount
+        );
+        amounts
+            .iter()
+            .enumerate()
+            .map(|(i, &amount)| self.normalize_amount(i, amount))
+            .collect()
+    }
+
+    /// 获取 token 的费率：单独设置的费率，没有设置时使用 pool 的费率
+    pub fn get_token_fee(&self, token_index: usize) -> (u64, u64) {
+        self.tokens[token_index]
+            .get_fee()
+            .unwrap_or((self.fee_numerator, self.fee_denominator))
+    }
+
+    /// 按 pool 的手续费策略获取一笔交换的费率（动态失衡手续费的基础费率）
+    pub fn get_swap_fee(&self, token_in_index: usize, token_out_index: usize) -> Result<(u64, u64)> {
+        let fee_in = self.get_token_fee(token_in_index);
+        let fee_out = self.get_token_fee(token_out_index);
+        Ok(match self.get_fee_policy()? {
+            FeePolicy::TokenIn => fee_in,
+            FeePolicy::TokenOut => fee_out,
+            FeePolicy::Max => {
+                // a/b >= c/d <=> a*d >= c*b
+                if fee_in.0 as u128 * fee_out.1 as u128 >= fee_out.0 as u128 * fee_in.1 as u128 {
+                    fee_in
+                } else {
+                    fee_out
+                }
+            }
+        })
+    }
+
+    /// 获取 token 用于定价的储备量：vault 余额减去待领取的协议手续费
+    pub fn get_reserve(&self, token_index: usize, vault_balance: u64) -> u64 {
+        vault_balance.saturating_sub(self.tokens[token_index].get_protocol_fees())
+    }
+
+    /// 计算 pool 的失衡程度（18 位定点数，0 表示完全平衡，最大为 1）
+    /// 每个 token 的价值占比 vault_i * weight_i / Σ(vault * weight) 与目标占比 weight_i / Σweight
+    /// 之差的绝对值求和后除以 2
+    /// 目标与 verify_initial_ratio 一致，按要求存入首次流动性的 pool 失衡程度为 0
+    /// Weighted 曲线的价值占比恒等于权重占比，失衡程度始终为 0
+    pub fn calculate_imbalance(&self, reserves: &[u64], now: i64) -> Result<u128> {
+        require!(
+            reserves.len() == self.get_token_count(),
+            ErrorCode::InvalidTokenCount
+        );
+        if self.get_curve_type()? == CurveType::Weighted {
+            return Ok(0);
+        }
+
+        let reserves = self.normalize_amounts(reserves)?;
+        let weights = self.get_weights(now);
+        let values = reserves
+            .iter()
+            .zip(weights.iter())
+            .map(|(&reserve, &weight)| {
+                reserve.checked_mul(weight as u128).ok_or(ErrorCode::MathOverflow.into())
+            })
+            .collect::<Result<Vec<u128>>>()?;
+        let total_value = values
+            .iter()
+            .try_fold(0u128, |total, &value| total.checked_add(value))
+            .ok_or(ErrorCode::MathOverflow)?;
+        let total_weight = weights.iter().map(|&w| w as u128).sum::<u128>();
+        if total_value == 0 || total_weight == 0 {
+            return Ok(0);
+        }
+
+        let mut deviation: u128 = 0;
+        for (&value, &weight) in values.iter().zip(weights.iter()) {
+            let weight = weight as u128;
+            let share = fixed_point::mul_div_down(value, fixed_point::ONE, total_value)?;
+            let target = fixed_point::mul_div_down(weight, fixed_point::ONE, total_weight)?;
+            deviation = deviation
+                .checked_add(share.abs_diff(target))
+                .ok_or(ErrorCode::MathOverflow)?;
+        }
+        Ok((deviation / 2).min(fixed_point::ONE))
+    }
+
+    /// 计算动态失衡手续费的费率
+    /// base_fee: 基础费率（get_swap_fee 的结果）
+    /// reserves_before / reserves_after: 交换前后所有 token 的储备量
+    /// 返回: (手续费分子, 手续费分母)
+    ///
+    /// 交换使失衡程度下降（恢复平衡）或交换后不超过阈值时，使用基础费率
+    /// 否则费率随交换后的失衡程度线性上升：
+    /// fee = base + (max - base) * (imbalance_after - threshold) / (1 - threshold)
+    pub fn calculate_surge_fee(
+        &self,
+        base_fee: (u64, u64),
+        reserves_before: &[u64],
+        reserves_after: &[u64],
+        now: i64,
+    ) -> Result<(u64, u64)> {
+        if !self.is_surge_fee_enabled() {
+            return Ok(base_fee);
+        }
+
+        let base_rate = fixed_point::mul_div_up(base_fee.0 as u128, fixed_point::ONE, base_fee.1 as u128)?;
+        let max_rate = self.surge_max_fee as u128;
+        if max_rate <= base_rate {
+            return Ok(base_fee);
+        }
+
+        let threshold = self.surge_threshold as u128;
+        let imbalance_before = self.calculate_imbalance(reserves_before, now)?;
+        let imbalance_after = self.calculate_imbalance(reserves_after, now)?;
+        if imbalance_after <= imbalance_before || imbalance_after <= threshold {
+            return Ok(base_fee);
+        }
+
+        // 手续费向上取整（有利于 pool）
+        let surge = fixed_point::mul_div_up(
+            max_rate - base_rate,
+            imbalance_after - threshold,
+            fixed_point::ONE - threshold,
+        )?;
+        let rate = (base_rate + surge).min(max_rate);
+        Ok((rate as u64, fixed_point::ONE as u64))
+    }
+
+    /// 计算 exact-in 交换的输出和手续费（含动态失衡手续费）
+    /// reserves: 交换前所有 token 的储备量，requires_all_reserves 为 false 时只需要输入和输出 token 的储备量
+    ///
+    /// 先按基础费率估算交换后的储备量，得到动态费率后再按动态费率重新计算输出
+    pub fn quote_swap_exact_in(
+        &self,
+        token_in_index: usize,
+        token_out_index: usize,
+        reserves: &[u64],
+        amount_in: u64,
+        now: i64,
+    ) -> Result<SwapQuote> {
+        let base_fee = self.get_swap_fee(token_in_index, token_out_index)?;
+        let quote = self.calculate_swap_output_with_fee(
+            token_in_index,
+            token_out_index,
+            reserves,
+            amount_in,
+            base_fee,
+            now,
+        )?;
+        if !self.is_surge_fee_enabled() {
+            return Ok(quote);
+        }
+
+        let reserves_after = self.calculate_reserves_after_swap(
+            reserves,
+            token_in_index,
+            quote.amount_in - quote.fee_amount,
+            token_out_index,
+            quote.amount_out,
+        )?;
+        let fee = self.calculate_surge_fee(base_fee, reserves, &reserves_after, now)?;
+        if fee == base_fee {
+            return Ok(quote);
+        }
+        self.calculate_swap_output_with_fee(
+            token_in_index,
+            token_out_index,
+            reserves,
+            amount_in,
+            fee,
+            now,
+        )
+    }
+
+    /// 计算 exact-out 交换所需的输入和手续费（含动态失衡手续费）
+    /// reserves: 交换前所有 token 的储备量，requires_all_reserves 为 false 时只需要输入和输出 token 的储备量
+    pub fn quote_swap_exact_out(
+        &self,
+        token_in_index: usize,
+        token_out_index: usize,
+        reserves: &[u64],
+        amount_out: u64,
+        now: i64,
+    ) -> Result<SwapQuote> {
+        let amount_in_minus_fees = if self.get_curve_type()? == CurveType::Stable {
+            self.calculate_stable_swap_input(token_in_index, token_out_index, reserves, amount_out, now)?
+        } else {
+            self.calculate_swap_input(
+                token_in_index,
+                token_out_index,
+                reserves[token_in_index],
+                reserves[token_out_index],
+                amount_out,
+                now,
+            )?
+        };
+
+        // 扣除手续费后的输入与费率无关，可以直接得到交换后的储备量
+        let base_fee = self.get_swap_fee(token_in_index, token_out_index)?;
+        let fee = if self.is_surge_fee_enabled() {
+            let reserves_after = self.calculate_reserves_after_swap(
+                reserves,
+                token_in_index,
+                amount_in_minus_fees,
+                token_out_index,
+                amount_out,
+            )?;
+            self.calculate_surge_fee(base_fee, reserves, &reserves_after, now)?
+        } else {
+            base_fee
+        };
+        let (fee_amount, amount_in) = self.calculate_amount_with_fee(fee, amount_in_minus_fees)?;
+
+        Ok(SwapQuote {
+            amount_in,
+            amount_out,
+            fee_amount,
+            fee_numerator: fee.0,
+            fee_denominator: fee.1,
+        })
+    }
+
+    /// 按给定费率计算 exact-in 交换的输出
+    fn calculate_swap_output_with_fee(
+        &self,
+        token_in_index: usize,
+        token_out_index: usize,
+        reserves: &[u64],
+        amount_in: u64,
+        fee: (u64, u64),
+        now: i64,
+    ) -> Result<SwapQuote> {
+        let (fee_amount, amount_in_minus_fees) = self.calculate_fee(fee, amount_in)?;
+        let amount_out = if self.get_curve_type()? == CurveType::Stable {
+            self.calculate_stable_swap_output(
+                token_in_index,
+                token_out_index,
+                reserves,
+                amount_in_minus_fees,
+                now,
+            )?
+        } else {
+            self.calculate_swap_output(
+                token_in_index,
+                token_out_index,
+                reserves[token_in_index],
+                reserves[token_out_index],
+                amount_in_minus_fees,
+                now,
+            )?
+        };
+
+        Ok(SwapQuote {
+            amount_in,
+            amount_out,
+            fee_amount,
+            fee_numerator: fee.0,
+            fee_denominator: fee.1,
+        })
+    }
+
+    /// 计算交换后的储备量（输入只计入扣除手续费后的部分）
+    fn calculate_reserves_after_swap(
+        &self,
+        reserves: &[u64],
+        token_in_index: usize,
+        amount_in: u64,
+        token_out_index: usize,
+        amount_out: u64,
+    ) -> Result<Vec<u64>> {
+        let mut reserves_after = reserves.to_vec();
+        reserves_after[token_in_index] = reserves[token_in_index]
+            .checked_add(amount_in)
+            .ok_or(ErrorCode::MathOverflow)?;
+        reserves_after[token_out_index] = reserves[token_out_index]
+            .checked_sub(amount_out)
+            .ok_or(ErrorCode::InsufficientLiquidity)?;
+        Ok(reserves_after)
+    }
+
+    /// 计算两个 token 之间的交换输出（按 pool 的定价曲线）
+    /// reserve_in / reserve_out: 交换前两个 vault 的储备量
+    /// 所有数量先换算到统一精度再计算，输出换算回输出 token 的精度（向下取整）
+    ///
+    /// 恒定乘积和（ConstantSum）：Σ(vault_i * weight_i) = constant
+    /// (vault_in + amount_in) * weight_in + (vault_out - amount_out) * weight_out =
     /// vault_in * weight_in + vault_out * weight_out
-    /// 
-    /// 因此：amount_in * weight_in = amount_out * weight_out
-    /// amount_out = (amount_in * weight_in) / weight_out
+    /// 因此：amount_out = (amount_in * weight_in) / weight_out，与储备量无关
+    ///
+    /// 加权几何平均（Weighted）：Π(vault_i ^ weight_i) = constant
+    /// amount_out = vault_out * (1 - (vault_in / (vault_in + amount_in)) ^ (weight_in / weight_out))
     pub fn calculate_swap_output(
         &self,
         token_in_index: usize,
         token_out_index: usize,
+        reserve_in: u64,
+        reserve_out: u64,
         amount_in: u64,
+        now: i64,
     ) -> Result<u64> {
         require!(
             token_in_index < self.get_token_count() && token_out_index < self.get_token_count(),
@@ -180,53 +902,648 @@ impl AnySwapPool {
         );
         require!(token_in_index != token_out_index, ErrorCode::SameTokenSwap);
 
-        let token_in = self.get_token(token_in_index)
-            .ok_or(ErrorCode::InvalidTokenIndex)?;
-        let token_out = self.get_token(token_out_index)
-            .ok_or(ErrorCode::InvalidTokenIndex)?;
+        let weight_in = self.get_token_weight(token_in_index, now);
+        let weight_out = self.get_token_weight(token_out_index, now);
+
+        require!(weight_in > 0 && weight_out > 0, ErrorCode::InvalidTokenCount);
+
+        let reserve_in = self.normalize_amount(token_in_index, reserve_in)?;
+        let reserve_out = self.normalize_amount(token_out_index, reserve_out)?;
+        let amount_in = self.normalize_amount(token_in_index, amount_in)?;
+
+        let amount_out = match self.get_curve_type()? {
+            CurveType::ConstantSum => {
+                // 使用恒定乘积和公式: amount_in * weight_in = amount_out * weight_out
+                let numerator = amount_in
+                    .checked_mul(weight_in as u128)
+                    .ok_or(ErrorCode::MathOverflow)?;
+                numerator
+                    .checked_div(weight_out as u128)
+                    .ok_or(ErrorCode::MathOverflow)?
+            }
+            CurveType::Weighted => weighted::calc_out_given_in(
+                reserve_in,
+                weight_in,
+                reserve_out,
+                weight_out,
+                amount_in,
+            )?,
+            // StableSwap 需要所有 token 的储备量，使用 calculate_stable_swap_output
+            CurveType::Stable => return Err(ErrorCode::InvalidCurveType.into()),
+        };
+
+        self.denormalize_amount(token_out_index, amount_out)
+    }
+
+    /// 计算 StableSwap 曲线的交换输出
+    /// reserves: 所有 token 的储备量（按 pool 中 token 的顺序）
+    /// now: 当前时间，用于计算放大系数
+    pub fn calculate_stable_swap_output(
+        &self,
+        token_in_index: usize,
+        token_out_index: usize,
+        reserves: &[u64],
+        amount_in: u64,
+        now: i64,
+    ) -> Result<u64> {
+        require!(
+            self.get_curve_type()? == CurveType::Stable,
+            ErrorCode::InvalidCurveType
+        );
+        require!(
+            reserves.len() == self.get_token_count(),
+            ErrorCode::InvalidTokenCount
+        );
+
+        let reserves = self.normalize_amounts(reserves)?;
+        let amount_in = self.normalize_amount(token_in_index, amount_in)?;
+        let amp = self.get_current_amp(now)?;
+        let amount_out =
+            stable::calc_out_given_in(&reserves, amp, token_in_index, token_out_index, amount_in)?;
+        self.denormalize_amount(token_out_index, amount_out)
+    }
 
-        let weight_in = token_in.get_weight();
-        let weight_out = token_out.get_weight();
+    /// 计算获得 amount_out 所需的输入（不含手续费，按 pool 的定价曲线）
+    /// calculate_swap_output 的逆运算，取整有利于 pool
+    /// 输出按统一精度向上取整，输入换算回输入 token 的精度时也向上取整
+    ///
+    /// ConstantSum: amount_in = ceil(amount_out * weight_out / weight_in)
+    /// Weighted: amount_in = vault_in * ((vault_out / (vault_out - amount_out)) ^ (weight_out / weight_in) - 1)
+    pub fn calculate_swap_input(
+        &self,
+        token_in_index: usize,
+        token_out_index: usize,
+        reserve_in: u64,
+        reserve_out: u64,
+        amount_out: u64,
+        now: i64,
+    ) -> Result<u64> {
+        require!(
+            token_in_index < self.get_token_count() && token_out_index < self.get_token_count(),
+            ErrorCode::InvalidTokenIndex
+        );
+        require!(token_in_index != token_out_index, ErrorCode::SameTokenSwap);
+
+        let weight_in = self.get_token_weight(token_in_index, now);
+        let weight_out = self.get_token_weight(token_out_index, now);
 
         require!(weight_in > 0 && weight_out > 0, ErrorCode::InvalidTokenCount);
 
-        // 使用恒定乘积和公式: amount_in * weight_in = amount_out * weight_out
-        let amount_in_u128 = amount_in as u128;
-        let weight_in_u128 = weight_in as u128;
-        let weight_out_u128 = weight_out as u128;
+        let reserve_in = self.normalize_amount(token_in_index, reserve_in)?;
+        let reserve_out = self.normalize_amount(token_out_index, reserve_out)?;
+        let amount_out = self.normalize_amount_up(token_out_index, amount_out)?;
+
+        let amount_in = match self.get_curve_type()? {
+            CurveType::ConstantSum => amount_out
+                .checked_mul(weight_out as u128)
+                .ok_or(ErrorCode::MathOverflow)?
+                .div_ceil(weight_in as u128),
+            CurveType::Weighted => weighted::calc_in_given_out(
+                reserve_in,
+                weight_in,
+                reserve_out,
+                weight_out,
+                amount_out,
+            )?,
+            // StableSwap 需要所有 token 的储备量，使用 calculate_stable_swap_input
+            CurveType::Stable => return Err(ErrorCode::InvalidCurveType.into()),
+        };
+
+        self.denormalize_amount_up(token_in_index, amount_in)
+    }
+
+    /// 计算 StableSwap 曲线获得 amount_out 所需的输入（不含手续费）
+    /// reserves: 所有 token 的储备量（按 pool 中 token 的顺序）
+    pub fn calculate_stable_swap_input(
+        &self,
+        token_in_index: usize,
+        token_out_index: usize,
+        reserves: &[u64],
+        amount_out: u64,
+        now: i64,
+    ) -> Result<u64> {
+        require!(
+            self.get_curve_type()? == CurveType::Stable,
+            ErrorCode::InvalidCurveType
+        );
+        require!(
+            reserves.len() == self.get_token_count(),
+            ErrorCode::InvalidTokenCount
+        );
+
+        let reserves = self.normalize_amounts(reserves)?;
+        let amount_out = self.normalize_amount_up(token_out_index, amount_out)?;
+        let amp = self.get_current_amp(now)?;
+        let amount_in =
+            stable::calc_in_given_out(&reserves, amp, token_in_index, token_out_index, amount_out)?;
+        self.denormalize_amount_up(token_in_index, amount_in)
+    }
+
+    /// 计算池的不变量（按 pool 的定价曲线）
+    /// reserves: 原始数量，先换算到统一精度再计算
+    /// ConstantSum: 返回 Σ(vault_i * weight_i)
+    /// Weighted: 返回 Π(vault_i ^ (weight_i / Σweight))，即加权几何平均
+    /// Stable: 返回 StableSwap 不变量 D（now 用于计算放大系数）
+    pub fn calculate_invariant(&self, reserves: &[u64], now: i64) -> Result<u128> {
+        let reserves = self.normalize_amounts(reserves)?;
+        self.calculate_normalized_invariant(&reserves, now)
+    }
+
+    /// 向已有流动性的 pool 添加 token 时，新 token 需要存入的数量（统一精度，向下取整）
+    /// reserves: 现有 token 的原始储备量
+    /// ConstantSum: Σ(vault_i * weight_i) / weight_new，新 token 的价值等于现有 token 的总价值
+    /// Stable: D / n，即平衡状态下每个 token 的数量（n 为现有 token 数量）
+    /// Weighted 的价格由新 token 的存入数量决定，不能从现有储备量推导，由管理员指定
+    pub fn calculate_new_token_seed(&self, reserves: &[u64], weight: u64, now: i64) -> Result<u128> {
+        let token_count = self.get_token_count() as u128;
+        let divisor = match self.get_curve_type()? {
+            CurveType::ConstantSum => weight as u128,
+            CurveType::Stable => token_count,
+            CurveType::Weighted => return Err(ErrorCode::InvalidCurveType.into()),
+        };
+        require!(divisor > 0, ErrorCode::InvalidTokenCount);
+        self.calculate_invariant(reserves, now)?
+            .checked_div(divisor)
+            .ok_or(ErrorCode::MathOverflow.into())
+    }
+
+    /// 计算池的不变量，reserves 已换算到统一精度
+    fn calculate_normalized_invariant(&self, reserves: &[u128], now: i64) -> Result<u128> {
+        require!(
+            reserves.len() == self.get_token_count(),
+            ErrorCode::InvalidTokenCount
+        );
+
+        let weights = self.get_weights(now);
+
+        match self.get_curve_type()? {
+            CurveType::ConstantSum => {
+                let mut invariant = 0u128;
+                for (&reserve, &weight) in reserves.iter().zip(weights.iter()) {
+                    let product = reserve
+                        .checked_mul(weight as u128)
+                        .ok_or(ErrorCode::MathOverflow)?;
+                    invariant = invariant
+                        .checked_add(product)
+                        .ok_or(ErrorCode::MathOverflow)?;
+                }
+                Ok(invariant)
+            }
+            CurveType::Weighted => weighted::calc_invariant(reserves, &weights),
+            CurveType::Stable => stable::calc_invariant(reserves, self.get_current_amp(now)?),
+        }
+    }
+
+    /// 按计算误差修正不变量：round_up 为 true 时返回不小于真实值的上界，否则返回下界
+    /// ConstantSum 的不变量是精确的；Weighted 的 ln/exp 最多有 MAX_POW_RELATIVE_ERROR 的相对误差；
+    /// Stable 的 Newton 迭代在相邻两次结果相差不超过 1 时停止
+    fn round_invariant(&self, invariant: u128, round_up: bool) -> Result<u128> {
+        let error = match self.get_curve_type()? {
+            CurveType::ConstantSum => 0,
+            CurveType::Weighted => {
+                fixed_point::mul_up(invariant, fixed_point::MAX_POW_RELATIVE_ERROR)? + 1
+            }
+            CurveType::Stable => 1,
+        };
+        if round_up {
+            invariant.checked_add(error).ok_or(ErrorCode::MathOverflow.into())
+        } else {
+            Ok(invariant.saturating_sub(error))
+        }
+    }
+
+    /// 计算首次添加流动性（pool 中没有 LP）时铸造的 LP 数量
+    /// amounts: 存入数量（按 pool 中 token 的顺序），先换算到统一精度（即 LP mint 的精度）
+    ///
+    /// ConstantSum: pool 价值 Σ(amount_i * weight_i) / Σweight
+    /// Weighted: 存入数量的加权几何平均（即存入后的不变量）
+    /// Stable: 存入后的 StableSwap 不变量 D
+    pub fn calculate_initial_lp(&self, amounts: &[u64], now: i64) -> Result<u64> {
+        require!(
+            amounts.len() == self.get_token_count() && !amounts.is_empty(),
+            ErrorCode::InvalidTokenCount
+        );
+        let amounts = self.normalize_amounts(amounts)?;
+        self.verify_initial_ratio(&amounts, now)?;
 
-        let numerator = amount_in_u128
-            .checked_mul(weight_in_u128)
+        let invariant = self.calculate_normalized_invariant(&amounts, now)?;
+        let amount = match self.get_curve_type()? {
+            CurveType::ConstantSum => {
+                let total_weight = self.get_weights(now).iter().map(|&w| w as u128).sum::<u128>();
+                require!(total_weight > 0, ErrorCode::InvalidTokenCount);
+                invariant / total_weight
+            }
+            CurveType::Weighted | CurveType::Stable => invariant,
+        };
+
+        u64::try_from(amount).map_err(|_| ErrorCode::MathOverflow.into())
+    }
+
+    /// 验证首次存入的价值占比与权重占比一致：amount_i * weight_i / Σ(amount * weight) ≈ weight_i / Σweight
+    /// 即各 token 换算到统一精度后的数量相同，与 calculate_imbalance 的目标一致
+    /// Weighted 曲线的价值占比恒等于权重占比，任意比例都是合法的初始价格，不做检查
+    /// amounts 必须已换算到统一精度，允许 INITIAL_RATIO_TOLERANCE 的相对误差
+    pub fn verify_initial_ratio(&self, amounts: &[u128], now: i64) -> Result<()> {
+        let weights = self.get_weights(now);
+        require!(amounts.len() == weights.len(), ErrorCode::InvalidTokenCount);
+        if self.get_curve_type()? == CurveType::Weighted {
+            return Ok(());
+        }
+
+        let values = amounts
+            .iter()
+            .zip(weights.iter())
+            .map(|(&amount, &weight)| {
+                amount.checked_mul(weight as u128).ok_or(ErrorCode::MathOverflow.into())
+            })
+            .collect::<Result<Vec<u128>>>()?;
+        let total_value = values
+            .iter()
+            .try_fold(0u128, |total, &value| total.checked_add(value))
             .ok_or(ErrorCode::MathOverflow)?;
-        let amount_out = numerator
-            .checked_div(weight_out_u128)
+        let total_weight = weights.iter().map(|&w| w as u128).sum::<u128>();
+        require!(
+            total_value > 0 && total_weight > 0,
+            ErrorCode::InsufficientTokenAmount
+        );
+
+        for (&value, &weight) in values.iter().zip(weights.iter()) {
+            let actual = fixed_point::mul_div_down(value, fixed_point::ONE, total_value)?;
+            let expected = fixed_point::mul_div_down(
+                weight as u128,
+                fixed_point::ONE,
+                total_weight,
+            )?;
+            // |actual - expected| <= expected * tolerance
+            require!(
+                actual.abs_diff(expected) <= fixed_point::mul_up(expected, INITIAL_RATIO_TOLERANCE)?,
+                ErrorCode::InvalidDepositRatio
+            );
+        }
+        Ok(())
+    }
+
+    /// 计算 token 在池中的价值占比（18 位定点数）
+    /// 用于单边存入/取出时区分等比例部分和隐含交换部分
+    /// ConstantSum: vault_i * weight_i / Σ(vault * weight)
+    /// Weighted: weight_i / Σweight
+    /// Stable: vault_i / Σvault
+    pub fn calculate_value_share(&self, token_index: usize, reserves: &[u64], now: i64) -> Result<u128> {
+        require!(
+            token_index < self.get_token_count() && reserves.len() == self.get_token_count(),
+            ErrorCode::InvalidTokenIndex
+        );
+
+        let reserves = self.normalize_amounts(reserves)?;
+        let weights = self.get_weights(now);
+        let (part, total) = match self.get_curve_type()? {
+            CurveType::ConstantSum => {
+                let part = reserves[token_index]
+                    .checked_mul(weights[token_index] as u128)
+                    .ok_or(ErrorCode::MathOverflow)?;
+                (part, self.calculate_normalized_invariant(&reserves, now)?)
+            }
+            CurveType::Weighted => {
+                let total = weights.iter().map(|&w| w as u128).sum::<u128>();
+                (weights[token_index] as u128, total)
+            }
+            CurveType::Stable => {
+                let total = reserves.iter().sum::<u128>();
+                (reserves[token_index], total)
+            }
+        };
+
+        require!(total > 0, ErrorCode::InsufficientLiquidity);
+        fixed_point::mul_div_down(part, fixed_point::ONE, total)
+    }
+
+    /// 计算单边存入 amount_in 个 token 时铸造的 LP 数量
+    /// reserves: 存入前所有 token 的储备量（按 pool 中 token 的顺序）
+    /// 返回: (LP 数量, 手续费)
+    ///
+    /// 等比例部分（amount_in * 价值占比）不收手续费，其余部分视为隐含交换，按该 token 的费率收取手续费
+    /// LP = total_minted * (I(存入后) - I(存入前)) / I(存入前)，I 为 calculate_invariant
+    /// I(存入前) 向上取整、I(存入后) 向下取整，保证铸造的 LP 不会多于真实值
+    /// 手续费留在 vault 中，但不计入存入后的不变量
+    pub fn calculate_single_deposit_lp(
+        &self,
+        token_index: usize,
+        reserves: &[u64],
+        amount_in: u64,
+        now: i64,
+    ) -> Result<(u64, u64)> {
+        let total_minted = self.get_total_amount_minted();
+        require!(total_minted > 0, ErrorCode::InsufficientLiquidity);
+
+        let invariant_before = self.calculate_invariant(reserves, now)?;
+        require!(invariant_before > 0, ErrorCode::InsufficientLiquidity);
+        let invariant_before = self.round_invariant(invariant_before, true)?;
+
+        // 隐含交换部分 = amount_in * (1 - 价值占比)
+        let share = self.calculate_value_share(token_index, reserves, now)?;
+        let taxable_amount = fixed_point::mul_div_up(
+            amount_in as u128,
+            fixed_point::complement(share),
+            fixed_point::ONE,
+        )? as u64;
+        let (fee_amount, _) = self.calculate_fee(self.get_token_fee(token_index), taxable_amount)?;
+
+        let mut new_reserves = reserves.to_vec();
+        new_reserves[token_index] = reserves[token_index]
+            .checked_add(amount_in - fee_amount)
             .ok_or(ErrorCode::MathOverflow)?;
+        let invariant_after = self.round_invariant(self.calculate_invariant(&new_reserves, now)?, false)?;
+
+        let lp_amount = fixed_point::mul_div_down(
+            total_minted as u128,
+            invariant_after.saturating_sub(invariant_before),
+            invariant_before,
+        )?;
+        let lp_amount = u64::try_from(lp_amount).map_err(|_| ErrorCode::MathOverflow)?;
 
-        Ok(amount_out as u64)
+        Ok((lp_amount, fee_amount))
     }
 
-    /// 计算池的恒定乘积和（用于验证）
-    /// 返回 Σ(vault_i * weight_i)
-    pub fn calculate_invariant(&self, reserves: &[u64]) -> Result<u128> {
+    /// 计算单边取出时销毁 lp_in 个 LP 能获得的 token 数量
+    /// reserves: 取出前的储备量（按 pool 中 token 的顺序）
+    /// single_withdraw_requires_all_reserves 为 false 时只使用 token i 的储备量，其他位置可以为 0
+    /// 返回: (实际取出数量, 手续费)
+    ///
+    /// 不变量按 LP 比例下降：I(取出后) = I(取出前) * (total_minted - lp_in) / total_minted
+    /// 只改变 token i 的余额来满足 I(取出后)，差额即为不含手续费的取出数量
+    /// - Weighted：只取决于 token i 的余额和权重（Balancer exitswapPoolAmountIn）
+    /// - ConstantSum：I(取出前) 使用记录的 Σ(vault * weight)，未同步时用所有储备量计算
+    /// - Stable：需要所有储备量计算 D
+    ///
+    /// 其中等比例部分（数量 * 价值占比）不收手续费，其余部分视为隐含交换，按该 token 的费率收取手续费
+    /// 手续费留在 vault 中
+    pub fn calculate_single_withdraw_amount(
+        &self,
+        token_index: usize,
+        reserves: &[u64],
+        lp_in: u64,
+        now: i64,
+    ) -> Result<(u64, u64)> {
+        require!(
+            token_index < self.get_token_count(),
+            ErrorCode::InvalidTokenIndex
+        );
+        let total_minted = self.get_total_amount_minted();
+        // 永久锁定的 LP 不能被销毁
+        require!(
+            total_minted.saturating_sub(lp_in) >= MINIMUM_LIQUIDITY,
+            ErrorCode::MinimumLiquidityLocked
+        );
+
         require!(
             reserves.len() == self.get_token_count(),
             ErrorCode::InvalidTokenCount
         );
 
-        let mut invariant = 0u128;
-        for i in 0..self.get_token_count() {
-            let token = self.get_token(i).ok_or(ErrorCode::InvalidTokenIndex)?;
-            let weight = token.get_weight();
-            let reserve = reserves[i] as u128;
-            
-            let product = reserve
-                .checked_mul(weight as u128)
-                .ok_or(ErrorCode::MathOverflow)?;
-            invariant = invariant
-                .checked_add(product)
-                .ok_or(ErrorCode::MathOverflow)?;
+        // 在统一精度下求解，取出数量最后换算回 token 的精度（向下取整）
+        let balance = self.normalize_amount(token_index, reserves[token_index])?;
+        let weight = self.get_token_weight(token_index, now);
+        // (token i 取出后的余额, token i 的价值占比)
+        let (new_balance, share) = match self.get_curve_type()? {
+            CurveType::ConstantSum => {
+                let invariant_before = match self.get_constant_sum_value() {
+                    Some(value) => value,
+                    None => self.calculate_invariant(reserves, now)?,
+                };
+                require!(invariant_before > 0, ErrorCode::InsufficientLiquidity);
+                let invariant_after = fixed_point::mul_div_up(
+                    invariant_before,
+                    (total_minted - lp_in) as u128,
+                    total_minted as u128,
+                )?;
+                // I 是线性的：token i 减少 (I(取出前) - I(取出后)) / weight_i
+                let amount = (invariant_before - invariant_after) / weight as u128;
+                // 记录值不超过真实值，价值占比最多为 1
+                let value = balance.checked_mul(weight as u128).ok_or(ErrorCode::MathOverflow)?;
+                let share = fixed_point::mul_div_down(value.min(invariant_before), fixed_point::ONE, invariant_before)?;
+                (balance.saturating_sub(amount), share)
+            }
+            CurveType::Weighted => {
+                // 加权几何平均按 LP 比例下降，只需要 token i 的余额和权重
+                let total_weight = self.get_weights(now).iter().map(|&w| w as u128).sum::<u128>();
+                require!(balance > 0 && total_weight > 0, ErrorCode::InsufficientLiquidity);
+                let invariant_ratio = fixed_point::mul_div_up(
+                    fixed_point::ONE,
+                    (total_minted - lp_in) as u128,
+                    total_minted as u128,
+                )?;
+                let new_balance = weighted::calc_balance_given_invariant(
+                    balance,
+                    weight,
+                    total_weight,
+                    invariant_ratio,
+                )?;
+                let share = fixed_point::mul_div_down(weight as u128, fixed_point::ONE, total_weight)?;
+                (new_balance, share)
+            }
+            CurveType::Stable => {
+                let normalized_reserves = self.normalize_amounts(reserves)?;
+                require!(normalized_reserves.len() >= 2, ErrorCode::InvalidTokenCount);
+                let invariant_before = self.calculate_normalized_invariant(&normalized_reserves, now)?;
+                require!(invariant_before > 0, ErrorCode::InsufficientLiquidity);
+                // 取出前和取出后的不变量都向上取整（少付）
+                let invariant_before = self.round_invariant(invariant_before, true)?;
+                let invariant_after = fixed_point::mul_div_up(
+                    invariant_before,
+                    (total_minted - lp_in) as u128,
+                    total_minted as u128,
+                )?;
+                // 其他 token 余额不变，求解 token i 在 I(取出后) 下的余额，多减 1 使取整有利于 pool
+                let other_index = (token_index + 1) % normalized_reserves.len();
+                let y = stable::calc_y(
+                    &normalized_reserves,
+                    self.get_current_amp(now)?,
+                    other_index,
+                    token_index,
+                    normalized_reserves[other_index],
+                    invariant_after,
+                )?;
+                let share = self.calculate_value_share(token_index, reserves, now)?;
+                (y.checked_add(1).ok_or(ErrorCode::MathOverflow)?, share)
+            }
+        };
+        let amount_without_fee =
+            self.denormalize_amount(token_index, balance.saturating_sub(new_balance))?;
+
+        // 隐含交换部分 = 数量 * (1 - 价值占比)
+        let taxable_amount = fixed_point::mul_div_up(
+            amount_without_fee as u128,
+            fixed_point::complement(share),
+            fixed_point::ONE,
+        )? as u64;
+        let (fee_amount, _) = self.calculate_fee(self.get_token_fee(token_index), taxable_amount)?;
+
+        Ok((amount_without_fee - fee_amount, fee_amount))
+    }
+}
+
+impl<H, T> PoolView<H, T>
+where
+    H: DerefMut<Target = AnySwapPool>,
+    T: DerefMut<Target = [AnySwapItem]>,
+{
+    /// 根据索引获取 token item（可变引用）
+    pub fn get_token_mut(&mut self, index: usize) -> Option<&mut AnySwapItem> {
+        if index < self.get_token_count() {
+            Some(&mut self.tokens[index])
+        } else {
+            None
         }
+    }
+
+    /// 移除 token：把最后一个 token 移动到该位置，然后减少计数
+    pub fn remove_token(&mut self, index: usize) -> Result<()> {
+        let token_count = self.get_token_count();
+        require!(index < token_count, ErrorCode::InvalidTokenIndex);
+        // 被移除 token 的 vault 余额不再计入，记录的 Σ(vault * weight) 需要重新同步
+        self.set_constant_sum_value(None);
 
-        Ok(invariant)
+        let last_index = token_count - 1;
+        // 从查找表中删除被移除的 token（后面的项前移）
+        let removed_mint = self.tokens[index].mint_account;
+        let position = self
+            .search_sorted_lookup(&removed_mint, token_count)
+            .map_err(|_| ErrorCode::InvalidTokenIndex)?;
+        for k in position..last_index {
+            let token_index = self.tokens[k + 1].get_sorted_token();
+            self.tokens[k].set_sorted_token(token_index);
+        }
+        // 最后一个 token 会被移动到 index
+        if index != last_index {
+            let moved_mint = self.tokens[last_index].mint_account;
+            let moved_position = self
+                .search_sorted_lookup(&moved_mint, last_index)
+                .map_err(|_| ErrorCode::InvalidTokenIndex)?;
+            self.tokens[moved_position].set_sorted_token(index);
+        }
+        if index != last_index {
+            // 查找表按位置存放在 item 中，移动 item 时保留该位置的查找表项
+            let sorted_token = self.tokens[index].get_sorted_token();
+            self.tokens[index] = self.tokens[last_index];
+            self.tokens[index].set_sorted_token(sorted_token);
+        }
+        self.token_count -= 1;
+        Ok(())
+    }
+
+    /// 添加新的 token（返回索引）
+    /// weight: 该 token 的权重，作为不变量保持不变
+    /// decimals: mint 的 decimals，用于把数量换算到统一精度
+    pub fn add_token(
+        &mut self,
+        mint: &Pubkey,
+        vault: &Pubkey,
+        weight: u64,
+        decimals: u8,
+    ) -> Result<usize> {
+        require!(
+            self.get_token_count() < MAX_TOKENS,
+            ErrorCode::MaxTokensReached
+        );
+        // 调用前需要把账户扩展到 space(token_count + 1)
+        require!(
+            self.get_token_count() < self.tokens.len(),
+            ErrorCode::PoolNotAllocated
+        );
+        require!(weight > 0, ErrorCode::InvalidTokenCount);
+
+        let index = self.get_token_count();
+        // 新 token 在查找表中的插入位置
+        let position = match self.search_sorted_lookup(mint, index) {
+            Ok(_) => return Err(ErrorCode::InvalidTokenMint.into()),
+            Err(position) => position,
+        };
+
+        let token = &mut self.tokens[index];
+        token.set_mint_account(mint);
+        token.set_vault_account(vault);
+        token.set_weight(weight);
+        token.set_decimals(decimals);
+        // 移除 token 时不会清空 item（旧的固定大小 pool 或缩小前的账户），这里重置协议手续费、单独设置的费率和禁用标志
+        token.set_protocol_fees(0);
+        token.set_fee(None);
+        token.set_flags(0);
+
+        // 插入查找表（后面的项后移）
+        for k in (position..index).rev() {
+            let token_index = self.tokens[k].get_sorted_token();
+            self.tokens[k + 1].set_sorted_token(token_index);
+        }
+        self.tokens[position].set_sorted_token(index);
+
+        self.token_count += 1;
+        Ok(index)
+    }
+
+    /// 用操作后所有 token 的储备量同步 ConstantSum 曲线记录的 Σ(vault * weight)
+    /// 有 token 的权重还会随时间变化时无法记录，保持未同步；其他曲线不记录
+    pub fn sync_constant_sum_value(&mut self, reserves: &[u64], now: i64) -> Result<()> {
+        if self.get_curve_type()? != CurveType::ConstantSum {
+            return Ok(());
+        }
+        let token_count = self.get_token_count();
+        let value = if self.tokens[..token_count].iter().any(|token| token.is_weight_changing(now)) {
+            None
+        } else {
+            Some(self.calculate_invariant(reserves, now)?)
+        };
+        self.set_constant_sum_value(value);
+        Ok(())
+    }
+
+    /// 按单个 token 储备量的变化更新记录的 Σ(vault * weight)（只读取该 token 的 vault 的操作使用）
+    /// 增加的部分向下取整、减少的部分向上取整，记录值不会超过真实值；未同步或其他曲线时不更新
+    pub fn update_constant_sum_value(
+        &mut self,
+        token_index: usize,
+        reserve_before: u64,
+        reserve_after: u64,
+        now: i64,
+    ) -> Result<()> {
+        if self.get_curve_type()? != CurveType::ConstantSum {
+            return Ok(());
+        }
+        let Some(value) = self.get_constant_sum_value() else {
+            return Ok(());
+        };
+        let weight = self.get_token_weight(token_index, now) as u128;
+        let value = if reserve_after >= reserve_before {
+            let delta = self.normalize_amount(token_index, reserve_after - reserve_before)?;
+            value
+                .checked_add(delta.checked_mul(weight).ok_or(ErrorCode::MathOverflow)?)
+                .ok_or(ErrorCode::MathOverflow)?
+        } else {
+            let delta = self.normalize_amount_up(token_index, reserve_before - reserve_after)?;
+            value.saturating_sub(delta.checked_mul(weight).ok_or(ErrorCode::MathOverflow)?)
+        };
+        // 保留 u128::MAX 作为未同步标记
+        self.set_constant_sum_value(Some(value.min(u128::MAX - 1)));
+        Ok(())
+    }
+
+    /// 建立按 mint 排序的查找表（迁移旧 pool 时调用）
+    pub fn build_sorted_lookup(&mut self) {
+        let token_count = self.get_token_count();
+        let mut order: Vec<usize> = (0..token_count).collect();
+        order.sort_unstable_by_key(|&i| self.tokens[i].mint_account);
+        for (position, token_index) in order.into_iter().enumerate() {
+            self.tokens[position].set_sorted_token(token_index);
+        }
+    }
+
+    /// 累加 token 待领取的协议手续费
+    pub fn accrue_protocol_fee(&mut self, token_index: usize, amount: u64) -> Result<()> {
+        let token = self.get_token_mut(token_index).ok_or(ErrorCode::InvalidTokenIndex)?;
+        let protocol_fees = token
+            .get_protocol_fees()
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        token.set_protocol_fees(protocol_fees);
+        Ok(())
     }
 }
@@ -0,0 +1,17 @@
+use anchor_lang::prelude::*;
+
+/// 交换报价：实际成交的数量和手续费
+/// 由 quote_swap 指令返回，客户端可以预览包含动态失衡手续费在内的实际费率
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SwapQuote {
+    /// 输入数量（含手续费）
+    pub amount_in: u64,
+    /// 输出数量
+    pub amount_out: u64,
+    /// 手续费（输入 token）
+    pub fee_amount: u64,
+    /// 实际费率分子
+    pub fee_numerator: u64,
+    /// 实际费率分母
+    pub fee_denominator: u64,
+}
@@ -0,0 +1,31 @@
+use anchor_lang::prelude::*;
+
+/// Pool 注册表（PDA: [b"pool_registry"]）
+///
+/// 账户数据：discriminator + pool_count + pool_count 个 pool 地址（每个 32 字节）
+/// 创建 pool 时追加到末尾，pool 在注册表中的索引即为其全局编号
+/// 只把计数放在 zero_copy 头部，地址列表直接按偏移读写，避免反序列化整个列表
+#[account(zero_copy)]
+#[repr(C)]
+#[derive(Debug)]
+pub struct PoolRegistry {
+    /// 已注册的 pool 数量
+    pub pool_count: u64,
+}
+
+impl PoolRegistry {
+    /// 头部空间（discriminator + pool_count）
+    pub const HEADER_SPACE: usize = 8 + 8;
+
+    /// 计算注册 pool_count 个 pool 所需的空间大小
+    pub fn space(pool_count: usize) -> usize {
+        Self::HEADER_SPACE + 32 * pool_count
+    }
+
+    /// 从注册表账户数据中读取第 index 个 pool 的地址
+    pub fn pool_at(data: &[u8], index: u64) -> Option<Pubkey> {
+        let offset = Self::HEADER_SPACE.checked_add((index as usize).checked_mul(32)?)?;
+        let bytes: [u8; 32] = data.get(offset..offset + 32)?.try_into().ok()?;
+        Some(Pubkey::new_from_array(bytes))
+    }
+}
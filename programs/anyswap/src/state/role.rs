@@ -0,0 +1,15 @@
+use anchor_lang::prelude::*;
+
+/// Pool 的权限角色
+/// 管理员始终拥有所有角色的权限，并可以授予或撤销角色
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Role {
+    /// 费率管理员 - 修改 pool 和 token 的费率、费率策略、动态失衡手续费
+    FeeManager,
+    /// 权重管理员 - 修改或平滑调整 token 的权重，调整 StableSwap 放大系数
+    WeightManager,
+    /// Token 管理员 - 向 pool 添加或移除 token
+    TokenCurator,
+    /// 紧急守护者 - 暂停 pool 或禁用 token，但不能解除
+    PauseGuardian,
+}
@@ -0,0 +1,74 @@
+use anchor_lang::prelude::*;
+use super::fee_policy::FeePolicy;
+use super::role::Role;
+
+/// 时间锁中排队的操作
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TimelockAction {
+    /// 修改 pool 的费率
+    ModifyFee { fee_numerator: u64, fee_denominator: u64 },
+    /// 修改单个 token 的费率（fee_denominator 为 0 表示恢复使用 pool 的费率）
+    ModifyTokenFee { mint: Pubkey, fee_numerator: u64, fee_denominator: u64 },
+    /// 修改 pool 的手续费策略
+    ModifyFeePolicy { fee_policy: FeePolicy },
+    /// 修改动态失衡手续费（max_fee 为 0 表示不启用）
+    ModifySurgeFee { max_fee: u64, threshold: u64 },
+    /// 修改 token 的权重（立即生效，并取消该 token 的权重调整计划）
+    ModifyTokenWeight { mint: Pubkey, new_weight: u64 },
+    /// 按时间线性调整 token 的权重（start_time 不能早于执行时间）
+    RampTokenWeight { mint: Pubkey, end_weight: u64, start_time: i64, end_time: i64 },
+    /// 调整 StableSwap 放大系数（从执行时的 A 开始，end_time 至少在执行后 MIN_RAMP_DURATION）
+    RampAmp { target_amp: u64, end_time: i64 },
+    /// 从 pool 中移除 token
+    RemoveToken { mint: Pubkey },
+    /// 提议转移管理员（执行后仍需新管理员调用 accept_admin 确认）
+    ChangeAdmin { new_admin: Pubkey },
+    /// 修改时间锁延迟（降低延迟必须排队，提高可以直接调用 set_timelock_delay）
+    SetTimelockDelay { delay: i64 },
+}
+
+impl TimelockAction {
+    /// 排队、执行或取消该操作所需的角色，None 表示只有管理员可以操作
+    pub fn role(&self) -> Option<Role> {
+        match self {
+            TimelockAction::ModifyFee { .. } => Some(Role::FeeManager),
+            TimelockAction::ModifyTokenFee { .. } => Some(Role::FeeManager),
+            TimelockAction::ModifyFeePolicy { .. } => Some(Role::FeeManager),
+            TimelockAction::ModifySurgeFee { .. } => Some(Role::FeeManager),
+            TimelockAction::ModifyTokenWeight { .. } => Some(Role::WeightManager),
+            TimelockAction::RampTokenWeight { .. } => Some(Role::WeightManager),
+            TimelockAction::RampAmp { .. } => Some(Role::WeightManager),
+            TimelockAction::RemoveToken { .. } => Some(Role::TokenCurator),
+            TimelockAction::ChangeAdmin { .. } => None,
+            TimelockAction::SetTimelockDelay { .. } => None,
+        }
+    }
+}
+
+/// 时间锁队列中的一个操作（PDA: [b"timelock", pool, action_id]）
+/// 在 eta 之后可以执行，执行或取消后关闭账户并把租金退还给 payer
+#[account]
+pub struct QueuedAction {
+    /// 所属的 pool
+    pub pool: Pubkey,
+    /// 操作编号（来自 pool 的 timelock_nonce）
+    pub action_id: u64,
+    /// 排队的操作
+    pub action: TimelockAction,
+    /// 最早可以执行的时间（Unix 时间戳）
+    pub eta: i64,
+    /// 支付账户租金的账户，关闭时退还
+    pub payer: Pubkey,
+}
+
+impl QueuedAction {
+    /// 计算账户所需的空间大小
+    pub fn space() -> usize {
+        8 + // discriminator
+        32 + // pool
+        8 + // action_id
+        (1 + 32 + 8 + 8 + 8) + // action（枚举标签 + 最大变体）
+        8 + // eta
+        32 // payer
+    }
+}
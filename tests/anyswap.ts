@@ -50,7 +50,7 @@ describe("anyswap", () => {
 
   /**
    * 在客户端创建 Pool 的辅助函数
-   * 因为 pool 账户太大（73KB），超过 CPI 的 10KB 限制，所以必须在客户端预先创建账户
+   * 在客户端预先创建只包含头部的 pool 账户，添加 token 时账户会自动扩展
    * 
    * @param program - Anchor 程序实例
    * @param connection - Solana 连接
@@ -98,8 +98,8 @@ describe("anyswap", () => {
     console.log("Pool Authority PDA:", poolAuthorityPda.toString());
     console.log("Pool Mint:", poolMint.toString());
 
-    // 计算账户大小：8 (discriminator) + 2 + 6 + 32 + 8 + 8 + 8 + (72 * 1024) = 73792 bytes
-    const poolSpace = 8 + 2 + 6 + 32 + 8 + 8 + 8 + (72 * 1024); // 73792 bytes
+    // 只分配头部空间（discriminator + AnySwapPool），旧的固定大小布局需要先迁移才能使用
+    const poolSpace = 8 + 2 + 1 + 1 + 1 + 3 + 32 + 8 + 8 + 8 + 32 + 8 + 32 + 8 + 8 + 32 + 32 + 32 + 32 + 32 + 8 + 8 + 16;
     const lamports = await connection.getMinimumBalanceForRentExemption(poolSpace);
 
     // 在客户端预先创建 pool 账户（类似 Openbook 的 bids/asks）
@@ -268,7 +268,7 @@ describe("anyswap", () => {
 
     // 添加 token0（第一个 token，pool 为空，不需要流动性）
     await program.methods
-      .addTokenToPool(weight0)
+      .addTokenToPool(weight0, null)
       .accountsPartial({
         pool: pool,
         mint: mint0,
@@ -283,7 +283,7 @@ describe("anyswap", () => {
 
     // 添加 token1（pool 中已有 token0，但 vault0 为空，不需要流动性）
     await program.methods
-      .addTokenToPool(weight1)
+      .addTokenToPool(weight1, null)
       .accountsPartial({
         pool: pool,
         mint: mint1,
@@ -301,7 +301,7 @@ describe("anyswap", () => {
 
     // 添加 token2（pool 中已有 token0 和 token1，但 vault 都为空，不需要流动性）
     await program.methods
-      .addTokenToPool(weight2)
+      .addTokenToPool(weight2, null)
       .accountsPartial({
         pool: pool,
         mint: mint2,
@@ -353,12 +353,12 @@ describe("anyswap", () => {
       payer.publicKey
     );
 
-    // 按比例铸造代币：权重 20:40:40，所以数量比例也应该是 20:40:40
-    // 例如：10000, 20000, 20000
-    const baseAmount = 10000 * 10 ** n_decimals;
-    const amount0 = baseAmount; // 20 的比例
-    const amount1 = baseAmount * 2; // 40 的比例
-    const amount2 = baseAmount * 2; // 40 的比例
+    // 首次添加流动性要求价值占比等于权重占比：amount_i * weight_i / Σ(amount * weight) = weight_i / Σweight
+    // 即三个 token 的数量相同，例如：20000, 20000, 20000
+    const baseAmount = 20000 * 10 ** n_decimals;
+    const amount0 = baseAmount;
+    const amount1 = baseAmount;
+    const amount2 = baseAmount;
 
     await token.mintTo(
       connection,
@@ -400,12 +400,12 @@ describe("anyswap", () => {
       payer.publicKey
     );
 
-    // 按比例的数量：20:40:40
-    const baseAmount = 10000 * 10 ** n_decimals;
+    // 价值占比等于权重占比：三个 token 的数量相同
+    const baseAmount = 20000 * 10 ** n_decimals;
     const amounts = [
-      new anchor.BN(baseAmount),      // token0: 10000
-      new anchor.BN(baseAmount * 2),  // token1: 20000
-      new anchor.BN(baseAmount * 2),  // token2: 20000
+      new anchor.BN(baseAmount),  // token0: 20000
+      new anchor.BN(baseAmount),  // token1: 20000
+      new anchor.BN(baseAmount),  // token2: 20000
     ];
 
     console.log("添加流动性:");
@@ -768,7 +768,7 @@ describe("anyswap", () => {
     }
     
     await program.methods
-      .addTokenToPool(weight3)
+      .addTokenToPool(weight3, null)
       .accountsPartial({
         pool: pool,
         mint: mint3,
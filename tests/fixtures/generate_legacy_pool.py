@@ -0,0 +1,153 @@
+"""
+生成旧的固定大小 pool 的测试账户（tests/lp.ts 中的迁移测试使用，由 Anchor.toml 加载到测试验证节点）
+
+旧布局：discriminator + 64 字节头部 + 1024 个 72 字节的 item = 8 + 73792 字节
+- 头部：token_count (u16) + padding (6) + admin + total_amount_minted + fee_numerator + fee_denominator
+- item：vault_account + mint_account + weight
+
+同时生成 pool 的 LP mint（PDA，供应量为 total_amount_minted）和 admin 的 LP token 账户（持有全部 LP），
+旧 pool 创建时没有锁定 MINIMUM_LIQUIDITY
+
+运行：python3 tests/fixtures/generate_legacy_pool.py
+"""
+import base64
+import hashlib
+import json
+import os
+import struct
+
+from cryptography.hazmat.primitives.asymmetric.ed25519 import Ed25519PrivateKey
+from cryptography.hazmat.primitives.serialization import Encoding, PublicFormat
+
+PROGRAM_ID = "3GBxn5VSThpKNyUgaQ96xjSXD2zJ1164LzK28MXv4MDC"
+TOKEN_PROGRAM_ID = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"
+ASSOCIATED_TOKEN_PROGRAM_ID = "ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL"
+# 与 tests/lp.ts 中的 Keypair.fromSeed(new Uint8Array(32).fill(7)) 一致
+ADMIN_SEED = bytes([7] * 32)
+TOKEN_DECIMALS = [6, 9]
+TOKEN_WEIGHTS = [1, 2]
+TOTAL_AMOUNT_MINTED = 3_000_000_000
+FEE_NUMERATOR = 3
+FEE_DENOMINATOR = 1000
+
+ALPHABET = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz"
+OUT_DIR = os.path.dirname(os.path.abspath(__file__))
+
+
+def b58encode(data: bytes) -> str:
+    n = int.from_bytes(data, "big")
+    out = ""
+    while n > 0:
+        n, r = divmod(n, 58)
+        out = ALPHABET[r] + out
+    return "1" * (len(data) - len(data.lstrip(b"\0"))) + out
+
+
+def b58decode(text: str) -> bytes:
+    n = 0
+    for c in text:
+        n = n * 58 + ALPHABET.index(c)
+    return n.to_bytes(32, "big")
+
+
+def is_on_curve(point: bytes) -> bool:
+    """按 ed25519 的点解码规则检查 32 字节是否为曲线上的点"""
+    p = 2**255 - 19
+    d = -121665 * pow(121666, p - 2, p) % p
+    y = int.from_bytes(point, "little") & ((1 << 255) - 1)
+    sign = point[31] >> 7
+    if y >= p:
+        return False
+    x2 = (y * y - 1) * pow(d * y * y + 1, p - 2, p) % p
+    if x2 == 0:
+        return sign == 0
+    return pow(x2, (p - 1) // 2, p) == 1
+
+
+def find_program_address(seeds: list, program_id: str) -> bytes:
+    for bump in range(255, -1, -1):
+        digest = hashlib.sha256(
+            b"".join(seeds) + bytes([bump]) + b58decode(program_id) + b"ProgramDerivedAddress"
+        ).digest()
+        if not is_on_curve(digest):
+            return digest
+    raise ValueError("no viable bump")
+
+
+def address(label: str) -> bytes:
+    return hashlib.sha256(("anyswap legacy " + label).encode()).digest()
+
+
+def rent_exempt(space: int) -> int:
+    return (space + 128) * 3480 * 2
+
+
+def write_account(name: str, pubkey: bytes, owner: str, data: bytes) -> None:
+    account = {
+        "pubkey": b58encode(pubkey),
+        "account": {
+            "lamports": rent_exempt(len(data)),
+            "data": [base64.b64encode(data).decode(), "base64"],
+            "owner": owner,
+            "executable": False,
+            "rentEpoch": 0,
+            "space": len(data),
+        },
+    }
+    with open(os.path.join(OUT_DIR, name), "w") as f:
+        json.dump(account, f, indent=2)
+        f.write("\n")
+
+
+def main() -> None:
+    admin = Ed25519PrivateKey.from_private_bytes(ADMIN_SEED).public_key().public_bytes(
+        Encoding.Raw, PublicFormat.Raw
+    )
+
+    mints = [address("mint %d" % i) for i in range(len(TOKEN_DECIMALS))]
+    vaults = [address("vault %d" % i) for i in range(len(TOKEN_DECIMALS))]
+    for i, (mint, decimals) in enumerate(zip(mints, TOKEN_DECIMALS)):
+        # SPL Token mint：mint_authority (COption) + supply + decimals + is_initialized + freeze_authority (COption)
+        data = struct.pack("<I", 1) + admin + struct.pack("<QBB", 0, decimals, 1) + struct.pack("<I", 0) + bytes(32)
+        write_account("legacy_mint_%d.json" % i, mint, TOKEN_PROGRAM_ID, data)
+
+    discriminator = hashlib.sha256(b"account:AnySwapPool").digest()[:8]
+    header = struct.pack("<H6x", len(mints)) + admin + struct.pack(
+        "<QQQ", TOTAL_AMOUNT_MINTED, FEE_NUMERATOR, FEE_DENOMINATOR
+    )
+    items = b"".join(
+        vault + mint + struct.pack("<Q", weight) for vault, mint, weight in zip(vaults, mints, TOKEN_WEIGHTS)
+    )
+    body = header + items
+    body += bytes(64 + 72 * 1024 - len(body))
+    assert len(body) == 73792
+    pool = address("pool")
+    write_account("legacy_pool.json", pool, PROGRAM_ID, discriminator + body)
+
+    # LP mint：mint_authority 为 pool authority PDA，decimals 与当前的 LP mint 一致
+    pool_authority = find_program_address([b"anyswap_authority", pool], PROGRAM_ID)
+    pool_mint = find_program_address([b"pool_mint", pool], PROGRAM_ID)
+    data = (
+        struct.pack("<I", 1) + pool_authority + struct.pack("<QBB", TOTAL_AMOUNT_MINTED, 9, 1)
+        + struct.pack("<I", 0) + bytes(32)
+    )
+    write_account("legacy_pool_mint.json", pool_mint, TOKEN_PROGRAM_ID, data)
+
+    # admin 的 LP token 账户（ATA）：mint + owner + amount + delegate (COption) + state + is_native (COption)
+    # + delegated_amount + close_authority (COption)
+    admin_pool_ata = find_program_address(
+        [admin, b58decode(TOKEN_PROGRAM_ID), pool_mint], ASSOCIATED_TOKEN_PROGRAM_ID
+    )
+    data = (
+        pool_mint + admin + struct.pack("<Q", TOTAL_AMOUNT_MINTED) + struct.pack("<I", 0) + bytes(32)
+        + struct.pack("<B", 1) + struct.pack("<I", 0) + struct.pack("<Q", 0) + struct.pack("<Q", 0)
+        + struct.pack("<I", 0) + bytes(32)
+    )
+    assert len(data) == 165
+    write_account("legacy_admin_pool_ata.json", admin_pool_ata, TOKEN_PROGRAM_ID, data)
+    for name, key in [("pool mint", pool_mint), ("admin pool ata", admin_pool_ata)]:
+        print(name, b58encode(key))
+
+
+if __name__ == "__main__":
+    main()
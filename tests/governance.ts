@@ -0,0 +1,950 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { Anyswap } from "../target/types/anyswap";
+import * as token from "@solana/spl-token";
+import { PublicKey, Keypair, SystemProgram, ComputeBudgetProgram } from "@solana/web3.js";
+import { expect } from "chai";
+
+describe("anyswap 治理测试", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace.anyswap as Program<Anyswap>;
+  const connection = provider.connection;
+  const payer = provider.wallet as anchor.Wallet;
+
+  // 费率：1/1000 = 0.1%
+  const fee_numerator = new anchor.BN(1);
+  const fee_denominator = new anchor.BN(1000);
+  const amp = new anchor.BN(100);
+
+  const n_decimals = 6;
+  const liquidityPerToken = 1_000_000 * 10 ** n_decimals;
+
+  /**
+   * 初始化协议全局配置（如果还没有初始化），测试中不限制费率、不收取创建费
+   * 需要测试验证器以可升级方式部署程序（Anchor.toml 中 [test] upgradeable = true）
+   */
+  async function ensureFactoryConfig() {
+    const [factoryConfig] = PublicKey.findProgramAddressSync(
+      [Buffer.from("factory_config")],
+      program.programId
+    );
+    if (await connection.getAccountInfo(factoryConfig)) {
+      return;
+    }
+    const [programData] = PublicKey.findProgramAddressSync(
+      [program.programId.toBuffer()],
+      new PublicKey("BPFLoaderUpgradeab1e11111111111111111111111")
+    );
+    await program.methods
+      .initializeFactoryConfig({
+        protocolAuthority: payer.publicKey,
+        treasury: payer.publicKey,
+        minFee: new anchor.BN(0),
+        maxFee: new anchor.BN("1000000000000000000"),
+        defaultProtocolFeeShare: new anchor.BN(0),
+        creationFee: new anchor.BN(0),
+        paused: false,
+      })
+      .accountsPartial({ authority: payer.publicKey, programData: programData })
+      .rpc();
+  }
+
+  async function newUser(): Promise<Keypair> {
+    const user = Keypair.generate();
+    const airdrop = await connection.requestAirdrop(
+      user.publicKey,
+      2 * anchor.web3.LAMPORTS_PER_SOL
+    );
+    await connection.confirmTransaction(airdrop);
+    return user;
+  }
+
+  async function expectError(promise: Promise<any>, code: string) {
+    try {
+      await promise;
+      expect.fail("应该失败");
+    } catch (e: any) {
+      expect(e.error.errorCode.code).to.equal(code);
+    }
+  }
+
+  /**
+   * 创建 2 个 token 的 Stable pool，并由 admin 为每个 vault 提供相同的流动性
+   */
+  async function setupPool(): Promise<{
+    pool: PublicKey;
+    admin: Keypair;
+    mints: PublicKey[];
+    vaults: PublicKey[];
+  }> {
+    await ensureFactoryConfig();
+
+    const admin = Keypair.generate();
+    const airdrop = await connection.requestAirdrop(
+      admin.publicKey,
+      2 * anchor.web3.LAMPORTS_PER_SOL
+    );
+    await connection.confirmTransaction(airdrop);
+
+    const poolKeypair = Keypair.generate();
+    const pool = poolKeypair.publicKey;
+    const [poolAuthorityPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("anyswap_authority"), pool.toBuffer()],
+      program.programId
+    );
+    const [poolMint] = PublicKey.findProgramAddressSync(
+      [Buffer.from("pool_mint"), pool.toBuffer()],
+      program.programId
+    );
+
+    // 只分配头部空间，添加 token 时账户会自动扩展
+    const poolSpace = 8 + 2 + 1 + 1 + 1 + 3 + 32 + 8 + 8 + 8 + 32 + 8 + 32 + 8 + 8 + 32 + 32 + 32 + 32 + 32 + 8 + 8 + 16;
+    const lamports = await connection.getMinimumBalanceForRentExemption(poolSpace);
+
+    const tx = new anchor.web3.Transaction().add(
+      SystemProgram.createAccount({
+        fromPubkey: payer.publicKey,
+        newAccountPubkey: pool,
+        space: poolSpace,
+        lamports,
+        programId: program.programId,
+      }),
+      await program.methods
+        .createPool(fee_numerator, fee_denominator, { stable: {} }, amp)
+        .accountsPartial({
+          poolCreator: admin.publicKey,
+          pool: pool,
+          poolAuthority: poolAuthorityPda,
+          poolMint: poolMint,
+          admin: admin.publicKey,
+          payer: payer.publicKey,
+          systemProgram: SystemProgram.programId,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+          rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        })
+        .instruction()
+    );
+    await provider.sendAndConfirm(tx, [payer.payer, poolKeypair, admin]);
+
+    const mints: PublicKey[] = [];
+    const vaults: PublicKey[] = [];
+    for (let i = 0; i < 2; i++) {
+      const mint = await token.createMint(
+        connection,
+        payer.payer,
+        payer.publicKey,
+        null,
+        n_decimals
+      );
+      const [vault] = PublicKey.findProgramAddressSync(
+        [Buffer.from("vault"), pool.toBuffer(), mint.toBuffer()],
+        program.programId
+      );
+      const adminToken = await token.getOrCreateAssociatedTokenAccount(
+        connection,
+        payer.payer,
+        mint,
+        admin.publicKey
+      );
+
+      // 所有 vault 为空，添加 token 不需要提供流动性
+      await program.methods
+        .addTokenToPool(new anchor.BN(1), null)
+        .accountsPartial({
+          pool: pool,
+          mint: mint,
+          vault: vault,
+          adminToken: adminToken.address,
+          admin: admin.publicKey,
+          payer: payer.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+          associatedTokenProgram: token.ASSOCIATED_TOKEN_PROGRAM_ID,
+        })
+        .remainingAccounts(
+          vaults.map((v) => ({ pubkey: v, isSigner: false, isWritable: false }))
+        )
+        .signers([admin])
+        .rpc();
+
+      mints.push(mint);
+      vaults.push(vault);
+    }
+
+    const adminPoolAta = await token.createAssociatedTokenAccount(
+      connection,
+      payer.payer,
+      poolMint,
+      admin.publicKey
+    );
+    const remainingAccounts = [];
+    for (let i = 0; i < mints.length; i++) {
+      const adminToken = await token.getAssociatedTokenAddress(mints[i], admin.publicKey);
+      await token.mintTo(connection, payer.payer, mints[i], adminToken, payer.publicKey, liquidityPerToken);
+      remainingAccounts.push(
+        { pubkey: adminToken, isSigner: false, isWritable: true },
+        { pubkey: vaults[i], isSigner: false, isWritable: true },
+        { pubkey: mints[i], isSigner: false, isWritable: false }
+      );
+    }
+
+    await program.methods
+      .addLiquidity(mints.map(() => new anchor.BN(liquidityPerToken)), new anchor.BN(0), null)
+      .accountsPartial({
+        pool: pool,
+        poolAuthority: poolAuthorityPda,
+        poolMint: poolMint,
+        userPoolAta: adminPoolAta,
+        owner: admin.publicKey,
+        tokenProgram: token.TOKEN_PROGRAM_ID,
+      })
+      .remainingAccounts(remainingAccounts)
+      .signers([admin])
+      .rpc();
+
+    return { pool, admin, mints, vaults };
+  }
+
+  /**
+   * 用户从 token in 交换到 token out，返回实际收到的数量
+   */
+  async function swap(
+    pool: PublicKey,
+    mints: PublicKey[],
+    vaults: PublicKey[],
+    user: Keypair,
+    inIndex: number,
+    outIndex: number,
+    amountIn: number
+  ): Promise<number> {
+    const userIn = await token.getOrCreateAssociatedTokenAccount(
+      connection,
+      payer.payer,
+      mints[inIndex],
+      user.publicKey
+    );
+    const userOut = await token.getOrCreateAssociatedTokenAccount(
+      connection,
+      payer.payer,
+      mints[outIndex],
+      user.publicKey
+    );
+    await token.mintTo(connection, payer.payer, mints[inIndex], userIn.address, payer.publicKey, amountIn);
+
+    const before = (await token.getAccount(connection, userOut.address)).amount;
+
+    // 按 token 顺序添加，mints[i] 在 pool 中的索引就是 i，直接作为索引提示
+    await program.methods
+      .swapAnyswap(new anchor.BN(amountIn), new anchor.BN(0), null, inIndex, outIndex)
+      .accountsPartial({
+        pool: pool,
+        vaultIn: vaults[inIndex],
+        vaultOut: vaults[outIndex],
+        userIn: userIn.address,
+        userOut: userOut.address,
+        mintIn: mints[inIndex],
+        mintOut: mints[outIndex],
+        owner: user.publicKey,
+        tokenProgram: token.TOKEN_PROGRAM_ID,
+      })
+      .remainingAccounts(
+        vaults.map((v) => ({ pubkey: v, isSigner: false, isWritable: false }))
+      )
+      .preInstructions([ComputeBudgetProgram.setComputeUnitLimit({ units: 1_400_000 })])
+      .signers([user])
+      .rpc();
+
+    const after = (await token.getAccount(connection, userOut.address)).amount;
+    return Number(after - before);
+  }
+
+  describe("暂停和 token 禁用标志", () => {
+    let pool: PublicKey;
+    let admin: Keypair;
+    let mints: PublicKey[];
+    let vaults: PublicKey[];
+    let user: Keypair;
+    let guardian: Keypair;
+    const amountIn = 1_000 * 10 ** n_decimals;
+
+    before(async () => {
+      ({ pool, admin, mints, vaults } = await setupPool());
+      user = await newUser();
+      guardian = await newUser();
+      await program.methods
+        .setGuardian(guardian.publicKey)
+        .accounts({ pool: pool, authority: admin.publicKey })
+        .signers([admin])
+        .rpc();
+    });
+
+    const setPaused = (paused: boolean, authority: Keypair) =>
+      program.methods
+        .setPoolPaused(paused)
+        .accounts({ pool: pool, authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+    const setFlags = (authority: Keypair, swapIn: boolean, swapOut: boolean, deposits: boolean) =>
+      program.methods
+        .setTokenFlags(swapIn, swapOut, deposits)
+        .accounts({ pool: pool, mint: mints[0], authority: authority.publicKey })
+        .signers([authority])
+        .rpc();
+
+    /**
+     * 用户按当前比例存入：token0 存入 amount，其他 token 最多存入 2 * amount
+     */
+    async function deposit(amount: number) {
+      const [poolMint] = PublicKey.findProgramAddressSync(
+        [Buffer.from("pool_mint"), pool.toBuffer()],
+        program.programId
+      );
+      const userPoolAta = await token.getOrCreateAssociatedTokenAccount(
+        connection,
+        payer.payer,
+        poolMint,
+        user.publicKey
+      );
+      const remainingAccounts = [];
+      for (let i = 0; i < mints.length; i++) {
+        const userToken = await token.getOrCreateAssociatedTokenAccount(
+          connection,
+          payer.payer,
+          mints[i],
+          user.publicKey
+        );
+        await token.mintTo(connection, payer.payer, mints[i], userToken.address, payer.publicKey, 2 * amount);
+        remainingAccounts.push(
+          { pubkey: userToken.address, isSigner: false, isWritable: true },
+          { pubkey: vaults[i], isSigner: false, isWritable: true },
+          { pubkey: mints[i], isSigner: false, isWritable: false }
+        );
+      }
+      await program.methods
+        .addLiquidity(mints.map((_, i) => new anchor.BN(i == 0 ? amount : 2 * amount)), new anchor.BN(0), null)
+        .accountsPartial({
+          pool: pool,
+          poolMint: poolMint,
+          userPoolAta: userPoolAta.address,
+          owner: user.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+        })
+        .remainingAccounts(remainingAccounts)
+        .signers([user])
+        .rpc();
+    }
+
+    it("守护者可以暂停，恢复需要管理员", async () => {
+      await setPaused(true, guardian);
+      await expectError(swap(pool, mints, vaults, user, 0, 1, amountIn), "PoolPaused");
+
+      // 守护者和其他用户都不能恢复
+      await expectError(setPaused(false, guardian), "InvalidAdmin");
+      await expectError(setPaused(false, user), "InvalidAdmin");
+
+      await setPaused(false, admin);
+      expect(await swap(pool, mints, vaults, user, 0, 1, amountIn)).to.be.greaterThan(0);
+    });
+
+    it("token 禁用标志分别禁止交换输入、交换输出和存入", async () => {
+      // 禁止 token0 作为输入，token0 仍可以作为输出
+      await setFlags(guardian, true, false, false);
+      await expectError(swap(pool, mints, vaults, user, 0, 1, amountIn), "TokenDisabled");
+      expect(await swap(pool, mints, vaults, user, 1, 0, amountIn)).to.be.greaterThan(0);
+      await deposit(amountIn);
+
+      // 再禁止 token0 作为输出
+      await setFlags(guardian, true, true, false);
+      await expectError(swap(pool, mints, vaults, user, 1, 0, amountIn), "TokenDisabled");
+      await deposit(amountIn);
+
+      // 再禁止存入 token0
+      await setFlags(guardian, true, true, true);
+      await expectError(deposit(amountIn), "TokenDisabled");
+    });
+
+    it("守护者不能解除禁用，管理员解除后恢复正常", async () => {
+      await expectError(setFlags(guardian, false, false, false), "InvalidAdmin");
+      await expectError(setFlags(guardian, true, true, false), "InvalidAdmin");
+
+      await setFlags(admin, false, false, false);
+      expect(await swap(pool, mints, vaults, user, 0, 1, amountIn)).to.be.greaterThan(0);
+      expect(await swap(pool, mints, vaults, user, 1, 0, amountIn)).to.be.greaterThan(0);
+      await deposit(amountIn);
+    });
+  });
+
+  describe("两步转移管理员", () => {
+    let pool: PublicKey;
+    let admin: Keypair;
+    let newAdmin: Keypair;
+    let other: Keypair;
+
+    before(async () => {
+      ({ pool, admin } = await setupPool());
+      newAdmin = await newUser();
+      other = await newUser();
+    });
+
+    const propose = (signer: Keypair, candidate: PublicKey) =>
+      program.methods
+        .proposeAdmin(candidate)
+        .accounts({ pool: pool, admin: signer.publicKey })
+        .signers([signer])
+        .rpc();
+    const accept = (signer: Keypair) =>
+      program.methods
+        .acceptAdmin()
+        .accounts({ pool: pool, pendingAdmin: signer.publicKey })
+        .signers([signer])
+        .rpc();
+
+    it("提议后由新管理员接受，接受前原管理员仍然有效", async () => {
+      await expectError(propose(other, other.publicKey), "InvalidAdmin");
+
+      await propose(admin, newAdmin.publicKey);
+      let poolAccount = await program.account.anySwapPool.fetch(pool);
+      expect(poolAccount.admin.toString()).to.equal(admin.publicKey.toString());
+      expect(poolAccount.pendingAdmin.toString()).to.equal(newAdmin.publicKey.toString());
+
+      // 只有被提议的账户可以接受
+      await expectError(accept(other), "InvalidPendingAdmin");
+
+      await accept(newAdmin);
+      poolAccount = await program.account.anySwapPool.fetch(pool);
+      expect(poolAccount.admin.toString()).to.equal(newAdmin.publicKey.toString());
+      expect(poolAccount.pendingAdmin.toString()).to.equal(PublicKey.default.toString());
+
+      // 原管理员失去权限
+      await expectError(propose(admin, admin.publicKey), "InvalidAdmin");
+    });
+
+    it("取消提议后不能再接受", async () => {
+      await propose(newAdmin, other.publicKey);
+      await program.methods
+        .cancelAdminProposal()
+        .accounts({ pool: pool, admin: newAdmin.publicKey })
+        .signers([newAdmin])
+        .rpc();
+      const poolAccount = await program.account.anySwapPool.fetch(pool);
+      expect(poolAccount.pendingAdmin.toString()).to.equal(PublicKey.default.toString());
+
+      await expectError(accept(other), "InvalidPendingAdmin");
+      expect((await program.account.anySwapPool.fetch(pool)).admin.toString()).to.equal(
+        newAdmin.publicKey.toString()
+      );
+    });
+  });
+
+  describe("角色权限", () => {
+    let pool: PublicKey;
+    let admin: Keypair;
+    let mints: PublicKey[];
+    let feeManager: Keypair;
+
+    before(async () => {
+      ({ pool, admin, mints } = await setupPool());
+      feeManager = await newUser();
+    });
+
+    const modifyFee = (signer: Keypair, numerator: anchor.BN, denominator: anchor.BN) =>
+      program.methods
+        .modifyFee(numerator, denominator)
+        .accounts({ pool: pool, admin: signer.publicKey })
+        .signers([signer])
+        .rpc();
+
+    it("非管理员不能授予角色", async () => {
+      await expectError(
+        program.methods
+          .grantRole({ feeManager: {} }, feeManager.publicKey)
+          .accounts({ pool: pool, admin: feeManager.publicKey })
+          .signers([feeManager])
+          .rpc(),
+        "InvalidAdmin"
+      );
+    });
+
+    it("费率管理员只能修改费率，撤销后失去权限", async () => {
+      const poolBefore = await program.account.anySwapPool.fetch(pool);
+
+      await program.methods
+        .grantRole({ feeManager: {} }, feeManager.publicKey)
+        .accounts({ pool: pool, admin: admin.publicKey })
+        .signers([admin])
+        .rpc();
+      let poolAccount = await program.account.anySwapPool.fetch(pool);
+      expect(poolAccount.feeManager.toString()).to.equal(feeManager.publicKey.toString());
+
+      await modifyFee(feeManager, poolBefore.feeNumerator.muln(2), poolBefore.feeDenominator);
+      poolAccount = await program.account.anySwapPool.fetch(pool);
+      expect(poolAccount.feeNumerator.toString()).to.equal(poolBefore.feeNumerator.muln(2).toString());
+
+      // 费率管理员不能移除 token
+      await expectError(
+        program.methods
+          .removeTokenFromPool()
+          .accounts({ pool: pool, mint: mints[0], admin: feeManager.publicKey })
+          .signers([feeManager])
+          .rpc(),
+        "InvalidAdmin"
+      );
+
+      // 管理员仍然可以修改费率
+      await modifyFee(admin, poolBefore.feeNumerator, poolBefore.feeDenominator);
+
+      await program.methods
+        .revokeRole({ feeManager: {} })
+        .accounts({ pool: pool, admin: admin.publicKey })
+        .signers([admin])
+        .rpc();
+
+      // 撤销后不能再修改费率
+      await expectError(
+        modifyFee(feeManager, poolBefore.feeNumerator, poolBefore.feeDenominator),
+        "InvalidAdmin"
+      );
+    });
+
+    it("权重管理员可以调整放大系数，费率管理员不能", async () => {
+      const weightManager = await newUser();
+      await program.methods
+        .grantRole({ weightManager: {} }, weightManager.publicKey)
+        .accounts({ pool: pool, admin: admin.publicKey })
+        .signers([admin])
+        .rpc();
+
+      const endTime = new anchor.BN(Math.floor(Date.now() / 1000) + 2 * 86400);
+      await expectError(
+        program.methods
+          .rampAmp(new anchor.BN(200), endTime)
+          .accounts({ pool: pool, admin: feeManager.publicKey })
+          .signers([feeManager])
+          .rpc(),
+        "InvalidAdmin"
+      );
+
+      await program.methods
+        .rampAmp(new anchor.BN(200), endTime)
+        .accounts({ pool: pool, admin: weightManager.publicKey })
+        .signers([weightManager])
+        .rpc();
+      await program.methods
+        .stopRampAmp()
+        .accounts({ pool: pool, admin: weightManager.publicKey })
+        .signers([weightManager])
+        .rpc();
+
+      const poolAccount = await program.account.anySwapPool.fetch(pool);
+      expect(poolAccount.initialAmp.toNumber()).to.equal(poolAccount.targetAmp.toNumber());
+    });
+  });
+
+  describe("时间锁", () => {
+    let pool: PublicKey;
+    let admin: Keypair;
+    let mints: PublicKey[];
+    let feeNumerator: anchor.BN;
+    let feeDenominator: anchor.BN;
+
+    before(async () => {
+      ({ pool, admin, mints } = await setupPool());
+      const poolAccount = await program.account.anySwapPool.fetch(pool);
+      feeNumerator = poolAccount.feeNumerator;
+      feeDenominator = poolAccount.feeDenominator;
+    });
+
+    const setDelay = (delay: number) =>
+      program.methods
+        .setTimelockDelay(new anchor.BN(delay))
+        .accounts({ pool: pool, admin: admin.publicKey })
+        .signers([admin])
+        .rpc();
+    const modifyFee = (numerator: anchor.BN) =>
+      program.methods
+        .modifyFee(numerator, feeDenominator)
+        .accounts({ pool: pool, admin: admin.publicKey })
+        .signers([admin])
+        .rpc();
+    const queue = async (action: any) => {
+      const poolAccount = await program.account.anySwapPool.fetch(pool);
+      const actionId: anchor.BN = poolAccount.timelockNonce;
+      const [queuedAction] = PublicKey.findProgramAddressSync(
+        [Buffer.from("timelock"), pool.toBuffer(), actionId.toArrayLike(Buffer, "le", 8)],
+        program.programId
+      );
+      const accounts: any = {
+        pool: pool,
+        queuedAction: queuedAction,
+        authority: admin.publicKey,
+        payer: payer.publicKey,
+        systemProgram: SystemProgram.programId,
+      };
+      await program.methods
+        .queueAction(actionId, action)
+        .accounts(accounts)
+        .signers([admin])
+        .rpc();
+      return queuedAction;
+    };
+    const resolveAccounts = (queuedAction: PublicKey): any => ({
+      pool: pool,
+      queuedAction: queuedAction,
+      authority: admin.publicKey,
+      payer: payer.publicKey,
+    });
+    const execute = (queuedAction: PublicKey) =>
+      program.methods
+        .executeAction()
+        .accounts(resolveAccounts(queuedAction))
+        .signers([admin])
+        .rpc();
+
+    it("启用时间锁后直接修改被拒绝，且不能直接降低延迟", async () => {
+      await setDelay(2);
+
+      await expectError(modifyFee(feeNumerator.muln(2)), "TimelockRequired");
+      await expectError(
+        program.methods
+          .modifyTokenWeight(new anchor.BN(2))
+          .accounts({ pool: pool, mint: mints[0], admin: admin.publicKey })
+          .signers([admin])
+          .rpc(),
+        "TimelockRequired"
+      );
+      await expectError(
+        program.methods
+          .rampTokenWeight(
+            new anchor.BN(2),
+            new anchor.BN(Math.floor(Date.now() / 1000) + 60),
+            new anchor.BN(Math.floor(Date.now() / 1000) + 3600)
+          )
+          .accounts({ pool: pool, mint: mints[0], admin: admin.publicKey })
+          .signers([admin])
+          .rpc(),
+        "TimelockRequired"
+      );
+      await expectError(
+        program.methods
+          .removeTokenFromPool()
+          .accounts({ pool: pool, mint: mints[1], admin: admin.publicKey })
+          .signers([admin])
+          .rpc(),
+        "TimelockRequired"
+      );
+      await expectError(
+        program.methods
+          .proposeAdmin(payer.publicKey)
+          .accounts({ pool: pool, admin: admin.publicKey })
+          .signers([admin])
+          .rpc(),
+        "TimelockRequired"
+      );
+      await expectError(
+        program.methods
+          .modifyTokenFee(feeNumerator.muln(2), feeDenominator)
+          .accounts({ pool: pool, mint: mints[0], admin: admin.publicKey })
+          .signers([admin])
+          .rpc(),
+        "TimelockRequired"
+      );
+      await expectError(
+        program.methods
+          .modifyFeePolicy({ max: {} })
+          .accounts({ pool: pool, admin: admin.publicKey })
+          .signers([admin])
+          .rpc(),
+        "TimelockRequired"
+      );
+      await expectError(
+        program.methods
+          .modifySurgeFee(new anchor.BN(10_000_000), new anchor.BN(100_000_000))
+          .accounts({ pool: pool, admin: admin.publicKey })
+          .signers([admin])
+          .rpc(),
+        "TimelockRequired"
+      );
+      await expectError(
+        program.methods
+          .rampAmp(new anchor.BN(200), new anchor.BN(Math.floor(Date.now() / 1000) + 2 * 86400))
+          .accounts({ pool: pool, admin: admin.publicKey })
+          .signers([admin])
+          .rpc(),
+        "TimelockRequired"
+      );
+
+      // 降低延迟必须排队，提高延迟可以直接修改
+      await expectError(setDelay(0), "TimelockRequired");
+      await expectError(setDelay(1), "TimelockRequired");
+      await setDelay(3);
+    });
+
+    it("到期后可以执行排队的操作，包括降低延迟", async () => {
+      const newFee = await queue({ modifyFee: { feeNumerator: feeNumerator.muln(2), feeDenominator } });
+      const newPolicy = await queue({ modifyFeePolicy: { feePolicy: { max: {} } } });
+      const newAmp = await queue({
+        rampAmp: {
+          targetAmp: new anchor.BN(200),
+          endTime: new anchor.BN(Math.floor(Date.now() / 1000) + 2 * 86400),
+        },
+      });
+      const newDelay = await queue({ setTimelockDelay: { delay: new anchor.BN(0) } });
+
+      // 等待时间锁到期
+      await new Promise((resolve) => setTimeout(resolve, 5000));
+      await execute(newFee);
+      await execute(newPolicy);
+      await execute(newAmp);
+      await execute(newDelay);
+
+      const poolAccount = await program.account.anySwapPool.fetch(pool);
+      expect(poolAccount.feeNumerator.toString()).to.equal(feeNumerator.muln(2).toString());
+      expect(poolAccount.feePolicy).to.equal(2);
+      expect(poolAccount.targetAmp.toNumber()).to.equal(200 * 100);
+      expect(poolAccount.timelockDelay.toNumber()).to.equal(0);
+      expect(await connection.getAccountInfo(newFee)).to.be.null;
+
+      // 延迟为 0 后可以直接修改
+      await modifyFee(feeNumerator);
+    });
+
+    it("到期前不能执行，可以取消", async () => {
+      await setDelay(3600);
+
+      const pending = await queue({ modifyFee: { feeNumerator: feeNumerator.muln(2), feeDenominator } });
+      await expectError(execute(pending), "TimelockNotReady");
+
+      await program.methods
+        .cancelAction()
+        .accounts(resolveAccounts(pending))
+        .signers([admin])
+        .rpc();
+      expect(await connection.getAccountInfo(pending)).to.be.null;
+
+      const poolAccount = await program.account.anySwapPool.fetch(pool);
+      expect(poolAccount.feeNumerator.toString()).to.equal(feeNumerator.toString());
+    });
+  });
+
+  describe("多签管理员", () => {
+    let pool: PublicKey;
+    let admin: Keypair;
+
+    before(async () => {
+      ({ pool, admin } = await setupPool());
+    });
+
+    it("达到阈值才能执行管理员指令，提案批准后执行", async () => {
+      const members = [Keypair.generate(), Keypair.generate(), Keypair.generate()];
+      const multisigKeypair = Keypair.generate();
+      const multisig = multisigKeypair.publicKey;
+      const [multisigSigner] = PublicKey.findProgramAddressSync(
+        [Buffer.from("multisig_signer"), multisig.toBuffer()],
+        program.programId
+      );
+      const poolBefore = await program.account.anySwapPool.fetch(pool);
+
+      await program.methods
+        .createMultisig(members.map((m) => m.publicKey), 2)
+        .accounts({ multisig: multisig, payer: payer.publicKey })
+        .signers([multisigKeypair])
+        .rpc();
+
+      // 以多签 signer PDA 身份执行 ix，signers 为签名的成员
+      const execute = async (ix: anchor.web3.TransactionInstruction, signers: Keypair[]) => {
+        const remainingAccounts = [
+          ...signers.map((m) => ({ pubkey: m.publicKey, isWritable: false, isSigner: true })),
+          ...ix.keys.map((key) => ({
+            pubkey: key.pubkey,
+            isWritable: key.isWritable,
+            isSigner: key.isSigner && !key.pubkey.equals(multisigSigner),
+          })),
+        ];
+        await program.methods
+          .executeMultisigInstruction(signers.length, ix.data)
+          .accounts({ multisig: multisig })
+          .remainingAccounts(remainingAccounts)
+          .signers(signers)
+          .rpc();
+      };
+
+      // 把管理员转移给多签
+      await program.methods
+        .proposeAdmin(multisigSigner)
+        .accounts({ pool: pool, admin: admin.publicKey })
+        .signers([admin])
+        .rpc();
+      const acceptIx = await program.methods
+        .acceptAdmin()
+        .accounts({ pool: pool, pendingAdmin: multisigSigner })
+        .instruction();
+      await execute(acceptIx, members.slice(0, 2));
+      let poolAccount = await program.account.anySwapPool.fetch(pool);
+      expect(poolAccount.admin.toString()).to.equal(multisigSigner.toString());
+
+      // 只有一个成员签名时失败
+      const modifyFeeIx = await program.methods
+        .modifyFee(poolBefore.feeNumerator.muln(2), poolBefore.feeDenominator)
+        .accounts({ pool: pool, admin: multisigSigner })
+        .instruction();
+      try {
+        await execute(modifyFeeIx, members.slice(0, 1));
+        expect.fail("应该失败");
+      } catch (e: any) {
+        expect(e.error.errorCode.code).to.equal("NotEnoughSigners");
+      }
+
+      // 提案流程：成员分别批准后执行，把管理员转回原来的 admin
+      const proposeIx = await program.methods
+        .proposeAdmin(admin.publicKey)
+        .accounts({ pool: pool, admin: multisigSigner })
+        .instruction();
+      const [proposal] = PublicKey.findProgramAddressSync(
+        [Buffer.from("multisig_proposal"), multisig.toBuffer(), new anchor.BN(0).toArrayLike(Buffer, "le", 8)],
+        program.programId
+      );
+      await program.methods
+        .createMultisigProposal(
+          new anchor.BN(0),
+          proposeIx.keys.map((key) => ({ pubkey: key.pubkey, isSigner: key.isSigner, isWritable: key.isWritable })),
+          proposeIx.data
+        )
+        .accounts({ multisig: multisig, proposer: members[0].publicKey, payer: payer.publicKey })
+        .signers([members[0]])
+        .rpc();
+      const executeAccounts: any = {
+        multisig: multisig,
+        proposal: proposal,
+        multisigSigner: multisigSigner,
+        payer: payer.publicKey,
+      };
+      const proposalAccounts = proposeIx.keys.map((key) => ({
+        pubkey: key.pubkey,
+        isWritable: key.isWritable,
+        isSigner: key.isSigner && !key.pubkey.equals(multisigSigner),
+      }));
+      try {
+        await program.methods
+          .executeMultisigProposal()
+          .accounts(executeAccounts)
+          .remainingAccounts(proposalAccounts)
+          .rpc();
+        expect.fail("应该失败");
+      } catch (e: any) {
+        expect(e.error.errorCode.code).to.equal("NotEnoughSigners");
+      }
+      await program.methods
+        .approveMultisigProposal()
+        .accounts({ multisig: multisig, proposal: proposal, member: members[2].publicKey })
+        .signers([members[2]])
+        .rpc();
+      await program.methods
+        .executeMultisigProposal()
+        .accounts(executeAccounts)
+        .remainingAccounts(proposalAccounts)
+        .rpc();
+
+      await program.methods
+        .acceptAdmin()
+        .accounts({ pool: pool, pendingAdmin: admin.publicKey })
+        .signers([admin])
+        .rpc();
+      poolAccount = await program.account.anySwapPool.fetch(pool);
+      expect(poolAccount.admin.toString()).to.equal(admin.publicKey.toString());
+    });
+  });
+
+  describe("全局配置", () => {
+    let pool: PublicKey;
+    let admin: Keypair;
+    let params: any;
+
+    const updateConfig = (overrides: any) =>
+      program.methods
+        .updateFactoryConfig({ ...params, ...overrides })
+        .accounts({ protocolAuthority: payer.publicKey })
+        .rpc();
+    const modifyFee = (numerator: number) =>
+      program.methods
+        .modifyFee(new anchor.BN(numerator), new anchor.BN(1000))
+        .accounts({ pool: pool, admin: admin.publicKey })
+        .signers([admin])
+        .rpc();
+    const modifySurgeFee = (maxFee: string) =>
+      program.methods
+        .modifySurgeFee(new anchor.BN(maxFee), new anchor.BN("100000000000000000"))
+        .accounts({ pool: pool, admin: admin.publicKey })
+        .signers([admin])
+        .rpc();
+
+    before(async () => {
+      ({ pool, admin } = await setupPool());
+      const [factoryConfig] = PublicKey.findProgramAddressSync(
+        [Buffer.from("factory_config")],
+        program.programId
+      );
+      const config = await program.account.factoryConfig.fetch(factoryConfig);
+      params = {
+        protocolAuthority: config.protocolAuthority,
+        treasury: config.treasury,
+        minFee: config.minFee,
+        maxFee: config.maxFee,
+        defaultProtocolFeeShare: config.defaultProtocolFeeShare,
+        creationFee: config.creationFee,
+        paused: config.paused,
+      };
+    });
+
+    // 恢复全局配置，避免影响其他测试
+    afterEach(async () => {
+      await updateConfig({});
+    });
+
+    it("费率超出范围或协议暂停时不能修改费率", async () => {
+      // 最高费率 1%
+      await updateConfig({ maxFee: new anchor.BN("10000000000000000") });
+      await expectError(modifyFee(20), "FeeOutOfBounds");
+      await modifyFee(fee_numerator.toNumber());
+
+      await updateConfig({ paused: true });
+      await expectError(modifyFee(fee_numerator.toNumber()), "FactoryPaused");
+    });
+
+    it("动态失衡手续费的最高费率受全局范围限制，关闭不受限制", async () => {
+      // 最高费率 1%
+      await updateConfig({ maxFee: new anchor.BN("10000000000000000") });
+      await expectError(modifySurgeFee("20000000000000000"), "FeeOutOfBounds");
+      await modifySurgeFee("5000000000000000");
+      let poolAccount = await program.account.anySwapPool.fetch(pool);
+      expect(poolAccount.surgeMaxFee.toString()).to.equal("5000000000000000");
+
+      await updateConfig({ paused: true });
+      await expectError(modifySurgeFee("5000000000000000"), "FactoryPaused");
+      await modifySurgeFee("0");
+      poolAccount = await program.account.anySwapPool.fetch(pool);
+      expect(poolAccount.surgeMaxFee.toNumber()).to.equal(0);
+    });
+
+    it("只有协议管理员可以修改协议手续费", async () => {
+      const treasury = Keypair.generate().publicKey;
+
+      await expectError(
+        program.methods
+          .modifyProtocolFee(new anchor.BN(5_000), admin.publicKey)
+          .accountsPartial({ pool: pool, protocolAuthority: admin.publicKey })
+          .signers([admin])
+          .rpc(),
+        "InvalidFactoryAuthority"
+      );
+
+      await program.methods
+        .modifyProtocolFee(new anchor.BN(5_000), treasury)
+        .accountsPartial({ pool: pool, protocolAuthority: payer.publicKey })
+        .rpc();
+      const poolAccount = await program.account.anySwapPool.fetch(pool);
+      expect(poolAccount.protocolFeeShare.toNumber()).to.equal(5_000);
+      expect(poolAccount.treasury.toString()).to.equal(treasury.toString());
+    });
+  });
+});
@@ -25,6 +25,9 @@ describe("anyswap LP 测试", () => {
   const fee_numerator = new anchor.BN(5);
   const fee_denominator = new anchor.BN(1000);
 
+  // 首次添加流动性时永久锁定的 LP 数量（与程序中的 MINIMUM_LIQUIDITY 一致）
+  const MINIMUM_LIQUIDITY = 1_000;
+
   // Token 相关
   let mint0: PublicKey;
   let mint1: PublicKey;
@@ -57,6 +60,58 @@ describe("anyswap LP 测试", () => {
 
   const n_decimals = 9;
 
+  // Pool 账户头部大小（discriminator + AnySwapPool），之后是每个 128 bytes 的 token 列表
+  const poolHeaderSpace = 8 + 2 + 1 + 1 + 1 + 3 + 32 + 8 + 8 + 8 + 32 + 8 + 32 + 8 + 8 + 32 + 32 + 32 + 32 + 32 + 8 + 8 + 16;
+
+  /**
+   * 读取 pool 账户：头部由 IDL 解码，token 列表按偏移读取前 tokenCount 个
+   */
+  async function fetchPool(address: PublicKey) {
+    const data = (await connection.getAccountInfo(address))!.data;
+    const header = program.coder.accounts.decode("anySwapPool", data);
+    const tokens = Array.from({ length: header.tokenCount }, (_, i) => {
+      const item = data.subarray(poolHeaderSpace + i * 128, poolHeaderSpace + (i + 1) * 128);
+      return {
+        vaultAccount: new PublicKey(item.subarray(0, 32)),
+        mintAccount: new PublicKey(item.subarray(32, 64)),
+        weight: new anchor.BN(item.subarray(64, 72), "le"),
+        decimals: item[120],
+        sortedToken: item.readUInt16LE(122),
+      };
+    });
+    return { ...header, tokens };
+  }
+
+  /**
+   * 初始化协议全局配置（如果还没有初始化），测试中不限制费率、不收取创建费
+   * 需要测试验证器以可升级方式部署程序（Anchor.toml 中 [test] upgradeable = true）
+   */
+  async function ensureFactoryConfig() {
+    const [factoryConfig] = PublicKey.findProgramAddressSync(
+      [Buffer.from("factory_config")],
+      program.programId
+    );
+    if (await connection.getAccountInfo(factoryConfig)) {
+      return;
+    }
+    const [programData] = PublicKey.findProgramAddressSync(
+      [program.programId.toBuffer()],
+      new PublicKey("BPFLoaderUpgradeab1e11111111111111111111111")
+    );
+    await program.methods
+      .initializeFactoryConfig({
+        protocolAuthority: payer.publicKey,
+        treasury: payer.publicKey,
+        minFee: new anchor.BN(0),
+        maxFee: new anchor.BN("1000000000000000000"),
+        defaultProtocolFeeShare: new anchor.BN(0),
+        creationFee: new anchor.BN(0),
+        paused: false,
+      })
+      .accountsPartial({ authority: payer.publicKey, programData: programData })
+      .rpc();
+  }
+
   /**
    * 在客户端创建 Pool 的辅助函数
    */
@@ -74,6 +129,8 @@ describe("anyswap LP 测试", () => {
     poolMint: PublicKey;
     signature: string;
   }> {
+    await ensureFactoryConfig();
+
     const poolKeypair = anchor.web3.Keypair.generate();
     const pool = poolKeypair.publicKey;
     
@@ -88,7 +145,8 @@ describe("anyswap LP 测试", () => {
       program.programId
     );
 
-    const poolSpace = 8 + 2 + 6 + 32 + 8 + 8 + 8 + (72 * 1024);
+    // 只分配头部空间，添加 token 时账户会自动扩展
+    const poolSpace = poolHeaderSpace;
     const lamports = await connection.getMinimumBalanceForRentExemption(poolSpace);
 
     const createAccountIx = SystemProgram.createAccount({
@@ -100,7 +158,7 @@ describe("anyswap LP 测试", () => {
     });
 
     const createPoolIx = await program.methods
-      .createPool(feeNumerator, feeDenominator)
+      .createPool(feeNumerator, feeDenominator, { constantSum: {} }, new anchor.BN(0))
       .accountsPartial({
         poolCreator: poolCreator.publicKey,
         pool: pool,
@@ -159,7 +217,7 @@ describe("anyswap LP 测试", () => {
     poolMint = poolResult.poolMint;
 
     // 验证 pool 创建成功
-    const poolAccount = await program.account.anySwapPool.fetch(pool);
+    const poolAccount = await fetchPool(pool);
     expect(poolAccount.tokenCount).to.equal(0);
     expect(poolAccount.feeNumerator.toNumber()).to.equal(5);
     expect(poolAccount.feeDenominator.toNumber()).to.equal(1000);
@@ -267,7 +325,7 @@ describe("anyswap LP 测试", () => {
 
     // 添加 token0（第一个 token，pool 为空，不需要流动性）
     await program.methods
-      .addTokenToPool(weight0)
+      .addTokenToPool(weight0, null)
       .accountsPartial({
         pool: pool,
         mint: mint0,
@@ -275,21 +333,25 @@ describe("anyswap LP 测试", () => {
         adminToken: adminToken0Account,
         admin: admin.publicKey,
         payer: payer.publicKey,
+        tokenProgram: token.TOKEN_PROGRAM_ID,
         associatedTokenProgram: token.ASSOCIATED_TOKEN_PROGRAM_ID,
       })
       .signers([admin])
       .rpc();
 
-    const poolAccountAfterToken0 = await program.account.anySwapPool.fetch(pool);
+    const poolAccountAfterToken0 = await fetchPool(pool);
     expect(poolAccountAfterToken0.tokenCount).to.equal(1);
     expect(poolAccountAfterToken0.tokens[0].mintAccount.toString()).to.equal(
       mint0.toString()
     );
     expect(poolAccountAfterToken0.tokens[0].weight.toNumber()).to.equal(20);
+    expect(poolAccountAfterToken0.tokens[0].decimals).to.equal(n_decimals);
+    // 添加 token 时 pool 账户扩展一个 token 的空间
+    expect((await connection.getAccountInfo(pool))!.data.length).to.equal(poolHeaderSpace + 128);
 
     // 添加 token1
     await program.methods
-      .addTokenToPool(weight1)
+      .addTokenToPool(weight1, null)
       .accountsPartial({
         pool: pool,
         mint: mint1,
@@ -297,6 +359,7 @@ describe("anyswap LP 测试", () => {
         adminToken: adminToken1Account,
         admin: admin.publicKey,
         payer: payer.publicKey,
+        tokenProgram: token.TOKEN_PROGRAM_ID,
         associatedTokenProgram: token.ASSOCIATED_TOKEN_PROGRAM_ID,
       })
       .remainingAccounts([
@@ -306,7 +369,7 @@ describe("anyswap LP 测试", () => {
       .rpc();
 
     // 验证 pool 状态
-    const poolAccountAfterToken1 = await program.account.anySwapPool.fetch(pool);
+    const poolAccountAfterToken1 = await fetchPool(pool);
     expect(poolAccountAfterToken1.tokenCount).to.equal(2);
     expect(poolAccountAfterToken1.tokens[0].weight.toNumber()).to.equal(20);
     expect(poolAccountAfterToken1.tokens[1].weight.toNumber()).to.equal(40);
@@ -332,10 +395,11 @@ describe("anyswap LP 测试", () => {
       admin.publicKey
     );
 
-    // 按权重比例铸造代币：权重 20:40，所以数量比例也应该是 20:40
-    const baseAmount = 10000 * 10 ** n_decimals;
-    const amount0 = baseAmount; // 20 的比例
-    const amount1 = baseAmount * 2; // 40 的比例
+    // 首次添加流动性要求价值占比等于权重占比：amount_i * weight_i / Σ(amount * weight) = weight_i / Σweight
+    // 恒定和曲线下即两个 token 的数量相同
+    const baseAmount = 20000 * 10 ** n_decimals;
+    const amount0 = baseAmount;
+    const amount1 = baseAmount;
 
     // 铸造代币到 admin 的 token 账户
     await token.mintTo(
@@ -376,7 +440,7 @@ describe("anyswap LP 测试", () => {
     console.log("  - Token1:", amounts[1].toString());
 
     await program.methods
-      .addLiquidity(amounts)
+      .addLiquidity(amounts, new anchor.BN(0), null)
       .accountsPartial({
         pool: pool,
         poolAuthority: poolAuthorityPda,
@@ -388,8 +452,10 @@ describe("anyswap LP 测试", () => {
       .remainingAccounts([
         { pubkey: adminToken0Account, isSigner: false, isWritable: true },
         { pubkey: vault0, isSigner: false, isWritable: true },
+        { pubkey: mint0, isSigner: false, isWritable: false },
         { pubkey: adminToken1Account, isSigner: false, isWritable: true },
         { pubkey: vault1, isSigner: false, isWritable: true },
+        { pubkey: mint1, isSigner: false, isWritable: false },
       ])
       .signers([admin])
       .rpc();
@@ -409,7 +475,10 @@ describe("anyswap LP 测试", () => {
     // 验证 admin 的 LP token 余额
     const adminLpBalance = await token.getAccount(connection, adminPoolAta);
     const adminLpAmount = Number(adminLpBalance.amount);
-    expect(adminLpAmount).to.be.greaterThan(0);
+    // 数量相同时初始 LP = Σ(amount * weight) / Σweight = baseAmount，其中 MINIMUM_LIQUIDITY 永久锁定
+    expect(adminLpAmount).to.equal(baseAmount - MINIMUM_LIQUIDITY);
+    const poolAccount = await fetchPool(pool);
+    expect(poolAccount.totalAmountMinted.toNumber()).to.equal(baseAmount);
 
     console.log("✓ Admin 添加流动性成功");
     console.log("  - Vault0 余额:", vault0BalanceAfter.toString());
@@ -482,7 +551,7 @@ describe("anyswap LP 测试", () => {
     console.log("  - Token1:", amounts[1].toString());
 
     await program.methods
-      .addLiquidity(amounts)
+      .addLiquidity(amounts, new anchor.BN(0), null)
       .accountsPartial({
         pool: pool,
         poolAuthority: poolAuthorityPda,
@@ -494,8 +563,10 @@ describe("anyswap LP 测试", () => {
       .remainingAccounts([
         { pubkey: user1Token0Account, isSigner: false, isWritable: true },
         { pubkey: vault0, isSigner: false, isWritable: true },
+        { pubkey: mint0, isSigner: false, isWritable: false },
         { pubkey: user1Token1Account, isSigner: false, isWritable: true },
         { pubkey: vault1, isSigner: false, isWritable: true },
+        { pubkey: mint1, isSigner: false, isWritable: false },
       ])
       .signers([user1])
       .rpc();
@@ -540,7 +611,7 @@ describe("anyswap LP 测试", () => {
 
     // 移除流动性
     await program.methods
-      .removeLiquidity(new anchor.BN(burnAmount))
+      .removeLiquidity(new anchor.BN(burnAmount), [new anchor.BN(0), new anchor.BN(0)], null)
       .accountsPartial({
         pool: pool,
         poolAuthority: poolAuthorityPda,
@@ -552,8 +623,10 @@ describe("anyswap LP 测试", () => {
       .remainingAccounts([
         { pubkey: adminToken0Account, isSigner: false, isWritable: true },
         { pubkey: vault0, isSigner: false, isWritable: true },
+        { pubkey: mint0, isSigner: false, isWritable: false },
         { pubkey: adminToken1Account, isSigner: false, isWritable: true },
         { pubkey: vault1, isSigner: false, isWritable: true },
+        { pubkey: mint1, isSigner: false, isWritable: false },
       ])
       .signers([admin])
       .rpc();
@@ -588,7 +661,7 @@ describe("anyswap LP 测试", () => {
   it("步骤 6: Admin 添加 token2 到 pool", async () => {
     const weight2 = new anchor.BN(40);
 
-    const poolAccountBeforeToken2 = await program.account.anySwapPool.fetch(pool);
+    const poolAccountBeforeToken2 = await fetchPool(pool);
     expect(poolAccountBeforeToken2.tokenCount).to.equal(2);
 
     // 创建 admin 的 token2 账户
@@ -606,7 +679,7 @@ describe("anyswap LP 测试", () => {
     const vault1Balance = (await token.getAccount(connection, vault1)).amount;
     
     // 获取当前权重
-    const poolAccount = await program.account.anySwapPool.fetch(pool);
+    const poolAccount = await fetchPool(pool);
     const weight0 = poolAccount.tokens[0].weight.toNumber();
     const weight1 = poolAccount.tokens[1].weight.toNumber();
     
@@ -634,7 +707,7 @@ describe("anyswap LP 测试", () => {
 
     // 添加 token2 到 pool
     await program.methods
-      .addTokenToPool(weight2)
+      .addTokenToPool(weight2, null)
       .accountsPartial({
         pool: pool,
         mint: mint2,
@@ -642,6 +715,7 @@ describe("anyswap LP 测试", () => {
         adminToken: adminToken2Account,
         admin: admin.publicKey,
         payer: payer.publicKey,
+        tokenProgram: token.TOKEN_PROGRAM_ID,
         associatedTokenProgram: token.ASSOCIATED_TOKEN_PROGRAM_ID,
       })
       .remainingAccounts([
@@ -652,7 +726,7 @@ describe("anyswap LP 测试", () => {
       .rpc();
 
     // 验证 pool 状态
-    const poolAccountAfter = await program.account.anySwapPool.fetch(pool);
+    const poolAccountAfter = await fetchPool(pool);
     expect(poolAccountAfter.tokenCount).to.equal(3);
     expect(poolAccountAfter.tokens[0].weight.toNumber()).to.equal(weight0);
     expect(poolAccountAfter.tokens[1].weight.toNumber()).to.equal(weight1);
@@ -755,7 +829,7 @@ describe("anyswap LP 测试", () => {
     console.log("  - Token2:", amounts[2].toString());
 
     await program.methods
-      .addLiquidity(amounts)
+      .addLiquidity(amounts, new anchor.BN(0), null)
       .accountsPartial({
         pool: pool,
         poolAuthority: poolAuthorityPda,
@@ -767,10 +841,13 @@ describe("anyswap LP 测试", () => {
       .remainingAccounts([
         { pubkey: user2Token0Account, isSigner: false, isWritable: true },
         { pubkey: vault0, isSigner: false, isWritable: true },
+        { pubkey: mint0, isSigner: false, isWritable: false },
         { pubkey: user2Token1Account, isSigner: false, isWritable: true },
         { pubkey: vault1, isSigner: false, isWritable: true },
+        { pubkey: mint1, isSigner: false, isWritable: false },
         { pubkey: user2Token2Account, isSigner: false, isWritable: true },
         { pubkey: vault2, isSigner: false, isWritable: true },
+        { pubkey: mint2, isSigner: false, isWritable: false },
       ])
       .signers([user2])
       .rpc();
@@ -831,7 +908,7 @@ describe("anyswap LP 测试", () => {
 
     // 移除全部流动性
     await program.methods
-      .removeLiquidity(new anchor.BN(user1LpAmount))
+      .removeLiquidity(new anchor.BN(user1LpAmount), [new anchor.BN(0), new anchor.BN(0), new anchor.BN(0)], null)
       .accountsPartial({
         pool: pool,
         poolAuthority: poolAuthorityPda,
@@ -843,10 +920,13 @@ describe("anyswap LP 测试", () => {
       .remainingAccounts([
         { pubkey: user1Token0Account, isSigner: false, isWritable: true },
         { pubkey: vault0, isSigner: false, isWritable: true },
+        { pubkey: mint0, isSigner: false, isWritable: false },
         { pubkey: user1Token1Account, isSigner: false, isWritable: true },
         { pubkey: vault1, isSigner: false, isWritable: true },
+        { pubkey: mint1, isSigner: false, isWritable: false },
         { pubkey: user1Token2Account, isSigner: false, isWritable: true },
         { pubkey: vault2, isSigner: false, isWritable: true },
+        { pubkey: mint2, isSigner: false, isWritable: false },
       ])
       .signers([user1])
       .rpc();
@@ -896,7 +976,7 @@ describe("anyswap LP 测试", () => {
 
   it("步骤 9: Admin 移除 token2 从 pool", async () => {
     // 记录移除前的 pool 状态
-    const poolAccountBefore = await program.account.anySwapPool.fetch(pool);
+    const poolAccountBefore = await fetchPool(pool);
     expect(poolAccountBefore.tokenCount).to.equal(3);
     expect(poolAccountBefore.tokens[2].mintAccount.toString()).to.equal(mint2.toString());
 
@@ -905,7 +985,25 @@ describe("anyswap LP 测试", () => {
     expect(Number(vault2BalanceBefore)).to.be.greaterThan(0);
     console.log("移除前 Vault2 余额:", vault2BalanceBefore.toString());
 
-    // 移除 token2
+    // token2 在 mint 反向索引中记录为 pool 的第 2 个 token
+    const [mint2Index] = PublicKey.findProgramAddressSync(
+      [Buffer.from("mint_index"), mint2.toBuffer()],
+      program.programId
+    );
+    const readMintIndex = async () => {
+      const data = (await connection.getAccountInfo(mint2Index))!.data;
+      const entryCount = new anchor.BN(data.subarray(40, 48), "le").toNumber();
+      return Array.from({ length: entryCount }, (_, i) => ({
+        pool: new PublicKey(data.subarray(48 + i * 40, 80 + i * 40)),
+        tokenIndex: new anchor.BN(data.subarray(80 + i * 40, 88 + i * 40), "le").toNumber(),
+      }));
+    };
+    const entriesBefore = await readMintIndex();
+    expect(entriesBefore.length).to.equal(1);
+    expect(entriesBefore[0].pool.toString()).to.equal(pool.toString());
+    expect(entriesBefore[0].tokenIndex).to.equal(2);
+
+    // 移除 token2（最后一个 token，不需要更新其他 token 的索引）
     await program.methods
       .removeTokenFromPool()
       .accounts({
@@ -917,8 +1015,9 @@ describe("anyswap LP 测试", () => {
       .rpc();
 
     // 验证 pool 状态
-    const poolAccountAfter = await program.account.anySwapPool.fetch(pool);
+    const poolAccountAfter = await fetchPool(pool);
     expect(poolAccountAfter.tokenCount).to.equal(2);
+    expect((await readMintIndex()).length).to.equal(0);
     // 验证 token0 和 token1 还在
     expect(poolAccountAfter.tokens[0].mintAccount.toString()).to.equal(mint0.toString());
     expect(poolAccountAfter.tokens[1].mintAccount.toString()).to.equal(mint1.toString());
@@ -928,6 +1027,25 @@ describe("anyswap LP 测试", () => {
     console.log("  - Token0 mint:", poolAccountAfter.tokens[0].mintAccount.toString());
     console.log("  - Token1 mint:", poolAccountAfter.tokens[1].mintAccount.toString());
     console.log("  - Vault2 余额（移除后，应该保持不变）:", vault2BalanceBefore.toString());
+
+    // 移除后账户大小不变，compact_pool 缩小到 2 个 token 并把多余的租金转给 recipient
+    expect((await connection.getAccountInfo(pool))!.data.length).to.equal(poolHeaderSpace + 3 * 128);
+    const recipient = Keypair.generate().publicKey;
+    await program.methods
+      .compactPool()
+      .accounts({
+        pool: pool,
+        admin: admin.publicKey,
+        recipient: recipient,
+      })
+      .signers([admin])
+      .rpc();
+    const poolInfoAfterCompact = (await connection.getAccountInfo(pool))!;
+    expect(poolInfoAfterCompact.data.length).to.equal(poolHeaderSpace + 2 * 128);
+    expect(poolInfoAfterCompact.lamports).to.equal(
+      await connection.getMinimumBalanceForRentExemption(poolHeaderSpace + 2 * 128)
+    );
+    expect(await connection.getBalance(recipient)).to.be.greaterThan(0);
   });
 
   it("步骤 10: User2 退出一半流动性（此时 pool 中只有 token0 和 token1）", async () => {
@@ -941,7 +1059,7 @@ describe("anyswap LP 测试", () => {
     console.log("User2 要移除的 LP Token 数量:", burnAmount);
 
     // 验证 pool 中只有 token0 和 token1
-    const poolAccount = await program.account.anySwapPool.fetch(pool);
+    const poolAccount = await fetchPool(pool);
     expect(poolAccount.tokenCount).to.equal(2);
     console.log("Pool 中 Token Count:", poolAccount.tokenCount);
 
@@ -961,7 +1079,7 @@ describe("anyswap LP 测试", () => {
 
     // 移除一半流动性（只需要 token0 和 token1，因为 token2 已经从 pool 中移除）
     await program.methods
-      .removeLiquidity(new anchor.BN(burnAmount))
+      .removeLiquidity(new anchor.BN(burnAmount), [new anchor.BN(0), new anchor.BN(0)], null)
       .accountsPartial({
         pool: pool,
         poolAuthority: poolAuthorityPda,
@@ -973,8 +1091,10 @@ describe("anyswap LP 测试", () => {
       .remainingAccounts([
         { pubkey: user2Token0Account, isSigner: false, isWritable: true },
         { pubkey: vault0, isSigner: false, isWritable: true },
+        { pubkey: mint0, isSigner: false, isWritable: false },
         { pubkey: user2Token1Account, isSigner: false, isWritable: true },
         { pubkey: vault1, isSigner: false, isWritable: true },
+        { pubkey: mint1, isSigner: false, isWritable: false },
       ])
       .signers([user2])
       .rpc();
@@ -1018,5 +1138,306 @@ describe("anyswap LP 测试", () => {
     // 最终验证：user2 的 token2 余额应该和移除前一样
     expect(user2Token2BalanceAfter).to.equal(user2Token2BalanceBefore);
   });
-});
 
+  it("首次存入后直接向 vault 捐赠不能抬高 LP 价格", async () => {
+    const attacker = Keypair.generate();
+    const victim = Keypair.generate();
+    for (const account of [attacker, victim]) {
+      await connection.confirmTransaction(
+        await connection.requestAirdrop(account.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL)
+      );
+    }
+    const { pool: donationPool, poolMint: donationPoolMint } = await createPoolOnClient(
+      program,
+      connection,
+      payer,
+      attacker,
+      fee_numerator,
+      fee_denominator
+    );
+
+    // 两个权重相同、decimals 为 9 的 token
+    const mints: PublicKey[] = [];
+    const vaults: PublicKey[] = [];
+    for (let i = 0; i < 2; i++) {
+      const mint = await token.createMint(connection, payer.payer, payer.publicKey, null, n_decimals);
+      const [vault] = PublicKey.findProgramAddressSync(
+        [Buffer.from("vault"), donationPool.toBuffer(), mint.toBuffer()],
+        program.programId
+      );
+      const attackerToken = await token.getOrCreateAssociatedTokenAccount(
+        connection,
+        payer.payer,
+        mint,
+        attacker.publicKey
+      );
+      await program.methods
+        .addTokenToPool(new anchor.BN(1), null)
+        .accountsPartial({
+          pool: donationPool,
+          mint: mint,
+          vault: vault,
+          adminToken: attackerToken.address,
+          admin: attacker.publicKey,
+          payer: payer.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+          associatedTokenProgram: token.ASSOCIATED_TOKEN_PROGRAM_ID,
+        })
+        .remainingAccounts(vaults.map((v) => ({ pubkey: v, isSigner: false, isWritable: false })))
+        .signers([attacker])
+        .rpc();
+      mints.push(mint);
+      vaults.push(vault);
+    }
+
+    const deposit = async (owner: Keypair, amount: number) => {
+      const ownerPoolAta = await token.getOrCreateAssociatedTokenAccount(
+        connection,
+        payer.payer,
+        donationPoolMint,
+        owner.publicKey
+      );
+      const remainingAccounts = [];
+      for (let i = 0; i < 2; i++) {
+        const ownerToken = await token.getOrCreateAssociatedTokenAccount(
+          connection,
+          payer.payer,
+          mints[i],
+          owner.publicKey
+        );
+        await token.mintTo(connection, payer.payer, mints[i], ownerToken.address, payer.publicKey, amount);
+        remainingAccounts.push(
+          { pubkey: ownerToken.address, isSigner: false, isWritable: true },
+          { pubkey: vaults[i], isSigner: false, isWritable: true },
+          { pubkey: mints[i], isSigner: false, isWritable: false }
+        );
+      }
+      await program.methods
+        .addLiquidity([new anchor.BN(amount), new anchor.BN(amount)], new anchor.BN(0), null)
+        .accountsPartial({
+          pool: donationPool,
+          poolMint: donationPoolMint,
+          userPoolAta: ownerPoolAta.address,
+          owner: owner.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+        })
+        .remainingAccounts(remainingAccounts)
+        .signers([owner])
+        .rpc();
+      return ownerPoolAta.address;
+    };
+    // 按 LP 占比可以取回的 token0 数量
+    const claim = async (ownerPoolAta: PublicKey) => {
+      const lp = Number((await token.getAccount(connection, ownerPoolAta)).amount);
+      const totalMinted = (await fetchPool(donationPool)).totalAmountMinted.toNumber();
+      return (lp * Number((await token.getAccount(connection, vaults[0])).amount)) / totalMinted;
+    };
+
+    // 首次存入只铸造 1 个单位的 LP 被拒绝
+    try {
+      await deposit(attacker, 1);
+      expect.fail("应该失败");
+    } catch (e: any) {
+      expect(e.error.errorCode.code).to.equal("MinimumLiquidityLocked");
+    }
+
+    // 攻击者存入后只持有 1 个单位的 LP，其余 MINIMUM_LIQUIDITY 永久锁定
+    const attackerPoolAta = await deposit(attacker, MINIMUM_LIQUIDITY + 1);
+    expect(Number((await token.getAccount(connection, attackerPoolAta)).amount)).to.equal(1);
+
+    // 攻击者直接向 vault 捐赠 1 个 token，试图抬高每个 LP 的价格
+    const donation = 10 ** n_decimals;
+    for (let i = 0; i < 2; i++) {
+      const attackerToken = await token.getAssociatedTokenAddress(mints[i], attacker.publicKey);
+      await token.mintTo(connection, payer.payer, mints[i], attackerToken, payer.publicKey, donation);
+      await token.transfer(connection, payer.payer, attackerToken, vaults[i], attacker, donation);
+    }
+
+    // 受害者存入 2 个 token：捐赠大部分归锁定的 LP，受害者的份额几乎没有被稀释
+    // （没有锁定时受害者只能铸造 1 个单位的 LP，与攻击者平分 pool，损失 25%）
+    const victimDeposit = 2 * donation;
+    const victimPoolAta = await deposit(victim, victimDeposit);
+    expect(await claim(victimPoolAta)).to.be.greaterThan(victimDeposit * 0.999);
+
+    // 攻击者取回的数量远少于存入和捐赠的数量
+    expect(await claim(attackerPoolAta)).to.be.lessThan(donation / 100);
+  });
+
+  it("PDA pool：地址可推导，并追加到注册表", async () => {
+    const creator = Keypair.generate();
+    const poolIdPda = new anchor.BN(7);
+    const [pdaPool] = PublicKey.findProgramAddressSync(
+      [Buffer.from("pool"), creator.publicKey.toBuffer(), poolIdPda.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const [poolRegistry] = PublicKey.findProgramAddressSync(
+      [Buffer.from("pool_registry")],
+      program.programId
+    );
+    const readPoolCount = async () =>
+      new anchor.BN((await connection.getAccountInfo(poolRegistry))!.data.subarray(8, 16), "le").toNumber();
+    const poolCountBefore = await readPoolCount();
+
+    await program.methods
+      .createPoolWithId(poolIdPda, fee_numerator, fee_denominator, { constantSum: {} }, new anchor.BN(0))
+      .accountsPartial({
+        poolCreator: creator.publicKey,
+        pool: pdaPool,
+        admin: creator.publicKey,
+        payer: payer.publicKey,
+      })
+      .signers([creator])
+      .rpc();
+
+    const poolAccount = await fetchPool(pdaPool);
+    expect(poolAccount.admin.toString()).to.equal(creator.publicKey.toString());
+    // PDA pool 创建时只分配头部
+    expect((await connection.getAccountInfo(pdaPool))!.data.length).to.equal(poolHeaderSpace);
+
+    const poolCountAfter = await readPoolCount();
+    expect(poolCountAfter).to.equal(poolCountBefore + 1);
+    const registryData = (await connection.getAccountInfo(poolRegistry))!.data;
+    const offset = 16 + poolCountBefore * 32;
+    expect(new PublicKey(registryData.subarray(offset, offset + 32)).toString()).to.equal(pdaPool.toString());
+  });
+
+  it("旧的固定大小 pool：迁移到当前布局", async () => {
+    await ensureFactoryConfig();
+
+    // 由 Anchor.toml 加载的旧 pool 账户（tests/fixtures/generate_legacy_pool.py 生成）
+    // 旧布局：discriminator + 64 字节头部 + 1024 个 72 字节的 item，包含两个 token（decimals 6 和 9，权重 1 和 2）
+    const legacyPool = new PublicKey("Hio4jzj7Z2x4PEnE9HGdGNjHwb6rvrreZztT1zmRQn47");
+    const legacyMints = [
+      new PublicKey("AfoN9CCrqcLK4tg2vE6MYHUbYCgc1mjqBj7rh5zHS3Wn"),
+      new PublicKey("paZVp1mG1fpTMAo1kxfiBKdjJfwKbXDFJ9yzPgNQSoU"),
+    ];
+    const legacyAdmin = Keypair.fromSeed(new Uint8Array(32).fill(7));
+    const mintIndexes = legacyMints.map(
+      (mint) => PublicKey.findProgramAddressSync([Buffer.from("mint_index"), mint.toBuffer()], program.programId)[0]
+    );
+    // 按 token 顺序传入 (mint, mint_index)
+    const mintAccounts = (order: number[]) => {
+      const accounts = [];
+      for (const i of order) {
+        accounts.push(
+          { pubkey: legacyMints[i], isSigner: false, isWritable: false },
+          { pubkey: mintIndexes[i], isSigner: false, isWritable: true }
+        );
+      }
+      return accounts;
+    };
+    const [poolRegistry] = PublicKey.findProgramAddressSync(
+      [Buffer.from("pool_registry")],
+      program.programId
+    );
+    const readPoolCount = async () =>
+      new anchor.BN((await connection.getAccountInfo(poolRegistry))!.data.subarray(8, 16), "le").toNumber();
+
+    const expectError = async (promise: Promise<any>, code: string) => {
+      try {
+        await promise;
+        expect.fail("应该失败");
+      } catch (e: any) {
+        expect(e.error.errorCode.code).to.equal(code);
+      }
+    };
+    const migrate = (admin: Keypair, remainingAccounts: any[]) =>
+      program.methods
+        .migratePool()
+        .accounts({ pool: legacyPool, admin: admin.publicKey, payer: payer.publicKey })
+        .remainingAccounts(remainingAccounts)
+        .signers([admin])
+        .rpc();
+    const modifyFee = (numerator: number) =>
+      program.methods
+        .modifyFee(new anchor.BN(numerator), new anchor.BN(1000))
+        .accounts({ pool: legacyPool, admin: legacyAdmin.publicKey })
+        .signers([legacyAdmin])
+        .rpc();
+
+    expect((await connection.getAccountInfo(legacyPool))!.data.length).to.equal(8 + 64 + 72 * 1024);
+
+    // 迁移前不能按当前布局使用
+    await expectError(modifyFee(5), "PoolNeedsMigration");
+
+    // 只有旧 pool 的管理员可以迁移，mint 必须按 pool 中的顺序传入
+    await expectError(migrate(Keypair.generate(), mintAccounts([0, 1])), "InvalidAdmin");
+    await expectError(migrate(legacyAdmin, mintAccounts([0])), "InvalidTokenCount");
+    await expectError(migrate(legacyAdmin, mintAccounts([1, 0])), "InvalidTokenMint");
+
+    const poolCountBefore = await readPoolCount();
+    await migrate(legacyAdmin, mintAccounts([0, 1]));
+
+    // 账户缩小到刚好容纳两个 token，多余的租金退还给 payer
+    const poolInfo = (await connection.getAccountInfo(legacyPool))!;
+    expect(poolInfo.data.length).to.equal(poolHeaderSpace + 2 * 128);
+    expect(poolInfo.lamports).to.equal(
+      await connection.getMinimumBalanceForRentExemption(poolHeaderSpace + 2 * 128)
+    );
+
+    // 旧头部和 token 列表保留，新增的字段使用默认值
+    let poolAccount = await fetchPool(legacyPool);
+    expect(poolAccount.admin.toString()).to.equal(legacyAdmin.publicKey.toString());
+    expect(poolAccount.guardian.toString()).to.equal(legacyAdmin.publicKey.toString());
+    expect(poolAccount.totalAmountMinted.toNumber()).to.equal(3_000_000_000);
+    expect(poolAccount.feeNumerator.toNumber()).to.equal(3);
+    expect(poolAccount.feeDenominator.toNumber()).to.equal(1000);
+    expect(poolAccount.curveType).to.equal(0);
+    expect(poolAccount.tokenCount).to.equal(2);
+    // 迁移时建立按 mint 排序的查找表
+    const sortedMints = poolAccount.tokens.map((t) => poolAccount.tokens[t.sortedToken].mintAccount);
+    expect(sortedMints.map((m) => m.toBuffer().toString("hex"))).to.deep.equal(
+      legacyMints.map((m) => m.toBuffer().toString("hex")).sort()
+    );
+    expect(poolAccount.tokens.map((t) => t.mintAccount.toString())).to.deep.equal(
+      legacyMints.map((m) => m.toString())
+    );
+    expect(poolAccount.tokens.map((t) => t.weight.toNumber())).to.deep.equal([1, 2]);
+    expect(poolAccount.tokens.map((t) => t.decimals)).to.deep.equal([6, 9]);
+
+    // 迁移后的 pool 追加到注册表末尾
+    expect(await readPoolCount()).to.equal(poolCountBefore + 1);
+    const registryData = (await connection.getAccountInfo(poolRegistry))!.data;
+    const registryOffset = 16 + poolCountBefore * 32;
+    expect(new PublicKey(registryData.subarray(registryOffset, registryOffset + 32)).toString()).to.equal(
+      legacyPool.toString()
+    );
+
+    // 每个 token 的 mint 反向索引记录该 pool 和 token 在 pool 中的索引
+    for (let i = 0; i < legacyMints.length; i++) {
+      const data = (await connection.getAccountInfo(mintIndexes[i]))!.data;
+      expect(new PublicKey(data.subarray(8, 40)).toString()).to.equal(legacyMints[i].toString());
+      expect(new anchor.BN(data.subarray(40, 48), "le").toNumber()).to.equal(1);
+      expect(new PublicKey(data.subarray(48, 80)).toString()).to.equal(legacyPool.toString());
+      expect(new anchor.BN(data.subarray(80, 88), "le").toNumber()).to.equal(i);
+    }
+
+    // 迁移后可以正常使用，不能再次迁移
+    await modifyFee(5);
+    poolAccount = await fetchPool(legacyPool);
+    expect(poolAccount.feeNumerator.toNumber()).to.equal(5);
+    await expectError(migrate(legacyAdmin, mintAccounts([0, 1])), "PoolAlreadyMigrated");
+
+    // 旧 pool 没有锁定 MINIMUM_LIQUIDITY，admin 持有全部 LP，销毁全部 LP 会使供应量低于 MINIMUM_LIQUIDITY
+    const [legacyPoolMint] = PublicKey.findProgramAddressSync(
+      [Buffer.from("pool_mint"), legacyPool.toBuffer()],
+      program.programId
+    );
+    const legacyAdminPoolAta = await token.getAssociatedTokenAddress(legacyPoolMint, legacyAdmin.publicKey);
+    expect(Number((await token.getAccount(connection, legacyAdminPoolAta)).amount)).to.equal(3_000_000_000);
+    await expectError(
+      program.methods
+        .removeLiquidity(new anchor.BN(3_000_000_000), [new anchor.BN(0), new anchor.BN(0)], null)
+        .accountsPartial({
+          pool: legacyPool,
+          poolMint: legacyPoolMint,
+          userPoolAta: legacyAdminPoolAta,
+          owner: legacyAdmin.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+        })
+        .signers([legacyAdmin])
+        .rpc(),
+      "MinimumLiquidityLocked"
+    );
+  });
+});
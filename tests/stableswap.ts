@@ -0,0 +1,433 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { Anyswap } from "../target/types/anyswap";
+import * as token from "@solana/spl-token";
+import { PublicKey, Keypair, SystemProgram, ComputeBudgetProgram } from "@solana/web3.js";
+import { expect } from "chai";
+
+describe("anyswap StableSwap 测试", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace.anyswap as Program<Anyswap>;
+  const connection = provider.connection;
+  const payer = provider.wallet as anchor.Wallet;
+
+  // 费率：1/1000 = 0.1%
+  const fee_numerator = new anchor.BN(1);
+  const fee_denominator = new anchor.BN(1000);
+  const amp = new anchor.BN(100);
+
+  const n_decimals = 6;
+  const liquidityPerToken = 1_000_000 * 10 ** n_decimals;
+
+  /**
+   * 初始化协议全局配置（如果还没有初始化），测试中不限制费率、不收取创建费
+   * 需要测试验证器以可升级方式部署程序（Anchor.toml 中 [test] upgradeable = true）
+   */
+  async function ensureFactoryConfig() {
+    const [factoryConfig] = PublicKey.findProgramAddressSync(
+      [Buffer.from("factory_config")],
+      program.programId
+    );
+    if (await connection.getAccountInfo(factoryConfig)) {
+      return;
+    }
+    const [programData] = PublicKey.findProgramAddressSync(
+      [program.programId.toBuffer()],
+      new PublicKey("BPFLoaderUpgradeab1e11111111111111111111111")
+    );
+    await program.methods
+      .initializeFactoryConfig({
+        protocolAuthority: payer.publicKey,
+        treasury: payer.publicKey,
+        minFee: new anchor.BN(0),
+        maxFee: new anchor.BN("1000000000000000000"),
+        defaultProtocolFeeShare: new anchor.BN(0),
+        creationFee: new anchor.BN(0),
+        paused: false,
+      })
+      .accountsPartial({ authority: payer.publicKey, programData: programData })
+      .rpc();
+  }
+
+  /**
+   * 创建 Stable pool，添加 n 个 token，并为每个 vault 提供相同的流动性
+   * n <= 3 时通过 add_liquidity 提供流动性（验证 LP 初始铸造），否则直接铸造到 vault
+   */
+  async function setupStablePool(n: number): Promise<{
+    pool: PublicKey;
+    admin: Keypair;
+    mints: PublicKey[];
+    vaults: PublicKey[];
+  }> {
+    await ensureFactoryConfig();
+
+    const admin = Keypair.generate();
+    const airdrop = await connection.requestAirdrop(
+      admin.publicKey,
+      2 * anchor.web3.LAMPORTS_PER_SOL
+    );
+    await connection.confirmTransaction(airdrop);
+
+    const poolKeypair = Keypair.generate();
+    const pool = poolKeypair.publicKey;
+    const [poolAuthorityPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("anyswap_authority"), pool.toBuffer()],
+      program.programId
+    );
+    const [poolMint] = PublicKey.findProgramAddressSync(
+      [Buffer.from("pool_mint"), pool.toBuffer()],
+      program.programId
+    );
+
+    // 只分配头部空间，添加 token 时账户会自动扩展
+    const poolSpace = 8 + 2 + 1 + 1 + 1 + 3 + 32 + 8 + 8 + 8 + 32 + 8 + 32 + 8 + 8 + 32 + 32 + 32 + 32 + 32 + 8 + 8 + 16;
+    const lamports = await connection.getMinimumBalanceForRentExemption(poolSpace);
+
+    const tx = new anchor.web3.Transaction().add(
+      SystemProgram.createAccount({
+        fromPubkey: payer.publicKey,
+        newAccountPubkey: pool,
+        space: poolSpace,
+        lamports,
+        programId: program.programId,
+      }),
+      await program.methods
+        .createPool(fee_numerator, fee_denominator, { stable: {} }, amp)
+        .accountsPartial({
+          poolCreator: admin.publicKey,
+          pool: pool,
+          poolAuthority: poolAuthorityPda,
+          poolMint: poolMint,
+          admin: admin.publicKey,
+          payer: payer.publicKey,
+          systemProgram: SystemProgram.programId,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+          rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        })
+        .instruction()
+    );
+    await provider.sendAndConfirm(tx, [payer.payer, poolKeypair, admin]);
+
+    const mints: PublicKey[] = [];
+    const vaults: PublicKey[] = [];
+    for (let i = 0; i < n; i++) {
+      const mint = await token.createMint(
+        connection,
+        payer.payer,
+        payer.publicKey,
+        null,
+        n_decimals
+      );
+      const [vault] = PublicKey.findProgramAddressSync(
+        [Buffer.from("vault"), pool.toBuffer(), mint.toBuffer()],
+        program.programId
+      );
+      const adminToken = await token.getOrCreateAssociatedTokenAccount(
+        connection,
+        payer.payer,
+        mint,
+        admin.publicKey
+      );
+
+      // 所有 vault 为空，添加 token 不需要提供流动性
+      await program.methods
+        .addTokenToPool(new anchor.BN(1), null)
+        .accountsPartial({
+          pool: pool,
+          mint: mint,
+          vault: vault,
+          adminToken: adminToken.address,
+          admin: admin.publicKey,
+          payer: payer.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+          associatedTokenProgram: token.ASSOCIATED_TOKEN_PROGRAM_ID,
+        })
+        .remainingAccounts(
+          vaults.map((v) => ({ pubkey: v, isSigner: false, isWritable: false }))
+        )
+        .signers([admin])
+        .rpc();
+
+      mints.push(mint);
+      vaults.push(vault);
+    }
+
+    if (n <= 3) {
+      const adminPoolAta = await token.createAssociatedTokenAccount(
+        connection,
+        payer.payer,
+        poolMint,
+        admin.publicKey
+      );
+      const remainingAccounts = [];
+      for (let i = 0; i < n; i++) {
+        const adminToken = await token.getAssociatedTokenAddress(mints[i], admin.publicKey);
+        await token.mintTo(connection, payer.payer, mints[i], adminToken, payer.publicKey, liquidityPerToken);
+        remainingAccounts.push(
+          { pubkey: adminToken, isSigner: false, isWritable: true },
+          { pubkey: vaults[i], isSigner: false, isWritable: true },
+          { pubkey: mints[i], isSigner: false, isWritable: false }
+        );
+      }
+
+      await program.methods
+        .addLiquidity(mints.map(() => new anchor.BN(liquidityPerToken)), new anchor.BN(0), null)
+        .accountsPartial({
+          pool: pool,
+          poolAuthority: poolAuthorityPda,
+          poolMint: poolMint,
+          userPoolAta: adminPoolAta,
+          owner: admin.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+        })
+        .remainingAccounts(remainingAccounts)
+        .signers([admin])
+        .rpc();
+
+      // 平衡的 StableSwap pool 中 D = Σx（换算到 LP 的 9 位精度）
+      const expectedLp = liquidityPerToken * 10 ** (9 - n_decimals) * n;
+      const poolAccount = await program.account.anySwapPool.fetch(pool);
+      expect(poolAccount.totalAmountMinted.toNumber()).to.equal(expectedLp);
+    } else {
+      // token 数量较多时 add_liquidity 超出交易大小限制，直接铸造到 vault
+      for (let i = 0; i < n; i++) {
+        await token.mintTo(connection, payer.payer, mints[i], vaults[i], payer.publicKey, liquidityPerToken);
+      }
+    }
+
+    return { pool, admin, mints, vaults };
+  }
+
+  /**
+   * 用户从 token in 交换到 token out，返回实际收到的数量
+   */
+  async function swap(
+    pool: PublicKey,
+    mints: PublicKey[],
+    vaults: PublicKey[],
+    user: Keypair,
+    inIndex: number,
+    outIndex: number,
+    amountIn: number
+  ): Promise<number> {
+    const userIn = await token.getOrCreateAssociatedTokenAccount(
+      connection,
+      payer.payer,
+      mints[inIndex],
+      user.publicKey
+    );
+    const userOut = await token.getOrCreateAssociatedTokenAccount(
+      connection,
+      payer.payer,
+      mints[outIndex],
+      user.publicKey
+    );
+    await token.mintTo(connection, payer.payer, mints[inIndex], userIn.address, payer.publicKey, amountIn);
+
+    const before = (await token.getAccount(connection, userOut.address)).amount;
+
+    // 按 token 顺序添加，mints[i] 在 pool 中的索引就是 i，直接作为索引提示
+    await program.methods
+      .swapAnyswap(new anchor.BN(amountIn), new anchor.BN(0), null, inIndex, outIndex)
+      .accountsPartial({
+        pool: pool,
+        vaultIn: vaults[inIndex],
+        vaultOut: vaults[outIndex],
+        userIn: userIn.address,
+        userOut: userOut.address,
+        mintIn: mints[inIndex],
+        mintOut: mints[outIndex],
+        owner: user.publicKey,
+        tokenProgram: token.TOKEN_PROGRAM_ID,
+      })
+      .remainingAccounts(
+        vaults.map((v) => ({ pubkey: v, isSigner: false, isWritable: false }))
+      )
+      .preInstructions([ComputeBudgetProgram.setComputeUnitLimit({ units: 1_400_000 })])
+      .signers([user])
+      .rpc();
+
+    const after = (await token.getAccount(connection, userOut.address)).amount;
+    return Number(after - before);
+  }
+
+  it("向已有流动性的 pool 添加 token 时按 D / n 存入，之后可以正常交换", async () => {
+    const { pool, admin, mints, vaults } = await setupStablePool(2);
+    const mint = await token.createMint(connection, payer.payer, payer.publicKey, null, n_decimals);
+    const [vault] = PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), pool.toBuffer(), mint.toBuffer()],
+      program.programId
+    );
+    const adminToken = await token.getOrCreateAssociatedTokenAccount(
+      connection,
+      payer.payer,
+      mint,
+      admin.publicKey
+    );
+    await token.mintTo(connection, payer.payer, mint, adminToken.address, payer.publicKey, 2 * liquidityPerToken);
+
+    const addToken = (seedAmount: anchor.BN | null) =>
+      program.methods
+        .addTokenToPool(new anchor.BN(1), seedAmount)
+        .accountsPartial({
+          pool: pool,
+          mint: mint,
+          vault: vault,
+          adminToken: adminToken.address,
+          admin: admin.publicKey,
+          payer: payer.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+          associatedTokenProgram: token.ASSOCIATED_TOKEN_PROGRAM_ID,
+        })
+        .remainingAccounts(
+          vaults.map((v) => ({ pubkey: v, isSigner: false, isWritable: false }))
+        )
+        .signers([admin])
+        .rpc();
+
+    // 初始数量由不变量决定，不能由管理员指定
+    try {
+      await addToken(new anchor.BN(1));
+      expect.fail("应该失败");
+    } catch (e: any) {
+      expect(e.error.errorCode.code).to.equal("InvalidSeedAmount");
+    }
+
+    // 平衡状态下 D = 2 * liquidityPerToken，新 token 存入 D / 2，与现有 token 的数量相同
+    await addToken(null);
+    expect(Number((await token.getAccount(connection, vault)).amount)).to.equal(liquidityPerToken);
+
+    // 三个 token 仍然平衡，小额交换接近 1:1
+    const user = Keypair.generate();
+    const airdrop = await connection.requestAirdrop(user.publicKey, 2 * anchor.web3.LAMPORTS_PER_SOL);
+    await connection.confirmTransaction(airdrop);
+    const amountIn = 1_000 * 10 ** n_decimals;
+    const allMints = [...mints, mint];
+    const allVaults = [...vaults, vault];
+    for (const [inIndex, outIndex] of [[2, 0], [1, 2]]) {
+      const amountOut = await swap(pool, allMints, allVaults, user, inIndex, outIndex, amountIn);
+      const afterFee = amountIn * 0.999;
+      expect(amountOut).to.be.lessThan(afterFee);
+      expect(amountOut).to.be.greaterThan(afterFee * 0.999);
+    }
+  });
+
+  for (const n of [2, 3, 16]) {
+    describe(`${n} 个 token`, () => {
+      let pool: PublicKey;
+      let admin: Keypair;
+      let mints: PublicKey[];
+      let vaults: PublicKey[];
+      let user: Keypair;
+
+      before(async () => {
+        ({ pool, admin, mints, vaults } = await setupStablePool(n));
+
+        user = Keypair.generate();
+        const airdrop = await connection.requestAirdrop(
+          user.publicKey,
+          2 * anchor.web3.LAMPORTS_PER_SOL
+        );
+        await connection.confirmTransaction(airdrop);
+      });
+
+      it("平衡状态下小额交换接近 1:1", async () => {
+        const amountIn = 1_000 * 10 ** n_decimals;
+        const amountOut = await swap(pool, mints, vaults, user, 0, n - 1, amountIn);
+
+        // 扣除 0.1% 手续费后，价格冲击应该非常小
+        const afterFee = amountIn * 0.999;
+        expect(amountOut).to.be.lessThan(afterFee);
+        expect(amountOut).to.be.greaterThan(afterFee * 0.999);
+      });
+
+      it("大额交换有价格冲击，且不会耗尽 vault", async () => {
+        const amountIn = 2 * liquidityPerToken;
+        const amountOut = await swap(pool, mints, vaults, user, 1, 0, amountIn);
+
+        const vaultOut = await token.getAccount(connection, vaults[0]);
+        expect(amountOut).to.be.lessThan(amountIn * 0.999);
+        expect(Number(vaultOut.amount)).to.be.greaterThan(0);
+      });
+
+      it("缺少 vault 账户时交换失败", async () => {
+        try {
+          await program.methods
+            .swapAnyswap(new anchor.BN(0), new anchor.BN(0), null, null, null)
+            .accountsPartial({
+              pool: pool,
+              vaultIn: vaults[0],
+              vaultOut: vaults[1],
+              userIn: await token.getAssociatedTokenAddress(mints[0], user.publicKey),
+              userOut: await token.getAssociatedTokenAddress(mints[1], user.publicKey),
+              mintIn: mints[0],
+              mintOut: mints[1],
+              owner: user.publicKey,
+              tokenProgram: token.TOKEN_PROGRAM_ID,
+            })
+            .remainingAccounts(
+              vaults.slice(1).map((v) => ({ pubkey: v, isSigner: false, isWritable: false }))
+            )
+            .signers([user])
+            .rpc();
+          expect.fail("应该失败");
+        } catch (e: any) {
+          expect(e.error.errorCode.code).to.equal("InvalidTokenCount");
+        }
+      });
+
+      it("放大系数调整", async () => {
+        const now = Math.floor(Date.now() / 1000);
+
+        // 调整时间不足 1 天
+        try {
+          await program.methods
+            .rampAmp(new anchor.BN(200), new anchor.BN(now + 3600))
+            .accounts({ pool: pool, admin: admin.publicKey })
+            .signers([admin])
+            .rpc();
+          expect.fail("应该失败");
+        } catch (e: any) {
+          expect(e.error.errorCode.code).to.equal("InvalidRampSchedule");
+        }
+
+        // 单次变化超过 10 倍
+        try {
+          await program.methods
+            .rampAmp(new anchor.BN(5000), new anchor.BN(now + 2 * 86400))
+            .accounts({ pool: pool, admin: admin.publicKey })
+            .signers([admin])
+            .rpc();
+          expect.fail("应该失败");
+        } catch (e: any) {
+          expect(e.error.errorCode.code).to.equal("InvalidRampSchedule");
+        }
+
+        await program.methods
+          .rampAmp(new anchor.BN(500), new anchor.BN(now + 2 * 86400))
+          .accounts({ pool: pool, admin: admin.publicKey })
+          .signers([admin])
+          .rpc();
+
+        let poolAccount = await program.account.anySwapPool.fetch(pool);
+        // 放大系数按 AMP_PRECISION (100) 精度记录
+        expect(poolAccount.initialAmp.toNumber()).to.equal(100 * 100);
+        expect(poolAccount.targetAmp.toNumber()).to.equal(500 * 100);
+
+        await program.methods
+          .stopRampAmp()
+          .accounts({ pool: pool, admin: admin.publicKey })
+          .signers([admin])
+          .rpc();
+
+        // 停止时的 A 不会被截断到整数
+        poolAccount = await program.account.anySwapPool.fetch(pool);
+        expect(poolAccount.initialAmp.toNumber()).to.be.at.least(100 * 100);
+        expect(poolAccount.initialAmp.toNumber()).to.equal(poolAccount.targetAmp.toNumber());
+        expect(poolAccount.ampEndTime.toNumber()).to.equal(poolAccount.ampStartTime.toNumber());
+      });
+    });
+  }
+});
@@ -0,0 +1,1096 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { Anyswap } from "../target/types/anyswap";
+import * as token from "@solana/spl-token";
+import { PublicKey, Keypair, SystemProgram } from "@solana/web3.js";
+import { expect } from "chai";
+
+describe("anyswap 交换功能测试", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace.anyswap as Program<Anyswap>;
+  const connection = provider.connection;
+  const payer = provider.wallet as anchor.Wallet;
+
+  // 费率：3/1000 = 0.3%
+  const fee_numerator = new anchor.BN(3);
+  const fee_denominator = new anchor.BN(1000);
+
+  const n_decimals = 6;
+  const unit = 10 ** n_decimals;
+
+  // Pool 账户头部大小（discriminator + AnySwapPool），添加 token 时账户会自动扩展
+  const poolHeaderSpace = 8 + 2 + 1 + 1 + 1 + 3 + 32 + 8 + 8 + 8 + 32 + 8 + 32 + 8 + 8 + 32 + 32 + 32 + 32 + 32 + 8 + 8 + 16;
+
+  type TestPool = {
+    pool: PublicKey;
+    poolAuthority: PublicKey;
+    poolMint: PublicKey;
+    admin: Keypair;
+    mints: PublicKey[];
+    vaults: PublicKey[];
+  };
+
+  /**
+   * 初始化协议全局配置（如果还没有初始化），测试中不限制费率、不收取创建费
+   * 需要测试验证器以可升级方式部署程序（Anchor.toml 中 [test] upgradeable = true）
+   */
+  async function ensureFactoryConfig() {
+    const [factoryConfig] = PublicKey.findProgramAddressSync(
+      [Buffer.from("factory_config")],
+      program.programId
+    );
+    if (await connection.getAccountInfo(factoryConfig)) {
+      return;
+    }
+    const [programData] = PublicKey.findProgramAddressSync(
+      [program.programId.toBuffer()],
+      new PublicKey("BPFLoaderUpgradeab1e11111111111111111111111")
+    );
+    await program.methods
+      .initializeFactoryConfig({
+        protocolAuthority: payer.publicKey,
+        treasury: payer.publicKey,
+        minFee: new anchor.BN(0),
+        maxFee: new anchor.BN("1000000000000000000"),
+        defaultProtocolFeeShare: new anchor.BN(0),
+        creationFee: new anchor.BN(0),
+        paused: false,
+      })
+      .accountsPartial({ authority: payer.publicKey, programData: programData })
+      .rpc();
+  }
+
+  async function newUser(): Promise<Keypair> {
+    const user = Keypair.generate();
+    const airdrop = await connection.requestAirdrop(
+      user.publicKey,
+      2 * anchor.web3.LAMPORTS_PER_SOL
+    );
+    await connection.confirmTransaction(airdrop);
+    return user;
+  }
+
+  async function expectError(promise: Promise<any>, code: string) {
+    try {
+      await promise;
+      expect.fail("应该失败");
+    } catch (e: any) {
+      expect(e.error.errorCode.code).to.equal(code);
+    }
+  }
+
+  /**
+   * 为 owner 创建 mint 的 ATA 并铸造 amount 个代币，返回 ATA 地址
+   */
+  async function fund(mint: PublicKey, owner: PublicKey, amount: number): Promise<PublicKey> {
+    const account = await token.getOrCreateAssociatedTokenAccount(
+      connection,
+      payer.payer,
+      mint,
+      owner
+    );
+    if (amount > 0) {
+      await token.mintTo(connection, payer.payer, mint, account.address, payer.publicKey, amount);
+    }
+    return account.address;
+  }
+
+  async function balance(account: PublicKey): Promise<number> {
+    return Number((await token.getAccount(connection, account)).amount);
+  }
+
+  /**
+   * 创建指定曲线的 pool 并按权重添加 token，不提供流动性
+   */
+  async function createTestPool(curveType: any, weights: number[], amp = 0): Promise<TestPool> {
+    await ensureFactoryConfig();
+
+    const admin = await newUser();
+    const poolKeypair = Keypair.generate();
+    const pool = poolKeypair.publicKey;
+    const [poolAuthority] = PublicKey.findProgramAddressSync(
+      [Buffer.from("anyswap_authority"), pool.toBuffer()],
+      program.programId
+    );
+    const [poolMint] = PublicKey.findProgramAddressSync(
+      [Buffer.from("pool_mint"), pool.toBuffer()],
+      program.programId
+    );
+
+    const lamports = await connection.getMinimumBalanceForRentExemption(poolHeaderSpace);
+    const tx = new anchor.web3.Transaction().add(
+      SystemProgram.createAccount({
+        fromPubkey: payer.publicKey,
+        newAccountPubkey: pool,
+        space: poolHeaderSpace,
+        lamports,
+        programId: program.programId,
+      }),
+      await program.methods
+        .createPool(fee_numerator, fee_denominator, curveType, new anchor.BN(amp))
+        .accountsPartial({
+          poolCreator: admin.publicKey,
+          pool: pool,
+          poolAuthority: poolAuthority,
+          poolMint: poolMint,
+          admin: admin.publicKey,
+          payer: payer.publicKey,
+          systemProgram: SystemProgram.programId,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+          rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        })
+        .instruction()
+    );
+    await provider.sendAndConfirm(tx, [payer.payer, poolKeypair, admin]);
+
+    const mints: PublicKey[] = [];
+    const vaults: PublicKey[] = [];
+    for (const weight of weights) {
+      const mint = await token.createMint(connection, payer.payer, payer.publicKey, null, n_decimals);
+      const [vault] = PublicKey.findProgramAddressSync(
+        [Buffer.from("vault"), pool.toBuffer(), mint.toBuffer()],
+        program.programId
+      );
+      const adminToken = await fund(mint, admin.publicKey, 0);
+
+      // 所有 vault 为空，添加 token 不需要提供流动性
+      await program.methods
+        .addTokenToPool(new anchor.BN(weight), null)
+        .accountsPartial({
+          pool: pool,
+          mint: mint,
+          vault: vault,
+          adminToken: adminToken,
+          admin: admin.publicKey,
+          payer: payer.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+          associatedTokenProgram: token.ASSOCIATED_TOKEN_PROGRAM_ID,
+        })
+        .remainingAccounts(
+          vaults.map((v) => ({ pubkey: v, isSigner: false, isWritable: false }))
+        )
+        .signers([admin])
+        .rpc();
+
+      mints.push(mint);
+      vaults.push(vault);
+    }
+
+    return { pool, poolAuthority, poolMint, admin, mints, vaults };
+  }
+
+  /**
+   * admin 按 amounts 提供首次流动性
+   */
+  async function addInitialLiquidity(p: TestPool, amounts: number[]) {
+    const { pool, poolAuthority, poolMint, admin, mints, vaults } = p;
+    for (let i = 0; i < mints.length; i++) {
+      await fund(mints[i], admin.publicKey, amounts[i]);
+    }
+
+    const adminPoolAta = await token.createAssociatedTokenAccount(
+      connection,
+      payer.payer,
+      poolMint,
+      admin.publicKey
+    );
+    const remainingAccounts = [];
+    for (let i = 0; i < mints.length; i++) {
+      remainingAccounts.push(
+        { pubkey: await token.getAssociatedTokenAddress(mints[i], admin.publicKey), isSigner: false, isWritable: true },
+        { pubkey: vaults[i], isSigner: false, isWritable: true },
+        { pubkey: mints[i], isSigner: false, isWritable: false }
+      );
+    }
+    await program.methods
+      .addLiquidity(amounts.map((a) => new anchor.BN(a)), new anchor.BN(0), null)
+      .accountsPartial({
+        pool: pool,
+        poolAuthority: poolAuthority,
+        poolMint: poolMint,
+        userPoolAta: adminPoolAta,
+        owner: admin.publicKey,
+        tokenProgram: token.TOKEN_PROGRAM_ID,
+      })
+      .remainingAccounts(remainingAccounts)
+      .signers([admin])
+      .rpc();
+  }
+
+  /**
+   * 创建加权几何平均曲线的 pool，并由 admin 按权重比例提供初始流动性
+   * 每个 token 存入 weight * liquidityPerWeight 个最小单位
+   */
+  async function setupWeightedPool(weights: number[], liquidityPerWeight = 10_000 * unit): Promise<TestPool> {
+    const p = await createTestPool({ weighted: {} }, weights);
+    await addInitialLiquidity(p, weights.map((w) => w * liquidityPerWeight));
+    return p;
+  }
+
+  /**
+   * 读取 token 待领取的协议手续费（pool 头部之后每个 token item 128 字节，protocol_fees 位于偏移 96）
+   */
+  async function protocolFees(p: TestPool, index: number): Promise<number> {
+    const data = (await connection.getAccountInfo(p.pool))!.data;
+    const offset = poolHeaderSpace + index * 128 + 96;
+    return new anchor.BN(data.subarray(offset, offset + 8), "le").toNumber();
+  }
+
+  /**
+   * 交换报价（模拟 quote_swap，不修改状态）
+   */
+  async function quote(p: TestPool, inIndex: number, outIndex: number, amountIn: number, allVaults = false) {
+    return await program.methods
+      .quoteSwap(new anchor.BN(amountIn))
+      .accountsPartial({
+        pool: p.pool,
+        vaultIn: p.vaults[inIndex],
+        vaultOut: p.vaults[outIndex],
+      })
+      .remainingAccounts(
+        allVaults ? p.vaults.map((v) => ({ pubkey: v, isSigner: false, isWritable: false })) : []
+      )
+      .view();
+  }
+
+  /**
+   * 用户从 token in 交换到 token out（用户需要已有足够的 token in），返回实际收到的数量
+   */
+  async function swap(
+    p: TestPool,
+    user: Keypair,
+    inIndex: number,
+    outIndex: number,
+    amountIn: number,
+    allVaults = false
+  ): Promise<number> {
+    const userIn = await token.getAssociatedTokenAddress(p.mints[inIndex], user.publicKey);
+    const userOut = await fund(p.mints[outIndex], user.publicKey, 0);
+    const before = await balance(userOut);
+    await program.methods
+      .swapAnyswap(new anchor.BN(amountIn), new anchor.BN(0), null, null, null)
+      .accountsPartial({
+        pool: p.pool,
+        vaultIn: p.vaults[inIndex],
+        vaultOut: p.vaults[outIndex],
+        userIn: userIn,
+        userOut: userOut,
+        mintIn: p.mints[inIndex],
+        mintOut: p.mints[outIndex],
+        owner: user.publicKey,
+        tokenProgram: token.TOKEN_PROGRAM_ID,
+      })
+      .remainingAccounts(
+        allVaults ? p.vaults.map((v) => ({ pubkey: v, isSigner: false, isWritable: false })) : []
+      )
+      .signers([user])
+      .rpc();
+    return (await balance(userOut)) - before;
+  }
+
+  describe("精确输出交换", () => {
+    let p: TestPool;
+    let user: Keypair;
+    let userTokens: PublicKey[];
+
+    before(async () => {
+      p = await setupWeightedPool([20, 30, 50]);
+      user = await newUser();
+      userTokens = [];
+      for (const mint of p.mints) {
+        userTokens.push(await fund(mint, user.publicKey, 100_000 * unit));
+      }
+    });
+
+    const exactOut = (amountOut: number, maxAmountIn: number) =>
+      program.methods
+        .swapExactOut(new anchor.BN(amountOut), new anchor.BN(maxAmountIn), null, null, null)
+        .accountsPartial({
+          pool: p.pool,
+          vaultIn: p.vaults[0],
+          vaultOut: p.vaults[2],
+          userIn: userTokens[0],
+          userOut: userTokens[2],
+          mintIn: p.mints[0],
+          mintOut: p.mints[2],
+          owner: user.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+        })
+        .signers([user])
+        .rpc();
+
+    /**
+     * 按当前状态用 quote_swap 二分查找获得 amountOut 所需的最小输入
+     */
+    async function minAmountInFor(amountOut: number): Promise<number> {
+      let low = 0;
+      let high = 2 * amountOut;
+      while ((await quote(p, 0, 2, high)).amountOut.toNumber() < amountOut) {
+        high *= 2;
+      }
+      while (high - low > 1) {
+        const mid = Math.floor((low + high) / 2);
+        if ((await quote(p, 0, 2, mid)).amountOut.toNumber() >= amountOut) {
+          high = mid;
+        } else {
+          low = mid;
+        }
+      }
+      return high;
+    }
+
+    it("获得精确的输出，支付的输入不超过上限并与报价一致", async () => {
+      const amountOut = 1_000 * unit;
+      const expectedIn = await minAmountInFor(amountOut);
+      const maxAmountIn = expectedIn + 100;
+
+      const inBefore = await balance(userTokens[0]);
+      const outBefore = await balance(userTokens[2]);
+      await exactOut(amountOut, maxAmountIn);
+      const paid = inBefore - (await balance(userTokens[0]));
+      const received = (await balance(userTokens[2])) - outBefore;
+
+      expect(received).to.equal(amountOut);
+      expect(paid).to.be.at.most(maxAmountIn);
+      // 两个方向都向有利于 pool 的方向取整，精确输出最多多付几个最小单位
+      expect(paid).to.be.at.least(expectedIn);
+      expect(paid - expectedIn).to.be.at.most(10);
+    });
+
+    it("所需输入超过 max_amount_in 时失败", async () => {
+      const amountOut = 1_000 * unit;
+      const expectedIn = await minAmountInFor(amountOut);
+      try {
+        await exactOut(amountOut, expectedIn - 1);
+        expect.fail("应该失败");
+      } catch (e: any) {
+        expect(e.error.errorCode.code).to.equal("SlippageExceeded");
+      }
+    });
+
+    it("输出不能耗尽 vault", async () => {
+      const vaultOut = await balance(p.vaults[2]);
+      try {
+        await exactOut(vaultOut, Number.MAX_SAFE_INTEGER);
+        expect.fail("应该失败");
+      } catch (e: any) {
+        expect(e.error.errorCode.code).to.equal("InsufficientLiquidity");
+      }
+    });
+  });
+
+  describe("批量交换", () => {
+    it("净转账与依次执行每笔交换的结果一致", async () => {
+      // 两个完全相同的 pool：一个批量交换，一个依次交换
+      const batchPool = await setupWeightedPool([20, 30, 50]);
+      const sequentialPool = await setupWeightedPool([20, 30, 50]);
+      const legs = [
+        { tokenInIndex: 0, tokenOutIndex: 1, amountIn: 5_000 * unit },
+        { tokenInIndex: 1, tokenOutIndex: 2, amountIn: 3_000 * unit },
+        { tokenInIndex: 2, tokenOutIndex: 0, amountIn: 4_000 * unit },
+      ];
+
+      const batchUser = await newUser();
+      const sequentialUser = await newUser();
+      const batchTokens: PublicKey[] = [];
+      const sequentialTokens: PublicKey[] = [];
+      for (let i = 0; i < 3; i++) {
+        batchTokens.push(await fund(batchPool.mints[i], batchUser.publicKey, 10_000 * unit));
+        sequentialTokens.push(await fund(sequentialPool.mints[i], sequentialUser.publicKey, 10_000 * unit));
+      }
+
+      const remainingAccounts = [];
+      for (let i = 0; i < 3; i++) {
+        remainingAccounts.push(
+          { pubkey: batchTokens[i], isSigner: false, isWritable: true },
+          { pubkey: batchPool.vaults[i], isSigner: false, isWritable: true },
+          { pubkey: batchPool.mints[i], isSigner: false, isWritable: false }
+        );
+      }
+      await program.methods
+        .batchSwap(
+          legs.map((leg) => ({
+            tokenInIndex: leg.tokenInIndex,
+            tokenOutIndex: leg.tokenOutIndex,
+            amountIn: new anchor.BN(leg.amountIn),
+            minAmountOut: new anchor.BN(0),
+          })),
+          null
+        )
+        .accountsPartial({ pool: batchPool.pool, owner: batchUser.publicKey, tokenProgram: token.TOKEN_PROGRAM_ID })
+        .remainingAccounts(remainingAccounts)
+        .signers([batchUser])
+        .rpc();
+
+      for (const leg of legs) {
+        await swap(sequentialPool, sequentialUser, leg.tokenInIndex, leg.tokenOutIndex, leg.amountIn);
+      }
+
+      for (let i = 0; i < 3; i++) {
+        expect(await balance(batchTokens[i])).to.equal(await balance(sequentialTokens[i]));
+        expect(await balance(batchPool.vaults[i])).to.equal(await balance(sequentialPool.vaults[i]));
+      }
+    });
+
+    it("某一笔交换的输出低于 min_amount_out 时整个批量交换失败", async () => {
+      const p = await setupWeightedPool([50, 50]);
+      const user = await newUser();
+      const userTokens = [
+        await fund(p.mints[0], user.publicKey, 10_000 * unit),
+        await fund(p.mints[1], user.publicKey, 0),
+      ];
+      const remainingAccounts = [];
+      for (let i = 0; i < 2; i++) {
+        remainingAccounts.push(
+          { pubkey: userTokens[i], isSigner: false, isWritable: true },
+          { pubkey: p.vaults[i], isSigner: false, isWritable: true },
+          { pubkey: p.mints[i], isSigner: false, isWritable: false }
+        );
+      }
+      const before = await balance(userTokens[0]);
+      try {
+        await program.methods
+          .batchSwap(
+            [
+              { tokenInIndex: 0, tokenOutIndex: 1, amountIn: new anchor.BN(1_000 * unit), minAmountOut: new anchor.BN(0) },
+              { tokenInIndex: 0, tokenOutIndex: 1, amountIn: new anchor.BN(1_000 * unit), minAmountOut: new anchor.BN(1_000 * unit) },
+            ],
+            null
+          )
+          .accountsPartial({ pool: p.pool, owner: user.publicKey, tokenProgram: token.TOKEN_PROGRAM_ID })
+          .remainingAccounts(remainingAccounts)
+          .signers([user])
+          .rpc();
+        expect.fail("应该失败");
+      } catch (e: any) {
+        expect(e.error.errorCode.code).to.equal("SlippageExceeded");
+      }
+      expect(await balance(userTokens[0])).to.equal(before);
+    });
+  });
+
+  describe("单边添加和移除流动性", () => {
+    let p: TestPool;
+    let user: Keypair;
+    let userToken: PublicKey;
+    let userPoolAta: PublicKey;
+
+    before(async () => {
+      p = await setupWeightedPool([20, 30, 50]);
+      user = await newUser();
+      userToken = await fund(p.mints[0], user.publicKey, 10_000 * unit);
+      userPoolAta = await token.createAssociatedTokenAccount(connection, payer.payer, p.poolMint, user.publicKey);
+    });
+
+    const allVaults = () => p.vaults.map((v) => ({ pubkey: v, isSigner: false, isWritable: false }));
+    const singleAccounts = () => ({
+      pool: p.pool,
+      poolAuthority: p.poolAuthority,
+      poolMint: p.poolMint,
+      vault: p.vaults[0],
+      userToken: userToken,
+      mint: p.mints[0],
+      userPoolAta: userPoolAta,
+      owner: user.publicKey,
+      tokenProgram: token.TOKEN_PROGRAM_ID,
+    });
+    const depositSingle = (amountIn: number, minLpOut: number) =>
+      program.methods
+        .addLiquiditySingle(new anchor.BN(amountIn), new anchor.BN(minLpOut))
+        .accountsPartial(singleAccounts())
+        .remainingAccounts(allVaults())
+        .signers([user])
+        .rpc();
+    const withdrawSingle = (lpIn: number, minAmountOut: number) =>
+      program.methods
+        .removeLiquiditySingle(new anchor.BN(lpIn), new anchor.BN(minAmountOut))
+        .accountsPartial(singleAccounts())
+        .remainingAccounts(allVaults())
+        .signers([user])
+        .rpc();
+
+    it("单边存入再单边取出，只损失隐含交换部分的手续费", async () => {
+      const amountIn = 1_000 * unit;
+      const tokenBefore = await balance(userToken);
+
+      await depositSingle(amountIn, 0);
+      const lp = await balance(userPoolAta);
+      expect(lp).to.be.greaterThan(0);
+
+      await withdrawSingle(lp, 0);
+      expect(await balance(userPoolAta)).to.equal(0);
+      const returned = (await balance(userToken)) - (tokenBefore - amountIn);
+
+      // token0 的价值占比为 20%，存入和取出时各有 80% 视为隐含交换，按 0.3% 收取手续费
+      const maxFees = 2 * amountIn * 0.8 * 0.003;
+      expect(returned).to.be.lessThan(amountIn);
+      expect(returned).to.be.greaterThan(amountIn - maxFees * 1.01);
+    });
+
+    it("铸造的 LP 少于 min_lp_out 时失败", async () => {
+      try {
+        await depositSingle(1_000 * unit, Number.MAX_SAFE_INTEGER);
+        expect.fail("应该失败");
+      } catch (e: any) {
+        expect(e.error.errorCode.code).to.equal("SlippageExceeded");
+      }
+    });
+
+    it("取出的数量少于 min_amount_out 时失败，LP 不会被销毁", async () => {
+      await depositSingle(1_000 * unit, 0);
+      const lp = await balance(userPoolAta);
+      try {
+        await withdrawSingle(lp, 1_000 * unit);
+        expect.fail("应该失败");
+      } catch (e: any) {
+        expect(e.error.errorCode.code).to.equal("SlippageExceeded");
+      }
+      expect(await balance(userPoolAta)).to.equal(lp);
+    });
+
+    it("加权曲线单边取出只需要取出 token 的 vault", async () => {
+      await depositSingle(1_000 * unit, 0);
+      const lp = await balance(userPoolAta);
+      const tokenBefore = await balance(userToken);
+
+      await program.methods
+        .removeLiquiditySingle(new anchor.BN(lp), new anchor.BN(1))
+        .accountsPartial(singleAccounts())
+        .signers([user])
+        .rpc();
+
+      expect(await balance(userPoolAta)).to.equal(0);
+      expect(await balance(userToken)).to.be.greaterThan(tokenBefore);
+    });
+
+    it("恒定乘积和曲线单边取出使用记录的 Σ(vault * weight)，只需要取出 token 的 vault", async () => {
+      const c = await createTestPool({ constantSum: {} }, [1, 2]);
+      // 换算到统一精度后数量相同
+      await addInitialLiquidity(c, [10_000 * unit, 10_000 * unit]);
+      const adminToken = await token.getAssociatedTokenAddress(c.mints[0], c.admin.publicKey);
+      const adminPoolAta = await token.getAssociatedTokenAddress(c.poolMint, c.admin.publicKey);
+      const lp = Math.floor((await balance(adminPoolAta)) / 10);
+      const tokenBefore = await balance(adminToken);
+
+      await program.methods
+        .removeLiquiditySingle(new anchor.BN(lp), new anchor.BN(0))
+        .accountsPartial({
+          pool: c.pool,
+          poolAuthority: c.poolAuthority,
+          poolMint: c.poolMint,
+          vault: c.vaults[0],
+          userToken: adminToken,
+          mint: c.mints[0],
+          userPoolAta: adminPoolAta,
+          owner: c.admin.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+        })
+        .signers([c.admin])
+        .rpc();
+
+      // pool 价值 Σ(vault * weight) = 30000，10% 的 LP 对应 3000，按 token0 的权重 1 全部取出 token0
+      // 其中 token0 价值占比 1/3，其余 2/3 按 0.3% 收取手续费
+      const returned = (await balance(adminToken)) - tokenBefore;
+      const expected = 3_000 * unit;
+      expect(returned).to.be.lessThan(expected);
+      expect(returned).to.be.greaterThan(expected * (1 - 0.003 * 2 / 3) - unit);
+    });
+  });
+
+  describe("首次添加流动性的比例检查", () => {
+    const expectInvalidRatio = async (p: TestPool, amounts: number[]) => {
+      try {
+        await addInitialLiquidity(p, amounts);
+        expect.fail("应该失败");
+      } catch (e: any) {
+        expect(e.error.errorCode.code).to.equal("InvalidDepositRatio");
+      }
+    };
+
+    it("加权曲线接受任意初始比例", async () => {
+      const p = await createTestPool({ weighted: {} }, [20, 80]);
+      await addInitialLiquidity(p, [1_000 * unit, 1_000 * unit]);
+      const poolAccount = await program.account.anySwapPool.fetch(p.pool);
+      expect(poolAccount.totalAmountMinted.toNumber()).to.be.greaterThan(0);
+    });
+
+    it("恒定和曲线要求价值占比等于权重占比", async () => {
+      // 数量与权重成比例时，token1 的价值占比为 40*2000 / (20*1000 + 40*2000) = 80%，而权重占比为 66.7%
+      const p = await createTestPool({ constantSum: {} }, [20, 40]);
+      await expectInvalidRatio(p, [1_000 * unit, 2_000 * unit]);
+
+      await addInitialLiquidity(p, [1_000 * unit, 1_000 * unit]);
+      const poolAccount = await program.account.anySwapPool.fetch(p.pool);
+      // LP = Σ(amount * weight) / Σweight，换算到 9 位精度
+      expect(poolAccount.totalAmountMinted.toNumber()).to.equal(1_000 * 10 ** 9);
+    });
+
+    it("StableSwap 曲线要求数量相同", async () => {
+      const p = await createTestPool({ stable: {} }, [1, 1], 100);
+      await expectInvalidRatio(p, [1_000 * unit, 2_000 * unit]);
+      await addInitialLiquidity(p, [1_000 * unit, 1_000 * unit]);
+    });
+  });
+
+  describe("协议手续费", () => {
+    // 两个相同的 pool，只有 pool A 收取协议手续费
+    let poolA: TestPool;
+    let poolB: TestPool;
+    let treasury: Keypair;
+    let user: Keypair;
+    const amountIn = 1_000 * unit;
+
+    before(async () => {
+      poolA = await setupWeightedPool([50, 50]);
+      poolB = await setupWeightedPool([50, 50]);
+      treasury = await newUser();
+      user = await newUser();
+
+      // 协议抽取 50% 的手续费（由协议管理员修改）
+      await program.methods
+        .modifyProtocolFee(new anchor.BN(5_000), treasury.publicKey)
+        .accountsPartial({ pool: poolA.pool, protocolAuthority: payer.publicKey })
+        .rpc();
+
+      for (const p of [poolA, poolB]) {
+        await fund(p.mints[0], user.publicKey, amountIn);
+        await swap(p, user, 0, 1, amountIn);
+      }
+    });
+
+    it("交换时按比例累计协议手续费", async () => {
+      // 手续费 = 1000 * 0.3% = 3，协议抽取一半
+      const fee = Math.floor((amountIn * 3) / 1000);
+      expect(await protocolFees(poolA, 0)).to.equal(Math.floor(fee / 2));
+      expect(await protocolFees(poolA, 1)).to.equal(0);
+      expect(await protocolFees(poolB, 0)).to.equal(0);
+      // 协议手续费留在 vault 中，两个 pool 的 vault 余额相同
+      expect(await balance(poolA.vaults[0])).to.equal(await balance(poolB.vaults[0]));
+    });
+
+    it("协议手续费不计入定价使用的储备量", async () => {
+      // pool A 的 token0 储备量少了协议手续费，反向交换得到的 token0 更少
+      const quoteA = (await quote(poolA, 1, 0, amountIn)).amountOut.toNumber();
+      const quoteB = (await quote(poolB, 1, 0, amountIn)).amountOut.toNumber();
+      expect(quoteA).to.be.lessThan(quoteB);
+    });
+
+    it("只有协议金库可以领取协议手续费", async () => {
+      const collect = async (signer: Keypair, destination: PublicKey) =>
+        program.methods
+          .collectProtocolFees()
+          .accountsPartial({
+            pool: poolA.pool,
+            treasury: signer.publicKey,
+            tokenProgram: token.TOKEN_PROGRAM_ID,
+            token2022Program: token.TOKEN_2022_PROGRAM_ID,
+          })
+          .remainingAccounts([
+            { pubkey: poolA.vaults[0], isSigner: false, isWritable: true },
+            { pubkey: destination, isSigner: false, isWritable: true },
+            { pubkey: poolA.mints[0], isSigner: false, isWritable: false },
+          ])
+          .signers([signer])
+          .rpc();
+
+      const accrued = await protocolFees(poolA, 0);
+      // pool 管理员和普通用户都不能领取
+      for (const signer of [poolA.admin, user]) {
+        const destination = await fund(poolA.mints[0], signer.publicKey, 0);
+        try {
+          await collect(signer, destination);
+          expect.fail("应该失败");
+        } catch (e: any) {
+          expect(e.error.errorCode.code).to.equal("InvalidTreasury");
+        }
+      }
+      expect(await protocolFees(poolA, 0)).to.equal(accrued);
+
+      const destination = await fund(poolA.mints[0], treasury.publicKey, 0);
+      const vaultBefore = await balance(poolA.vaults[0]);
+      await collect(treasury, destination);
+      expect(await balance(destination)).to.equal(accrued);
+      expect(await balance(poolA.vaults[0])).to.equal(vaultBefore - accrued);
+      expect(await protocolFees(poolA, 0)).to.equal(0);
+    });
+  });
+
+  describe("token 单独费率和手续费策略", () => {
+    let p: TestPool;
+    const amountIn = 1_000 * unit;
+
+    const setTokenFee = (index: number, numerator: number, denominator: number) =>
+      program.methods
+        .modifyTokenFee(new anchor.BN(numerator), new anchor.BN(denominator))
+        .accountsPartial({ pool: p.pool, mint: p.mints[index], admin: p.admin.publicKey })
+        .signers([p.admin])
+        .rpc();
+    const setPolicy = (feePolicy: any) =>
+      program.methods
+        .modifyFeePolicy(feePolicy)
+        .accountsPartial({ pool: p.pool, admin: p.admin.publicKey })
+        .signers([p.admin])
+        .rpc();
+
+    /**
+     * 检查 in -> out 交换报价使用的费率为 numerator / denominator，手续费按该费率向下取整
+     */
+    async function expectFee(inIndex: number, outIndex: number, numerator: number, denominator: number) {
+      const q = await quote(p, inIndex, outIndex, amountIn);
+      expect(q.feeNumerator.toNumber() * denominator).to.equal(numerator * q.feeDenominator.toNumber());
+      expect(q.feeAmount.toNumber()).to.equal(Math.floor((amountIn * numerator) / denominator));
+    }
+
+    before(async () => {
+      p = await setupWeightedPool([50, 50, 50]);
+      // token0: 1%，token1: 0.5%，token2 没有单独设置，使用 pool 的 0.3%
+      await setTokenFee(0, 1, 100);
+      await setTokenFee(1, 5, 1000);
+    });
+
+    it("TokenIn 策略使用输入 token 的费率", async () => {
+      await setPolicy({ tokenIn: {} });
+      await expectFee(0, 1, 1, 100);
+      await expectFee(1, 0, 5, 1000);
+      await expectFee(2, 1, 3, 1000);
+    });
+
+    it("TokenOut 策略使用输出 token 的费率", async () => {
+      await setPolicy({ tokenOut: {} });
+      await expectFee(0, 1, 5, 1000);
+      await expectFee(1, 0, 1, 100);
+      await expectFee(0, 2, 3, 1000);
+    });
+
+    it("Max 策略使用两者中较高的费率，实际交换与报价一致", async () => {
+      await setPolicy({ max: {} });
+      await expectFee(1, 0, 1, 100);
+      await expectFee(2, 1, 5, 1000);
+      await expectFee(1, 2, 5, 1000);
+
+      const user = await newUser();
+      await fund(p.mints[2], user.publicKey, amountIn);
+      const expected = (await quote(p, 2, 0, amountIn)).amountOut.toNumber();
+      expect(await swap(p, user, 2, 0, amountIn)).to.equal(expected);
+    });
+
+    it("取消单独设置后恢复使用 pool 的费率", async () => {
+      await setTokenFee(0, 0, 0);
+      await expectFee(0, 2, 3, 1000);
+      await expectFee(0, 1, 5, 1000);
+    });
+  });
+
+  describe("动态失衡手续费", () => {
+    let p: TestPool;
+    let user: Keypair;
+    const reserve = 10_000 * unit;
+    // 最高费率 5%，失衡阈值 10%（18 位定点数）
+    const maxFee = new anchor.BN("50000000000000000");
+    const threshold = new anchor.BN("100000000000000000");
+
+    const setSurgeFee = (target: TestPool, max: anchor.BN, thresh: anchor.BN) =>
+      program.methods
+        .modifySurgeFee(max, thresh)
+        .accountsPartial({ pool: target.pool, admin: target.admin.publicKey })
+        .signers([target.admin])
+        .rpc();
+    const baseFee = (amountIn: number) => Math.floor((amountIn * 3) / 1000);
+
+    before(async () => {
+      // 恒定和曲线，等权重、等数量存入，初始失衡程度为 0
+      p = await createTestPool({ constantSum: {} }, [1, 1]);
+      await addInitialLiquidity(p, [reserve, reserve]);
+      await setSurgeFee(p, maxFee, threshold);
+      user = await newUser();
+      await fund(p.mints[0], user.publicKey, 10_000 * unit);
+      await fund(p.mints[1], user.publicKey, 10_000 * unit);
+    });
+
+    it("交换后失衡程度不超过阈值时使用基础费率", async () => {
+      // 交换后失衡程度约为 100 / 20000 = 0.5%
+      const amountIn = 100 * unit;
+      const q = await quote(p, 0, 1, amountIn, true);
+      expect(q.feeAmount.toNumber()).to.equal(baseFee(amountIn));
+    });
+
+    it("失衡程度超过阈值后费率随失衡程度上升", async () => {
+      // 交换后失衡程度约为 15% 和 20%
+      const smaller = await quote(p, 0, 1, 3_000 * unit, true);
+      const larger = await quote(p, 0, 1, 4_000 * unit, true);
+      const smallerRate = smaller.feeAmount.toNumber() / (3_000 * unit);
+      const largerRate = larger.feeAmount.toNumber() / (4_000 * unit);
+      expect(smallerRate).to.be.greaterThan(0.003);
+      expect(largerRate).to.be.greaterThan(smallerRate);
+      expect(largerRate).to.be.at.most(0.05);
+    });
+
+    it("实际交换的输出与报价一致", async () => {
+      const amountIn = 4_000 * unit;
+      const q = await quote(p, 0, 1, amountIn, true);
+      expect(await swap(p, user, 0, 1, amountIn, true)).to.equal(q.amountOut.toNumber());
+    });
+
+    it("使 pool 恢复平衡的交换使用基础费率", async () => {
+      // 上一步之后 pool 的失衡程度约为 20%，反向交换降低失衡程度
+      const amountIn = 1_000 * unit;
+      const q = await quote(p, 1, 0, amountIn, true);
+      expect(q.feeAmount.toNumber()).to.equal(baseFee(amountIn));
+      expect(await swap(p, user, 1, 0, amountIn, true)).to.equal(q.amountOut.toNumber());
+    });
+
+    it("加权曲线的 pool 不能启用动态失衡手续费", async () => {
+      const weightedPool = await setupWeightedPool([50, 50]);
+      try {
+        await setSurgeFee(weightedPool, maxFee, threshold);
+        expect.fail("应该失败");
+      } catch (e: any) {
+        expect(e.error.errorCode.code).to.equal("InvalidCurveType");
+      }
+    });
+  });
+
+  describe("权重随时间调整", () => {
+    let p: TestPool;
+    const amountIn = 100 * unit;
+    const reserve = 50 * 10_000 * unit;
+
+    async function chainTime(): Promise<number> {
+      return (await connection.getBlockTime(await connection.getSlot()))!;
+    }
+
+    async function waitUntil(timestamp: number) {
+      while ((await chainTime()) < timestamp) {
+        await new Promise((resolve) => setTimeout(resolve, 500));
+      }
+    }
+
+    /**
+     * 加权曲线的交换输出：B * (1 - (B / (B + a)) ^ (w_in / w_out))，a 为扣除 0.3% 手续费后的输入
+     */
+    function expectedOut(weightIn: number, weightOut: number): number {
+      const amountInMinusFees = amountIn - Math.floor((amountIn * 3) / 1000);
+      return reserve * (1 - Math.pow(reserve / (reserve + amountInMinusFees), weightIn / weightOut));
+    }
+
+    before(async () => {
+      p = await setupWeightedPool([50, 50]);
+    });
+
+    it("按时间插值的权重改变交换价格", async () => {
+      const startTime = (await chainTime()) + 3;
+      const endTime = startTime + 8;
+      // token0 的权重从 50 调整到 200
+      await program.methods
+        .rampTokenWeight(new anchor.BN(200), new anchor.BN(startTime), new anchor.BN(endTime))
+        .accountsPartial({ pool: p.pool, mint: p.mints[0], admin: p.admin.publicKey })
+        .signers([p.admin])
+        .rpc();
+
+      // 开始之前保持原来的权重
+      const before = (await quote(p, 0, 1, amountIn)).amountOut.toNumber();
+      expect(Math.abs(before - expectedOut(50, 50))).to.be.at.most(expectedOut(50, 50) * 1e-6);
+
+      // 调整期间 token0 的权重上升，同样的输入换到更多 token1
+      await waitUntil(startTime + 4);
+      const during = (await quote(p, 0, 1, amountIn)).amountOut.toNumber();
+      expect(during).to.be.greaterThan(before);
+      expect(during).to.be.lessThan(expectedOut(200, 50));
+
+      // 结束后使用目标权重
+      await waitUntil(endTime);
+      const after = (await quote(p, 0, 1, amountIn)).amountOut.toNumber();
+      expect(after).to.be.greaterThan(during);
+      expect(Math.abs(after - expectedOut(200, 50))).to.be.at.most(expectedOut(200, 50) * 1e-6);
+    });
+  });
+
+  describe("token 索引提示", () => {
+    let p: TestPool;
+    let user: Keypair;
+    let userTokens: PublicKey[];
+    const amountIn = 1_000 * unit;
+
+    before(async () => {
+      p = await createTestPool({ constantSum: {} }, [1, 1, 1]);
+      await addInitialLiquidity(p, [1_000_000 * unit, 1_000_000 * unit, 1_000_000 * unit]);
+      user = await newUser();
+      userTokens = [];
+      for (const mint of p.mints) {
+        userTokens.push(await fund(mint, user.publicKey, 10 * amountIn));
+      }
+    });
+
+    // 用 token 0 交换 token 1，inHint / outHint 为索引提示（null 表示按 mint 查找）
+    const swapWithHints = (inHint: number | null, outHint: number | null) =>
+      program.methods
+        .swapAnyswap(new anchor.BN(amountIn), new anchor.BN(0), null, inHint, outHint)
+        .accountsPartial({
+          pool: p.pool,
+          vaultIn: p.vaults[0],
+          vaultOut: p.vaults[1],
+          userIn: userTokens[0],
+          userOut: userTokens[1],
+          mintIn: p.mints[0],
+          mintOut: p.mints[1],
+          owner: user.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+        })
+        .signers([user])
+        .rpc();
+
+    it("正确的提示或省略提示都可以交换", async () => {
+      await swapWithHints(0, 1);
+      await swapWithHints(null, null);
+      await swapWithHints(0, null);
+    });
+
+    it("错误的 token 索引提示被拒绝", async () => {
+      // 提示的索引与 vault 的 mint 不一致
+      await expectError(swapWithHints(1, 0), "InvalidTokenIndex");
+      await expectError(swapWithHints(0, 2), "InvalidTokenIndex");
+      // 提示的索引超出 token 数量
+      await expectError(swapWithHints(0, 3), "InvalidTokenIndex");
+    });
+  });
+
+  describe("滑点和截止时间", () => {
+    let p: TestPool;
+    let user: Keypair;
+    let userTokens: PublicKey[];
+
+    before(async () => {
+      p = await createTestPool({ constantSum: {} }, [1, 1]);
+      await addInitialLiquidity(p, [1_000_000 * unit, 1_000_000 * unit]);
+      user = await newUser();
+      userTokens = [];
+      for (const mint of p.mints) {
+        userTokens.push(await fund(mint, user.publicKey, 1_000_000));
+      }
+    });
+
+    const swapAccounts = () => ({
+      pool: p.pool,
+      vaultIn: p.vaults[0],
+      vaultOut: p.vaults[1],
+      userIn: userTokens[0],
+      userOut: userTokens[1],
+      mintIn: p.mints[0],
+      mintOut: p.mints[1],
+      owner: user.publicKey,
+      tokenProgram: token.TOKEN_PROGRAM_ID,
+    });
+    const expired = () => new anchor.BN(Math.floor(Date.now() / 1000) - 60);
+
+    it("超过截止时间的交换失败", async () => {
+      await expectError(
+        program.methods
+          .swapAnyswap(new anchor.BN(1_000), new anchor.BN(0), expired(), null, null)
+          .accountsPartial(swapAccounts())
+          .signers([user])
+          .rpc(),
+        "DeadlineExceeded"
+      );
+    });
+
+    it("超过截止时间的精确输出交换失败", async () => {
+      await expectError(
+        program.methods
+          .swapExactOut(new anchor.BN(1_000), new anchor.BN(2_000), expired(), null, null)
+          .accountsPartial(swapAccounts())
+          .signers([user])
+          .rpc(),
+        "DeadlineExceeded"
+      );
+    });
+
+    it("超过截止时间的批量交换失败", async () => {
+      const legs = [
+        { tokenInIndex: 0, tokenOutIndex: 1, amountIn: new anchor.BN(1_000), minAmountOut: new anchor.BN(0) },
+      ];
+      await expectError(
+        program.methods
+          .batchSwap(legs, expired())
+          .accountsPartial({ pool: p.pool, owner: user.publicKey, tokenProgram: token.TOKEN_PROGRAM_ID })
+          .remainingAccounts([
+            { pubkey: userTokens[0], isSigner: false, isWritable: true },
+            { pubkey: p.vaults[0], isSigner: false, isWritable: true },
+            { pubkey: p.mints[0], isSigner: false, isWritable: false },
+            { pubkey: userTokens[1], isSigner: false, isWritable: true },
+            { pubkey: p.vaults[1], isSigner: false, isWritable: true },
+            { pubkey: p.mints[1], isSigner: false, isWritable: false },
+          ])
+          .signers([user])
+          .rpc(),
+        "DeadlineExceeded"
+      );
+    });
+
+    it("输出低于 min_amount_out 或输入超过 max_amount_in 时返回 SlippageExceeded", async () => {
+      // 扣除手续费后输出少于输入
+      await expectError(
+        program.methods
+          .swapAnyswap(new anchor.BN(1_000), new anchor.BN(1_000), null, null, null)
+          .accountsPartial(swapAccounts())
+          .signers([user])
+          .rpc(),
+        "SlippageExceeded"
+      );
+      await expectError(
+        program.methods
+          .swapExactOut(new anchor.BN(1_000), new anchor.BN(1_000), null, null, null)
+          .accountsPartial(swapAccounts())
+          .signers([user])
+          .rpc(),
+        "SlippageExceeded"
+      );
+    });
+  });
+
+  describe("向已有流动性的加权 pool 添加 token", () => {
+    it("必须指定初始数量，存入的数量决定新 token 的价格", async () => {
+      const p = await setupWeightedPool([50, 50]);
+      const mint = await token.createMint(connection, payer.payer, payer.publicKey, null, n_decimals);
+      const [vault] = PublicKey.findProgramAddressSync(
+        [Buffer.from("vault"), p.pool.toBuffer(), mint.toBuffer()],
+        program.programId
+      );
+      // 与现有 token 相同的数量和权重，新 token 的价格与现有 token 相同
+      const seedAmount = 50 * 10_000 * unit;
+      const adminToken = await fund(mint, p.admin.publicKey, seedAmount);
+      const addToken = (amount: anchor.BN | null) =>
+        program.methods
+          .addTokenToPool(new anchor.BN(50), amount)
+          .accountsPartial({
+            pool: p.pool,
+            mint: mint,
+            vault: vault,
+            adminToken: adminToken,
+            admin: p.admin.publicKey,
+            payer: payer.publicKey,
+            tokenProgram: token.TOKEN_PROGRAM_ID,
+            associatedTokenProgram: token.ASSOCIATED_TOKEN_PROGRAM_ID,
+          })
+          .remainingAccounts(
+            p.vaults.map((v) => ({ pubkey: v, isSigner: false, isWritable: false }))
+          )
+          .signers([p.admin])
+          .rpc();
+
+      await expectError(addToken(null), "InvalidSeedAmount");
+      await expectError(addToken(new anchor.BN(0)), "InvalidSeedAmount");
+
+      await addToken(new anchor.BN(seedAmount));
+      expect(await balance(vault)).to.equal(seedAmount);
+
+      p.mints.push(mint);
+      p.vaults.push(vault);
+      const amountIn = 100 * unit;
+      const fromNewToken = (await quote(p, 2, 0, amountIn)).amountOut.toNumber();
+      const fromExistingToken = (await quote(p, 1, 0, amountIn)).amountOut.toNumber();
+      expect(Math.abs(fromNewToken - fromExistingToken)).to.be.at.most(1);
+    });
+  });
+});
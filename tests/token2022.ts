@@ -0,0 +1,377 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { Anyswap } from "../target/types/anyswap";
+import * as token from "@solana/spl-token";
+import { PublicKey, Keypair, SystemProgram, Transaction } from "@solana/web3.js";
+import { expect } from "chai";
+
+describe("anyswap Token-2022 测试", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace.anyswap as Program<Anyswap>;
+  const connection = provider.connection;
+  const payer = provider.wallet as anchor.Wallet;
+
+  const n_decimals = 6;
+  // Token-2022 mint 的转账手续费：1%
+  const transferFeeBasisPoints = 100;
+
+  let admin: Keypair;
+  let user: Keypair;
+  let pool: PublicKey;
+  let poolAuthorityPda: PublicKey;
+  let poolMint: PublicKey;
+
+  // mintA：普通 Token；mintB：带转账手续费的 Token-2022
+  let mintA: PublicKey;
+  let mintB: PublicKey;
+  let vaultA: PublicKey;
+  let vaultB: PublicKey;
+  let adminTokenA: PublicKey;
+  let adminTokenB: PublicKey;
+  let userTokenA: PublicKey;
+  let userTokenB: PublicKey;
+
+  /**
+   * 初始化协议全局配置（如果还没有初始化），测试中不限制费率、不收取创建费
+   */
+  async function ensureFactoryConfig() {
+    const [factoryConfig] = PublicKey.findProgramAddressSync(
+      [Buffer.from("factory_config")],
+      program.programId
+    );
+    if (await connection.getAccountInfo(factoryConfig)) {
+      return;
+    }
+    const [programData] = PublicKey.findProgramAddressSync(
+      [program.programId.toBuffer()],
+      new PublicKey("BPFLoaderUpgradeab1e11111111111111111111111")
+    );
+    await program.methods
+      .initializeFactoryConfig({
+        protocolAuthority: payer.publicKey,
+        treasury: payer.publicKey,
+        minFee: new anchor.BN(0),
+        maxFee: new anchor.BN("1000000000000000000"),
+        defaultProtocolFeeShare: new anchor.BN(0),
+        creationFee: new anchor.BN(0),
+        paused: false,
+      })
+      .accountsPartial({ authority: payer.publicKey, programData: programData })
+      .rpc();
+  }
+
+  /**
+   * 创建 Token-2022 mint，extensionIxs 在初始化 mint 之前执行
+   */
+  async function createToken2022Mint(
+    extensions: token.ExtensionType[],
+    extensionIxs: (mint: PublicKey) => anchor.web3.TransactionInstruction[]
+  ): Promise<PublicKey> {
+    const mintKeypair = Keypair.generate();
+    const mint = mintKeypair.publicKey;
+    const mintLen = token.getMintLen(extensions);
+    const lamports = await connection.getMinimumBalanceForRentExemption(mintLen);
+
+    const tx = new Transaction().add(
+      SystemProgram.createAccount({
+        fromPubkey: payer.publicKey,
+        newAccountPubkey: mint,
+        space: mintLen,
+        lamports,
+        programId: token.TOKEN_2022_PROGRAM_ID,
+      }),
+      ...extensionIxs(mint),
+      token.createInitializeMintInstruction(
+        mint,
+        n_decimals,
+        payer.publicKey,
+        null,
+        token.TOKEN_2022_PROGRAM_ID
+      )
+    );
+    await provider.sendAndConfirm(tx, [payer.payer, mintKeypair]);
+    return mint;
+  }
+
+  function getVault(mint: PublicKey): PublicKey {
+    const [vault] = PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), pool.toBuffer(), mint.toBuffer()],
+      program.programId
+    );
+    return vault;
+  }
+
+  async function balance(account: PublicKey, programId: PublicKey): Promise<number> {
+    return Number((await token.getAccount(connection, account, undefined, programId)).amount);
+  }
+
+  // 与 Token-2022 的计算一致：向上取整
+  function transferFee(amount: number): number {
+    return Math.ceil((amount * transferFeeBasisPoints) / 10_000);
+  }
+
+  before(async () => {
+    await ensureFactoryConfig();
+
+    admin = Keypair.generate();
+    user = Keypair.generate();
+    for (const kp of [admin, user]) {
+      const airdrop = await connection.requestAirdrop(
+        kp.publicKey,
+        2 * anchor.web3.LAMPORTS_PER_SOL
+      );
+      await connection.confirmTransaction(airdrop);
+    }
+
+    const poolKeypair = Keypair.generate();
+    pool = poolKeypair.publicKey;
+    [poolAuthorityPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("anyswap_authority"), pool.toBuffer()],
+      program.programId
+    );
+    [poolMint] = PublicKey.findProgramAddressSync(
+      [Buffer.from("pool_mint"), pool.toBuffer()],
+      program.programId
+    );
+
+    // 只分配头部空间，添加 token 时账户会自动扩展
+    const poolSpace = 8 + 2 + 1 + 1 + 1 + 3 + 32 + 8 + 8 + 8 + 32 + 8 + 32 + 8 + 8 + 32 + 32 + 32 + 32 + 32 + 8 + 8 + 16;
+    const lamports = await connection.getMinimumBalanceForRentExemption(poolSpace);
+    const tx = new Transaction().add(
+      SystemProgram.createAccount({
+        fromPubkey: payer.publicKey,
+        newAccountPubkey: pool,
+        space: poolSpace,
+        lamports,
+        programId: program.programId,
+      }),
+      await program.methods
+        .createPool(new anchor.BN(1), new anchor.BN(1000), { constantSum: {} }, new anchor.BN(0))
+        .accountsPartial({
+          poolCreator: admin.publicKey,
+          pool: pool,
+          poolAuthority: poolAuthorityPda,
+          poolMint: poolMint,
+          admin: admin.publicKey,
+          payer: payer.publicKey,
+          systemProgram: SystemProgram.programId,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+          rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        })
+        .instruction()
+    );
+    await provider.sendAndConfirm(tx, [payer.payer, poolKeypair, admin]);
+
+    mintA = await token.createMint(connection, payer.payer, payer.publicKey, null, n_decimals);
+    mintB = await createToken2022Mint([token.ExtensionType.TransferFeeConfig], (mint) => [
+      token.createInitializeTransferFeeConfigInstruction(
+        mint,
+        payer.publicKey,
+        payer.publicKey,
+        transferFeeBasisPoints,
+        BigInt(1_000_000 * 10 ** n_decimals),
+        token.TOKEN_2022_PROGRAM_ID
+      ),
+    ]);
+    vaultA = getVault(mintA);
+    vaultB = getVault(mintB);
+
+    adminTokenA = (await token.getOrCreateAssociatedTokenAccount(
+      connection, payer.payer, mintA, admin.publicKey
+    )).address;
+    adminTokenB = (await token.getOrCreateAssociatedTokenAccount(
+      connection, payer.payer, mintB, admin.publicKey, false, undefined, undefined, token.TOKEN_2022_PROGRAM_ID
+    )).address;
+    userTokenA = (await token.getOrCreateAssociatedTokenAccount(
+      connection, payer.payer, mintA, user.publicKey
+    )).address;
+    userTokenB = (await token.getOrCreateAssociatedTokenAccount(
+      connection, payer.payer, mintB, user.publicKey, false, undefined, undefined, token.TOKEN_2022_PROGRAM_ID
+    )).address;
+  });
+
+  it("添加普通 Token 和 Token-2022 token，vault 由对应的 token 程序创建", async () => {
+    await program.methods
+      .addTokenToPool(new anchor.BN(1), null)
+      .accountsPartial({
+        pool: pool,
+        mint: mintA,
+        vault: vaultA,
+        adminToken: adminTokenA,
+        admin: admin.publicKey,
+        payer: payer.publicKey,
+        tokenProgram: token.TOKEN_PROGRAM_ID,
+      })
+      .signers([admin])
+      .rpc();
+
+    await program.methods
+      .addTokenToPool(new anchor.BN(1), null)
+      .accountsPartial({
+        pool: pool,
+        mint: mintB,
+        vault: vaultB,
+        adminToken: adminTokenB,
+        admin: admin.publicKey,
+        payer: payer.publicKey,
+        tokenProgram: token.TOKEN_2022_PROGRAM_ID,
+      })
+      .remainingAccounts([{ pubkey: vaultA, isSigner: false, isWritable: false }])
+      .signers([admin])
+      .rpc();
+
+    expect((await connection.getAccountInfo(vaultA))!.owner.toString()).to.equal(
+      token.TOKEN_PROGRAM_ID.toString()
+    );
+    expect((await connection.getAccountInfo(vaultB))!.owner.toString()).to.equal(
+      token.TOKEN_2022_PROGRAM_ID.toString()
+    );
+  });
+
+  it("添加流动性按 vault 实际收到的数量记账", async () => {
+    const adminPoolAta = await token.createAssociatedTokenAccount(
+      connection,
+      payer.payer,
+      poolMint,
+      admin.publicKey
+    );
+
+    // token B 转入时扣除 1% 手续费，vault 实际收到 amountB - fee = amountA，与权重 1:1 成比例
+    const amountB = 1_000_000 * 10 ** n_decimals;
+    const amountA = amountB - transferFee(amountB);
+    await token.mintTo(connection, payer.payer, mintA, adminTokenA, payer.publicKey, amountA);
+    await token.mintTo(
+      connection, payer.payer, mintB, adminTokenB, payer.publicKey, amountB, [], undefined, token.TOKEN_2022_PROGRAM_ID
+    );
+
+    await program.methods
+      .addLiquidity([new anchor.BN(amountA), new anchor.BN(amountB)], new anchor.BN(0), null)
+      .accountsPartial({
+        pool: pool,
+        poolAuthority: poolAuthorityPda,
+        poolMint: poolMint,
+        userPoolAta: adminPoolAta,
+        owner: admin.publicKey,
+        tokenProgram: token.TOKEN_PROGRAM_ID,
+        token2022Program: token.TOKEN_2022_PROGRAM_ID,
+      })
+      .remainingAccounts([
+        { pubkey: adminTokenA, isSigner: false, isWritable: true },
+        { pubkey: vaultA, isSigner: false, isWritable: true },
+        { pubkey: mintA, isSigner: false, isWritable: false },
+        { pubkey: adminTokenB, isSigner: false, isWritable: true },
+        { pubkey: vaultB, isSigner: false, isWritable: true },
+        { pubkey: mintB, isSigner: false, isWritable: false },
+      ])
+      .signers([admin])
+      .rpc();
+
+    expect(await balance(vaultA, token.TOKEN_PROGRAM_ID)).to.equal(amountA);
+    expect(await balance(vaultB, token.TOKEN_2022_PROGRAM_ID)).to.equal(amountA);
+    expect(await balance(adminTokenB, token.TOKEN_2022_PROGRAM_ID)).to.equal(0);
+    expect(await balance(adminPoolAta, token.TOKEN_PROGRAM_ID)).to.be.greaterThan(0);
+  });
+
+  it("交换输出 Token-2022 token 时由用户承担转账手续费", async () => {
+    const amountIn = 1_000 * 10 ** n_decimals;
+    await token.mintTo(connection, payer.payer, mintA, userTokenA, payer.publicKey, amountIn);
+
+    const vaultBBefore = await balance(vaultB, token.TOKEN_2022_PROGRAM_ID);
+    const userBBefore = await balance(userTokenB, token.TOKEN_2022_PROGRAM_ID);
+
+    await program.methods
+      .swapAnyswap(new anchor.BN(amountIn), new anchor.BN(0), null, null, null)
+      .accountsPartial({
+        pool: pool,
+        vaultIn: vaultA,
+        vaultOut: vaultB,
+        userIn: userTokenA,
+        userOut: userTokenB,
+        mintIn: mintA,
+        mintOut: mintB,
+        owner: user.publicKey,
+        tokenProgram: token.TOKEN_PROGRAM_ID,
+        token2022Program: token.TOKEN_2022_PROGRAM_ID,
+      })
+      .signers([user])
+      .rpc();
+
+    const vaultOut = vaultBBefore - await balance(vaultB, token.TOKEN_2022_PROGRAM_ID);
+    const userReceived = await balance(userTokenB, token.TOKEN_2022_PROGRAM_ID) - userBBefore;
+    expect(vaultOut).to.be.greaterThan(0);
+    expect(userReceived).to.equal(vaultOut - transferFee(vaultOut));
+  });
+
+  it("交换输入 Token-2022 token 时按 vault 实际收到的数量计算输出", async () => {
+    const amountIn = 1_000 * 10 ** n_decimals;
+    await token.mintTo(
+      connection, payer.payer, mintB, userTokenB, payer.publicKey, amountIn, [], undefined, token.TOKEN_2022_PROGRAM_ID
+    );
+
+    const vaultBBefore = await balance(vaultB, token.TOKEN_2022_PROGRAM_ID);
+    const userABefore = await balance(userTokenA, token.TOKEN_PROGRAM_ID);
+
+    await program.methods
+      .swapAnyswap(new anchor.BN(amountIn), new anchor.BN(0), null, null, null)
+      .accountsPartial({
+        pool: pool,
+        vaultIn: vaultB,
+        vaultOut: vaultA,
+        userIn: userTokenB,
+        userOut: userTokenA,
+        mintIn: mintB,
+        mintOut: mintA,
+        owner: user.publicKey,
+        tokenProgram: token.TOKEN_PROGRAM_ID,
+        token2022Program: token.TOKEN_2022_PROGRAM_ID,
+      })
+      .signers([user])
+      .rpc();
+
+    const received = amountIn - transferFee(amountIn);
+    expect(await balance(vaultB, token.TOKEN_2022_PROGRAM_ID) - vaultBBefore).to.equal(received);
+
+    // 恒定乘积和、权重 1:1：输出 = 实际收到的输入扣除 0.1% 的交换手续费
+    const userReceived = await balance(userTokenA, token.TOKEN_PROGRAM_ID) - userABefore;
+    expect(userReceived).to.be.at.most(received - Math.floor(received / 1000));
+    expect(userReceived).to.be.at.least(received - Math.ceil(received / 1000) - 1);
+  });
+
+  it("含有不支持的扩展（永久代理）的 mint 不能上架", async () => {
+    const mint = await createToken2022Mint([token.ExtensionType.PermanentDelegate], (mint) => [
+      token.createInitializePermanentDelegateInstruction(
+        mint,
+        payer.publicKey,
+        token.TOKEN_2022_PROGRAM_ID
+      ),
+    ]);
+    const adminToken = (await token.getOrCreateAssociatedTokenAccount(
+      connection, payer.payer, mint, admin.publicKey, false, undefined, undefined, token.TOKEN_2022_PROGRAM_ID
+    )).address;
+
+    try {
+      await program.methods
+        .addTokenToPool(new anchor.BN(1), null)
+        .accountsPartial({
+          pool: pool,
+          mint: mint,
+          vault: getVault(mint),
+          adminToken: adminToken,
+          admin: admin.publicKey,
+          payer: payer.publicKey,
+          tokenProgram: token.TOKEN_2022_PROGRAM_ID,
+        })
+        .remainingAccounts([
+          { pubkey: vaultA, isSigner: false, isWritable: false },
+          { pubkey: vaultB, isSigner: false, isWritable: false },
+        ])
+        .signers([admin])
+        .rpc();
+      expect.fail("应该失败");
+    } catch (e: any) {
+      expect(e.error.errorCode.code).to.equal("UnsupportedMintExtension");
+    }
+  });
+});